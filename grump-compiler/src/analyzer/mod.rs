@@ -2,15 +2,35 @@
 //! 
 //! Performs type checking, ownership analysis, and animation validation.
 
-use crate::parser::{Program, Expression, Statement, Item};
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::{Program, Expression, Statement, Item, Span};
+use crate::parser::extensions::{MacroBody, MacroDeclaration, NetworkDeclaration, NetworkStatement, SyncMode};
 use crate::error::{GrumpError, GrumpResult};
-use crate::analyzer::types::{Type, TypeContext, ast_type_to_type};
+use crate::plugin::PluginHost;
+use crate::analyzer::types::{FunctionSignature, Type, TypeContext, ast_type_to_type};
 
 pub mod types;
 
 pub struct Analyzer {
     context: TypeContext,
     errors: Vec<GrumpError>,
+    bindings: Vec<(String, Type)>,  // `let`s checked so far, with their types
+    templates: HashMap<String, MacroDeclaration>,  // Template macros, whose calls are left to codegen
+    in_async: bool,  // Checking an async function or block, where `await` is allowed
+    sync_fields: HashMap<String, Type>,  // Fields of every `network` declaration, for `sync` statements
+}
+
+/// Most sync fields a `network` can declare; deltas mark changed fields in a 64-bit mask
+const MAX_SYNC_FIELDS: usize = 64;
+
+/// What checking a single item found
+#[derive(Debug, Default)]
+pub struct ItemAnalysis {
+    pub span: Span,
+    pub errors: Vec<GrumpError>,
+    /// Types of the item's `let` bindings, in order
+    pub bindings: Vec<(String, Type)>,
 }
 
 impl Analyzer {
@@ -18,6 +38,10 @@ impl Analyzer {
         let mut analyzer = Self {
             context: TypeContext::new(),
             errors: Vec::new(),
+            bindings: Vec::new(),
+            templates: HashMap::new(),
+            in_async: false,
+            sync_fields: HashMap::new(),
         };
         
         // Add built-in functions
@@ -26,6 +50,13 @@ impl Analyzer {
         analyzer
     }
     
+    /// Register the built-in functions plugins add
+    pub fn add_plugin_functions(&mut self, plugins: &PluginHost) {
+        for (name, signature) in plugins.functions() {
+            self.context.add_function(name.clone(), signature.clone());
+        }
+    }
+
     fn add_builtin_functions(&mut self) {
         // Math functions
         self.context.add_function("sin".to_string(), FunctionSignature {
@@ -130,17 +161,50 @@ impl Analyzer {
             params: vec![],
             return_type: Type::Float,
         });
+        
+        // Async functions that resume on a later frame
+        self.context.add_function("wait".to_string(), FunctionSignature {
+            params: vec![("duration".to_string(), Type::Duration)],
+            return_type: Type::Async(Box::new(Type::Never)),
+        });
+        self.context.add_function("animation_finished".to_string(), FunctionSignature {
+            params: vec![("target".to_string(), Type::Unknown)],
+            return_type: Type::Async(Box::new(Type::Never)),
+        });
+        self.context.add_function("event".to_string(), FunctionSignature {
+            params: vec![("name".to_string(), Type::String)],
+            return_type: Type::Async(Box::new(Type::Never)),
+        });
+        self.context.add_function("emit".to_string(), FunctionSignature {
+            params: vec![("name".to_string(), Type::String)],
+            return_type: Type::Never,
+        });
+        
+        // Entity functions: an instance of the named entity, and taking one
+        // out of the world again (which cancels its scripts)
+        self.context.add_function("spawn".to_string(), FunctionSignature {
+            params: vec![("entity".to_string(), Type::String)],
+            return_type: Type::Unknown,
+        });
+        self.context.add_function("despawn".to_string(), FunctionSignature {
+            params: vec![("entity".to_string(), Type::Unknown)],
+            return_type: Type::Never,
+        });
     }
     
     pub fn analyze(&mut self, program: &Program) -> GrumpResult<()> {
-        // First pass: collect all type definitions
-        for item in &program.items {
+        // First pass: collect all type definitions, including those in apps
+        // and modules (the other files of a project)
+        let items = program.all_items();
+        for item in &items {
             self.collect_types(item)?;
         }
         
         // Second pass: type check everything
-        for item in &program.items {
-            self.check_item(item)?;
+        for item in items {
+            if !matches!(item, Item::App(_) | Item::Module(_)) {
+                self.check_item(item)?;
+            }
         }
         
         if !self.errors.is_empty() {
@@ -150,9 +214,97 @@ impl Analyzer {
         Ok(())
     }
     
+    /// Check every item (including those nested in apps and modules) and
+    /// report all errors per item instead of stopping at the first.
+    ///
+    /// The result lines up with `program.all_items()`.
+    pub fn analyze_items(&mut self, program: &Program) -> Vec<ItemAnalysis> {
+        let items = program.all_items();
+        self.declare(&items);
+        items.into_iter().map(|item| self.analyze_item(item)).collect()
+    }
+    
+    /// Register the types and function signatures `items` declare
+    pub fn declare(&mut self, items: &[&Item]) {
+        for item in items {
+            if let Err(e) = self.collect_types(item) {
+                self.errors.push(e);
+            }
+        }
+    }
+    
+    /// Check one item against what has been declared so far. Apps and
+    /// modules get an empty analysis: their contents are items of their own.
+    pub fn analyze_item(&mut self, item: &Item) -> ItemAnalysis {
+        if !matches!(item, Item::App(_) | Item::Module(_)) {
+            if let Err(e) = self.check_item(item) {
+                self.errors.push(e);
+            }
+        }
+        ItemAnalysis {
+            span: item.span(),
+            errors: std::mem::take(&mut self.errors),
+            bindings: std::mem::take(&mut self.bindings),
+        }
+    }
+    
+    /// Types and functions known so far (just the built-ins before analysis)
+    pub fn context(&self) -> &TypeContext {
+        &self.context
+    }
+
+    /// Declare and check one item on top of everything before it (as entered
+    /// at the REPL). An item with errors is not declared.
+    pub fn check_declaration(&mut self, item: &Item) -> GrumpResult<()> {
+        let saved = self.context.clone();
+        self.declare(&[item]);
+        let analysis = self.analyze_item(item);
+        match analysis.errors.into_iter().next() {
+            Some(e) => {
+                self.context = saved;
+                Err(e)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check a top-level statement; its bindings stay visible to later ones
+    pub fn check_statement(&mut self, stmt: &Statement) -> GrumpResult<()> {
+        let mut scope = self.context.clone();
+        let result = self.check_statement_with_context(stmt, &mut scope);
+        self.bindings.clear();
+        self.finish(result)?;
+        self.context = scope;
+        Ok(())
+    }
+
+    /// Type of `expr` in the top-level scope
+    pub fn type_of(&mut self, expr: &Expression) -> GrumpResult<Type> {
+        let scope = self.context.clone();
+        let result = self.check_expression(expr, &scope);
+        self.finish(result)
+    }
+
+    /// Make a top-level variable visible to later checks
+    pub fn bind(&mut self, name: String, type_: Type) {
+        self.context.add_variable(name, type_);
+    }
+
+    /// `result`, or the first error recorded while producing it
+    fn finish<T>(&mut self, result: GrumpResult<T>) -> GrumpResult<T> {
+        let value = result?;
+        if !self.errors.is_empty() {
+            let first = self.errors.remove(0);
+            self.errors.clear();
+            return Err(first);
+        }
+        Ok(value)
+    }
+
     fn collect_types(&mut self, item: &Item) -> GrumpResult<()> {
         match item {
             Item::Component(comp) => {
+                crate::expander::derive::derives(comp)?;
                 // Register component type
                 let mut fields = Vec::new();
                 for field in &comp.fields {
@@ -162,6 +314,11 @@ impl Analyzer {
                 let component_type = Type::Named(format!("Component_{}", comp.name));
                 self.context.types.insert(comp.name.clone(), component_type);
             }
+            Item::Enum(enum_decl) => {
+                // Register enum type; the enum name is also a value (`Direction.Up`)
+                self.context.add_enum(enum_decl.name.clone(), enum_decl.variants.clone());
+                self.context.add_variable(enum_decl.name.clone(), Type::Named(enum_decl.name.clone()));
+            }
             Item::Entity(entity) => {
                 // Register entity type
                 let entity_type = Type::Named(format!("Entity_{}", entity.name));
@@ -178,17 +335,31 @@ impl Analyzer {
                         params.push((param.name.clone(), Type::Unknown));
                     }
                 }
-                let return_type = if let Some(rt) = &func.return_type {
+                let mut return_type = if let Some(rt) = &func.return_type {
                     ast_type_to_type(rt)
                 } else {
                     Type::Never
                 };
+                // Callers get a value to `await`
+                if func.is_async {
+                    return_type = Type::Async(Box::new(return_type));
+                }
                 let sig = FunctionSignature {
                     params,
                     return_type,
                 };
                 self.context.add_function(func.name.clone(), sig);
             }
+            Item::Macro(decl) => {
+                if let MacroBody::Template(_) = decl.body {
+                    self.templates.insert(decl.name.clone(), decl.clone());
+                }
+            }
+            Item::Network(network) => {
+                for field in &network.sync_fields {
+                    self.sync_fields.insert(field.name.clone(), ast_type_to_type(&field.type_));
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -202,13 +373,17 @@ impl Analyzer {
                 }
             }
             Item::Scene(scene) => {
-                for stmt in &scene.body {
-                    self.check_statement(stmt)?;
-                }
+                // A scene's bindings are local to it, and it runs as a script,
+                // so cutscenes can `await`
+                let mut scope = self.context.clone();
+                let outer = std::mem::replace(&mut self.in_async, true);
+                let checked = scene.body.iter().try_for_each(|stmt| self.check_statement_with_context(stmt, &mut scope));
+                self.in_async = outer;
+                checked?;
             }
             Item::Function(func) => {
-                // Create new scope for function
-                let mut func_ctx = TypeContext::new();
+                // Create new scope for function (globals and other functions stay visible)
+                let mut func_ctx = self.context.clone();
                 
                 // Add parameters to context
                 for param in &func.params {
@@ -218,22 +393,23 @@ impl Analyzer {
                     }
                 }
                 
-                // If async function, return type should be wrapped in Async
-                if func.is_async {
-                    if let Some(return_type) = &func.return_type {
-                        let rt = ast_type_to_type(return_type);
-                        // TODO: Wrap return type in Async if not already
-                    }
-                }
-                
                 // Type check function body
-                for stmt in &func.body {
-                    self.check_statement_with_context(stmt, &mut func_ctx)?;
+                let outer = std::mem::replace(&mut self.in_async, func.is_async);
+                let checked = func.body.iter().try_for_each(|stmt| self.check_statement_with_context(stmt, &mut func_ctx));
+                self.in_async = outer;
+                checked?;
+            }
+            Item::Test(test) => {
+                let mut scope = self.context.clone();
+                add_test_functions(&mut scope);
+                for stmt in &test.body {
+                    self.check_statement_with_context(stmt, &mut scope)?;
                 }
             }
             Item::Entity(entity) => {
+                let mut scope = self.context.clone();
                 for stmt in &entity.body {
-                    self.check_statement(stmt)?;
+                    self.check_statement_with_context(stmt, &mut scope)?;
                 }
             }
             Item::Shader(_) => {
@@ -244,37 +420,32 @@ impl Analyzer {
                 // Behavior tree validation
                 // TODO: Check that all referenced actions exist
             }
-            Item::Network(_) => {
-                // Network validation
-                // TODO: Check sync field types, RPC signatures
+            Item::Network(network) => {
+                self.check_network(network);
             }
-            Item::Macro(_) => {
-                // Macro validation
-                // TODO: Check macro parameter types
+            Item::Macro(decl) => {
+                // Calls are checked once expanded; the declaration must make sense on its own
+                crate::expander::validate(decl)?;
             }
-            Item::Plugin(_) => {
-                // Plugin validation
-                // TODO: Check plugin dependencies exist
+            Item::Plugin(plugin) => {
+                // Loading it is the `PluginHost`'s job; the declaration must make sense on its own
+                crate::plugin::validate(plugin)?;
             }
-            Item::Package(_) => {
-                // Package validation
-                // TODO: Check version constraints, resolve dependencies
+            Item::Package(package) => {
+                // Versions and requirements must parse; resolving them is `grump fetch`'s job
+                crate::package::validate(package)?;
             }
             _ => {}
         }
         Ok(())
     }
     
-    fn check_statement(&mut self, stmt: &Statement) -> GrumpResult<()> {
-        self.check_statement_with_context(stmt, &mut self.context)
-    }
-    
     fn check_statement_with_context(&mut self, stmt: &Statement, ctx: &mut TypeContext) -> GrumpResult<()> {
         match stmt {
-            Statement::Let { name, type_, value } => {
+            Statement::Let { name, type_, value, .. } => {
                 let value_type = self.check_expression(value, ctx)?;
                 
-                if let Some(declared_type) = type_ {
+                let binding_type = if let Some(declared_type) = type_ {
                     let declared = ast_type_to_type(declared_type);
                     if !value_type.is_compatible_with(&declared) {
                         self.errors.push(GrumpError::Type {
@@ -284,13 +455,15 @@ impl Analyzer {
                             ),
                         });
                     }
-                    ctx.add_variable(name.clone(), declared);
+                    declared
                 } else {
                     // Type inference
-                    ctx.add_variable(name.clone(), value_type);
-                }
+                    value_type
+                };
+                self.bindings.push((name.clone(), binding_type.clone()));
+                ctx.add_variable(name.clone(), binding_type);
             }
-            Statement::Assign { target, value } => {
+            Statement::Assign { target, value, .. } => {
                 let target_type = self.check_expression(target, ctx)?;
                 let value_type = self.check_expression(value, ctx)?;
                 
@@ -303,7 +476,7 @@ impl Analyzer {
                     });
                 }
             }
-            Statement::If { condition, then, else_ } => {
+            Statement::If { condition, then, else_, .. } => {
                 let cond_type = self.check_expression(condition, ctx)?;
                 if cond_type != Type::Bool {
                     self.errors.push(GrumpError::Type {
@@ -321,12 +494,12 @@ impl Analyzer {
                     }
                 }
             }
-            Statement::Return(expr) => {
+            Statement::Return(expr, _) => {
                 if let Some(expr) = expr {
                     self.check_expression(expr, ctx)?;
                 }
             }
-            Statement::Expression(expr) => {
+            Statement::Expression(expr, _) => {
                 self.check_expression(expr, ctx)?;
             }
             Statement::Animate(animate) => {
@@ -346,27 +519,32 @@ impl Analyzer {
                 }
             }
             Statement::Await { expr } => {
-                // Check that expression is async
-                let expr_type = self.check_expression(expr, ctx)?;
-                match expr_type {
-                    Type::Async(inner) => {
-                        // Await unwraps the async type
-                        // The result type would be *inner, but for now we'll use Unknown
-                        // TODO: Properly handle async type unwrapping
-                    }
-                    _ => {
+                self.check_await(expr, ctx)?;
+            }
+            Statement::Debugger(_) => {
+                // Debugger statements are always valid (no-op in release)
+            }
+            Statement::Network(NetworkStatement::Sync { field, value }) => {
+                let value_type = self.check_expression(value, ctx)?;
+                match self.sync_fields.get(field) {
+                    Some(field_type) if !value_type.is_compatible_with(field_type) => {
                         self.errors.push(GrumpError::Type {
-                            message: format!("Cannot await non-async expression of type {:?}", expr_type),
+                            message: format!("Cannot sync {:?} to field '{}' of type {:?}", value_type, field, field_type),
                         });
                     }
+                    Some(_) => {}
+                    None => self.errors.push(GrumpError::Type {
+                        message: format!("'{}' isn't a sync field of any network", field),
+                    }),
                 }
             }
-            Statement::Debugger(_) => {
-                // Debugger statements are always valid (no-op in release)
+            Statement::Network(NetworkStatement::Send { args, .. }) => {
+                for arg in args {
+                    self.check_expression(arg, ctx)?;
+                }
             }
             Statement::Network(_) => {
-                // Network statements are checked separately
-                // TODO: Add network-specific type checking
+                // Connections are the transport's business
             }
             _ => {
                 // TODO: Check other statement types
@@ -417,6 +595,8 @@ impl Analyzer {
                             (Type::Int, Type::Float) | (Type::Float, Type::Int) => Ok(Type::Float),
                             (Type::Vec2, Type::Vec2) => Ok(Type::Vec2),
                             (Type::Vec3, Type::Vec3) => Ok(Type::Vec3),
+                            // An operand that's already unchecked; don't pile on errors
+                            (Type::Unknown, _) | (_, Type::Unknown) => Ok(Type::Unknown),
                             _ => {
                                 self.errors.push(GrumpError::Type {
                                     message: format!("Cannot apply {:?} to {:?} and {:?}", op, left_type, right_type),
@@ -503,10 +683,22 @@ impl Analyzer {
                         }
                     }
                     Type::Named(name) => {
+                        let variants = ctx.get_enum(&name).or_else(|| self.context.get_enum(&name));
+                        if let Some(variants) = variants {
+                            if variants.iter().any(|v| v == member) {
+                                return Ok(Type::Named(name));
+                            }
+                            self.errors.push(GrumpError::Type {
+                                message: format!("Enum {} has no variant '{}'", name, member),
+                            });
+                            return Ok(Type::Unknown);
+                        }
                         // Check if it's a component or entity type
                         // TODO: Look up actual type definition
                         Ok(Type::Unknown)
                     }
+                    // Already unchecked (e.g. a component of an entity); don't pile on errors
+                    Type::Unknown => Ok(Type::Unknown),
                     _ => {
                         self.errors.push(GrumpError::Type {
                             message: format!("Cannot access member '{}' on type {:?}", member, object_type),
@@ -515,27 +707,43 @@ impl Analyzer {
                     }
                 }
             }
-            Expression::Await(expr) => {
-                // Check that expression is async
-                let expr_type = self.check_expression(expr, ctx)?;
-                match expr_type {
-                    Type::Async(inner) => Ok(*inner),  // Await unwraps async
-                    _ => {
-                        self.errors.push(GrumpError::Type {
-                            message: format!("Cannot await non-async expression of type {:?}", expr_type),
-                        });
-                        Ok(Type::Unknown)
+            Expression::Await(expr) => self.check_await(expr, ctx),
+            Expression::AsyncBlock(body) => {
+                // The block's value is its last expression, awaited by whoever holds it
+                let mut scope = ctx.clone();
+                let outer = std::mem::replace(&mut self.in_async, true);
+                let value = self.check_block(body, &mut scope);
+                self.in_async = outer;
+                Ok(Type::Async(Box::new(value?)))
+            }
+            Expression::MacroCall { name, args, .. } => {
+                // The expander replaces every call to a macro it knows, except
+                // template macros, whose target code has no G-Rump type
+                let Some(decl) = self.templates.get(name).cloned() else {
+                    self.errors.push(GrumpError::Type {
+                        message: format!("Undefined macro: {}!", name),
+                    });
+                    return Ok(Type::Unknown);
+                };
+                if let Some(message) = crate::expander::arity_error(&decl, args.len()) {
+                    self.errors.push(GrumpError::Type { message });
+                }
+                // Arguments line up with parameters up to a variadic one
+                let lined_up = decl.params.iter().take_while(|p| !p.is_variadic).count();
+                for (i, arg) in args.iter().enumerate() {
+                    let arg_type = self.check_expression(arg, ctx)?;
+                    let param_type = decl.params.get(i).filter(|_| i < lined_up).and_then(|p| p.type_.as_ref());
+                    if let Some(param_type) = param_type.map(ast_type_to_type) {
+                        if !arg_type.is_compatible_with(&param_type) {
+                            self.errors.push(GrumpError::Type {
+                                message: format!(
+                                    "Argument {} to '{}!' has wrong type: expected {:?}, got {:?}",
+                                    i, name, param_type, arg_type
+                                ),
+                            });
+                        }
                     }
                 }
-            }
-            Expression::AsyncBlock(_) => {
-                // Async block returns an async type
-                // TODO: Infer the actual return type from the block
-                Ok(Type::Async(Box::new(Type::Unknown)))
-            }
-            Expression::MacroCall { name: _, args: _ } => {
-                // Macro calls are expanded before type checking
-                // This should not be reached in normal flow
                 Ok(Type::Unknown)
             }
             Expression::Array(elements) => {
@@ -590,5 +798,114 @@ impl Analyzer {
             }
         }
     }
+    
+    /// `await expr`: only in async code, and only of async values, which it unwraps
+    fn check_await(&mut self, expr: &Expression, ctx: &TypeContext) -> GrumpResult<Type> {
+        if !self.in_async {
+            self.errors.push(GrumpError::Type {
+                message: "`await` outside an async function or block (declare the function `async fn`)".to_string(),
+            });
+        }
+        let expr_type = self.check_expression(expr, ctx)?;
+        match expr_type {
+            Type::Async(inner) => Ok(*inner),
+            Type::Unknown => Ok(Type::Unknown),
+            _ => {
+                self.errors.push(GrumpError::Type {
+                    message: format!("Cannot await non-async expression of type {:?}", expr_type),
+                });
+                Ok(Type::Unknown)
+            }
+        }
+    }
+    
+    /// Sync fields and RPC parameters must be types the replication runtime
+    /// can serialize, and only numbers and vectors can be interpolated
+    fn check_network(&mut self, network: &NetworkDeclaration) {
+        if network.sync_fields.len() > MAX_SYNC_FIELDS {
+            self.errors.push(GrumpError::Type {
+                message: format!(
+                    "Network '{}' syncs {} fields, at most {} are supported",
+                    network.name, network.sync_fields.len(), MAX_SYNC_FIELDS
+                ),
+            });
+        }
+        let mut seen = HashSet::new();
+        for field in &network.sync_fields {
+            if !seen.insert(field.name.as_str()) {
+                self.errors.push(GrumpError::Type {
+                    message: format!("Network '{}' syncs '{}' twice", network.name, field.name),
+                });
+            }
+            let field_type = ast_type_to_type(&field.type_);
+            if !is_replicable(&field_type) {
+                self.errors.push(GrumpError::Type {
+                    message: format!("Sync field '{}.{}' has type {:?}, which can't be replicated", network.name, field.name, field_type),
+                });
+            } else if matches!(field.sync_mode, SyncMode::Interpolated) && !is_interpolatable(&field_type) {
+                self.errors.push(GrumpError::Type {
+                    message: format!(
+                        "Sync field '{}.{}' is @interpolated but has type {:?}; only numbers and vectors interpolate",
+                        network.name, field.name, field_type
+                    ),
+                });
+            }
+        }
+        for rpc in &network.rpc_functions {
+            for param in &rpc.params {
+                let param_type = param.type_.as_ref().map(ast_type_to_type).unwrap_or(Type::Unknown);
+                if !is_replicable(&param_type) {
+                    self.errors.push(GrumpError::Type {
+                        message: format!(
+                            "Parameter '{}' of RPC '{}.{}' has type {:?}, which can't be replicated",
+                            param.name, network.name, rpc.name, param_type
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    
+    /// Check `body` in `scope`; its type is that of a trailing expression
+    fn check_block(&mut self, body: &[Statement], scope: &mut TypeContext) -> GrumpResult<Type> {
+        for (i, stmt) in body.iter().enumerate() {
+            match stmt {
+                Statement::Expression(expr, _) if i + 1 == body.len() => return self.check_expression(expr, scope),
+                _ => self.check_statement_with_context(stmt, scope)?,
+            }
+        }
+        Ok(Type::Never)
+    }
+}
+
+
+/// Types `runtime::network` can serialize
+fn is_replicable(type_: &Type) -> bool {
+    matches!(
+        type_,
+        Type::Int | Type::Int64 | Type::Float | Type::Double | Type::Bool | Type::String
+            | Type::Vec2 | Type::Vec3 | Type::Color | Type::Angle | Type::Duration
+    )
 }
 
+/// Types `runtime::network` can interpolate between snapshots
+fn is_interpolatable(type_: &Type) -> bool {
+    matches!(type_, Type::Float | Type::Double | Type::Vec2 | Type::Vec3 | Type::Angle)
+}
+
+/// Built-ins only `test` bodies can call
+fn add_test_functions(ctx: &mut TypeContext) {
+    ctx.add_function("assert".to_string(), FunctionSignature {
+        params: vec![("condition".to_string(), Type::Bool)],
+        return_type: Type::Never,
+    });
+    ctx.add_function("assert_eq".to_string(), FunctionSignature {
+        params: vec![("actual".to_string(), Type::Unknown), ("expected".to_string(), Type::Unknown)],
+        return_type: Type::Never,
+    });
+    // Run every system this many frames
+    ctx.add_function("simulate".to_string(), FunctionSignature {
+        params: vec![("frames".to_string(), Type::Int)],
+        return_type: Type::Never,
+    });
+}
@@ -4,8 +4,9 @@
 
 use crate::parser::Type as AstType;
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Type {
     // Primitives
     Int,
@@ -71,7 +72,7 @@ pub enum Type {
     Plugin,  // Plugin type
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ShaderType {
     pub uniforms: Vec<(String, Type)>,  // Uniform name and type
     pub has_vertex: bool,
@@ -97,12 +98,19 @@ impl Type {
             (Type::Int, Type::Int64) => true,
             (Type::Int64, Type::Int) => true,
             
+            // Plain numbers are seconds
+            (Type::Duration, Type::Duration) | (Type::Int | Type::Float, Type::Duration) => true,
+            (Type::Async(a), Type::Async(b)) => a.is_compatible_with(b),
+            
             // Animation primitives
             (Type::Vec2, Type::Point) => true,
             (Type::Point, Type::Vec2) => true,
             (Type::Angle, Type::Rotation) => true,
             (Type::Rotation, Type::Angle) => true,
             
+            // Unchecked (untyped parameters, entity components): anything goes
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            
             // Optional unwrapping
             (Type::Optional(ref inner), other) => inner.is_compatible_with(other),
             (inner, Type::Optional(ref other)) => inner.is_compatible_with(other),
@@ -148,11 +156,47 @@ impl Type {
     }
 }
 
+/// Types as they are written in G-Rump source (`vec2`, `list<float>`)
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |types: &[Type]| types.iter().map(Type::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Type::List(inner) => write!(f, "list<{}>", inner),
+            Type::Array(inner, 0) => write!(f, "[{}]", inner),
+            Type::Array(inner, n) => write!(f, "[{}; {}]", inner, n),
+            Type::Set(inner) => write!(f, "set<{}>", inner),
+            Type::Map(k, v) => write!(f, "map<{}, {}>", k, v),
+            Type::Tuple(types) => write!(f, "({})", list(types)),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Result { ok, err } => write!(f, "result<{}, {}>", ok, err),
+            Type::Named(name) => {
+                // Components and entities are registered as `Component_Name`
+                let name = name.strip_prefix("Component_")
+                    .or_else(|| name.strip_prefix("Entity_"))
+                    .unwrap_or(name);
+                write!(f, "{}", name)
+            }
+            Type::Animatable(inner) => write!(f, "animatable<{}>", inner),
+            Type::Reactive(inner) => write!(f, "reactive<{}>", inner),
+            Type::Clamped { min, max, inner } => write!(f, "clamped<{}, {}, {}>", inner, min, max),
+            Type::Wrapped { min, max, inner } => write!(f, "wrapped<{}, {}, {}>", inner, min, max),
+            Type::Async(inner) => write!(f, "async {}", inner),
+            Type::Shader(_) => write!(f, "shader"),
+            Type::Never => write!(f, "never"),
+            Type::Unknown => write!(f, "_"),
+            Type::BehaviorTree => write!(f, "behavior_tree"),
+            other => write!(f, "{}", format!("{:?}", other).to_lowercase()),
+        }
+    }
+}
+
 /// Type context for type checking
+#[derive(Clone)]
 pub struct TypeContext {
     variables: HashMap<String, Type>,
     functions: HashMap<String, FunctionSignature>,
     types: HashMap<String, Type>,  // User-defined types
+    enums: HashMap<String, Vec<String>>,  // Enum name -> variants
 }
 
 #[derive(Debug, Clone)]
@@ -167,6 +211,7 @@ impl TypeContext {
             variables: HashMap::new(),
             functions: HashMap::new(),
             types: HashMap::new(),
+            enums: HashMap::new(),
         }
     }
     
@@ -174,6 +219,15 @@ impl TypeContext {
         self.variables.insert(name, type_);
     }
     
+    pub fn add_enum(&mut self, name: String, variants: Vec<String>) {
+        self.types.insert(name.clone(), Type::Named(name.clone()));
+        self.enums.insert(name, variants);
+    }
+    
+    pub fn get_enum(&self, name: &str) -> Option<&[String]> {
+        self.enums.get(name).map(Vec::as_slice)
+    }
+    
     pub fn get_variable(&self, name: &str) -> Option<&Type> {
         self.variables.get(name)
     }
@@ -185,6 +239,13 @@ impl TypeContext {
     pub fn get_function(&self, name: &str) -> Option<&FunctionSignature> {
         self.functions.get(name)
     }
+    
+    /// Every function signature, sorted by name
+    pub fn functions(&self) -> Vec<(&str, &FunctionSignature)> {
+        let mut functions: Vec<_> = self.functions.iter().map(|(n, s)| (n.as_str(), s)).collect();
+        functions.sort_by_key(|(name, _)| *name);
+        functions
+    }
 }
 
 /// Convert AST type to internal type
@@ -786,6 +786,8 @@ pub enum Platform {
     Web,
     Android,
     Flutter,
+    /// Animated GIF exports
+    Gif,
 }
 
 #[derive(Debug, Clone)]
@@ -800,6 +802,22 @@ pub struct FrameRateNormalization {
     pub ease_adjustment: Vec<(Platform, f64)>,
 }
 
+impl FrameRateNormalization {
+    pub fn target_fps(&self, platform: &Platform) -> Option<u32> {
+        self.target_fps.iter().find(|(p, _)| p == platform).map(|(_, fps)| *fps)
+    }
+
+    /// Frame delay in centiseconds for a GIF of an animation authored at
+    /// `fps`. GIF delays are whole centiseconds and most viewers slow
+    /// anything under 2cs down to 10cs, so the rate is capped at the GIF
+    /// target and rounded to the nearest delay that plays back as written.
+    pub fn gif_delay(&self, fps: f64) -> u16 {
+        let cap = self.target_fps(&Platform::Gif).unwrap_or(50) as f64;
+        let fps = fps.min(cap).max(1.0);
+        ((100.0 / fps).round() as u16).max(2)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InterpolationMethod {
     Linear,
@@ -831,6 +849,35 @@ pub enum ExportFormat {
     Lottie,
     SpriteSheet,
     Code,
+    Css,
+    WebAnimations,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name.to_lowercase().as_str() {
+            "gif" => Some(ExportFormat::Gif),
+            "mp4" => Some(ExportFormat::Mp4),
+            "lottie" | "bodymovin" => Some(ExportFormat::Lottie),
+            "spritesheet" | "sprite-sheet" | "sprites" => Some(ExportFormat::SpriteSheet),
+            "code" => Some(ExportFormat::Code),
+            "css" => Some(ExportFormat::Css),
+            "waapi" | "web-animations" | "js" => Some(ExportFormat::WebAnimations),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Gif => "gif",
+            ExportFormat::Mp4 => "mp4",
+            ExportFormat::Lottie => "lottie",
+            ExportFormat::SpriteSheet => "spritesheet",
+            ExportFormat::Code => "code",
+            ExportFormat::Css => "css",
+            ExportFormat::WebAnimations => "waapi",
+        }
+    }
 }
 
 impl Default for CrossPlatformConsistency {
@@ -868,6 +915,7 @@ impl Default for CrossPlatformConsistency {
                     (Platform::Ios, 120),
                     (Platform::Android, 60),
                     (Platform::Flutter, 60),
+                    (Platform::Gif, 50),
                 ],
                 interpolation: InterpolationMethod::CatmullRom,
                 ease_adjustment: vec![
@@ -875,6 +923,7 @@ impl Default for CrossPlatformConsistency {
                     (Platform::Ios, 1.0),
                     (Platform::Android, 0.95),
                     (Platform::Flutter, 0.95),
+                    (Platform::Gif, 1.0),
                 ],
             },
             personality_exports: PersonalityExports {
@@ -1383,7 +1432,7 @@ impl Default for NicheOptimizations {
                 },
                 reusable_assets: true,
                 predictable_rigs: true,
-                export_formats: vec![ExportFormat::Gif, ExportFormat::Mp4, ExportFormat::Lottie, ExportFormat::SpriteSheet, ExportFormat::Code],
+                export_formats: vec![ExportFormat::Gif, ExportFormat::Mp4, ExportFormat::Lottie, ExportFormat::SpriteSheet, ExportFormat::Code, ExportFormat::Css, ExportFormat::WebAnimations],
             },
             animation_language: AnimationLanguageOptimizations {
                 generate_code: true,
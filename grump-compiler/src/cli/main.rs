@@ -28,9 +28,9 @@ enum Commands {
         /// Source file or directory
         input: PathBuf,
         
-        /// Target platform (ios, android, web, all)
-        #[arg(short, long, default_value = "ios")]
-        target: String,
+        /// Target platform (ios, android, web, flutter, typescript, godot, rust, all); defaults to the manifest's targets
+        #[arg(short, long)]
+        target: Option<String>,
         
         /// Output directory
         #[arg(short, long)]
@@ -39,6 +39,10 @@ enum Commands {
         /// Optimization level (debug, release, size)
         #[arg(short, long, default_value = "debug")]
         optimization: String,
+        
+        /// Rust target: depend on the grump-runtime crate here instead of writing a copy
+        #[arg(long)]
+        runtime: Option<PathBuf>,
     },
     
     /// Run a G-Rump project in development mode
@@ -51,12 +55,143 @@ enum Commands {
         target: String,
     },
     
+    /// Map a stack trace from generated code back to .grump lines
+    Symbolicate {
+        /// Source map written next to the generated entry file (e.g. build/android/.../Game.kt.map)
+        map: PathBuf,
+        
+        /// File containing the stack trace (reads stdin when omitted)
+        trace: Option<PathBuf>,
+    },
+    
+    /// Export animations and timelines for use outside a game build
+    Export {
+        /// Source file
+        input: PathBuf,
+        
+        /// Export format (lottie, css, waapi, spritesheet, gif)
+        #[arg(short, long, default_value = "lottie")]
+        format: String,
+        
+        /// Output directory (defaults to build/export)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        
+        /// Only export the animation or timeline with this name
+        #[arg(short, long)]
+        animation: Option<String>,
+        
+        /// Frame rate (defaults to the app's @fps)
+        #[arg(long)]
+        fps: Option<f64>,
+        
+        /// Frame size for rendered formats, e.g. 256x256
+        #[arg(long)]
+        size: Option<String>,
+        
+        /// Record this scene running (gif only)
+        #[arg(long)]
+        scene: Option<String>,
+        
+        /// Number of frames to record with --scene
+        #[arg(long)]
+        frames: Option<usize>,
+    },
+    
     /// Check code without building
     Check {
         /// Source file or directory
         input: PathBuf,
     },
     
+    /// Run the `test` blocks in a project
+    Test {
+        /// Project directory or source file
+        #[arg(default_value = ".")]
+        input: PathBuf,
+        
+        /// Only run tests whose name contains this
+        #[arg(long)]
+        filter: Option<String>,
+        
+        /// Report format (human, junit)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+        
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Resolve the package's dependencies and write grump.lock
+    Fetch {
+        /// Project directory
+        #[arg(default_value = ".")]
+        project: PathBuf,
+        
+        /// Registry directory for version-only dependencies (default: $GRUMP_REGISTRY)
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    
+    /// Add a dependency to the package declaration and fetch it
+    Add {
+        /// Package name
+        name: String,
+        
+        /// Version requirement (e.g. 1.2 for ^1.2)
+        #[arg(long = "version")]
+        version_req: Option<String>,
+        
+        /// Local directory, relative to the project
+        #[arg(long)]
+        path: Option<String>,
+        
+        /// Git repository (a local path or file:// URL)
+        #[arg(long)]
+        git: Option<String>,
+        
+        /// Git branch to follow
+        #[arg(long)]
+        branch: Option<String>,
+        
+        /// Features to enable, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+        
+        /// Add it to dev_dependencies
+        #[arg(long)]
+        dev: bool,
+        
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        project: PathBuf,
+        
+        /// Registry directory for version-only dependencies (default: $GRUMP_REGISTRY)
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    
+    /// Publish the package to a directory registry
+    Publish {
+        /// Project directory
+        #[arg(default_value = ".")]
+        project: PathBuf,
+        
+        /// Registry directory, created if missing (default: $GRUMP_REGISTRY)
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    
+    /// Run the language server on stdio (for editors)
+    Lsp,
+    
+    /// Evaluate expressions, declarations and animation previews interactively
+    Repl {
+        /// Source file whose declarations are loaded first
+        input: Option<PathBuf>,
+    },
+    
     /// Format G-Rump code
     Format {
         /// Source file or directory
@@ -89,15 +224,54 @@ fn main() -> GrumpResult<()> {
         Commands::Init { name } => {
             init_project(&name)?;
         }
-        Commands::Build { input, target, output, optimization } => {
-            build_project(&input, &target, output.as_ref(), &optimization)?;
+        Commands::Build { input, target, output, optimization, runtime } => {
+            build_project(&input, target.as_deref(), output.as_ref(), &optimization, runtime.as_ref())?;
         }
         Commands::Run { input, target } => {
             run_project(&input, &target)?;
         }
+        Commands::Symbolicate { map, trace } => {
+            symbolicate_trace(&map, trace.as_ref())?;
+        }
+        Commands::Export { input, format, output, animation, fps, size, scene, frames } => {
+            let options = grump_compiler::export::ExportOptions { fps, only: animation, scene, frames, ..Default::default() };
+            export_animations(&input, &format, output.as_ref(), size.as_deref(), options)?;
+        }
         Commands::Check { input } => {
             check_project(&input)?;
         }
+        Commands::Test { input, filter, format, output } => {
+            run_tests(&input, filter.as_deref(), &format, output.as_ref())?;
+        }
+        Commands::Fetch { project, registry } => {
+            let lock = grump_compiler::package::fetch(&project, registry_dir(registry).as_deref())?;
+            print_lock(&lock);
+        }
+        Commands::Add { name, version_req, path, git, branch, features, dev, project, registry } => {
+            let dependency = grump_compiler::parser::extensions::Dependency {
+                name,
+                version: version_req,
+                path,
+                git,
+                branch,
+                features,
+            };
+            let lock = grump_compiler::package::add(&project, &dependency, dev, registry_dir(registry).as_deref())?;
+            print_lock(&lock);
+        }
+        Commands::Publish { project, registry } => {
+            let registry = registry_dir(registry).ok_or_else(|| {
+                grump_compiler::GrumpError::Other(anyhow::anyhow!("No registry: pass --registry or set GRUMP_REGISTRY"))
+            })?;
+            let release = grump_compiler::package::publish(&project, &registry)?;
+            println!("✅ Published {} to {} (sha256 {})", release.version, registry.display(), release.checksum);
+        }
+        Commands::Lsp => {
+            grump_compiler::lsp::run()?;
+        }
+        Commands::Repl { input } => {
+            run_repl(input.as_ref())?;
+        }
         Commands::Format { input } => {
             format_project(&input)?;
         }
@@ -128,13 +302,8 @@ fn init_project(name: &str) -> GrumpResult<()> {
     std::fs::create_dir_all(format!("{}/build", name))?;
     
     // Create manifest
-    let manifest = format!(r#"{{
-    "name": "{}",
-    "version": "0.1.0",
-    "targets": ["ios", "android", "web", "flutter"],
-    "fps": 60
-}}"#, name);
-    std::fs::write(format!("{}/grump.manifest", name), manifest)?;
+    let manifest = grump_compiler::manifest::Manifest::new(name);
+    std::fs::write(format!("{}/{}", name, grump_compiler::manifest::MANIFEST_FILE), manifest.to_json())?;
     
     // Create main.grump
     let main_code = r#"app MyApp {
@@ -154,25 +323,39 @@ fn init_project(name: &str) -> GrumpResult<()> {
     std::fs::write(format!("{}/src/main.grump", name), main_code)?;
     
     // Create .gitignore
-    let gitignore = "build/\ntarget/\n*.swift\n*.kt\n*.js\n*.dart\n";
+    let gitignore = "build/\ntarget/\n.grump/\n*.swift\n*.kt\n*.js\n*.dart\n";
     std::fs::write(format!("{}/.gitignore", name), gitignore)?;
     
     println!("✓ Project initialized!");
     Ok(())
 }
 
-fn build_project(input: &PathBuf, target: &str, output: Option<&PathBuf>, optimization: &str) -> GrumpResult<()> {
-    println!("🐸 G-Rump: Building for {}...", target);
+fn build_project(input: &PathBuf, target: Option<&str>, output: Option<&PathBuf>, optimization: &str, runtime: Option<&PathBuf>) -> GrumpResult<()> {
+    use grump_compiler::manifest::{BuildPlan, Manifest};
     
-    // Read source file
-    let source = std::fs::read_to_string(input)?;
+    // Read and parse every file of the project, resolving `use`s
+    let project = grump_compiler::loader::Project::load(input)?;
+    let mut program = project.program();
     
-    // Parse
-    let mut parser = grump_compiler::parser::Parser::new(&source);
-    let mut program = parser.parse()?;
+    // Reconcile --target, grump.manifest and the app's @target list
+    let manifest = match Manifest::find(input) {
+        Some(path) => Some(Manifest::load(&path)?),
+        None => None,
+    };
+    let plan = BuildPlan::resolve(target, manifest.as_ref(), &program)?;
+    for warning in &plan.warnings {
+        println!("   ⚠ {}", warning);
+    }
+    plan.apply(&mut program);
     
-    // Type check
+    let names: Vec<&str> = plan.targets.iter().map(|t| t.name()).collect();
+    println!("🐸 G-Rump: Building for {}...", names.join(", "));
+    
+    // Load plugins, then type check with their built-in functions
+    let project_root = project_dir(input);
+    let plugins = grump_compiler::plugin::PluginHost::load(&program, project_root)?;
     let mut analyzer = grump_compiler::analyzer::Analyzer::new();
+    analyzer.add_plugin_functions(&plugins);
     analyzer.analyze(&program)?;
     
     // Optimize
@@ -185,38 +368,118 @@ fn build_project(input: &PathBuf, target: &str, output: Option<&PathBuf>, optimi
     let mut optimizer = grump_compiler::optimizer::Optimizer::new(opt_level);
     optimizer.optimize(&mut program)?;
     
-    // Generate code
-    let codegen_target = match target {
-        "ios" => grump_compiler::codegen::Target::Ios,
-        "android" => grump_compiler::codegen::Target::Android,
-        "web" => grump_compiler::codegen::Target::Web,
-        "flutter" => grump_compiler::codegen::Target::Flutter,
-        _ => grump_compiler::codegen::Target::Ios,
-    };
-    let mut codegen = grump_compiler::codegen::CodeGenerator::new(codegen_target);
-    let generated_code = codegen.generate(&program)?;
-    
-    // Write output
-    let output_path = output.map(|p| p.clone()).unwrap_or_else(|| {
-        PathBuf::from("build").join(target)
-    });
-    std::fs::create_dir_all(&output_path)?;
-    
-    // Write appropriate file extension based on target
-    let filename = match target {
-        "ios" => "main.swift",
-        "android" => "main.kt",
-        "web" => "main.js",
-        "flutter" => "main.dart",
-        _ => "main.swift",
+    // With several targets, each gets its own subdirectory of --output
+    let output_root = output.cloned().unwrap_or_else(|| PathBuf::from("build"));
+    let single_output = output.filter(|_| plan.targets.len() == 1).cloned();
+    
+    // Generate every target in parallel, then run plugin codegen hooks and write each
+    // Source maps point into the file each item came from
+    let program = &program;
+    let sources: Vec<(String, Option<String>)> = project.sources().into_iter()
+        .map(|f| (f.path.canonicalize().unwrap_or_else(|_| f.path.clone()).display().to_string(), Some(f.source.clone())))
+        .collect();
+    let sources = &sources;
+    // Written into the generated Cargo.toml, which lives elsewhere
+    let runtime = runtime.map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()).display().to_string());
+    let runtime = &runtime;
+    let results: Vec<(&str, GrumpResult<PathBuf>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = plan.targets.iter().map(|&codegen_target| {
+            let output_path = single_output.clone()
+                .unwrap_or_else(|| output_root.join(codegen_target.name()));
+            let handle = scope.spawn(move || -> GrumpResult<grump_compiler::codegen::OutputTree> {
+                let mut codegen = grump_compiler::codegen::CodeGenerator::new(codegen_target)
+                    .with_sources(sources.clone());
+                if let Some(runtime) = runtime {
+                    codegen = codegen.with_rust_runtime(runtime.clone());
+                }
+                let mut tree = codegen.generate(program)?;
+                
+                // Bundle project assets into the target's asset directory
+                copy_assets(&project_root.join("assets"), "", &mut tree)?;
+                Ok(tree)
+            });
+            (output_path, handle)
+        }).collect();
+        plan.targets.iter().zip(handles).map(|(t, (output_path, handle))| {
+            let result = handle.join().unwrap_or_else(|_| {
+                Err(grump_compiler::GrumpError::Other(anyhow::anyhow!("codegen panicked")))
+            });
+            (t.name(), result.map(|tree| (output_path, tree)))
+        }).collect::<Vec<_>>()
+    }).into_iter().map(|(name, result)| {
+        let result = result.and_then(|(output_path, mut tree)| {
+            plugins.transform(name, &mut tree)?;
+            // Write output (replaces the previous build in one step)
+            tree.write_atomic(&output_path)?;
+            Ok(output_path)
+        });
+        (name, result)
+    }).collect();
+    
+    let mut failed = Vec::new();
+    for (name, result) in results {
+        match result {
+            Ok(path) => println!("✓ {} build complete! Output: {}", name, path.display()),
+            Err(e) => {
+                eprintln!("✗ {} build failed: {}", name, e);
+                failed.push(name);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(grump_compiler::GrumpError::Other(anyhow::anyhow!(
+            "Build failed for: {}", failed.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// The directory a project's `assets/` and `build/` are in
+fn project_dir(input: &std::path::Path) -> &std::path::Path {
+    if input.is_dir() {
+        return input;
+    }
+    match input.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    }
+}
+
+fn copy_assets(dir: &std::path::Path, prefix: &str, tree: &mut grump_compiler::codegen::OutputTree) -> GrumpResult<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        if entry.file_type()?.is_dir() {
+            copy_assets(&entry.path(), &relative, tree)?;
+        } else {
+            tree.add_asset(&relative, std::fs::read(entry.path())?);
+        }
+    }
+    Ok(())
+}
+
+fn symbolicate_trace(map: &PathBuf, trace: Option<&PathBuf>) -> GrumpResult<()> {
+    use std::io::Read;
+    
+    let map = grump_compiler::codegen::SourceMap::from_json(&std::fs::read_to_string(map)?)?;
+    let trace = match trace {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        }
     };
-    std::fs::write(output_path.join(filename), generated_code)?;
-    
-    println!("✓ Build complete! Output: {}", output_path.display());
+    print!("{}", map.symbolicate(&trace));
     Ok(())
 }
 
 fn run_project(input: &PathBuf, target: &str) -> GrumpResult<()> {
+    grump_compiler::codegen::Target::from_name(target)?;
     println!("🐸 G-Rump: Running on {}...", target);
     
     // TODO: Implement dev server / simulator
@@ -224,21 +487,133 @@ fn run_project(input: &PathBuf, target: &str) -> GrumpResult<()> {
     Ok(())
 }
 
+fn export_animations(input: &PathBuf, format: &str, output: Option<&PathBuf>, size: Option<&str>, mut options: grump_compiler::export::ExportOptions) -> GrumpResult<()> {
+    use grump_compiler::export::ExportFormat;
+    
+    let format = ExportFormat::from_name(format).ok_or_else(|| {
+        grump_compiler::GrumpError::Other(anyhow::anyhow!("Unknown export format '{}'", format))
+    })?;
+    println!("🐸 G-Rump: Exporting {}...", format.name());
+    
+    let program = grump_compiler::loader::Project::load(input)?.program();
+    
+    options.size = match size {
+        Some(size) => {
+            let parsed = size.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+            Some(parsed.ok_or_else(|| {
+                grump_compiler::GrumpError::Other(anyhow::anyhow!("Expected --size WIDTHxHEIGHT, got '{}'", size))
+            })?)
+        }
+        None => None,
+    };
+    options.assets = Some(project_dir(input).join("assets"));
+    let tree = grump_compiler::export::export(&program, &format, &options)?;
+    let output_path = output.cloned().unwrap_or_else(|| PathBuf::from("build/export"));
+    tree.write_atomic(&output_path)?;
+    println!("✓ Export complete! Output: {}", output_path.display());
+    Ok(())
+}
+
 fn check_project(input: &PathBuf) -> GrumpResult<()> {
     println!("🐸 G-Rump: Checking code...");
     
-    // Read source file
-    let source = std::fs::read_to_string(input)?;
+    let project = grump_compiler::loader::Project::load(input)?;
+    let program = project.program();
+    let plugins = grump_compiler::plugin::PluginHost::load(&program, project_dir(input))?;
+    if let ([file], true) = (project.files.as_slice(), plugins.is_empty()) {
+        // Parse and type check, reusing whatever the last check found for unchanged items
+        let cache = project_dir(input).join("build/.cache/check.json");
+        let mut db = grump_compiler::query::Database::load(&cache);
+        let name = file.path.display().to_string();
+        db.set_source(&name, &file.source);
+        let result = db.check(&name);
+        if let Err(e) = db.save(&cache) {
+            eprintln!("   ⚠ Couldn't save the check cache: {}", e);
+        }
+        result?;
+    } else {
+        let mut analyzer = grump_compiler::analyzer::Analyzer::new();
+        analyzer.add_plugin_functions(&plugins);
+        analyzer.analyze(&program)?;
+    }
     
-    // Parse
-    let mut parser = grump_compiler::parser::Parser::new(&source);
-    let program = parser.parse()?;
+    println!("✓ No errors found!");
+    Ok(())
+}
+
+fn run_tests(input: &PathBuf, filter: Option<&str>, format: &str, output: Option<&PathBuf>) -> GrumpResult<()> {
+    let report = grump_compiler::testing::run_project(input, filter)?;
+    let text = match format {
+        "human" => report.human(),
+        "junit" => report.junit(),
+        _ => {
+            return Err(grump_compiler::GrumpError::Other(anyhow::anyhow!(
+                "Unknown report format '{}' (expected human or junit)", format
+            )));
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, &text)?;
+            println!("{} passed, {} failed. Report: {}", report.passed(), report.failed(), path.display());
+        }
+        None => print!("{}", text),
+    }
     
-    // Type check
-    let mut analyzer = grump_compiler::analyzer::Analyzer::new();
-    analyzer.analyze(&program)?;
+    if !report.success() {
+        return Err(grump_compiler::GrumpError::Other(anyhow::anyhow!(
+            "{} of {} tests failed", report.failed(), report.results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// `--registry`, or `GRUMP_REGISTRY` without it
+fn registry_dir(flag: Option<PathBuf>) -> Option<PathBuf> {
+    flag.or_else(|| std::env::var_os("GRUMP_REGISTRY").map(PathBuf::from))
+}
+
+fn print_lock(lock: &grump_compiler::package::Lockfile) {
+    for package in &lock.packages {
+        println!("   {} {} ({})", package.name, package.version, package.source);
+    }
+    println!("✅ Locked {} package{} in {}", lock.packages.len(), if lock.packages.len() == 1 { "" } else { "s" }, grump_compiler::package::LOCK_FILE);
+}
+
+fn run_repl(input: Option<&PathBuf>) -> GrumpResult<()> {
+    use grump_compiler::repl::{editor::LineEditor, Repl};
     
-    println!("✓ No errors found!");
+    let mut repl = Repl::new();
+    if let Some(input) = input {
+        repl.load(&std::fs::read_to_string(input)?)?;
+        println!("Loaded {}", input.display());
+    }
+    println!("🐸 G-Rump REPL. Type :help for commands, :quit to leave.");
+    
+    let mut editor = LineEditor::new()?;
+    let mut pending = String::new();
+    loop {
+        let prompt = if pending.is_empty() { "grump> " } else { "  ...> " };
+        let line = match editor.read_line(prompt)? {
+            Some(line) => line,
+            None => break,
+        };
+        editor.add_history(&line);
+        pending.push_str(&line);
+        pending.push('\n');
+        if !Repl::is_complete(&pending) {
+            continue;
+        }
+        let input = std::mem::take(&mut pending);
+        if matches!(input.trim(), ":quit" | ":q" | ":exit") {
+            break;
+        }
+        match repl.eval(&input) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("{}", e.format_with_personality()),
+        }
+    }
     Ok(())
 }
 
@@ -266,15 +641,13 @@ fn format_project(input: &PathBuf) -> GrumpResult<()> {
 fn lint_project(input: &PathBuf) -> GrumpResult<()> {
     println!("🐸 G-Rump: Linting (prepare for brutal honesty)...");
     
-    // Read source file
-    let source = std::fs::read_to_string(input)?;
-    
-    // Parse
-    let mut parser = grump_compiler::parser::Parser::new(&source);
-    let program = parser.parse()?;
+    // Read and parse every file of the project
+    let program = grump_compiler::loader::Project::load(input)?.program();
+    let plugins = grump_compiler::plugin::PluginHost::load(&program, project_dir(input))?;
     
     // Analyze (type checking)
     let mut analyzer = grump_compiler::analyzer::Analyzer::new();
+    analyzer.add_plugin_functions(&plugins);
     analyzer.analyze(&program)?;
     
     // Report errors with G-Rump personality
@@ -286,6 +659,15 @@ fn lint_project(input: &PathBuf) -> GrumpResult<()> {
         return Err(analyzer.errors.remove(0));
     }
     
+    // Plugins' lint rules only warn
+    let warnings = plugins.lint(&program)?;
+    if !warnings.is_empty() {
+        println!("\n⚠ Plugins have {} complaint(s):", warnings.len());
+        for warning in &warnings {
+            println!("   {}", warning);
+        }
+    }
+    
     println!("✓ No errors! (G-Rump is surprised but won't admit it)");
     Ok(())
 }
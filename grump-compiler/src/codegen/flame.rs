@@ -0,0 +1,534 @@
+//! Flutter + Flame Code Generator
+//!
+//! Generates a runnable Flame game project from G-Rump AST: a `FlameGame`
+//! subclass, one `Component` per entity, systems as components driven by
+//! `update(dt)`, and animations built from Flame effects.
+
+use crate::parser::{Program, EntityDeclaration, SystemDeclaration, Keyframe, Expression, Literal, LoopMode};
+use crate::error::GrumpResult;
+use super::{CodeGenerator, OutputTree, duration_seconds, pascal_case, snake_case};
+use super::project::ProgramItems;
+
+/// Flame version the generated `pubspec.yaml` depends on
+const FLAME_VERSION: &str = "^1.18.0";
+
+pub struct FlameCodegen;
+
+impl FlameCodegen {
+    /// Generate the whole Flutter project (paths are relative to the project root)
+    pub fn generate_project(gen: &CodeGenerator, program: &Program) -> GrumpResult<OutputTree> {
+        let items = ProgramItems::collect(program);
+        let app_name = items.app.map(|a| a.name.clone()).unwrap_or_else(|| "GrumpGame".to_string());
+        let game_class = format!("{}Game", pascal_case(&app_name));
+
+        let mut files = OutputTree::new();
+        files.add_text("pubspec.yaml", Self::generate_pubspec(&app_name));
+        files.add_text("lib/main.dart", Self::generate_main(&game_class));
+        files.add_text("lib/grump/runtime.dart", Self::generate_runtime());
+        files.add_text("lib/game.dart", Self::generate_game(gen, &items, &game_class)?);
+        files.add_text("lib/components.dart", Self::generate_components(gen, &items)?);
+        files.add_text("lib/animations.dart", Self::generate_animations(gen, &items)?);
+
+        for entity in &items.entities {
+            files.add_text(
+                format!("lib/entities/{}.dart", snake_case(&entity.name)),
+                Self::generate_entity(gen, &items, entity, &game_class)?,
+            );
+        }
+        for system in &items.systems {
+            files.add_text(
+                format!("lib/systems/{}.dart", snake_case(&system.name)),
+                Self::generate_system(gen, &items, system, &game_class)?,
+            );
+        }
+
+        files.add_text("assets/images/.keep", "");
+        files.set_entry("lib/game.dart");
+        files.set_assets_dir("assets/images");
+        Ok(files)
+    }
+
+    fn generate_pubspec(app_name: &str) -> String {
+        let mut output = String::new();
+        output.push_str("# Generated by G-Rump\n");
+        output.push_str(&format!("name: {}\n", snake_case(app_name)));
+        output.push_str(&format!("description: {}\n", app_name));
+        output.push_str("publish_to: 'none'\n");
+        output.push_str("version: 1.0.0+1\n\n");
+        output.push_str("environment:\n");
+        output.push_str("  sdk: '>=3.0.0 <4.0.0'\n\n");
+        output.push_str("dependencies:\n");
+        output.push_str("  flutter:\n");
+        output.push_str("    sdk: flutter\n");
+        output.push_str(&format!("  flame: {}\n\n", FLAME_VERSION));
+        output.push_str("flutter:\n");
+        output.push_str("  assets:\n");
+        output.push_str("    - assets/images/\n");
+        output
+    }
+
+    fn generate_main(game_class: &str) -> String {
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/game.dart';\n");
+        output.push_str("import 'package:flutter/widgets.dart';\n\n");
+        output.push_str("import 'game.dart';\n\n");
+        output.push_str("void main() {\n");
+        output.push_str(&format!("  runApp(GameWidget(game: {}()));\n", game_class));
+        output.push_str("}\n");
+        output
+    }
+
+    /// Small support library shared by the generated entities and systems
+    fn generate_runtime() -> String {
+        let mut output = String::new();
+        output.push_str("// G-Rump runtime support for Flame\n");
+        output.push_str("import 'dart:async';\n\n");
+        output.push_str("import 'package:flame/components.dart';\n");
+        output.push_str("import 'package:flame/effects.dart';\n");
+        output.push_str("import 'package:flutter/animation.dart';\n\n");
+        output.push_str("/// Entities carry their G-Rump components in a type-keyed map.\n");
+        output.push_str("mixin GrumpEntity on Component {\n");
+        output.push_str("  final Map<Type, Object> components = {};\n\n");
+        output.push_str("  T? get<T extends Object>() => components[T] as T?;\n\n");
+        output.push_str("  void set<T extends Object>(T component) => components[T] = component;\n\n");
+        output.push_str("  bool hasAll(List<Type> types) => types.every(components.containsKey);\n");
+        output.push_str("}\n\n");
+        output.push_str("/// All entities under [root] that have every component in [types].\n");
+        output.push_str("Iterable<GrumpEntity> queryEntities(Component root, List<Type> types) {\n");
+        output.push_str("  return root.descendants().whereType<GrumpEntity>().where((e) => e.hasAll(types));\n");
+        output.push_str("}\n\n");
+        output.push_str("class GrumpKeyframe {\n");
+        output.push_str("  final double time;\n");
+        output.push_str("  final Object value;\n");
+        output.push_str("  final Curve curve;\n\n");
+        output.push_str("  const GrumpKeyframe(this.time, this.value, [this.curve = Curves.linear]);\n");
+        output.push_str("}\n\n");
+        output.push_str("/// Build a single effect that moves [property] from keyframe to keyframe.\n");
+        output.push_str("Effect keyframeEffect(\n");
+        output.push_str("  String property,\n");
+        output.push_str("  List<GrumpKeyframe> keyframes, {\n");
+        output.push_str("  bool infinite = false,\n");
+        output.push_str("  bool alternate = false,\n");
+        output.push_str("}) {\n");
+        output.push_str("  final effects = <Effect>[];\n");
+        output.push_str("  var previous = 0.0;\n");
+        output.push_str("  for (final kf in keyframes) {\n");
+        output.push_str("    // Flame controllers need a non-zero duration\n");
+        output.push_str("    final duration = (kf.time - previous).clamp(0.0001, double.infinity);\n");
+        output.push_str("    final controller = EffectController(duration: duration, curve: kf.curve);\n");
+        output.push_str("    effects.add(_propertyEffect(property, kf.value, controller));\n");
+        output.push_str("    previous = kf.time;\n");
+        output.push_str("  }\n");
+        output.push_str("  return SequenceEffect(effects, infinite: infinite, alternate: alternate);\n");
+        output.push_str("}\n\n");
+        output.push_str("Effect _propertyEffect(String property, Object value, EffectController controller) {\n");
+        output.push_str("  switch (property) {\n");
+        output.push_str("    case 'position':\n");
+        output.push_str("      return MoveEffect.to(_vector(value), controller);\n");
+        output.push_str("    case 'scale':\n");
+        output.push_str("      return ScaleEffect.to(_vector(value), controller);\n");
+        output.push_str("    case 'size':\n");
+        output.push_str("      return SizeEffect.to(_vector(value), controller);\n");
+        output.push_str("    case 'rotation':\n");
+        output.push_str("    case 'angle':\n");
+        output.push_str("      return RotateEffect.to((value as num).toDouble(), controller);\n");
+        output.push_str("    case 'opacity':\n");
+        output.push_str("      return OpacityEffect.to((value as num).toDouble(), controller);\n");
+        output.push_str("    default:\n");
+        output.push_str("      throw ArgumentError('G-Rump: cannot animate \"$property\" with Flame effects');\n");
+        output.push_str("  }\n");
+        output.push_str("}\n\n");
+        output.push_str("Vector2 _vector(Object value) {\n");
+        output.push_str("  if (value is Vector2) return value;\n");
+        output.push_str("  if (value is List) return Vector2((value[0] as num).toDouble(), (value[1] as num).toDouble());\n");
+        output.push_str("  final v = (value as num).toDouble();\n");
+        output.push_str("  return Vector2.all(v);\n");
+        output.push_str("}\n\n");
+        output.push_str("/// Game time, and the futures waiting on it; the game adds one in `onLoad`.\n");
+        output.push_str("class GrumpClock extends Component {\n");
+        output.push_str("  static double elapsed = 0;\n");
+        output.push_str("  static final Map<String, double> _animations = {};\n");
+        output.push_str("  static final List<(bool Function(), Completer<void>)> _waiters = [];\n\n");
+        output.push_str("  /// Record an `animate` of the target keyed [key] that runs for [seconds].\n");
+        output.push_str("  static void animating(String key, double seconds) {\n");
+        output.push_str("    final end = elapsed + seconds;\n");
+        output.push_str("    _animations[key] = end > (_animations[key] ?? 0) ? end : _animations[key]!;\n");
+        output.push_str("  }\n\n");
+        output.push_str("  static bool isAnimating(String key) => (_animations[key] ?? 0) > elapsed;\n\n");
+        output.push_str("  static Future<void> until(bool Function() ready) {\n");
+        output.push_str("    if (ready()) return Future.value();\n");
+        output.push_str("    final completer = Completer<void>();\n");
+        output.push_str("    _waiters.add((ready, completer));\n");
+        output.push_str("    return completer.future;\n");
+        output.push_str("  }\n\n");
+        output.push_str("  @override\n");
+        output.push_str("  void update(double dt) {\n");
+        output.push_str("    super.update(dt);\n");
+        output.push_str("    elapsed += dt;\n");
+        output.push_str("    final ready = _waiters.where((w) => w.$1()).toList();\n");
+        output.push_str("    _waiters.removeWhere((w) => ready.contains(w));\n");
+        output.push_str("    for (final (_, completer) in ready) {\n");
+        output.push_str("      completer.complete();\n");
+        output.push_str("    }\n");
+        output.push_str("  }\n");
+        output.push_str("}\n\n");
+        output.push_str("/// Completes on the first frame [seconds] of game time from now.\n");
+        output.push_str("Future<void> wait(double seconds) {\n");
+        output.push_str("  final end = GrumpClock.elapsed + seconds;\n");
+        output.push_str("  return GrumpClock.until(() => GrumpClock.elapsed >= end);\n");
+        output.push_str("}\n\n");
+        output.push_str("/// Completes once no `animate` of the target keyed [key] is running.\n");
+        output.push_str("Future<void> animation_finished(String key) {\n");
+        output.push_str("  return GrumpClock.until(() => !GrumpClock.isAnimating(key));\n");
+        output.push_str("}\n");
+        output
+    }
+
+    fn generate_game(gen: &CodeGenerator, items: &ProgramItems, game_class: &str) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/components.dart';\n");
+        output.push_str("import 'package:flame/game.dart';\n\n");
+        output.push_str("import 'animations.dart';\n");
+        output.push_str("import 'components.dart';\n");
+        output.push_str("import 'grump/runtime.dart';\n");
+        for entity in &items.entities {
+            output.push_str(&format!("import 'entities/{}.dart';\n", snake_case(&entity.name)));
+        }
+        for system in &items.systems {
+            output.push_str(&format!("import 'systems/{}.dart';\n", snake_case(&system.name)));
+        }
+        output.push('\n');
+
+        // The first scene becomes the game's world; without one we use an empty world
+        let world_class = items.scenes.first()
+            .map(|scene| format!("{}Scene", pascal_case(&scene.name)))
+            .unwrap_or_else(|| "World".to_string());
+
+        if let Some(directive) = items.app.and_then(|a| gen.line_directive(a.span)) {
+            output.push_str(&directive);
+        }
+        output.push_str(&format!("class {} extends FlameGame {{\n", game_class));
+        output.push_str(&format!("  {}() : super(world: {}());\n\n", game_class, world_class));
+        output.push_str("  @override\n");
+        output.push_str("  Future<void> onLoad() async {\n");
+        if let Some(fps) = items.app.and_then(|a| a.fps) {
+            output.push_str(&format!("    // Target FPS: {}\n", fps));
+        }
+        output.push_str("    await add(GrumpClock());\n");
+        if items.scenes.is_empty() {
+            for entity in &items.entities {
+                output.push_str(&format!("    await world.add({}());\n", pascal_case(&entity.name)));
+            }
+        }
+        for system in &items.systems {
+            output.push_str(&format!("    await add({}System());\n", pascal_case(&system.name)));
+        }
+        output.push_str("  }\n");
+        output.push_str("}\n\n");
+
+        for scene in &items.scenes {
+            if let Some(directive) = gen.line_directive(scene.span) {
+                output.push_str(&directive);
+            }
+            output.push_str(&format!("class {}Scene extends World with HasGameReference<{}> {{\n", pascal_case(&scene.name), game_class));
+            output.push_str("  @override\n");
+            output.push_str("  Future<void> onLoad() async {\n");
+            for entity in &items.entities {
+                output.push_str(&format!("    await add({}());\n", pascal_case(&entity.name)));
+            }
+            for stmt in &scene.body {
+                output.push_str("    ");
+                output.push_str(&gen.generate_dart_statement(stmt)?);
+                output.push('\n');
+            }
+            output.push_str("  }\n");
+            output.push_str("}\n\n");
+        }
+
+        // Free functions live next to the game so scenes and systems can call them
+        for func in &items.functions {
+            if let Some(directive) = gen.line_directive(func.span) {
+                output.push_str(&directive);
+            }
+            output.push_str(&gen.generate_dart_function(func)?);
+        }
+
+        Ok(output)
+    }
+
+    fn generate_components(gen: &CodeGenerator, items: &ProgramItems) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/components.dart';\n\n");
+        for comp in &items.components {
+            if let Some(directive) = gen.line_directive(comp.span) {
+                output.push_str(&directive);
+            }
+            output.push_str(&format!("class {} {{\n", comp.name));
+            for field in &comp.fields {
+                output.push_str(&format!("  {} {};\n", Self::flame_type(gen, &field.type_), field.name));
+            }
+            output.push('\n');
+            if comp.fields.is_empty() {
+                output.push_str(&format!("  {}();\n", comp.name));
+            } else {
+                output.push_str(&format!("  {}({{\n", comp.name));
+                for field in &comp.fields {
+                    output.push_str("    ");
+                    match &field.default {
+                        Some(default) => {
+                            output.push_str(&format!("this.{} = {},\n", field.name, Self::flame_expression(gen, default)?));
+                        }
+                        None => {
+                            output.push_str(&format!("required this.{},\n", field.name));
+                        }
+                    }
+                }
+                output.push_str("  });\n");
+            }
+            output.push_str("}\n\n");
+        }
+        Ok(output)
+    }
+
+    fn generate_entity(gen: &CodeGenerator, items: &ProgramItems, entity: &EntityDeclaration, game_class: &str) -> GrumpResult<String> {
+        let class_name = pascal_case(&entity.name);
+        let sprite = entity.components.iter().find(|c| c.name == "sprite");
+
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/components.dart';\n\n");
+        output.push_str("import '../animations.dart';\n");
+        output.push_str("import '../components.dart';\n");
+        output.push_str("import '../game.dart';\n");
+        output.push_str("import '../grump/runtime.dart';\n\n");
+
+        let base = if sprite.is_some() { "SpriteComponent" } else { "PositionComponent" };
+        if let Some(directive) = gen.line_directive(entity.span) {
+            output.push_str(&directive);
+        }
+        output.push_str(&format!(
+            "class {} extends {} with GrumpEntity, HasGameReference<{}> {{\n",
+            class_name, base, game_class
+        ));
+        output.push_str("  @override\n");
+        output.push_str("  Future<void> onLoad() async {\n");
+        for comp in &entity.components {
+            let args = comp.args.iter()
+                .map(|arg| Self::flame_expression(gen, arg))
+                .collect::<GrumpResult<Vec<_>>>()?;
+            match (comp.name.as_str(), items.component(&comp.name)) {
+                (_, Some(decl)) => {
+                    // Declared component: positional args map onto its fields in order
+                    let named: Vec<String> = decl.fields.iter().zip(args.iter())
+                        .map(|(field, arg)| format!("{}: {}", field.name, arg))
+                        .collect();
+                    output.push_str(&format!("    set({}({}));\n", decl.name, named.join(", ")));
+                }
+                ("sprite", None) => {
+                    if let Some(path) = args.first() {
+                        output.push_str(&format!("    sprite = await game.loadSprite({});\n", path));
+                    }
+                }
+                ("position", None) | ("size", None) | ("scale", None) => {
+                    output.push_str(&format!("    {} = {};\n", comp.name, Self::vector_literal(&args)));
+                }
+                ("rotation", None) | ("angle", None) => {
+                    if let Some(angle) = args.first() {
+                        output.push_str(&format!("    angle = {};\n", angle));
+                    }
+                }
+                (name, None) => {
+                    output.push_str(&format!("    // Unknown component '{}' ({})\n", name, args.join(", ")));
+                }
+            }
+        }
+        for stmt in &entity.body {
+            output.push_str("    ");
+            output.push_str(&gen.generate_dart_statement(stmt)?);
+            output.push('\n');
+        }
+        output.push_str("  }\n");
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn generate_system(gen: &CodeGenerator, items: &ProgramItems, system: &SystemDeclaration, game_class: &str) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/components.dart';\n\n");
+        output.push_str("import '../animations.dart';\n");
+        output.push_str("import '../components.dart';\n");
+        output.push_str("import '../game.dart';\n");
+        output.push_str("import '../grump/runtime.dart';\n\n");
+        if let Some(directive) = gen.line_directive(system.span) {
+            output.push_str(&directive);
+        }
+        output.push_str(&format!(
+            "class {}System extends Component with HasGameReference<{}> {{\n",
+            pascal_case(&system.name), game_class
+        ));
+        output.push_str("  @override\n");
+        output.push_str("  void update(double dt) {\n");
+        output.push_str("    super.update(dt);\n");
+        if system.query.is_empty() {
+            for stmt in &system.body {
+                output.push_str("    ");
+                output.push_str(&gen.generate_dart_statement(stmt)?);
+                output.push('\n');
+            }
+        } else {
+            output.push_str(&format!(
+                "    for (final entity in queryEntities(game.world, const [{}])) {{\n",
+                system.query.join(", ")
+            ));
+            // Bind each queried component to a local so the body can use `position.x` etc.
+            for comp in &system.query {
+                let type_name = items.component(comp).map(|c| c.name.clone()).unwrap_or_else(|| comp.clone());
+                output.push_str(&format!(
+                    "      final {} = entity.get<{}>()!;\n",
+                    snake_case(comp), type_name
+                ));
+            }
+            for stmt in &system.body {
+                output.push_str("      ");
+                output.push_str(&gen.generate_dart_statement(stmt)?);
+                output.push('\n');
+            }
+            output.push_str("    }\n");
+        }
+        output.push_str("  }\n");
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn generate_animations(gen: &CodeGenerator, items: &ProgramItems) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("// Generated Flutter + Flame code from G-Rump\n");
+        output.push_str("import 'package:flame/components.dart';\n");
+        output.push_str("import 'package:flame/effects.dart';\n");
+        output.push_str("import 'package:flutter/animation.dart';\n\n");
+        output.push_str("import 'grump/runtime.dart';\n\n");
+
+        for anim in &items.animations {
+            let mut keyframes: Vec<&Keyframe> = anim.keyframes.iter().collect();
+            let (infinite, alternate) = match &anim.loop_mode {
+                Some(LoopMode::Loop) => (true, false),
+                Some(LoopMode::PingPong) => (true, true),
+                Some(LoopMode::Reverse) => {
+                    keyframes.reverse();
+                    (false, false)
+                }
+                _ => (false, false),
+            };
+            let default_property = match anim.keyframes.first().map(|kf| &kf.value) {
+                Some(Expression::Array(values)) | Some(Expression::Tuple(values)) if values.len() == 2 => "position",
+                Some(Expression::Literal(Literal::Vec2 { .. })) => "position",
+                _ => "scale",
+            };
+
+            if let Some(directive) = gen.line_directive(anim.span) {
+                output.push_str(&directive);
+            }
+            output.push_str(&format!("/// Animation `{}`\n", anim.name));
+            output.push_str(&format!(
+                "Effect {}Animation({{String property = '{}'}}) {{\n",
+                Self::camel_case(&anim.name), default_property
+            ));
+            output.push_str("  return keyframeEffect(property, [\n");
+            // Reversed animations play the keyframe values back-to-front on the same timeline
+            let times: Vec<String> = anim.keyframes.iter()
+                .map(|kf| Self::seconds(gen, &kf.time))
+                .collect::<GrumpResult<Vec<_>>>()?;
+            for (time, kf) in times.iter().zip(keyframes.iter()) {
+                output.push_str(&format!(
+                    "    GrumpKeyframe({}, {}, {}),\n",
+                    time,
+                    Self::flame_value(gen, &kf.value)?,
+                    Self::dart_curve(kf.ease_in.as_ref().or(kf.ease_out.as_ref())),
+                ));
+            }
+            output.push_str(&format!("  ], infinite: {}, alternate: {});\n", infinite, alternate));
+            output.push_str("}\n\n");
+        }
+        Ok(output)
+    }
+
+    /// Keyframe times as seconds (durations with units are normalized)
+    fn seconds(gen: &CodeGenerator, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Literal(Literal::Duration { value, unit }) => Ok(format!("{:?}", duration_seconds(*value, unit))),
+            Expression::Literal(Literal::Integer(n)) => Ok(format!("{:?}", *n as f64)),
+            Expression::Literal(Literal::Float(f)) => Ok(format!("{:?}", f)),
+            other => gen.generate_dart_expression(other),
+        }
+    }
+
+    /// Keyframe values: vectors become `Vector2`, everything else goes through the Dart generator
+    fn flame_value(gen: &CodeGenerator, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Array(values) | Expression::Tuple(values) if values.len() == 2 => {
+                let args = values.iter()
+                    .map(|v| Self::flame_expression(gen, v))
+                    .collect::<GrumpResult<Vec<_>>>()?;
+                Ok(Self::vector_literal(&args))
+            }
+            _ => Self::flame_expression(gen, expr),
+        }
+    }
+
+    fn flame_expression(gen: &CodeGenerator, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Literal(Literal::Vec2 { x, y }) => Ok(format!("Vector2({:?}, {:?})", x, y)),
+            _ => gen.generate_dart_expression(expr),
+        }
+    }
+
+    fn vector_literal(args: &[String]) -> String {
+        match args {
+            [x, y, ..] => format!("Vector2({}, {})", x, y),
+            [v] => format!("Vector2.all({})", v),
+            [] => "Vector2.zero()".to_string(),
+        }
+    }
+
+    fn flame_type(gen: &CodeGenerator, type_: &crate::parser::Type) -> String {
+        match type_ {
+            crate::parser::Type::Vec2 => "Vector2".to_string(),
+            crate::parser::Type::Vec3 => "Vector3".to_string(),
+            crate::parser::Type::Named(name) => name.clone(),
+            _ => gen.dart_type(type_),
+        }
+    }
+
+    /// Map G-Rump easing names onto Flutter curves
+    fn dart_curve(ease: Option<&Expression>) -> &'static str {
+        let name = match ease {
+            Some(Expression::Identifier(name)) => name.as_str(),
+            Some(Expression::Literal(Literal::String(name))) => name.as_str(),
+            _ => return "Curves.linear",
+        };
+        match name {
+            "linear" => "Curves.linear",
+            "ease_in" | "in" => "Curves.easeIn",
+            "ease_out" | "out" | "fast_out" => "Curves.easeOut",
+            "smooth" | "ease_in_out" | "in_out" | "sine" => "Curves.easeInOut",
+            "bounce" => "Curves.bounceOut",
+            "elastic" => "Curves.elasticOut",
+            "heavy" => "Curves.easeInCubic",
+            "back" | "overshoot" => "Curves.easeOutBack",
+            _ => "Curves.easeInOut",
+        }
+    }
+
+    fn camel_case(name: &str) -> String {
+        let pascal = pascal_case(name);
+        let mut chars = pascal.chars();
+        match chars.next() {
+            Some(first) => first.to_lowercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+}
@@ -0,0 +1,859 @@
+//! Godot 4 Code Generator
+//!
+//! Generates a Godot 4 project from G-Rump AST: a `.tscn` per scene with a
+//! `Node2D` per entity and a `Node` per system, GDScript for systems and
+//! functions, and `Animation` resources (played through each scene's
+//! `AnimationPlayer`) from animation declarations.
+
+use std::cell::Cell;
+
+use crate::parser::{
+    Program, EntityDeclaration, SystemDeclaration, SceneDeclaration, FunctionDeclaration,
+    ComponentDeclaration, AnimationDeclaration, AnimateStatement, Keyframe, Statement,
+    Expression, Literal, Pattern, Type, BinaryOp, UnaryOp, LoopMode,
+};
+use crate::error::{GrumpError, GrumpResult};
+use crate::expander::derive::{self, Derive};
+use super::{CodeGenerator, OutputTree, Target, angle_radians, duration_seconds, pascal_case, returns_value, snake_case, template_call};
+use super::project::{AppInfo, ProgramItems};
+use super::source_map::located;
+
+/// Godot release the project targets (`when` match guards need 4.3)
+const GODOT_VERSION: &str = "4.3";
+
+/// Helpers provided by the `Grump` autoload (`scripts/grump.gd`)
+const GRUMP_HELPERS: &[&str] = &[
+    "ease_in_out", "concat", "substring", "str_length", "rgb", "rgba", "hsl",
+    "translate", "rotate", "scale", "now", "delta_time",
+];
+
+pub struct GodotCodegen<'a> {
+    gen: &'a CodeGenerator,
+    items: ProgramItems<'a>,
+    /// Counter for tween temporaries
+    tweens: Cell<usize>,
+}
+
+impl<'a> GodotCodegen<'a> {
+    /// Generate the whole Godot project (paths are relative to `res://`)
+    pub fn generate_project(gen: &'a CodeGenerator, program: &'a Program) -> GrumpResult<OutputTree> {
+        let codegen = Self {
+            gen,
+            items: ProgramItems::collect(program),
+            tweens: Cell::new(0),
+        };
+        let app = AppInfo::from_program(program);
+
+        // Godot needs a main scene; programs without one get everything in `Main`
+        let scene_names: Vec<String> = if codegen.items.scenes.is_empty() {
+            vec!["Main".to_string()]
+        } else {
+            codegen.items.scenes.iter().map(|s| s.name.clone()).collect()
+        };
+        let main_scene = format!("scenes/{}.tscn", snake_case(&scene_names[0]));
+
+        let mut files = OutputTree::new();
+        files.add_text("project.godot", Self::generate_project_file(&app, &main_scene));
+        files.add_text(".gitignore", ".godot/\n");
+        files.add_text("scripts/grump.gd", RUNTIME);
+        files.add_text("scripts/types.gd", codegen.generate_types());
+
+        for comp in &codegen.items.components {
+            files.add_text(
+                format!("scripts/components/{}.gd", snake_case(&comp.name)),
+                codegen.generate_component(comp)?,
+            );
+        }
+        for entity in &codegen.items.entities {
+            files.add_text(
+                format!("scripts/entities/{}.gd", snake_case(&entity.name)),
+                codegen.generate_entity(entity)?,
+            );
+        }
+        for system in &codegen.items.systems {
+            files.add_text(
+                format!("scripts/systems/{}.gd", snake_case(&system.name)),
+                codegen.generate_system(system)?,
+            );
+        }
+        for func in &codegen.items.functions {
+            files.add_text(
+                format!("scripts/functions/{}.gd", snake_case(&func.name)),
+                codegen.generate_function(func)?,
+            );
+        }
+        for anim in &codegen.items.animations {
+            files.add_text(
+                format!("animations/{}.tres", snake_case(&anim.name)),
+                codegen.generate_animation(anim)?,
+            );
+        }
+
+        for (i, name) in scene_names.iter().enumerate() {
+            let scene = codegen.items.scenes.get(i).copied();
+            files.add_text(
+                format!("scripts/scenes/{}.gd", snake_case(name)),
+                codegen.generate_scene_script(scene)?,
+            );
+            files.add_text(format!("scenes/{}.tscn", snake_case(name)), codegen.generate_scene(name));
+        }
+
+        files.set_entry(main_scene);
+        files.set_assets_dir("assets");
+        Ok(files)
+    }
+
+    fn generate_project_file(app: &AppInfo, main_scene: &str) -> String {
+        let mut output = String::new();
+        output.push_str("; Engine configuration file.\n");
+        output.push_str("; Generated by G-Rump\n\n");
+        output.push_str("config_version=5\n\n");
+        output.push_str("[application]\n\n");
+        output.push_str(&format!("config/name={}\n", quote(&app.name)));
+        output.push_str(&format!("config/version={}\n", quote(&app.version)));
+        output.push_str(&format!("run/main_scene=\"res://{}\"\n", main_scene));
+        output.push_str(&format!("run/max_fps={}\n", app.fps.round() as i64));
+        output.push_str(&format!("config/features=PackedStringArray(\"{}\")\n\n", GODOT_VERSION));
+        output.push_str("[autoload]\n\n");
+        output.push_str("Grump=\"*res://scripts/grump.gd\"\n\n");
+        output.push_str("[physics]\n\n");
+        output.push_str(&format!("common/physics_ticks_per_second={}\n", app.fps.round() as i64));
+        output
+    }
+
+    /// Enums live on one global class so every script can name them
+    fn generate_types(&self) -> String {
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        output.push_str("class_name GrumpTypes\n");
+        output.push_str("extends RefCounted\n");
+        for enum_decl in &self.items.enums {
+            output.push('\n');
+            self.directive(&mut output, enum_decl.span);
+            output.push_str(&format!("enum {} {{ {} }}\n", enum_decl.name, enum_decl.variants.join(", ")));
+        }
+        output
+    }
+
+    /// Components are resources so they show up in the inspector
+    fn generate_component(&self, comp: &ComponentDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        self.directive(&mut output, comp.span);
+        output.push_str(&format!("class_name {}\n", component_class(&comp.name)));
+        output.push_str("extends Resource\n\n");
+        for field in &comp.fields {
+            let value = match &field.default {
+                Some(default) => self.expression(default)?,
+                None => self.zero_value(&field.type_),
+            };
+            let type_ = self.gd_type(&field.type_);
+            // `Variant` properties can't be exported
+            let export = if type_ == "Variant" { "" } else { "@export " };
+            output.push_str(&format!("{}var {}: {} = {}\n", export, field.name, type_, value));
+        }
+        output.push_str(&self.generate_derives(comp));
+        Ok(output)
+    }
+
+    /// What the component's `@derive(...)` asks for, as methods
+    fn generate_derives(&self, comp: &ComponentDeclaration) -> String {
+        let class = component_class(&comp.name);
+        let mut output = String::new();
+        if derive::has(comp, Derive::Serialize) {
+            let entries: Vec<String> = comp.fields.iter().map(|f| format!("{}: {}", quote(&f.name), f.name)).collect();
+            output.push_str("\nfunc serialize() -> String:\n");
+            output.push_str(&format!("\treturn var_to_str({{{}}})\n", entries.join(", ")));
+            output.push_str(&format!("\nstatic func deserialize(text: String) -> {}:\n", class));
+            output.push_str("\tvar data: Dictionary = str_to_var(text)\n");
+            output.push_str(&format!("\tvar value := {}.new()\n", class));
+            for field in &comp.fields {
+                output.push_str(&format!("\tvalue.{} = data.get({}, value.{})\n", field.name, quote(&field.name), field.name));
+            }
+            output.push_str("\treturn value\n");
+        }
+        if derive::has(comp, Derive::Inspect) {
+            output.push_str("\nstatic func inspector() -> Array[Dictionary]:\n");
+            output.push_str("\treturn [\n");
+            for field in derive::inspector(comp, &self.items.enums) {
+                let options: Vec<String> = field.options.iter().map(|o| quote(o)).collect();
+                output.push_str(&format!(
+                    "\t\t{{\"name\": {}, \"type\": {}, \"animatable\": {}, \"options\": [{}]}},\n",
+                    quote(field.name), quote(&field.type_name), field.animatable, options.join(", ")
+                ));
+            }
+            output.push_str("\t]\n");
+        }
+        if derive::has(comp, Derive::Lerp) {
+            let fields = derive::lerp_fields(comp);
+            for field in &fields {
+                let value = match &field.type_ {
+                    Type::Int | Type::Int64 => format!("roundi(lerpf({}, to.{}, t))", field.name, field.name),
+                    Type::Vec2 | Type::Vec3 | Type::Vec4 | Type::Color => format!("{}.lerp(to.{}, t)", field.name, field.name),
+                    _ => format!("lerpf({}, to.{}, t)", field.name, field.name),
+                };
+                output.push_str(&format!(
+                    "\nfunc lerp_{}(to: {}, t: float) -> {}:\n\treturn {}\n",
+                    field.name, class, self.gd_type(&field.type_), value
+                ));
+            }
+            output.push_str("\n## Other fields keep this component's values\n");
+            output.push_str(&format!("func interpolate(to: {}, t: float) -> {}:\n", class, class));
+            output.push_str(&format!("\tvar result: {} = duplicate()\n", class));
+            for field in &fields {
+                output.push_str(&format!("\tresult.{} = lerp_{}(to, t)\n", field.name, field.name));
+            }
+            output.push_str("\treturn result\n");
+        }
+        output
+    }
+
+    /// Entities are `Node2D`s; components are stored as node metadata and the
+    /// node joins a group per component so systems can query it
+    fn generate_entity(&self, entity: &EntityDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        self.directive(&mut output, entity.span);
+        output.push_str("extends Node2D\n\n");
+        output.push_str("func _ready() -> void:\n");
+
+        let mut position = None;
+        for comp in &entity.components {
+            let Some(decl) = self.items.component(&comp.name) else {
+                output.push_str(&format!("\t# Unknown component '{}'\n", comp.name));
+                continue;
+            };
+            let var = format!("{}_component", snake_case(&decl.name));
+            output.push_str(&format!("\tvar {} := {}.new()\n", var, component_class(&decl.name)));
+            // Positional args map onto the component's fields in order
+            let args: Vec<&Expression> = match comp.args.as_slice() {
+                [Expression::Tuple(values)] => values.iter().collect(),
+                args => args.iter().collect(),
+            };
+            for (field, arg) in decl.fields.iter().zip(args) {
+                output.push_str(&format!("\t{}.{} = {}\n", var, field.name, self.expression(arg)?));
+            }
+            output.push_str(&format!("\tset_meta(\"{}\", {})\n", decl.name, var));
+            output.push_str(&format!("\tadd_to_group(\"{}\")\n", decl.name));
+
+            let has_field = |name: &str| decl.fields.iter().any(|f| f.name == name);
+            if decl.name.eq_ignore_ascii_case("position") && has_field("x") && has_field("y") {
+                position = Some(decl.name.clone());
+            }
+        }
+        output.push_str(&self.block(&entity.body, 1)?);
+
+        // Keep the node where the Position component says it is
+        if let Some(name) = position {
+            output.push_str("\nfunc _process(_delta: float) -> void:\n");
+            output.push_str(&format!("\tvar component: {} = get_meta(\"{}\")\n", component_class(&name), name));
+            output.push_str("\tposition = Vector2(component.x, component.y)\n");
+        }
+        Ok(output)
+    }
+
+    fn generate_system(&self, system: &SystemDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        self.directive(&mut output, system.span);
+        output.push_str("extends Node\n\n");
+        output.push_str("func _process(dt: float) -> void:\n");
+
+        let Some((first, rest)) = system.query.split_first() else {
+            output.push_str(&self.block(&system.body, 1)?);
+            return Ok(output);
+        };
+        output.push_str(&format!("\tfor entity in get_tree().get_nodes_in_group(\"{}\"):\n", self.component_name(first)));
+        for comp in rest {
+            output.push_str(&format!("\t\tif not entity.is_in_group(\"{}\"):\n", self.component_name(comp)));
+            output.push_str("\t\t\tcontinue\n");
+        }
+        for comp in &system.query {
+            let name = self.component_name(comp);
+            output.push_str(&format!(
+                "\t\tvar {}: {} = entity.get_meta(\"{}\")\n",
+                snake_case(comp), component_class(&name), name
+            ));
+        }
+        output.push_str(&self.block(&system.body, 2)?);
+        Ok(output)
+    }
+
+    /// Each function is a static method on its own global class (`<Name>Fn`)
+    fn generate_function(&self, func: &FunctionDeclaration) -> GrumpResult<String> {
+        let params: Vec<String> = func.params.iter()
+            .map(|p| match &p.type_ {
+                Some(t) => format!("{}: {}", p.name, self.gd_type(t)),
+                None => p.name.clone(),
+            })
+            .collect();
+        let return_type = match &func.return_type {
+            Some(t) => format!(" -> {}", self.gd_type(t)),
+            None if !returns_value(&func.body) => " -> void".to_string(),
+            None => String::new(),
+        };
+
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        self.directive(&mut output, func.span);
+        output.push_str(&format!("class_name {}Fn\n", pascal_case(&func.name)));
+        output.push_str("extends RefCounted\n\n");
+        output.push_str(&format!("static func {}({}){}:\n", func.name, params.join(", "), return_type));
+        output.push_str(&self.block(&func.body, 1)?);
+        Ok(output)
+    }
+
+    /// Scene script: top-level `let`s become member variables, the rest runs in `_ready`
+    fn generate_scene_script(&self, scene: Option<&SceneDeclaration>) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("# Generated GDScript from G-Rump\n");
+        if let Some(scene) = scene {
+            self.directive(&mut output, scene.span);
+        }
+        output.push_str("extends Node2D\n\n");
+
+        let mut body = Vec::new();
+        for stmt in scene.map(|s| s.body.as_slice()).unwrap_or_default() {
+            match stmt {
+                Statement::Let { .. } => output.push_str(&self.statement(stmt, 0)?),
+                other => body.push(other.clone()),
+            }
+        }
+        if !output.ends_with("\n\n") {
+            output.push('\n');
+        }
+        output.push_str("func _ready() -> void:\n");
+        output.push_str(&self.block(&body, 1)?);
+        Ok(output)
+    }
+
+    /// `.tscn` with the scene script on the root, a node per entity and system,
+    /// and an `AnimationPlayer` holding every animation
+    fn generate_scene(&self, name: &str) -> String {
+        let mut resources = Vec::new();
+        resources.push(("Script", format!("res://scripts/scenes/{}.gd", snake_case(name))));
+        for entity in &self.items.entities {
+            resources.push(("Script", format!("res://scripts/entities/{}.gd", snake_case(&entity.name))));
+        }
+        for system in &self.items.systems {
+            resources.push(("Script", format!("res://scripts/systems/{}.gd", snake_case(&system.name))));
+        }
+        for anim in &self.items.animations {
+            resources.push(("Animation", format!("res://animations/{}.tres", snake_case(&anim.name))));
+        }
+        let has_animations = !self.items.animations.is_empty();
+        let load_steps = resources.len() + 1 + usize::from(has_animations);
+
+        let mut output = String::new();
+        output.push_str(&format!("[gd_scene load_steps={} format=3]\n\n", load_steps));
+        for (i, (kind, path)) in resources.iter().enumerate() {
+            output.push_str(&format!("[ext_resource type=\"{}\" path=\"{}\" id=\"{}\"]\n", kind, path, i + 1));
+        }
+        output.push('\n');
+
+        let first_animation = 2 + self.items.entities.len() + self.items.systems.len();
+        if has_animations {
+            output.push_str("[sub_resource type=\"AnimationLibrary\" id=\"AnimationLibrary_1\"]\n");
+            output.push_str("_data = {\n");
+            let entries: Vec<String> = self.items.animations.iter().enumerate()
+                .map(|(i, anim)| format!("\"{}\": ExtResource(\"{}\")", snake_case(&anim.name), first_animation + i))
+                .collect();
+            output.push_str(&entries.join(",\n"));
+            output.push_str("\n}\n\n");
+        }
+
+        output.push_str(&format!("[node name=\"{}\" type=\"Node2D\"]\n", pascal_case(name)));
+        output.push_str("script = ExtResource(\"1\")\n");
+        let mut id = 2;
+        for entity in &self.items.entities {
+            output.push_str(&format!("\n[node name=\"{}\" type=\"Node2D\" parent=\".\"]\n", pascal_case(&entity.name)));
+            output.push_str(&format!("script = ExtResource(\"{}\")\n", id));
+            id += 1;
+        }
+        for system in &self.items.systems {
+            output.push_str(&format!("\n[node name=\"{}System\" type=\"Node\" parent=\".\"]\n", pascal_case(&system.name)));
+            output.push_str(&format!("script = ExtResource(\"{}\")\n", id));
+            id += 1;
+        }
+        if has_animations {
+            output.push_str("\n[node name=\"AnimationPlayer\" type=\"AnimationPlayer\" parent=\".\"]\n");
+            output.push_str("libraries = {\n\"\": SubResource(\"AnimationLibrary_1\")\n}\n");
+        }
+        output
+    }
+
+    /// Animation resource with one value track on the scene root's metadata
+    /// (`get_meta("<name>")`), since declarations don't name a target property
+    fn generate_animation(&self, anim: &AnimationDeclaration) -> GrumpResult<String> {
+        let mut keys = Vec::new();
+        for kf in &anim.keyframes {
+            keys.push((self.constant_seconds(&anim.name, &kf.time)?, self.resource_value(&anim.name, &kf.value)?, transition(kf)));
+        }
+        let last_key = keys.iter().map(|(time, _, _)| *time).fold(0.0, f64::max);
+        let mut length = match &anim.duration {
+            Some(duration) => self.constant_seconds(&anim.name, duration)?,
+            None => last_key,
+        };
+
+        let loop_mode = match &anim.loop_mode {
+            Some(LoopMode::Loop) => 1,
+            Some(LoopMode::PingPong) => 2,
+            Some(LoopMode::Reverse) => {
+                // Godot can't play a clip backwards on its own; mirror the keys
+                for key in &mut keys {
+                    key.0 = length - key.0;
+                }
+                keys.reverse();
+                0
+            }
+            Some(LoopMode::Section { start, end }) => {
+                // Loop just the section, rebased to start at zero
+                let start = self.constant_seconds(&anim.name, start)?;
+                let end = self.constant_seconds(&anim.name, end)?;
+                keys.retain(|(time, _, _)| *time >= start && *time <= end);
+                for key in &mut keys {
+                    key.0 -= start;
+                }
+                length = end - start;
+                1
+            }
+            Some(LoopMode::None) | None => 0,
+        };
+
+        let join = |values: Vec<String>| values.join(", ");
+        let mut output = String::new();
+        output.push_str("[gd_resource type=\"Animation\" format=3]\n\n");
+        output.push_str("[resource]\n");
+        output.push_str(&format!("resource_name = \"{}\"\n", snake_case(&anim.name)));
+        output.push_str(&format!("length = {:?}\n", length));
+        output.push_str(&format!("loop_mode = {}\n", loop_mode));
+        output.push_str("tracks/0/type = \"value\"\n");
+        output.push_str("tracks/0/imported = false\n");
+        output.push_str("tracks/0/enabled = true\n");
+        output.push_str(&format!("tracks/0/path = NodePath(\".:metadata/{}\")\n", snake_case(&anim.name)));
+        output.push_str("tracks/0/interp = 1\n");
+        output.push_str("tracks/0/loop_wrap = true\n");
+        output.push_str("tracks/0/keys = {\n");
+        output.push_str(&format!("\"times\": PackedFloat32Array({}),\n", join(keys.iter().map(|k| format!("{:?}", k.0)).collect())));
+        output.push_str(&format!("\"transitions\": PackedFloat32Array({}),\n", join(keys.iter().map(|k| format!("{:?}", k.2)).collect())));
+        output.push_str("\"update\": 0,\n");
+        output.push_str(&format!("\"values\": [{}]\n", join(keys.into_iter().map(|k| k.1).collect())));
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn directive(&self, output: &mut String, span: crate::parser::Span) {
+        if let Some(directive) = self.gen.line_directive(span) {
+            output.push_str(&directive);
+        }
+    }
+
+    /// Declared spelling of a component name used in a query
+    fn component_name(&self, name: &str) -> String {
+        self.items.component(name).map(|c| c.name.clone()).unwrap_or_else(|| pascal_case(name))
+    }
+
+    /// Statements indented by `depth` tabs; GDScript blocks can't be empty
+    fn block(&self, statements: &[Statement], depth: usize) -> GrumpResult<String> {
+        let mut output = String::new();
+        for stmt in statements {
+            output.push_str(&self.statement(stmt, depth)?);
+        }
+        if output.is_empty() {
+            output.push_str(&format!("{}pass\n", "\t".repeat(depth)));
+        }
+        Ok(output)
+    }
+
+    fn statement(&self, stmt: &Statement, depth: usize) -> GrumpResult<String> {
+        let indent = "\t".repeat(depth);
+        let code = match stmt {
+            Statement::Let { name, type_, value, .. } => match type_ {
+                Some(t) => format!("{}var {}: {} = {}\n", indent, name, self.gd_type(t), self.expression(value)?),
+                None => format!("{}var {} = {}\n", indent, name, self.expression(value)?),
+            },
+            Statement::Assign { target, value, .. } => {
+                format!("{}{} = {}\n", indent, self.expression(target)?, self.expression(value)?)
+            }
+            Statement::If { condition, then, else_, .. } => {
+                let mut code = format!("{}if {}:\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(then, depth + 1)?);
+                if let Some(else_body) = else_ {
+                    code.push_str(&format!("{}else:\n", indent));
+                    code.push_str(&self.block(else_body, depth + 1)?);
+                }
+                code
+            }
+            Statement::Match { expr, arms, .. } => {
+                let mut code = format!("{}match {}:\n", indent, self.expression(expr)?);
+                for arm in arms {
+                    let pattern = self.pattern(&arm.pattern)?;
+                    match &arm.guard {
+                        Some(guard) => code.push_str(&format!("{}\t{} when {}:\n", indent, pattern, self.expression(guard)?)),
+                        None => code.push_str(&format!("{}\t{}:\n", indent, pattern)),
+                    }
+                    code.push_str(&self.block(&arm.body, depth + 2)?);
+                }
+                code
+            }
+            Statement::For { var, iter, body, .. } => {
+                let mut code = format!("{}for {} in {}:\n", indent, var, self.expression(iter)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code
+            }
+            Statement::While { condition, body, .. } => {
+                let mut code = format!("{}while {}:\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code
+            }
+            Statement::Return(Some(expr), _) => format!("{}return {}\n", indent, self.expression(expr)?),
+            Statement::Return(None, _) => format!("{}return\n", indent),
+            Statement::Break => format!("{}break\n", indent),
+            Statement::Continue => format!("{}continue\n", indent),
+            Statement::Expression(expr, _) => format!("{}{}\n", indent, self.expression(expr)?),
+            Statement::Await { expr } => format!("{}await {}\n", indent, self.expression(expr)?),
+            Statement::Animate(animate) => self.animate(animate, &indent)?,
+            Statement::Timeline { name, .. } => format!("{}pass # timeline '{}' is not supported by the Godot target\n", indent, name),
+            Statement::Debugger(_) => format!("{}breakpoint\n", indent),
+            Statement::Network(_) => format!("{}pass # network statements are not supported by the Godot target\n", indent),
+        };
+        Ok(located(stmt.span(), None, code))
+    }
+
+    /// `animate target { keyframes }` becomes a chain of tweens on the target's property
+    fn animate(&self, animate: &AnimateStatement, indent: &str) -> GrumpResult<String> {
+        let (object, property) = match &animate.target {
+            Expression::Member { object, member } => (self.expression(object)?, member.clone()),
+            Expression::Identifier(name) => ("self".to_string(), name.clone()),
+            _ => {
+                return Err(GrumpError::Other(anyhow::anyhow!("Godot target can only animate properties (`object.field`)")));
+            }
+        };
+        let id = self.tweens.get();
+        self.tweens.set(id + 1);
+        let tween = format!("tween{}", id);
+
+        let mut code = format!("{}var {} := (Engine.get_main_loop() as SceneTree).create_tween()\n", indent, tween);
+        let mut previous = "0.0".to_string();
+        for kf in &animate.keyframes {
+            let time = self.seconds(&kf.time)?;
+            let step = match (previous.parse::<f64>(), time.parse::<f64>()) {
+                (Ok(a), Ok(b)) => format!("{:?}", (b - a).max(0.0)),
+                _ => format!("max({} - {}, 0.0)", time, previous),
+            };
+            let ease = match (&kf.ease_in, &kf.ease_out) {
+                (Some(_), Some(_)) => ".set_trans(Tween.TRANS_SINE).set_ease(Tween.EASE_IN_OUT)",
+                (Some(_), None) => ".set_trans(Tween.TRANS_SINE).set_ease(Tween.EASE_IN)",
+                (None, Some(_)) => ".set_trans(Tween.TRANS_SINE).set_ease(Tween.EASE_OUT)",
+                (None, None) => "",
+            };
+            code.push_str(&format!(
+                "{}{}.tween_property({}, \"{}\", {}, {}){}\n",
+                indent, tween, object, property, self.expression(&kf.value)?, step, ease
+            ));
+            previous = time;
+        }
+        Ok(code)
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> GrumpResult<String> {
+        Ok(match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Literal(lit) => self.literal(lit),
+            Pattern::Identifier(name) => match self.items.enum_of_variant(name) {
+                Some(enum_decl) => format!("GrumpTypes.{}.{}", enum_decl.name, name),
+                None => format!("var {}", name),
+            },
+            Pattern::Tuple(patterns) => {
+                let patterns = patterns.iter().map(|p| self.pattern(p)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("[{}]", patterns.join(", "))
+            }
+            Pattern::Struct { name, .. } => {
+                return Err(GrumpError::Other(anyhow::anyhow!(
+                    "Struct pattern '{}' is not supported by the Godot target", name
+                )));
+            }
+        })
+    }
+
+    fn expression(&self, expr: &Expression) -> GrumpResult<String> {
+        Ok(match expr {
+            Expression::Literal(lit) => self.literal(lit),
+            Expression::Identifier(name) => {
+                if self.items.enums.iter().any(|e| &e.name == name) {
+                    format!("GrumpTypes.{}", name)
+                } else if let Some(enum_decl) = self.items.enum_of_variant(name) {
+                    format!("GrumpTypes.{}.{}", enum_decl.name, name)
+                } else {
+                    name.clone()
+                }
+            }
+            Expression::Binary { op, left, right } => {
+                let op = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Mod => "%",
+                    BinaryOp::Eq => "==",
+                    BinaryOp::Ne => "!=",
+                    BinaryOp::Lt => "<",
+                    BinaryOp::Gt => ">",
+                    BinaryOp::Le => "<=",
+                    BinaryOp::Ge => ">=",
+                    BinaryOp::And => "and",
+                    BinaryOp::Or => "or",
+                    BinaryOp::Xor => "^",
+                    BinaryOp::ShiftLeft => "<<",
+                    BinaryOp::ShiftRight => ">>",
+                };
+                format!("({} {} {})", self.expression(left)?, op, self.expression(right)?)
+            }
+            Expression::Unary { op, expr } => match op {
+                UnaryOp::Neg => format!("-{}", self.expression(expr)?),
+                UnaryOp::Not => format!("not {}", self.expression(expr)?),
+                // Objects are references in GDScript
+                UnaryOp::Deref | UnaryOp::Ref | UnaryOp::MutRef => self.expression(expr)?,
+            },
+            Expression::Call { func, args } => self.call(func, args)?,
+            Expression::Member { object, member } => format!("{}.{}", self.expression(object)?, member),
+            Expression::Index { object, index } => format!("{}[{}]", self.expression(object)?, self.expression(index)?),
+            Expression::Tuple(values) | Expression::Array(values) => {
+                let values = values.iter().map(|v| self.expression(v)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("[{}]", values.join(", "))
+            }
+            Expression::If { condition, then, else_ } => format!(
+                "({} if {} else {})",
+                self.expression(then)?, self.expression(condition)?, self.expression(else_)?
+            ),
+            Expression::Lambda { params, body } => {
+                let params: Vec<String> = params.iter()
+                    .map(|p| match &p.type_ {
+                        Some(t) => format!("{}: {}", p.name, self.gd_type(t)),
+                        None => p.name.clone(),
+                    })
+                    .collect();
+                format!("func({}): return {}", params.join(", "), self.expression(body)?)
+            }
+            Expression::Await(expr) => format!("(await {})", self.expression(expr)?),
+            Expression::Block(_) | Expression::AsyncBlock(_) => {
+                return Err(GrumpError::Other(anyhow::anyhow!("Block expressions are not supported by the Godot target")));
+            }
+            Expression::MacroCall { name, args, span } => {
+                let args = args.iter().map(|arg| self.expression(arg)).collect::<GrumpResult<Vec<_>>>()?;
+                template_call(self.items.macros.iter().copied(), Target::Godot, name, &args, *span)?
+            }
+        })
+    }
+
+    /// Calls to user functions go through their `<Name>Fn` class; builtins map
+    /// onto GDScript globals, methods, or the `Grump` autoload
+    fn call(&self, func: &Expression, args: &[Expression]) -> GrumpResult<String> {
+        let args = args.iter().map(|a| self.expression(a)).collect::<GrumpResult<Vec<_>>>()?;
+        let Expression::Identifier(name) = func else {
+            return Ok(format!("{}({})", self.expression(func)?, args.join(", ")));
+        };
+        if self.items.functions.iter().any(|f| &f.name == name) {
+            return Ok(format!("{}Fn.{}({})", pascal_case(name), name, args.join(", ")));
+        }
+        Ok(match (name.as_str(), args.as_slice()) {
+            ("length", [v]) => format!("{}.length()", v),
+            ("normalize", [v]) => format!("{}.normalized()", v),
+            ("dot", [a, b]) => format!("{}.dot({})", a, b),
+            ("random", [min, max]) => format!("randf_range({}, {})", min, max),
+            (helper, _) if GRUMP_HELPERS.contains(&helper) => format!("Grump.{}({})", helper, args.join(", ")),
+            _ => format!("{}({})", name, args.join(", ")),
+        })
+    }
+
+    fn literal(&self, lit: &Literal) -> String {
+        match lit {
+            Literal::Integer(n) => n.to_string(),
+            Literal::Float(f) => format!("{:?}", f),
+            Literal::String(s) => quote(s),
+            Literal::Char(c) => quote(&c.to_string()),
+            Literal::Bool(b) => b.to_string(),
+            Literal::Color { r, g, b, a } => format!("Color8({}, {}, {}, {})", r, g, b, a),
+            Literal::Vec2 { x, y } => format!("Vector2({:?}, {:?})", x, y),
+            Literal::Vec3 { x, y, z } => format!("Vector3({:?}, {:?}, {:?})", x, y, z),
+            Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
+            Literal::Angle { value, unit } => format!("{:?}", angle_radians(*value, unit)),
+        }
+    }
+
+    /// Keyframe times are seconds
+    fn seconds(&self, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Literal(Literal::Integer(n)) => Ok(format!("{:?}", *n as f64)),
+            other => self.expression(other),
+        }
+    }
+
+    /// Animation resources are data, so times must be known at compile time
+    fn constant_seconds(&self, anim: &str, expr: &Expression) -> GrumpResult<f64> {
+        match expr {
+            Expression::Literal(Literal::Integer(n)) => Ok(*n as f64),
+            Expression::Literal(Literal::Float(f)) => Ok(*f),
+            Expression::Literal(Literal::Duration { value, unit }) => Ok(duration_seconds(*value, unit)),
+            _ => Err(GrumpError::Animation {
+                message: format!("Animation '{}' needs constant keyframe times for the Godot target", anim),
+            }),
+        }
+    }
+
+    /// Keyframe value in Godot's resource syntax
+    fn resource_value(&self, anim: &str, expr: &Expression) -> GrumpResult<String> {
+        let number = |expr: &Expression| match expr {
+            Expression::Literal(Literal::Integer(n)) => Some(*n as f64),
+            Expression::Literal(Literal::Float(f)) => Some(*f),
+            Expression::Literal(Literal::Angle { value, unit }) => Some(angle_radians(*value, unit)),
+            Expression::Unary { op: UnaryOp::Neg, expr } => match expr.as_ref() {
+                Expression::Literal(Literal::Integer(n)) => Some(-(*n as f64)),
+                Expression::Literal(Literal::Float(f)) => Some(-f),
+                _ => None,
+            },
+            _ => None,
+        };
+        let value = match expr {
+            Expression::Literal(Literal::Vec2 { x, y }) => Some(format!("Vector2({:?}, {:?})", x, y)),
+            Expression::Literal(Literal::Vec3 { x, y, z }) => Some(format!("Vector3({:?}, {:?}, {:?})", x, y, z)),
+            Expression::Literal(Literal::Color { r, g, b, a }) => Some(format!(
+                "Color({:?}, {:?}, {:?}, {:?})",
+                *r as f64 / 255.0, *g as f64 / 255.0, *b as f64 / 255.0, *a as f64 / 255.0
+            )),
+            Expression::Literal(Literal::Bool(b)) => Some(b.to_string()),
+            Expression::Literal(Literal::String(s)) => Some(quote(s)),
+            Expression::Tuple(values) | Expression::Array(values) => {
+                match values.iter().map(number).collect::<Option<Vec<f64>>>().as_deref() {
+                    Some([x, y]) => Some(format!("Vector2({:?}, {:?})", x, y)),
+                    Some([x, y, z]) => Some(format!("Vector3({:?}, {:?}, {:?})", x, y, z)),
+                    _ => None,
+                }
+            }
+            other => number(other).map(|n| format!("{:?}", n)),
+        };
+        value.ok_or_else(|| GrumpError::Animation {
+            message: format!("Animation '{}' needs constant keyframe values for the Godot target", anim),
+        })
+    }
+
+    fn gd_type(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Int | Type::Int64 => "int".to_string(),
+            Type::Float | Type::Double | Type::Angle | Type::Duration | Type::Rotation => "float".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::String | Type::Char => "String".to_string(),
+            Type::Vec2 => "Vector2".to_string(),
+            Type::Vec3 => "Vector3".to_string(),
+            Type::Vec4 => "Vector4".to_string(),
+            Type::Color => "Color".to_string(),
+            Type::Transform => "Transform2D".to_string(),
+            // GDScript has no optional or sum types
+            Type::Optional(_) | Type::Result { .. } => "Variant".to_string(),
+            Type::Tuple(_) => "Array".to_string(),
+            Type::Array(inner) => match inner.as_ref() {
+                Type::Optional(_) | Type::Result { .. } | Type::Tuple(_) | Type::Array(_) => "Array".to_string(),
+                inner => format!("Array[{}]", self.gd_type(inner)),
+            },
+            Type::Named(name) => {
+                if self.items.enums.iter().any(|e| &e.name == name) {
+                    format!("GrumpTypes.{}", name)
+                } else if let Some(comp) = self.items.component(name) {
+                    component_class(&comp.name)
+                } else {
+                    name.clone()
+                }
+            }
+        }
+    }
+
+    /// Value for a component field without a default
+    fn zero_value(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Int | Type::Int64 => "0".to_string(),
+            Type::Float | Type::Double | Type::Angle | Type::Duration | Type::Rotation => "0.0".to_string(),
+            Type::Bool => "false".to_string(),
+            Type::String | Type::Char => "\"\"".to_string(),
+            Type::Vec2 => "Vector2.ZERO".to_string(),
+            Type::Vec3 => "Vector3.ZERO".to_string(),
+            Type::Vec4 => "Vector4.ZERO".to_string(),
+            Type::Color => "Color.BLACK".to_string(),
+            Type::Transform => "Transform2D.IDENTITY".to_string(),
+            Type::Tuple(_) | Type::Array(_) => "[]".to_string(),
+            Type::Named(name) => match self.items.enums.iter().find(|e| &e.name == name) {
+                Some(enum_decl) => format!("GrumpTypes.{}.{}", enum_decl.name, enum_decl.variants[0]),
+                None => "null".to_string(),
+            },
+            Type::Optional(_) | Type::Result { .. } => "null".to_string(),
+        }
+    }
+}
+
+/// Components get a suffix so they can't collide with Godot's own classes (`Timer`, `Camera2D`, ...)
+fn component_class(name: &str) -> String {
+    format!("{}Component", pascal_case(name))
+}
+
+/// Godot animation transition curve for a keyframe's easing
+fn transition(kf: &Keyframe) -> f64 {
+    match (&kf.ease_in, &kf.ease_out) {
+        (Some(_), Some(_)) => -2.0,
+        (Some(_), None) => 2.0,
+        (None, Some(_)) => 0.5,
+        (None, None) => 1.0,
+    }
+}
+
+/// Double-quoted string with escapes (valid in GDScript and Godot config/resource files)
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+const RUNTIME: &str = r#"# G-Rump runtime support for Godot (autoloaded as `Grump`)
+extends Node
+
+var _delta := 0.0
+var _elapsed := 0.0
+
+func _process(delta: float) -> void:
+	_delta = delta
+	_elapsed += delta
+
+func delta_time() -> float:
+	return _delta
+
+func now() -> float:
+	return _elapsed
+
+func ease_in_out(t: float) -> float:
+	return ease(t, -2.0)
+
+func concat(a: String, b: String) -> String:
+	return a + b
+
+func substring(s: String, start: int, end: int) -> String:
+	return s.substr(start, end - start)
+
+func str_length(s: String) -> int:
+	return s.length()
+
+func rgb(r: int, g: int, b: int) -> Color:
+	return Color8(r, g, b)
+
+func rgba(r: int, g: int, b: int, a: int) -> Color:
+	return Color8(r, g, b, a)
+
+func hsl(h: float, s: float, l: float) -> Color:
+	var v := l + s * minf(l, 1.0 - l)
+	var sv := 0.0 if v == 0.0 else 2.0 * (1.0 - l / v)
+	return Color.from_hsv(fposmod(h, 360.0) / 360.0, sv, v)
+
+func translate(x: float, y: float) -> Transform2D:
+	return Transform2D(0.0, Vector2(x, y))
+
+func rotate(angle: float) -> Transform2D:
+	return Transform2D(angle, Vector2.ZERO)
+
+func scale(x: float, y: float) -> Transform2D:
+	return Transform2D.IDENTITY.scaled(Vector2(x, y))
+"#;
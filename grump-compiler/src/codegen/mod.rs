@@ -1,35 +1,220 @@
 //! Code Generator for G-Rump
 //! 
-//! Generates target code (Swift, Kotlin, Dart, JavaScript) from G-Rump AST.
+//! Generates target code (Swift, Kotlin, Dart, JavaScript, TypeScript, GDScript, Rust) from G-Rump AST.
 
-use crate::parser::Program;
+use std::collections::HashSet;
+
+use crate::parser::{AnimateStatement, Expression, Item, Literal, Program, Span, Statement};
+use crate::parser::extensions::{MacroBody, MacroDeclaration};
 use crate::error::{GrumpError, GrumpResult};
+use crate::expander::template;
+
+mod output;
+pub use output::{FileContents, OutputTree, BUILD_MARKER};
+
+mod project;
+use project::AppInfo;
+
+mod source_map;
+pub use source_map::{Mapping, SourceMap};
+use source_map::located;
 
 mod phaser;
 use phaser::PhaserCodegen;
 
+mod flame;
+use flame::FlameCodegen;
+
+mod typescript;
+use typescript::TypeScriptCodegen;
+
+mod godot;
+use godot::GodotCodegen;
+
+mod rust;
+use rust::RustCodegen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Target {
     Ios,      // Swift + Metal
     Android,  // Kotlin + OpenGL
     Web,      // JavaScript + WebGL
     Flutter,  // Dart + Skia
+    TypeScript, // Typed TypeScript + Canvas
+    Godot,    // GDScript + Godot 4 scenes
+    Rust,     // Rust on the grump runtime
+}
+
+impl Target {
+    pub const ALL: [Target; 7] = [
+        Target::Ios, Target::Android, Target::Web, Target::Flutter, Target::TypeScript, Target::Godot, Target::Rust,
+    ];
+
+    pub fn from_name(name: &str) -> GrumpResult<Target> {
+        match name.to_ascii_lowercase().as_str() {
+            "ios" => Ok(Target::Ios),
+            "android" => Ok(Target::Android),
+            "web" => Ok(Target::Web),
+            "flutter" => Ok(Target::Flutter),
+            "typescript" | "ts" => Ok(Target::TypeScript),
+            "godot" => Ok(Target::Godot),
+            "rust" => Ok(Target::Rust),
+            _ => Err(GrumpError::Other(anyhow::anyhow!(
+                "Unknown target '{}' (expected one of: ios, android, web, flutter, typescript, godot, rust, all)",
+                name
+            ))),
+        }
+    }
+
+    /// Name used on the command line and for `build/<target>`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Ios => "ios",
+            Target::Android => "android",
+            Target::Web => "web",
+            Target::Flutter => "flutter",
+            Target::TypeScript => "typescript",
+            Target::Godot => "godot",
+            Target::Rust => "rust",
+        }
+    }
 }
 
 pub struct CodeGenerator {
     target: Target,
+    sources: Vec<(String, Option<String>)>,  // `.grump` files, indexed by `Span::file`
+    source_map: SourceMap,
+    templates: Vec<MacroDeclaration>,  // Template macros of the program being generated
+    async_functions: HashSet<String>,  // Its `async fn`s, whose calls are awaited directly
+    rust_runtime: Option<String>,  // Runtime crate the Rust target depends on, instead of a copy
 }
 
 impl CodeGenerator {
     pub fn new(target: Target) -> Self {
-        Self { target }
+        Self {
+            target,
+            sources: vec![("main.grump".to_string(), None)],
+            source_map: SourceMap::default(),
+            templates: Vec::new(),
+            async_functions: HashSet::new(),
+            rust_runtime: None,
+        }
     }
     
-    pub fn generate(&mut self, program: &Program) -> GrumpResult<String> {
+    /// Name the `.grump` file being compiled, for source maps and line directives.
+    /// `content` is embedded in the source map when given.
+    pub fn with_source(mut self, path: impl Into<String>, content: Option<String>) -> Self {
+        self.sources = vec![(path.into(), content)];
+        self
+    }
+    
+    /// Name every file of a project, in `Project::sources` order, so items
+    /// from each map back to their own file
+    pub fn with_sources(mut self, sources: Vec<(String, Option<String>)>) -> Self {
+        if !sources.is_empty() {
+            self.sources = sources;
+        }
+        self
+    }
+    
+    /// Have the Rust target depend on the `grump-runtime` crate at `path`
+    /// rather than writing a copy of the runtime into its output
+    pub fn with_rust_runtime(mut self, path: impl Into<String>) -> Self {
+        self.rust_runtime = Some(path.into());
+        self
+    }
+    
+    /// Generate the target's complete project.
+    ///
+    /// Paths are relative to the output directory; the entry file holds the
+    /// main generated source and `<entry>.map` maps its declarations and
+    /// statements back to the `.grump` files. Other files with statements
+    /// get a `.map` of their own.
+    pub fn generate(&mut self, program: &Program) -> GrumpResult<OutputTree> {
+        let app = AppInfo::from_program(program);
+        self.source_map = SourceMap::with_sources("", self.sources.clone());
+        self.templates = program.all_items().into_iter()
+            .filter_map(|item| match item {
+                Item::Macro(decl) if matches!(decl.body, MacroBody::Template(_)) => Some(decl.clone()),
+                _ => None,
+            })
+            .collect();
+        self.async_functions = program.all_items().into_iter()
+            .filter_map(|item| match item {
+                Item::Function(func) if func.is_async => Some(func.name.clone()),
+                _ => None,
+            })
+            .chain(ASYNC_BUILTINS.iter().map(|name| name.to_string()))
+            .collect();
+        
+        let mut tree = match self.target {
+            Target::Ios => project::ios_project(&app, self.generate_swift(program)?),
+            Target::Android => project::android_project(&app, self.generate_kotlin(program)?),
+            Target::Web => project::web_project(PhaserCodegen::generate_game(program, &mut self.source_map)?), // Use Phaser for web
+            Target::Flutter => FlameCodegen::generate_project(self, program)?,
+            Target::TypeScript => TypeScriptCodegen::generate_project(program, &mut self.source_map)?,
+            Target::Godot => GodotCodegen::generate_project(self, program)?,
+            Target::Rust => RustCodegen::generate_project(self, program)?,
+        };
+        
+        // Statements are mapped wherever they landed: in the entry's map, or
+        // one of their own next to the other files
+        let entry = tree.entry().map(str::to_string);
+        let mut map = std::mem::take(&mut self.source_map);
+        let mut maps = Vec::new();
+        for (path, text) in tree.texts_mut() {
+            let directive = |span| self.line_directive(span).filter(|_| self.target == Target::Ios);
+            if entry.as_ref() == Some(path) {
+                map.take_marks(text, directive);
+                continue;
+            }
+            let mut own = SourceMap::with_sources(path.rsplit('/').next().unwrap_or(path), self.sources.clone());
+            own.take_marks(text, directive);
+            if !own.is_empty() {
+                maps.push((format!("{}.map", path), own.to_json()));
+            }
+        }
+        self.source_map = map;
+        if let Some(entry) = entry {
+            if !self.source_map.is_empty() {
+                self.source_map.set_file(entry.rsplit('/').next().unwrap_or(&entry));
+                maps.push((format!("{}.map", entry), self.source_map.to_json()));
+            }
+        }
+        for (path, json) in maps {
+            tree.add_text(path, json);
+        }
+        Ok(tree)
+    }
+    
+    /// Source map recorded by the last `generate` call
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+    
+    /// Comment or directive that attributes the following generated lines to `span`
+    fn line_directive(&self, span: Span) -> Option<String> {
+        let (file, _) = self.sources.get(span.file).filter(|_| span.line != 0)?;
         match self.target {
-            Target::Ios => self.generate_swift(program),
-            Target::Android => self.generate_kotlin(program),
-            Target::Web => PhaserCodegen::generate_game(program), // Use Phaser for web
-            Target::Flutter => self.generate_dart(program),
+            Target::Ios => Some(format!("#sourceLocation(file: \"{}\", line: {})\n", file, span.line)),
+            Target::Android | Target::Flutter | Target::Rust => Some(format!("// grump: {}:{}\n", file, span.line)),
+            Target::Godot => Some(format!("# grump: {}:{}\n", file, span.line)),
+            Target::Web | Target::TypeScript => None, // covered by the entry's .map
+        }
+    }
+    
+    /// Append an item's generated code, recording where it came from
+    fn emit_item(&self, output: &mut String, item: &Item, code: &str) {
+        if code.is_empty() {
+            return;
+        }
+        let directive = self.line_directive(item.span());
+        if let Some(directive) = &directive {
+            output.push_str(directive);
+        }
+        output.push_str(&located(Some(item.span()), Some(item.name()), code.to_string()));
+        if directive.is_some() && self.target == Target::Ios {
+            output.push_str("#sourceLocation()\n");
         }
     }
     
@@ -41,56 +226,33 @@ impl CodeGenerator {
         output.push_str("import MetalKit\n\n");
         
         // Generate code for each item
-        for item in &program.items {
-            match item {
+        for item in module_items(&program.items) {
+            let code = match item {
                 crate::parser::Item::App(app) => {
-                    output.push_str(&format!("// App: {}\n", app.name));
+                    let mut code = format!("// App: {}\n", app.name);
                     if let Some(version) = &app.version {
-                        output.push_str(&format!("// Version: {}\n", version));
+                        code.push_str(&format!("// Version: {}\n", version));
                     }
                     if let Some(fps) = app.fps {
-                        output.push_str(&format!("// Target FPS: {}\n", fps));
+                        code.push_str(&format!("// Target FPS: {}\n", fps));
                     }
-                    output.push_str("\n");
-                }
-                crate::parser::Item::Scene(scene) => {
-                    output.push_str(&format!("// Scene: {}\n", scene.name));
-                    output.push_str("// TODO: Generate scene code\n\n");
-                }
-                crate::parser::Item::Animation(anim) => {
-                    output.push_str(&self.generate_swift_animation(anim)?);
-                }
-                crate::parser::Item::Shader(shader) => {
-                    output.push_str(&self.generate_swift_shader(shader)?);
-                }
-                crate::parser::Item::BehaviorTree(bt) => {
-                    output.push_str(&self.generate_swift_behavior_tree(bt)?);
-                }
-                crate::parser::Item::Function(func) => {
-                    output.push_str(&self.generate_swift_function(func)?);
-                }
-                crate::parser::Item::Component(comp) => {
-                    output.push_str(&self.generate_swift_component(comp)?);
-                }
-                crate::parser::Item::Entity(entity) => {
-                    output.push_str(&self.generate_swift_entity(entity)?);
-                }
-                crate::parser::Item::System(system) => {
-                    output.push_str(&self.generate_swift_system(system)?);
-                }
-                crate::parser::Item::App(app) => {
-                    output.push_str(&self.generate_swift_app(app)?);
-                }
-                crate::parser::Item::Scene(scene) => {
-                    output.push_str(&self.generate_swift_scene(scene)?);
-                }
-                crate::parser::Item::BehaviorTree(bt) => {
-                    output.push_str(&self.generate_swift_behavior_tree(bt)?);
-                }
+                    code.push_str("\n");
+                    code
+                }
+                crate::parser::Item::Scene(scene) => self.generate_swift_scene(scene)?,
+                crate::parser::Item::Animation(anim) => self.generate_swift_animation(anim)?,
+                crate::parser::Item::Shader(shader) => self.generate_swift_shader(shader)?,
+                crate::parser::Item::BehaviorTree(bt) => self.generate_swift_behavior_tree(bt)?,
+                crate::parser::Item::Function(func) => self.generate_swift_function(func)?,
+                crate::parser::Item::Component(comp) => self.generate_swift_component(comp)?,
+                crate::parser::Item::Entity(entity) => self.generate_swift_entity(entity)?,
+                crate::parser::Item::System(system) => self.generate_swift_system(system)?,
                 _ => {
                     // Other items
+                    continue;
                 }
-            }
+            };
+            self.emit_item(&mut output, item, &code);
         }
         
         Ok(output)
@@ -195,10 +357,10 @@ impl CodeGenerator {
     }
     
     fn generate_swift_statement(&self, stmt: &crate::parser::Statement) -> GrumpResult<String> {
-        match stmt {
-            crate::parser::Statement::Let { name, type_, value } => {
+        let code: GrumpResult<String> = match stmt {
+            crate::parser::Statement::Let { name, mutable, type_, value, .. } => {
                 let mut code = String::new();
-                code.push_str("let ");
+                code.push_str(if *mutable { "var " } else { "let " });
                 code.push_str(name);
                 if let Some(type_) = type_ {
                     code.push_str(": ");
@@ -209,13 +371,13 @@ impl CodeGenerator {
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Assign { target, value } => {
+            crate::parser::Statement::Assign { target, value, .. } => {
                 Ok(format!("{} = {};", 
                     self.generate_swift_expression(target)?,
                     self.generate_swift_expression(value)?
                 ))
             }
-            crate::parser::Statement::Return(expr) => {
+            crate::parser::Statement::Return(expr, _) => {
                 let mut code = String::new();
                 code.push_str("return");
                 if let Some(expr) = expr {
@@ -225,10 +387,10 @@ impl CodeGenerator {
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 Ok(format!("{};", self.generate_swift_expression(expr)?))
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 let mut code = String::new();
                 code.push_str("if ");
                 code.push_str(&self.generate_swift_expression(condition)?);
@@ -251,9 +413,9 @@ impl CodeGenerator {
                 Ok(code)
             }
             crate::parser::Statement::Await { expr } => {
-                Ok(format!("let _ = try await {};", self.generate_swift_expression(expr)?))
+                Ok(format!("_ = {}", self.swift_await(expr)?))
             }
-            crate::parser::Statement::For { var, iter, body } => {
+            crate::parser::Statement::For { var, iter, body, .. } => {
                 let mut code = String::new();
                 code.push_str("for ");
                 code.push_str(var);
@@ -268,7 +430,7 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Statement::While { condition, body } => {
+            crate::parser::Statement::While { condition, body, .. } => {
                 let mut code = String::new();
                 code.push_str("while ");
                 code.push_str(&self.generate_swift_expression(condition)?);
@@ -288,9 +450,10 @@ impl CodeGenerator {
                 Ok("continue;".to_string())
             }
             crate::parser::Statement::Animate(animate) => {
-                let mut code = String::new();
+                let target = self.generate_swift_expression(&animate.target)?;
+                let mut code = format!("GrumpFrames.shared.animating({}, for: {:?})\n    ", animation_key(&target), animate_seconds(animate));
                 code.push_str("animate(");
-                code.push_str(&self.generate_swift_expression(&animate.target)?);
+                code.push_str(&target);
                 code.push_str(") {\n");
                 if !animate.keyframes.is_empty() {
                     code.push_str("        keyframes: [\n");
@@ -311,7 +474,7 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Statement::Match { expr, arms } => {
+            crate::parser::Statement::Match { expr, arms, .. } => {
                 let mut code = String::new();
                 code.push_str("switch ");
                 code.push_str(&self.generate_swift_expression(expr)?);
@@ -380,7 +543,8 @@ impl CodeGenerator {
             _ => {
                 Ok(format!("// TODO: Generate {}", format!("{:?}", stmt)))
             }
-        }
+        };
+        Ok(located(stmt.span(), None, code?))
     }
     
     fn generate_swift_pattern(&self, pattern: &crate::parser::Pattern) -> GrumpResult<String> {
@@ -453,6 +617,9 @@ impl CodeGenerator {
                 Ok(format!("({} {} {})", left_str, op_str, right_str))
             }
             crate::parser::Expression::Call { func, args } => {
+                if let Some(target) = animation_target(func, args) {
+                    return Ok(format!("animation_finished({})", animation_key(&self.generate_swift_expression(target)?)));
+                }
                 let func_str = self.generate_swift_expression(func)?;
                 let mut args_str = String::new();
                 for (i, arg) in args.iter().enumerate() {
@@ -461,9 +628,7 @@ impl CodeGenerator {
                 }
                 Ok(format!("{}({})", func_str, args_str))
             }
-            crate::parser::Expression::Await(expr) => {
-                Ok(format!("try await {}", self.generate_swift_expression(expr)?))
-            }
+            crate::parser::Expression::Await(expr) => self.swift_await(expr),
             crate::parser::Expression::Array(elements) => {
                 let mut code = String::new();
                 code.push_str("[");
@@ -529,7 +694,7 @@ impl CodeGenerator {
             }
             crate::parser::Expression::AsyncBlock(statements) => {
                 let mut code = String::new();
-                code.push_str("async {\n");
+                code.push_str("Task {\n");
                 for stmt in statements {
                     code.push_str("        ");
                     code.push_str(&self.generate_swift_statement(stmt)?);
@@ -538,15 +703,11 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Expression::MacroCall { name, args } => {
-                let mut code = format!("{}!", name);
-                code.push_str("(");
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 { code.push_str(", "); }
-                    code.push_str(&self.generate_swift_expression(arg)?);
-                }
-                code.push_str(")");
-                Ok(code)
+            crate::parser::Expression::MacroCall { name, args, span } => {
+                let args = args.iter()
+                    .map(|arg| self.generate_swift_expression(arg))
+                    .collect::<GrumpResult<Vec<_>>>()?;
+                template_call(&self.templates, self.target, name, &args, *span)
             }
             _ => {
                 Ok(format!("/* TODO: {}", format!("{:?}", expr)))
@@ -554,10 +715,18 @@ impl CodeGenerator {
         }
     }
     
+    /// `await expr`: calls to async functions are awaited directly, other
+    /// async values are `Task`s
+    fn swift_await(&self, expr: &Expression) -> GrumpResult<String> {
+        let code = self.generate_swift_expression(expr)?;
+        Ok(if self.calls_async(expr) { format!("try await {}", code) } else { format!("try await {}.value", code) })
+    }
+    
     fn swift_literal(&self, lit: &crate::parser::Literal) -> String {
         match lit {
             crate::parser::Literal::Integer(n) => n.to_string(),
             crate::parser::Literal::Float(f) => f.to_string(),
+            crate::parser::Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
             crate::parser::Literal::String(s) => format!("\"{}\"", s),
             crate::parser::Literal::Bool(b) => b.to_string(),
             crate::parser::Literal::Char(c) => format!("'{}'", c),
@@ -587,39 +756,26 @@ impl CodeGenerator {
         output.push_str("// Generated Kotlin + OpenGL code from G-Rump\n");
         output.push_str("package com.grump.generated\n\n");
         output.push_str("import android.opengl.GLES20\n");
-        output.push_str("import android.opengl.GLSurfaceView\n\n");
+        output.push_str("import android.opengl.GLSurfaceView\n");
+        output.push_str("import kotlinx.coroutines.async\n\n");
         
         // Generate code for each item
-        for item in &program.items {
-            match item {
-                crate::parser::Item::Function(func) => {
-                    output.push_str(&self.generate_kotlin_function(func)?);
-                }
-                crate::parser::Item::Component(comp) => {
-                    output.push_str(&self.generate_kotlin_component(comp)?);
-                }
-                crate::parser::Item::Entity(entity) => {
-                    output.push_str(&self.generate_kotlin_entity(entity)?);
-                }
-                crate::parser::Item::System(system) => {
-                    output.push_str(&self.generate_kotlin_system(system)?);
-                }
-                crate::parser::Item::App(app) => {
-                    output.push_str(&self.generate_kotlin_app(app)?);
-                }
-                crate::parser::Item::Scene(scene) => {
-                    output.push_str(&self.generate_kotlin_scene(scene)?);
-                }
-                crate::parser::Item::BehaviorTree(bt) => {
-                    output.push_str(&self.generate_kotlin_behavior_tree(bt)?);
-                }
-                crate::parser::Item::Shader(shader) => {
-                    output.push_str(&self.generate_kotlin_shader(shader)?);
-                }
+        for item in module_items(&program.items) {
+            let code = match item {
+                crate::parser::Item::Function(func) => self.generate_kotlin_function(func)?,
+                crate::parser::Item::Component(comp) => self.generate_kotlin_component(comp)?,
+                crate::parser::Item::Entity(entity) => self.generate_kotlin_entity(entity)?,
+                crate::parser::Item::System(system) => self.generate_kotlin_system(system)?,
+                crate::parser::Item::App(app) => self.generate_kotlin_app(app)?,
+                crate::parser::Item::Scene(scene) => self.generate_kotlin_scene(scene)?,
+                crate::parser::Item::BehaviorTree(bt) => self.generate_kotlin_behavior_tree(bt)?,
+                crate::parser::Item::Shader(shader) => self.generate_kotlin_shader(shader)?,
                 _ => {
                     // Other items
+                    continue;
                 }
-            }
+            };
+            self.emit_item(&mut output, item, &code);
         }
         
         Ok(output)
@@ -662,10 +818,10 @@ impl CodeGenerator {
     }
     
     fn generate_kotlin_statement(&self, stmt: &crate::parser::Statement) -> GrumpResult<String> {
-        match stmt {
-            crate::parser::Statement::Let { name, type_, value } => {
+        let code: GrumpResult<String> = match stmt {
+            crate::parser::Statement::Let { name, mutable, type_, value, .. } => {
                 let mut code = String::new();
-                code.push_str("val ");
+                code.push_str(if *mutable { "var " } else { "val " });
                 code.push_str(name);
                 if let Some(type_) = type_ {
                     code.push_str(": ");
@@ -675,13 +831,13 @@ impl CodeGenerator {
                 code.push_str(&self.generate_kotlin_expression(value)?);
                 Ok(code)
             }
-            crate::parser::Statement::Assign { target, value } => {
+            crate::parser::Statement::Assign { target, value, .. } => {
                 Ok(format!("{} = {}", 
                     self.generate_kotlin_expression(target)?,
                     self.generate_kotlin_expression(value)?
                 ))
             }
-            crate::parser::Statement::Return(expr) => {
+            crate::parser::Statement::Return(expr, _) => {
                 let mut code = String::new();
                 code.push_str("return");
                 if let Some(expr) = expr {
@@ -690,10 +846,10 @@ impl CodeGenerator {
                 }
                 Ok(code)
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 Ok(self.generate_kotlin_expression(expr)?)
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 let mut code = String::new();
                 code.push_str("if (");
                 code.push_str(&self.generate_kotlin_expression(condition)?);
@@ -715,10 +871,8 @@ impl CodeGenerator {
                 }
                 Ok(code)
             }
-            crate::parser::Statement::Await { expr } => {
-                Ok(format!("{}()", self.generate_kotlin_expression(expr)?))
-            }
-            crate::parser::Statement::For { var, iter, body } => {
+            crate::parser::Statement::Await { expr } => self.kotlin_await(expr),
+            crate::parser::Statement::For { var, iter, body, .. } => {
                 let mut code = String::new();
                 code.push_str("for (");
                 code.push_str(var);
@@ -733,7 +887,7 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Statement::While { condition, body } => {
+            crate::parser::Statement::While { condition, body, .. } => {
                 let mut code = String::new();
                 code.push_str("while (");
                 code.push_str(&self.generate_kotlin_expression(condition)?);
@@ -753,9 +907,10 @@ impl CodeGenerator {
                 Ok("continue".to_string())
             }
             crate::parser::Statement::Animate(animate) => {
-                let mut code = String::new();
+                let target = self.generate_kotlin_expression(&animate.target)?;
+                let mut code = format!("GrumpFrames.animating({}, {:?})\n    ", animation_key(&target), animate_seconds(animate));
                 code.push_str("animate(");
-                code.push_str(&self.generate_kotlin_expression(&animate.target)?);
+                code.push_str(&target);
                 code.push_str(") {\n");
                 if !animate.keyframes.is_empty() {
                     code.push_str("        keyframes = listOf(\n");
@@ -774,7 +929,8 @@ impl CodeGenerator {
             _ => {
                 Ok(format!("// TODO: {}", format!("{:?}", stmt)))
             }
-        }
+        };
+        Ok(located(stmt.span(), None, code?))
     }
     
     fn generate_kotlin_expression(&self, expr: &crate::parser::Expression) -> GrumpResult<String> {
@@ -818,6 +974,9 @@ impl CodeGenerator {
                 Ok(format!("({} {} {})", left_str, op_str, right_str))
             }
             crate::parser::Expression::Call { func, args } => {
+                if let Some(target) = animation_target(func, args) {
+                    return Ok(format!("animation_finished({})", animation_key(&self.generate_kotlin_expression(target)?)));
+                }
                 let func_str = self.generate_kotlin_expression(func)?;
                 let mut args_str = String::new();
                 for (i, arg) in args.iter().enumerate() {
@@ -879,9 +1038,10 @@ impl CodeGenerator {
                 code.push_str(&self.generate_kotlin_expression(else_)?);
                 Ok(code)
             }
+            crate::parser::Expression::Await(expr) => self.kotlin_await(expr),
             crate::parser::Expression::AsyncBlock(statements) => {
                 let mut code = String::new();
-                code.push_str("async {\n");
+                code.push_str("grumpScope.async {\n");
                 for stmt in statements {
                     code.push_str("        ");
                     code.push_str(&self.generate_kotlin_statement(stmt)?);
@@ -890,15 +1050,11 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Expression::MacroCall { name, args } => {
-                let mut code = format!("{}!", name);
-                code.push_str("(");
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 { code.push_str(", "); }
-                    code.push_str(&self.generate_kotlin_expression(arg)?);
-                }
-                code.push_str(")");
-                Ok(code)
+            crate::parser::Expression::MacroCall { name, args, span } => {
+                let args = args.iter()
+                    .map(|arg| self.generate_kotlin_expression(arg))
+                    .collect::<GrumpResult<Vec<_>>>()?;
+                template_call(&self.templates, self.target, name, &args, *span)
             }
             _ => {
                 Ok(format!("/* TODO: {}", format!("{:?}", expr)))
@@ -910,6 +1066,7 @@ impl CodeGenerator {
         match lit {
             crate::parser::Literal::Integer(n) => n.to_string(),
             crate::parser::Literal::Float(f) => f.to_string(),
+            crate::parser::Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
             crate::parser::Literal::String(s) => format!("\"{}\"", s),
             crate::parser::Literal::Bool(b) => b.to_string(),
             crate::parser::Literal::Char(c) => format!("'{}'", c),
@@ -1016,36 +1173,22 @@ impl CodeGenerator {
         output.push_str("// Generated JavaScript + WebGL code from G-Rump\n");
         
         // Generate code for each item
-        for item in &program.items {
-            match item {
-                crate::parser::Item::Function(func) => {
-                    output.push_str(&self.generate_javascript_function(func)?);
-                }
-                crate::parser::Item::Component(comp) => {
-                    output.push_str(&self.generate_javascript_component(comp)?);
-                }
-                crate::parser::Item::Entity(entity) => {
-                    output.push_str(&self.generate_javascript_entity(entity)?);
-                }
-                crate::parser::Item::System(system) => {
-                    output.push_str(&self.generate_javascript_system(system)?);
-                }
-                crate::parser::Item::App(app) => {
-                    output.push_str(&self.generate_javascript_app(app)?);
-                }
-                crate::parser::Item::Scene(scene) => {
-                    output.push_str(&self.generate_javascript_scene(scene)?);
-                }
-                crate::parser::Item::BehaviorTree(bt) => {
-                    output.push_str(&self.generate_javascript_behavior_tree(bt)?);
-                }
-                crate::parser::Item::Shader(shader) => {
-                    output.push_str(&self.generate_javascript_shader(shader)?);
-                }
+        for item in module_items(&program.items) {
+            let code = match item {
+                crate::parser::Item::Function(func) => self.generate_javascript_function(func)?,
+                crate::parser::Item::Component(comp) => self.generate_javascript_component(comp)?,
+                crate::parser::Item::Entity(entity) => self.generate_javascript_entity(entity)?,
+                crate::parser::Item::System(system) => self.generate_javascript_system(system)?,
+                crate::parser::Item::App(app) => self.generate_javascript_app(app)?,
+                crate::parser::Item::Scene(scene) => self.generate_javascript_scene(scene)?,
+                crate::parser::Item::BehaviorTree(bt) => self.generate_javascript_behavior_tree(bt)?,
+                crate::parser::Item::Shader(shader) => self.generate_javascript_shader(shader)?,
                 _ => {
                     // Other items
+                    continue;
                 }
-            }
+            };
+            self.emit_item(&mut output, item, &code);
         }
         
         Ok(output)
@@ -1076,23 +1219,23 @@ impl CodeGenerator {
     }
     
     fn generate_javascript_statement(&self, stmt: &crate::parser::Statement) -> GrumpResult<String> {
-        match stmt {
-            crate::parser::Statement::Let { name, type_, value } => {
+        let code: GrumpResult<String> = match stmt {
+            crate::parser::Statement::Let { name, mutable, type_, value, .. } => {
                 let mut code = String::new();
-                code.push_str("let ");
+                code.push_str(if *mutable { "let " } else { "const " });
                 code.push_str(name);
                 code.push_str(" = ");
                 code.push_str(&self.generate_javascript_expression(value)?);
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Assign { target, value } => {
+            crate::parser::Statement::Assign { target, value, .. } => {
                 Ok(format!("{} = {};", 
                     self.generate_javascript_expression(target)?,
                     self.generate_javascript_expression(value)?
                 ))
             }
-            crate::parser::Statement::Return(expr) => {
+            crate::parser::Statement::Return(expr, _) => {
                 let mut code = String::new();
                 code.push_str("return");
                 if let Some(expr) = expr {
@@ -1102,10 +1245,10 @@ impl CodeGenerator {
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 Ok(format!("{};", self.generate_javascript_expression(expr)?))
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 let mut code = String::new();
                 code.push_str("if (");
                 code.push_str(&self.generate_javascript_expression(condition)?);
@@ -1130,7 +1273,7 @@ impl CodeGenerator {
             crate::parser::Statement::Await { expr } => {
                 Ok(format!("await {};", self.generate_javascript_expression(expr)?))
             }
-            crate::parser::Statement::For { var, iter, body } => {
+            crate::parser::Statement::For { var, iter, body, .. } => {
                 let mut code = String::new();
                 code.push_str("for (let ");
                 code.push_str(var);
@@ -1145,7 +1288,7 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Statement::While { condition, body } => {
+            crate::parser::Statement::While { condition, body, .. } => {
                 let mut code = String::new();
                 code.push_str("while (");
                 code.push_str(&self.generate_javascript_expression(condition)?);
@@ -1186,7 +1329,8 @@ impl CodeGenerator {
             _ => {
                 Ok(format!("// TODO: {}", format!("{:?}", stmt)))
             }
-        }
+        };
+        Ok(located(stmt.span(), None, code?))
     }
     
     fn generate_javascript_expression(&self, expr: &crate::parser::Expression) -> GrumpResult<String> {
@@ -1302,15 +1446,11 @@ impl CodeGenerator {
                 code.push_str("    })()");
                 Ok(code)
             }
-            crate::parser::Expression::MacroCall { name, args } => {
-                let mut code = format!("{}!", name);
-                code.push_str("(");
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 { code.push_str(", "); }
-                    code.push_str(&self.generate_javascript_expression(arg)?);
-                }
-                code.push_str(")");
-                Ok(code)
+            crate::parser::Expression::MacroCall { name, args, span } => {
+                let args = args.iter()
+                    .map(|arg| self.generate_javascript_expression(arg))
+                    .collect::<GrumpResult<Vec<_>>>()?;
+                template_call(&self.templates, self.target, name, &args, *span)
             }
             _ => {
                 Ok(format!("/* TODO: {}", format!("{:?}", expr)))
@@ -1322,6 +1462,7 @@ impl CodeGenerator {
         match lit {
             crate::parser::Literal::Integer(n) => n.to_string(),
             crate::parser::Literal::Float(f) => f.to_string(),
+            crate::parser::Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
             crate::parser::Literal::String(s) => format!("\"{}\"", s),
             crate::parser::Literal::Bool(b) => b.to_string(),
             crate::parser::Literal::Char(c) => format!("'{}'", c),
@@ -1438,50 +1579,12 @@ impl CodeGenerator {
         Ok(code)
     }
     
-    fn generate_dart(&mut self, program: &Program) -> GrumpResult<String> {
-        let mut output = String::new();
-        output.push_str("// Generated Dart + Skia code from G-Rump\n");
-        output.push_str("import 'package:flutter/material.dart';\n");
-        output.push_str("import 'package:skia/skia.dart' as skia;\n\n");
-        
-        // Generate code for each item
-        for item in &program.items {
-            match item {
-                crate::parser::Item::Function(func) => {
-                    output.push_str(&self.generate_dart_function(func)?);
-                }
-                crate::parser::Item::Component(comp) => {
-                    output.push_str(&self.generate_dart_component(comp)?);
-                }
-                crate::parser::Item::Entity(entity) => {
-                    output.push_str(&self.generate_dart_entity(entity)?);
-                }
-                crate::parser::Item::System(system) => {
-                    output.push_str(&self.generate_dart_system(system)?);
-                }
-                crate::parser::Item::App(app) => {
-                    output.push_str(&self.generate_dart_app(app)?);
-                }
-                crate::parser::Item::Scene(scene) => {
-                    output.push_str(&self.generate_dart_scene(scene)?);
-                }
-                crate::parser::Item::BehaviorTree(bt) => {
-                    output.push_str(&self.generate_dart_behavior_tree(bt)?);
-                }
-                _ => {
-                    // Other items
-                }
-            }
-        }
-        
-        Ok(output)
-    }
-    
     fn generate_dart_function(&self, func: &crate::parser::FunctionDeclaration) -> GrumpResult<String> {
         let mut code = String::new();
         
         if func.is_async {
-            code.push_str("Future<void> ");
+            let value = func.return_type.as_ref().map(|t| self.dart_type(t)).unwrap_or_else(|| "void".to_string());
+            code.push_str(&format!("Future<{}> ", value));
         } else {
             code.push_str("void ");
         }
@@ -1513,12 +1616,15 @@ impl CodeGenerator {
     }
     
     fn generate_dart_statement(&self, stmt: &crate::parser::Statement) -> GrumpResult<String> {
-        match stmt {
-            crate::parser::Statement::Let { name, type_, value } => {
+        let code: GrumpResult<String> = match stmt {
+            crate::parser::Statement::Let { name, mutable, type_, value, .. } => {
                 let mut code = String::new();
+                if !*mutable {
+                    code.push_str("final ");
+                }
                 if let Some(type_) = type_ {
                     code.push_str(&format!("{} ", self.dart_type(type_)));
-                } else {
+                } else if *mutable {
                     code.push_str("var ");
                 }
                 code.push_str(name);
@@ -1527,7 +1633,7 @@ impl CodeGenerator {
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Return(expr) => {
+            crate::parser::Statement::Return(expr, _) => {
                 let mut code = String::new();
                 code.push_str("return");
                 if let Some(expr) = expr {
@@ -1537,10 +1643,10 @@ impl CodeGenerator {
                 code.push_str(";");
                 Ok(code)
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 Ok(format!("{};", self.generate_dart_expression(expr)?))
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 let mut code = String::new();
                 code.push_str("if (");
                 code.push_str(&self.generate_dart_expression(condition)?);
@@ -1565,7 +1671,7 @@ impl CodeGenerator {
             crate::parser::Statement::Await { expr } => {
                 Ok(format!("await {};", self.generate_dart_expression(expr)?))
             }
-            crate::parser::Statement::For { var, iter, body } => {
+            crate::parser::Statement::For { var, iter, body, .. } => {
                 let mut code = String::new();
                 code.push_str("for (var ");
                 code.push_str(var);
@@ -1580,7 +1686,7 @@ impl CodeGenerator {
                 code.push_str("    }");
                 Ok(code)
             }
-            crate::parser::Statement::While { condition, body } => {
+            crate::parser::Statement::While { condition, body, .. } => {
                 let mut code = String::new();
                 code.push_str("while (");
                 code.push_str(&self.generate_dart_expression(condition)?);
@@ -1600,9 +1706,10 @@ impl CodeGenerator {
                 Ok("continue;".to_string())
             }
             crate::parser::Statement::Animate(animate) => {
-                let mut code = String::new();
+                let target = self.generate_dart_expression(&animate.target)?;
+                let mut code = format!("GrumpClock.animating({}, {:?});\n    ", animation_key(&target), animate_seconds(animate));
                 code.push_str("animate(");
-                code.push_str(&self.generate_dart_expression(&animate.target)?);
+                code.push_str(&target);
                 code.push_str(", [\n");
                 for kf in &animate.keyframes {
                     code.push_str("        Keyframe(");
@@ -1617,7 +1724,8 @@ impl CodeGenerator {
             _ => {
                 Ok(format!("// TODO: {}", format!("{:?}", stmt)))
             }
-        }
+        };
+        Ok(located(stmt.span(), None, code?))
     }
     
     fn generate_dart_expression(&self, expr: &crate::parser::Expression) -> GrumpResult<String> {
@@ -1663,6 +1771,9 @@ impl CodeGenerator {
                 Ok(format!("({} {} {})", left_str, op_str, right_str))
             }
             crate::parser::Expression::Call { func, args } => {
+                if let Some(target) = animation_target(func, args) {
+                    return Ok(format!("animation_finished({})", animation_key(&self.generate_dart_expression(target)?)));
+                }
                 let func_str = self.generate_dart_expression(func)?;
                 let mut args_str = String::new();
                 for (i, arg) in args.iter().enumerate() {
@@ -1723,6 +1834,9 @@ impl CodeGenerator {
                 code.push_str(&self.generate_dart_expression(else_)?);
                 Ok(code)
             }
+            crate::parser::Expression::Await(expr) => {
+                Ok(format!("(await {})", self.generate_dart_expression(expr)?))
+            }
             crate::parser::Expression::AsyncBlock(statements) => {
                 let mut code = String::new();
                 code.push_str("(() async {\n");
@@ -1734,15 +1848,11 @@ impl CodeGenerator {
                 code.push_str("    })()");
                 Ok(code)
             }
-            crate::parser::Expression::MacroCall { name, args } => {
-                let mut code = format!("{}!", name);
-                code.push_str("(");
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 { code.push_str(", "); }
-                    code.push_str(&self.generate_dart_expression(arg)?);
-                }
-                code.push_str(")");
-                Ok(code)
+            crate::parser::Expression::MacroCall { name, args, span } => {
+                let args = args.iter()
+                    .map(|arg| self.generate_dart_expression(arg))
+                    .collect::<GrumpResult<Vec<_>>>()?;
+                template_call(&self.templates, self.target, name, &args, *span)
             }
             _ => {
                 Ok(format!("/* TODO: {}", format!("{:?}", expr)))
@@ -1754,6 +1864,7 @@ impl CodeGenerator {
         match lit {
             crate::parser::Literal::Integer(n) => n.to_string(),
             crate::parser::Literal::Float(f) => f.to_string(),
+            crate::parser::Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
             crate::parser::Literal::String(s) => format!("\"{}\"", s),
             crate::parser::Literal::Bool(b) => b.to_string(),
             crate::parser::Literal::Char(c) => format!("'{}'", c),
@@ -1819,110 +1930,14 @@ impl CodeGenerator {
         Ok(code)
     }
     
-    fn generate_dart_component(&self, comp: &crate::parser::ComponentDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", comp.name);
-        for field in &comp.fields {
-            code.push_str("    ");
-            code.push_str(&self.dart_type(&field.type_));
-            code.push_str(" ");
-            code.push_str(&field.name);
-            if let Some(default) = &field.default {
-                code.push_str(" = ");
-                code.push_str(&self.generate_dart_expression(default)?);
-            }
-            code.push_str(";\n");
-        }
-        code.push_str("    \n");
-        code.push_str("    {}(", comp.name);
-        for (i, field) in comp.fields.iter().enumerate() {
-            if i > 0 { code.push_str(", "); }
-            code.push_str(&self.dart_type(&field.type_));
-            code.push_str(" this.");
-            code.push_str(&field.name);
-        }
-        code.push_str(");\n");
-        code.push_str("}\n\n");
-        Ok(code)
-    }
-    
-    fn generate_dart_entity(&self, entity: &crate::parser::EntityDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", entity.name);
-        for comp in &entity.components {
-            code.push_str("    ");
-            code.push_str(&comp.name);
-            code.push_str(" ");
-            code.push_str(&comp.name);
-            code.push_str(";\n");
-        }
-        code.push_str("    \n");
-        code.push_str("    {}() {{\n", entity.name);
-        for comp in &entity.components {
-            code.push_str("        this.");
-            code.push_str(&comp.name);
-            code.push_str(" = ");
-            code.push_str(&comp.name);
-            code.push_str("(");
-            for (i, arg) in comp.args.iter().enumerate() {
-                if i > 0 { code.push_str(", "); }
-                code.push_str(&self.generate_dart_expression(arg)?);
-            }
-            code.push_str(");\n");
-        }
-        code.push_str("    }}\n");
-        code.push_str("}}\n\n");
-        Ok(code)
-    }
-    
-    fn generate_dart_system(&self, system: &crate::parser::SystemDeclaration) -> GrumpResult<String> {
-        let mut code = format!("void {}() {{\n", system.name);
-        if !system.query.is_empty() {
-            code.push_str("    final entities = world.query([");
-            for (i, comp) in system.query.iter().enumerate() {
-                if i > 0 { code.push_str(", "); }
-                code.push_str(&format!("\"{}\"", comp));
-            }
-            code.push_str("]);\n");
-            code.push_str("    for (final entity in entities) {{\n");
-            for stmt in &system.body {
-                code.push_str("        ");
-                code.push_str(&self.generate_dart_statement(stmt)?);
-                code.push_str("\n");
-            }
-            code.push_str("    }}\n");
-        } else {
-            for stmt in &system.body {
-                code.push_str("    ");
-                code.push_str(&self.generate_dart_statement(stmt)?);
-                code.push_str("\n");
-            }
-        }
-        code.push_str("}}\n\n");
-        Ok(code)
-    }
-    
-    fn generate_swift_app(&self, app: &crate::parser::AppDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", app.name);
-        for item in &app.body {
-            if let crate::parser::Item::Scene(scene) = item {
-                code.push_str("    var {}: {}\n", scene.name, scene.name);
-            }
-        }
-        code.push_str("    \n");
-        code.push_str("    func start() {{\n");
-        code.push_str("        // App initialization\n");
-        code.push_str("    }}\n");
-        code.push_str("}}\n\n");
-        Ok(code)
-    }
-    
     fn generate_swift_scene(&self, scene: &crate::parser::SceneDeclaration) -> GrumpResult<String> {
         let mut code = format!("class {} {{\n", scene.name);
         for stmt in &scene.body {
             code.push_str("    ");
             code.push_str(&self.generate_swift_statement(stmt)?);
-            code.push_str("\n");
+            code.push('\n');
         }
-        code.push_str("}}\n\n");
+        code.push_str("}\n\n");
         Ok(code)
     }
     
@@ -1962,35 +1977,143 @@ impl CodeGenerator {
         code.push_str("}\n\n");
         Ok(code)
     }
-    
-    fn generate_dart_app(&self, app: &crate::parser::AppDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", app.name);
-        code.push_str("    void start() {{\n");
-        code.push_str("        // App initialization\n");
-        code.push_str("    }}\n");
-        code.push_str("}}\n\n");
-        Ok(code)
-    }
-    
-    fn generate_dart_scene(&self, scene: &crate::parser::SceneDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", scene.name);
-        for stmt in &scene.body {
-            code.push_str("    ");
-            code.push_str(&self.generate_dart_statement(stmt)?);
-            code.push_str("\n");
+}
+
+/// Async built-ins, which every runtime provides
+const ASYNC_BUILTINS: &[&str] = &["wait", "animation_finished"];
+
+impl CodeGenerator {
+    /// Whether `expr` calls an async function. Targets whose async values
+    /// are tasks await those differently from calls.
+    fn calls_async(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Call { func, .. } => matches!(func.as_ref(), Expression::Identifier(name) if self.async_functions.contains(name)),
+            _ => false,
         }
-        code.push_str("}}\n\n");
-        Ok(code)
     }
     
-    fn generate_dart_behavior_tree(&self, bt: &crate::parser::extensions::BehaviorTreeDeclaration) -> GrumpResult<String> {
-        let mut code = format!("class {} {{\n", bt.name);
-        code.push_str("    bool execute() {{\n");
-        code.push_str("        // Behavior tree logic\n");
-        code.push_str("        return true;\n");
-        code.push_str("    }}\n");
-        code.push_str("}}\n\n");
-        Ok(code)
+    /// `await expr`: calls to suspend functions suspend by themselves, other
+    /// async values are `Deferred`s
+    fn kotlin_await(&self, expr: &Expression) -> GrumpResult<String> {
+        let code = self.generate_kotlin_expression(expr)?;
+        Ok(if self.calls_async(expr) { code } else { format!("{}.await()", code) })
     }
 }
 
+/// The `x` of an `animation_finished(x)` call. Runtimes track the `animate`s
+/// running on a target by the target's code, so the call passes that
+/// instead of `x`'s value.
+pub(super) fn animation_target<'a>(func: &Expression, args: &'a [Expression]) -> Option<&'a Expression> {
+    match (func, args) {
+        (Expression::Identifier(name), [target]) if name == "animation_finished" => Some(target),
+        _ => None,
+    }
+}
+
+/// `items` with each module's items in its place, recursively; other
+/// files of a project come through as modules
+fn module_items(items: &[Item]) -> Vec<&Item> {
+    items.iter()
+        .flat_map(|item| match item {
+            Item::Module(module) => module_items(&module.items),
+            item => vec![item],
+        })
+        .collect()
+}
+
+/// A target's code as a string literal, the key runtimes track its `animate`s by
+pub(super) fn animation_key(code: &str) -> String {
+    format!("{:?}", code)
+}
+
+/// How long an `animate` runs: its duration, or else its last keyframe's
+/// time; only literals count
+fn animate_seconds(animate: &AnimateStatement) -> f64 {
+    let seconds = |expr: &Expression| match expr {
+        Expression::Literal(Literal::Duration { value, unit }) => Some(duration_seconds(*value, unit)),
+        Expression::Literal(Literal::Float(f)) => Some(*f),
+        Expression::Literal(Literal::Integer(n)) => Some(*n as f64),
+        _ => None,
+    };
+    animate.duration.as_ref().and_then(seconds)
+        .or_else(|| animate.keyframes.iter().filter_map(|kf| seconds(&kf.time)).reduce(f64::max))
+        .unwrap_or(0.0)
+}
+
+/// A duration literal in seconds
+pub(super) fn duration_seconds(value: f64, unit: &str) -> f64 {
+    match unit {
+        "ms" => value / 1000.0,
+        "min" | "m" => value * 60.0,
+        _ => value,
+    }
+}
+
+/// An angle literal in radians
+pub(super) fn angle_radians(value: f64, unit: &str) -> f64 {
+    match unit {
+        "deg" => value.to_radians(),
+        "turn" | "turns" => value * std::f64::consts::TAU,
+        _ => value,
+    }
+}
+
+/// Does any `return` in the body carry a value?
+pub(super) fn returns_value(body: &[Statement]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Statement::Return(Some(_), _) => true,
+        Statement::If { then, else_, .. } => returns_value(then) || else_.as_deref().map(returns_value).unwrap_or(false),
+        Statement::For { body, .. } | Statement::While { body, .. } => returns_value(body),
+        Statement::Match { arms, .. } => arms.iter().any(|arm| returns_value(&arm.body)),
+        _ => false,
+    })
+}
+
+/// Code for a call to template macro `name`, given the code for its arguments
+pub(crate) fn template_call<'a>(
+    macros: impl IntoIterator<Item = &'a MacroDeclaration>,
+    target: Target,
+    name: &str,
+    args: &[String],
+    span: Span,
+) -> GrumpResult<String> {
+    match macros.into_iter().find(|decl| decl.name == name) {
+        Some(decl) => template::instantiate(decl, target, args, span),
+        None => Err(GrumpError::Other(anyhow::anyhow!(
+            "Macro '{}!' must be expanded before {} code generation", name, target.name()
+        ))),
+    }
+}
+
+/// `my_game` / `My Game` -> `MyGame`
+pub(crate) fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// `MyGame` / `My Game` -> `my_game`
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && prev_lower && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            prev_lower = c.is_lowercase() || c.is_numeric();
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+            prev_lower = false;
+        }
+    }
+    out.trim_end_matches('_').to_string()
+}
@@ -0,0 +1,231 @@
+//! Virtual output tree for code generation
+//!
+//! Codegen produces a set of files (sources, manifests, assets and runtime
+//! support) keyed by path relative to the target's output directory. The tree
+//! is only written to disk at the end, atomically, by `write_atomic`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{GrumpError, GrumpResult};
+
+/// File every build leaves in its output directory, marking it as one
+/// `write_atomic` may replace
+pub const BUILD_MARKER: &str = ".grump-build";
+
+const MARKER_CONTENTS: &str = "Generated by grump. `grump build` replaces this directory.\n";
+
+/// Contents of a single generated file
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileContents {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl FileContents {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            FileContents::Text(text) => text.as_bytes(),
+            FileContents::Binary(bytes) => bytes,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            FileContents::Text(text) => Some(text),
+            FileContents::Binary(_) => None,
+        }
+    }
+}
+
+/// Generated project files, keyed by `/`-separated path relative to the output directory
+#[derive(Debug, Clone, Default)]
+pub struct OutputTree {
+    files: BTreeMap<String, FileContents>,
+    entry: Option<String>,
+    assets_dir: Option<String>,
+}
+
+impl OutputTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_text(&mut self, path: impl Into<String>, contents: impl Into<String>) {
+        self.files.insert(path.into(), FileContents::Text(contents.into()));
+    }
+
+    pub fn add_binary(&mut self, path: impl Into<String>, contents: Vec<u8>) {
+        self.files.insert(path.into(), FileContents::Binary(contents));
+    }
+
+    /// Mark the file holding the main generated source (shown by the web playground)
+    pub fn set_entry(&mut self, path: impl Into<String>) {
+        self.entry = Some(path.into());
+    }
+
+    pub fn entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    /// Directory that project assets are copied into
+    pub fn set_assets_dir(&mut self, path: impl Into<String>) {
+        self.assets_dir = Some(path.into());
+    }
+
+    pub fn assets_dir(&self) -> Option<&str> {
+        self.assets_dir.as_deref()
+    }
+
+    /// Add a file under the assets directory (or the root if the target has none)
+    pub fn add_asset(&mut self, relative: &str, contents: Vec<u8>) {
+        let path = match &self.assets_dir {
+            Some(dir) => format!("{}/{}", dir, relative),
+            None => relative.to_string(),
+        };
+        self.add_binary(path, contents);
+    }
+
+    /// Text of the entry file, if there is one
+    pub fn entry_source(&self) -> Option<&str> {
+        self.entry.as_ref().and_then(|path| self.get_text(path))
+    }
+
+    pub fn get(&self, path: &str) -> Option<&FileContents> {
+        self.files.get(path)
+    }
+
+    pub fn get_text(&self, path: &str) -> Option<&str> {
+        self.files.get(path).and_then(FileContents::as_text)
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<FileContents> {
+        self.files.remove(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FileContents)> {
+        self.files.iter()
+    }
+
+    /// Paths and contents of the text files, for rewriting in place
+    pub fn texts_mut(&mut self) -> impl Iterator<Item = (&String, &mut String)> {
+        self.files.iter_mut().filter_map(|(path, contents)| match contents {
+            FileContents::Text(text) => Some((path, text)),
+            FileContents::Binary(_) => None,
+        })
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.files.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Move every file of `other` under `prefix` (e.g. a sub-project)
+    pub fn merge_under(&mut self, prefix: &str, other: OutputTree) {
+        for (path, contents) in other.files {
+            let path = if prefix.is_empty() { path } else { format!("{}/{}", prefix.trim_end_matches('/'), path) };
+            self.files.insert(path, contents);
+        }
+    }
+
+    /// Write the tree to `dest`, replacing a previous build there.
+    ///
+    /// Files are staged in a sibling directory and swapped in with renames, so
+    /// a failed build never leaves a half-written `dest` behind. Each build
+    /// leaves a [`BUILD_MARKER`] file, and an existing `dest` is only replaced
+    /// if it has one (or is empty), so pointing `--output` at the wrong
+    /// directory can't delete it.
+    pub fn write_atomic(&self, dest: &Path) -> GrumpResult<()> {
+        for path in self.files.keys() {
+            Self::validate_path(path)?;
+        }
+        if dest.exists() && !Self::replaceable(dest)? {
+            return Err(GrumpError::Other(anyhow::anyhow!(
+                "Refusing to replace '{}': it has no {} file, so grump didn't build it",
+                dest.display(),
+                BUILD_MARKER
+            )));
+        }
+
+        let parent = dest.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(parent)?;
+        let name = dest.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "build".to_string());
+        let staging = parent.join(format!(".{}.tmp-{}", name, std::process::id()));
+        let backup = parent.join(format!(".{}.old-{}", name, std::process::id()));
+
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        let swapped = self.write_into(&staging)
+            .and_then(|()| Ok(std::fs::write(staging.join(BUILD_MARKER), MARKER_CONTENTS)?))
+            .and_then(|()| Self::swap(&staging, dest, &backup));
+        if let Err(e) = swapped {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        if backup.exists() {
+            std::fs::remove_dir_all(&backup)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `dest` is a previous build, or an empty directory
+    fn replaceable(dest: &Path) -> GrumpResult<bool> {
+        if !dest.is_dir() {
+            return Ok(false);
+        }
+        Ok(dest.join(BUILD_MARKER).is_file() || std::fs::read_dir(dest)?.next().is_none())
+    }
+
+    /// Move `dest` aside to `backup` and `staging` into its place, putting
+    /// `dest` back if that fails
+    fn swap(staging: &Path, dest: &Path, backup: &Path) -> GrumpResult<()> {
+        if dest.exists() {
+            std::fs::rename(dest, backup)?;
+        }
+        if let Err(e) = std::fs::rename(staging, dest) {
+            if backup.exists() {
+                let _ = std::fs::rename(backup, dest);
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn write_into(&self, dir: &Path) -> GrumpResult<()> {
+        std::fs::create_dir_all(dir)?;
+        for (path, contents) in &self.files {
+            let full: PathBuf = dir.join(path);
+            if let Some(parent) = full.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&full, contents.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Generated paths must stay inside the output directory
+    fn validate_path(path: &str) -> GrumpResult<()> {
+        let escapes = path.is_empty()
+            || path.starts_with('/')
+            || path.contains('\\')
+            || path.split('/').any(|part| part == ".." || part.is_empty());
+        if escapes {
+            return Err(GrumpError::Other(anyhow::anyhow!("Invalid output path: '{}'", path)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,494 @@
+//! Per-target project layouts
+//!
+//! Wraps generated sources in the scaffolding each platform's tooling expects,
+//! so `build/<target>` can be opened directly: a Swift package for Xcode, a
+//! Gradle project for Android Studio, and a static site for browsers.
+
+use crate::parser::{
+    Program, Item, AppDeclaration, SceneDeclaration, EntityDeclaration, ComponentDeclaration,
+    EnumDeclaration, SystemDeclaration, AnimationDeclaration, FunctionDeclaration,
+};
+use crate::parser::extensions::{MacroBody, MacroDeclaration, NetworkDeclaration};
+use super::{OutputTree, pascal_case, snake_case};
+
+/// Items collected from the program (including those nested in `app` bodies)
+pub(super) struct ProgramItems<'a> {
+    pub app: Option<&'a AppDeclaration>,
+    pub scenes: Vec<&'a SceneDeclaration>,
+    pub entities: Vec<&'a EntityDeclaration>,
+    pub components: Vec<&'a ComponentDeclaration>,
+    pub enums: Vec<&'a EnumDeclaration>,
+    pub systems: Vec<&'a SystemDeclaration>,
+    pub animations: Vec<&'a AnimationDeclaration>,
+    pub functions: Vec<&'a FunctionDeclaration>,
+    /// Template macros; code macros are expanded before codegen
+    pub macros: Vec<&'a MacroDeclaration>,
+    pub networks: Vec<&'a NetworkDeclaration>,
+}
+
+impl<'a> ProgramItems<'a> {
+    pub fn collect(program: &'a Program) -> Self {
+        let mut items = Self {
+            app: None,
+            scenes: Vec::new(),
+            entities: Vec::new(),
+            components: Vec::new(),
+            enums: Vec::new(),
+            systems: Vec::new(),
+            animations: Vec::new(),
+            functions: Vec::new(),
+            macros: Vec::new(),
+            networks: Vec::new(),
+        };
+        for item in &program.items {
+            items.add(item);
+        }
+        items
+    }
+
+    fn add(&mut self, item: &'a Item) {
+        match item {
+            Item::App(app) => {
+                if self.app.is_none() {
+                    self.app = Some(app);
+                }
+                for nested in &app.body {
+                    self.add(nested);
+                }
+            }
+            Item::Scene(scene) => self.scenes.push(scene),
+            Item::Entity(entity) => self.entities.push(entity),
+            Item::Component(comp) => self.components.push(comp),
+            Item::Enum(enum_decl) => self.enums.push(enum_decl),
+            Item::System(system) => self.systems.push(system),
+            Item::Animation(anim) => self.animations.push(anim),
+            Item::Function(func) => self.functions.push(func),
+            Item::Macro(decl) if matches!(decl.body, MacroBody::Template(_)) => self.macros.push(decl),
+            Item::Network(network) => self.networks.push(network),
+            Item::Module(module) => {
+                for nested in &module.items {
+                    self.add(nested);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn component(&self, name: &str) -> Option<&'a ComponentDeclaration> {
+        self.components.iter().copied().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Enum declaring `variant`, if any
+    pub fn enum_of_variant(&self, variant: &str) -> Option<&'a EnumDeclaration> {
+        self.enums.iter().copied().find(|e| e.variants.iter().any(|v| v == variant))
+    }
+}
+
+/// App metadata shared by all project layouts
+pub(super) struct AppInfo {
+    pub name: String,
+    pub version: String,
+    pub fps: f64,
+}
+
+impl AppInfo {
+    pub fn from_program(program: &Program) -> Self {
+        let app = program.items.iter().find_map(|item| match item {
+            Item::App(app) => Some(app),
+            _ => None,
+        });
+        Self {
+            name: app.map(|a| a.name.clone()).unwrap_or_else(|| "GrumpGame".to_string()),
+            version: app.and_then(|a| a.version.clone()).unwrap_or_else(|| "1.0.0".to_string()),
+            fps: app.and_then(|a| a.fps).unwrap_or(60.0),
+        }
+    }
+
+    /// Identifier-safe module name (`Flappy Clone` -> `FlappyClone`)
+    pub fn module_name(&self) -> String {
+        let name = pascal_case(&self.name);
+        if name.is_empty() { "GrumpGame".to_string() } else { name }
+    }
+}
+
+/// Swift package that Xcode opens directly (File > Open > Package.swift)
+pub(super) fn ios_project(app: &AppInfo, source: String) -> OutputTree {
+    let module = app.module_name();
+    let sources = format!("Sources/{}", module);
+
+    let mut package = String::new();
+    package.push_str("// swift-tools-version:5.9\n");
+    package.push_str("// Generated by G-Rump\n");
+    package.push_str("import PackageDescription\n\n");
+    package.push_str("let package = Package(\n");
+    package.push_str(&format!("    name: \"{}\",\n", module));
+    package.push_str("    platforms: [.iOS(.v16), .macOS(.v13)],\n");
+    package.push_str("    targets: [\n");
+    package.push_str("        .executableTarget(\n");
+    package.push_str(&format!("            name: \"{}\",\n", module));
+    package.push_str("            resources: [.process(\"Resources\")]\n");
+    package.push_str("        ),\n");
+    package.push_str("    ]\n");
+    package.push_str(")\n");
+
+    let mut main = String::new();
+    main.push_str("// Generated by G-Rump\n");
+    main.push_str("import SwiftUI\n\n");
+    main.push_str("@main\n");
+    main.push_str(&format!("struct {}App: App {{\n", module));
+    main.push_str("    var body: some Scene {\n");
+    main.push_str("        WindowGroup {\n");
+    main.push_str("            GrumpGameView()\n");
+    main.push_str("        }\n");
+    main.push_str("    }\n");
+    main.push_str("}\n\n");
+    main.push_str("struct GrumpGameView: View {\n");
+    main.push_str(&format!("    private let frameInterval = 1.0 / {:?}\n\n", app.fps));
+    main.push_str("    var body: some View {\n");
+    main.push_str("        TimelineView(.animation(minimumInterval: frameInterval)) { context in\n");
+    main.push_str("            Canvas { _, _ in\n");
+    main.push_str("                world.update(at: context.date)\n");
+    main.push_str("            }\n");
+    main.push_str("        }\n");
+    main.push_str("        .ignoresSafeArea()\n");
+    main.push_str("    }\n");
+    main.push_str("}\n");
+
+    let mut tree = OutputTree::new();
+    tree.add_text("Package.swift", package);
+    tree.add_text(format!("{}/{}App.swift", sources, module), main);
+    tree.add_text(format!("{}/Game.swift", sources), source);
+    tree.add_text(format!("{}/GrumpRuntime.swift", sources), SWIFT_RUNTIME);
+    tree.add_text(format!("{}/Resources/.keep", sources), "");
+    tree.set_entry(format!("{}/Game.swift", sources));
+    tree.set_assets_dir(format!("{}/Resources", sources));
+    tree
+}
+
+/// Gradle project that Android Studio opens directly
+pub(super) fn android_project(app: &AppInfo, source: String) -> OutputTree {
+    let package_dir = "app/src/main/java/com/grump/generated";
+
+    let mut settings = String::new();
+    settings.push_str("// Generated by G-Rump\n");
+    settings.push_str("pluginManagement {\n");
+    settings.push_str("    repositories {\n");
+    settings.push_str("        google()\n");
+    settings.push_str("        mavenCentral()\n");
+    settings.push_str("        gradlePluginPortal()\n");
+    settings.push_str("    }\n");
+    settings.push_str("}\n\n");
+    settings.push_str("dependencyResolutionManagement {\n");
+    settings.push_str("    repositories {\n");
+    settings.push_str("        google()\n");
+    settings.push_str("        mavenCentral()\n");
+    settings.push_str("    }\n");
+    settings.push_str("}\n\n");
+    settings.push_str(&format!("rootProject.name = \"{}\"\n", app.module_name()));
+    settings.push_str("include(\":app\")\n");
+
+    let mut root_build = String::new();
+    root_build.push_str("// Generated by G-Rump\n");
+    root_build.push_str("plugins {\n");
+    root_build.push_str("    id(\"com.android.application\") version \"8.5.0\" apply false\n");
+    root_build.push_str("    id(\"org.jetbrains.kotlin.android\") version \"1.9.24\" apply false\n");
+    root_build.push_str("}\n");
+
+    let mut app_build = String::new();
+    app_build.push_str("// Generated by G-Rump\n");
+    app_build.push_str("plugins {\n");
+    app_build.push_str("    id(\"com.android.application\")\n");
+    app_build.push_str("    id(\"org.jetbrains.kotlin.android\")\n");
+    app_build.push_str("}\n\n");
+    app_build.push_str("android {\n");
+    app_build.push_str("    namespace = \"com.grump.generated\"\n");
+    app_build.push_str("    compileSdk = 34\n\n");
+    app_build.push_str("    defaultConfig {\n");
+    app_build.push_str(&format!("        applicationId = \"com.grump.{}\"\n", snake_case(&app.name)));
+    app_build.push_str("        minSdk = 24\n");
+    app_build.push_str("        targetSdk = 34\n");
+    app_build.push_str("        versionCode = 1\n");
+    app_build.push_str(&format!("        versionName = \"{}\"\n", app.version));
+    app_build.push_str("    }\n\n");
+    app_build.push_str("    compileOptions {\n");
+    app_build.push_str("        sourceCompatibility = JavaVersion.VERSION_17\n");
+    app_build.push_str("        targetCompatibility = JavaVersion.VERSION_17\n");
+    app_build.push_str("    }\n\n");
+    app_build.push_str("    kotlinOptions {\n");
+    app_build.push_str("        jvmTarget = \"17\"\n");
+    app_build.push_str("    }\n");
+    app_build.push_str("}\n\n");
+    app_build.push_str("dependencies {\n");
+    app_build.push_str("    implementation(\"org.jetbrains.kotlinx:kotlinx-coroutines-android:1.8.1\")\n");
+    app_build.push_str("}\n");
+
+    let mut manifest = String::new();
+    manifest.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    manifest.push_str("<!-- Generated by G-Rump -->\n");
+    manifest.push_str("<manifest xmlns:android=\"http://schemas.android.com/apk/res/android\">\n");
+    manifest.push_str("    <uses-feature android:glEsVersion=\"0x00020000\" android:required=\"true\" />\n");
+    manifest.push_str(&format!("    <application android:label=\"{}\">\n", app.name));
+    manifest.push_str("        <activity android:name=\".MainActivity\" android:exported=\"true\">\n");
+    manifest.push_str("            <intent-filter>\n");
+    manifest.push_str("                <action android:name=\"android.intent.action.MAIN\" />\n");
+    manifest.push_str("                <category android:name=\"android.intent.category.LAUNCHER\" />\n");
+    manifest.push_str("            </intent-filter>\n");
+    manifest.push_str("        </activity>\n");
+    manifest.push_str("    </application>\n");
+    manifest.push_str("</manifest>\n");
+
+    let mut activity = String::new();
+    activity.push_str("// Generated by G-Rump\n");
+    activity.push_str("package com.grump.generated\n\n");
+    activity.push_str("import android.app.Activity\n");
+    activity.push_str("import android.opengl.GLES20\n");
+    activity.push_str("import android.opengl.GLSurfaceView\n");
+    activity.push_str("import android.os.Bundle\n");
+    activity.push_str("import javax.microedition.khronos.egl.EGLConfig\n");
+    activity.push_str("import javax.microedition.khronos.opengles.GL10\n\n");
+    activity.push_str("class MainActivity : Activity() {\n");
+    activity.push_str("    override fun onCreate(savedInstanceState: Bundle?) {\n");
+    activity.push_str("        super.onCreate(savedInstanceState)\n");
+    activity.push_str("        val view = GLSurfaceView(this)\n");
+    activity.push_str("        view.setEGLContextClientVersion(2)\n");
+    activity.push_str("        view.setRenderer(GrumpRenderer())\n");
+    activity.push_str("        setContentView(view)\n");
+    activity.push_str("    }\n");
+    activity.push_str("}\n\n");
+    activity.push_str("class GrumpRenderer : GLSurfaceView.Renderer {\n");
+    activity.push_str("    private var lastFrame = System.nanoTime()\n\n");
+    activity.push_str("    override fun onSurfaceCreated(gl: GL10?, config: EGLConfig?) {\n");
+    activity.push_str("        GLES20.glClearColor(0f, 0f, 0f, 1f)\n");
+    activity.push_str("    }\n\n");
+    activity.push_str("    override fun onSurfaceChanged(gl: GL10?, width: Int, height: Int) {\n");
+    activity.push_str("        GLES20.glViewport(0, 0, width, height)\n");
+    activity.push_str("    }\n\n");
+    activity.push_str("    override fun onDrawFrame(gl: GL10?) {\n");
+    activity.push_str("        val now = System.nanoTime()\n");
+    activity.push_str("        world.update((now - lastFrame) / 1_000_000_000.0)\n");
+    activity.push_str("        lastFrame = now\n");
+    activity.push_str("        GLES20.glClear(GLES20.GL_COLOR_BUFFER_BIT)\n");
+    activity.push_str("    }\n");
+    activity.push_str("}\n");
+
+    let mut tree = OutputTree::new();
+    tree.add_text("settings.gradle.kts", settings);
+    tree.add_text("build.gradle.kts", root_build);
+    tree.add_text("gradle.properties", "android.useAndroidX=true\nkotlin.code.style=official\n");
+    tree.add_text("app/build.gradle.kts", app_build);
+    tree.add_text("app/src/main/AndroidManifest.xml", manifest);
+    tree.add_text(format!("{}/MainActivity.kt", package_dir), activity);
+    tree.add_text(format!("{}/Game.kt", package_dir), source);
+    tree.add_text(format!("{}/GrumpRuntime.kt", package_dir), KOTLIN_RUNTIME);
+    tree.set_entry(format!("{}/Game.kt", package_dir));
+    tree.set_assets_dir("app/src/main/assets");
+    tree
+}
+
+/// Static site: open `index.html` in a browser (or serve the directory)
+pub(super) fn web_project(mut tree: OutputTree) -> OutputTree {
+    tree.set_assets_dir("assets");
+    tree
+}
+
+const SWIFT_RUNTIME: &str = r#"// G-Rump runtime support for Swift
+import Foundation
+
+protocol Component {}
+
+enum LoopMode {
+    case none, loop, pingPong, reverse
+}
+
+struct Keyframe {
+    let time: Double
+    let value: Any
+}
+
+struct Property {
+    let target: Any
+    let keyframes: [(String, Any)]
+}
+
+struct TimelineEntry {
+    let time: Double
+    let properties: [Property]
+}
+
+struct TimelineAnimation {
+    let entries: [TimelineEntry]
+}
+
+final class GrumpEntity {
+    var components: [ObjectIdentifier: Any] = [:]
+
+    func get<T>(_ type: T.Type) -> T? {
+        components[ObjectIdentifier(type)] as? T
+    }
+
+    func set<T>(_ component: T) {
+        components[ObjectIdentifier(T.self)] = component
+    }
+}
+
+final class GrumpWorld {
+    private(set) var entities: [GrumpEntity] = []
+    var systems: [(Double) -> Void] = []
+    private var lastUpdate: Date?
+
+    func spawn() -> GrumpEntity {
+        let entity = GrumpEntity()
+        entities.append(entity)
+        return entity
+    }
+
+    func query(_ types: [Any.Type]) -> [GrumpEntity] {
+        entities.filter { entity in
+            types.allSatisfy { entity.components[ObjectIdentifier($0)] != nil }
+        }
+    }
+
+    func update(at date: Date) {
+        let delta = lastUpdate.map { date.timeIntervalSince($0) } ?? 0
+        lastUpdate = date
+        GrumpFrames.shared.tick(delta)
+        for system in systems {
+            system(delta)
+        }
+    }
+}
+
+let world = GrumpWorld()
+
+/// Game time, and the tasks waiting on it; `GrumpWorld.update` ticks it
+final class GrumpFrames {
+    static let shared = GrumpFrames()
+
+    private(set) var elapsed: Double = 0
+    private var animations: [String: Double] = [:]
+    private var waiters: [(ready: () -> Bool, resume: CheckedContinuation<Void, Never>)] = []
+
+    /// Record an `animate` of the target keyed `key` that runs for `seconds`
+    func animating(_ key: String, for seconds: Double) {
+        animations[key] = max(animations[key] ?? 0, elapsed + seconds)
+    }
+
+    func isAnimating(_ key: String) -> Bool {
+        (animations[key] ?? 0) > elapsed
+    }
+
+    func until(_ ready: @escaping () -> Bool) async {
+        if ready() { return }
+        await withCheckedContinuation { waiters.append((ready, $0)) }
+    }
+
+    func tick(_ delta: Double) {
+        elapsed += delta
+        let ready = waiters.filter { $0.ready() }
+        waiters.removeAll { $0.ready() }
+        for waiter in ready {
+            waiter.resume.resume()
+        }
+    }
+}
+
+/// Resumes on the first frame `seconds` of game time from now
+func wait(_ seconds: Double) async {
+    let end = GrumpFrames.shared.elapsed + seconds
+    await GrumpFrames.shared.until { GrumpFrames.shared.elapsed >= end }
+}
+
+/// Resumes once no `animate` of the target keyed `key` is running
+func animation_finished(_ key: String) async {
+    await GrumpFrames.shared.until { !GrumpFrames.shared.isAnimating(key) }
+}
+"#;
+
+const KOTLIN_RUNTIME: &str = r#"// G-Rump runtime support for Kotlin
+package com.grump.generated
+
+import kotlin.coroutines.resume
+import kotlin.reflect.KClass
+import kotlinx.coroutines.CoroutineScope
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.SupervisorJob
+import kotlinx.coroutines.suspendCancellableCoroutine
+
+interface Component
+
+data class Vector2(val x: Float, val y: Float)
+
+data class Vector3(val x: Float, val y: Float, val z: Float)
+
+class GrumpEntity {
+    val components = mutableMapOf<KClass<*>, Any>()
+
+    inline fun <reified T : Any> get(): T? = components[T::class] as T?
+
+    fun set(component: Any) {
+        components[component::class] = component
+    }
+}
+
+class GrumpWorld {
+    val entities = mutableListOf<GrumpEntity>()
+    val systems = mutableListOf<(Double) -> Unit>()
+
+    fun spawn(): GrumpEntity = GrumpEntity().also { entities.add(it) }
+
+    fun query(types: List<KClass<*>>): List<GrumpEntity> =
+        entities.filter { entity -> types.all { entity.components.containsKey(it) } }
+
+    fun update(delta: Double) {
+        GrumpFrames.tick(delta)
+        systems.forEach { it(delta) }
+    }
+}
+
+val world = GrumpWorld()
+
+/** Scope of `async` blocks; they resume on the thread that updates the world */
+val grumpScope = CoroutineScope(SupervisorJob() + Dispatchers.Main.immediate)
+
+/** Game time, and the coroutines waiting on it; `GrumpWorld.update` ticks it */
+object GrumpFrames {
+    var elapsed = 0.0
+        private set
+    private val animations = mutableMapOf<String, Double>()
+    private val waiters = mutableListOf<Pair<() -> Boolean, () -> Unit>>()
+
+    /** Record an `animate` of the target keyed `key` that runs for `seconds` */
+    fun animating(key: String, seconds: Double) {
+        animations[key] = maxOf(animations[key] ?: 0.0, elapsed + seconds)
+    }
+
+    fun isAnimating(key: String): Boolean = (animations[key] ?: 0.0) > elapsed
+
+    suspend fun until(ready: () -> Boolean) {
+        if (ready()) return
+        suspendCancellableCoroutine<Unit> { continuation ->
+            val waiter = ready to { continuation.resume(Unit) }
+            waiters.add(waiter)
+            continuation.invokeOnCancellation { waiters.remove(waiter) }
+        }
+    }
+
+    fun tick(delta: Double) {
+        elapsed += delta
+        val ready = waiters.filter { it.first() }
+        waiters.removeAll(ready)
+        ready.forEach { it.second() }
+    }
+}
+
+/** Resumes on the first frame `seconds` of game time from now */
+suspend fun wait(seconds: Double) {
+    val end = GrumpFrames.elapsed + seconds
+    GrumpFrames.until { GrumpFrames.elapsed >= end }
+}
+
+/** Resumes once no `animate` of the target keyed `key` is running */
+suspend fun animation_finished(key: String) {
+    GrumpFrames.until { !GrumpFrames.isAnimating(key) }
+}
+"#;
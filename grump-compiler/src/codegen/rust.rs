@@ -0,0 +1,1009 @@
+//! Rust Code Generator
+//!
+//! Generates a Cargo crate that runs on the G-Rump runtime: components
+//! become structs, systems implement `runtime::ecs::System`, and animations
+//! register with the `AnimationManager`. Async functions take the
+//! `runtime::scheduler::Script` they run as, which their waits go through,
+//! so they're started with `runtime.scheduler.spawn`. Network declarations
+//! become `runtime::network::Schema`s for a replication server and client.
+//! The crate ships a smoke test so the game can be exercised with `cargo test`.
+//!
+//! A copy of the runtime is written next to it as the `grump-runtime` crate,
+//! so the output builds on its own; `CodeGenerator::with_rust_runtime` points
+//! it at an existing copy instead.
+
+use crate::parser::{
+    Program, EntityDeclaration, SystemDeclaration, SceneDeclaration, FunctionDeclaration,
+    ComponentDeclaration, EnumDeclaration, AnimationDeclaration, Statement, Expression,
+    Literal, Pattern, Type, BinaryOp, UnaryOp, LoopMode, MatchArm, Span,
+};
+use crate::parser::extensions::{NetworkDeclaration, SyncMode};
+use crate::error::{GrumpError, GrumpResult};
+use crate::expander::derive::{self, Derive};
+use super::{
+    CodeGenerator, OutputTree, Target, angle_radians, animation_key, duration_seconds, pascal_case, returns_value,
+    snake_case, template_call,
+};
+use super::project::{AppInfo, ProgramItems};
+use super::source_map::located;
+
+/// Directory of the runtime copy, relative to the crate root
+const RUNTIME_DIR: &str = "grump-runtime";
+
+/// The runtime's sources, as laid out in its crate
+const RUNTIME_FILES: &[(&str, &str)] = &[
+    ("src/lib.rs", include_str!("../runtime/mod.rs")),
+    ("src/ecs.rs", include_str!("../runtime/ecs.rs")),
+    ("src/animation.rs", include_str!("../runtime/animation.rs")),
+    ("src/game_loop.rs", include_str!("../runtime/game_loop.rs")),
+    ("src/scheduler.rs", include_str!("../runtime/scheduler.rs")),
+    ("src/network/mod.rs", include_str!("../runtime/network/mod.rs")),
+    ("src/network/codec.rs", include_str!("../runtime/network/codec.rs")),
+    ("src/network/interpolation.rs", include_str!("../runtime/network/interpolation.rs")),
+    ("src/network/replication.rs", include_str!("../runtime/network/replication.rs")),
+    ("src/network/transport.rs", include_str!("../runtime/network/transport.rs")),
+];
+
+/// Helpers defined in the generated crate's `prelude` module
+const PRELUDE_FUNCTIONS: &[&str] = &[
+    "lerp", "length", "normalize", "dot", "ease_in_out", "random", "concat", "substring",
+    "str_length", "rgb", "rgba", "print",
+];
+
+pub struct RustCodegen<'a> {
+    gen: &'a CodeGenerator,
+    items: ProgramItems<'a>,
+    /// Component bindings in scope (name -> component) for float inference
+    bindings: std::cell::RefCell<Vec<(String, &'a ComponentDeclaration)>>,
+}
+
+impl<'a> RustCodegen<'a> {
+    /// Generate the whole crate (paths are relative to the crate root)
+    pub fn generate_project(gen: &'a CodeGenerator, program: &'a Program) -> GrumpResult<OutputTree> {
+        let codegen = Self {
+            gen,
+            items: ProgramItems::collect(program),
+            bindings: std::cell::RefCell::new(Vec::new()),
+        };
+        let app = AppInfo::from_program(program);
+        let crate_name = match snake_case(&app.name) {
+            name if name.is_empty() => "grump_game".to_string(),
+            name => name,
+        };
+
+        let mut files = OutputTree::new();
+        let runtime_path = match &gen.rust_runtime {
+            Some(path) => path.clone(),
+            None => {
+                files.add_text(format!("{}/Cargo.toml", RUNTIME_DIR), Self::generate_runtime_manifest());
+                for (path, source) in RUNTIME_FILES {
+                    files.add_text(format!("{}/{}", RUNTIME_DIR, path), *source);
+                }
+                RUNTIME_DIR.to_string()
+            }
+        };
+        files.add_text("Cargo.toml", Self::generate_manifest(&crate_name, &app, &runtime_path));
+        files.add_text(".gitignore", "/target\n");
+        files.add_text("src/lib.rs", codegen.generate_lib(&app)?);
+        files.add_text("src/prelude.rs", PRELUDE);
+        files.add_text("src/main.rs", Self::generate_main(&crate_name));
+        files.add_text("tests/game.rs", codegen.generate_test(&crate_name));
+        files.set_entry("src/lib.rs");
+        files.set_assets_dir("assets");
+        Ok(files)
+    }
+
+    fn generate_manifest(crate_name: &str, app: &AppInfo, runtime_path: &str) -> String {
+        let mut output = String::new();
+        output.push_str("# Generated by G-Rump\n");
+        output.push_str("[package]\n");
+        output.push_str(&format!("name = \"{}\"\n", crate_name));
+        output.push_str(&format!("version = \"{}\"\n", app.version));
+        output.push_str("edition = \"2021\"\n");
+        output.push_str("publish = false\n\n");
+        output.push_str("[dependencies]\n");
+        output.push_str(&format!(
+            "grump-runtime = {{ path = {} }}\n",
+            serde_json::to_string(runtime_path).unwrap_or_default()
+        ));
+        output.push_str("serde = { version = \"1\", features = [\"derive\"] }\n");
+        output.push_str("serde_json = \"1\"\n");
+        output
+    }
+
+    /// Manifest of the runtime copy, versioned like the compiler it came from
+    fn generate_runtime_manifest() -> String {
+        let mut output = String::new();
+        output.push_str("# Generated by G-Rump: the runtime this game was built against\n");
+        output.push_str("[package]\n");
+        output.push_str("name = \"grump-runtime\"\n");
+        output.push_str(&format!("version = \"{}\"\n", env!("CARGO_PKG_VERSION")));
+        output.push_str("edition = \"2021\"\n");
+        output.push_str("publish = false\n\n");
+        output.push_str("[dependencies]\n");
+        output.push_str("thiserror = \"1.0\"\n");
+        output
+    }
+
+    fn generate_main(crate_name: &str) -> String {
+        let mut output = String::new();
+        output.push_str("// Generated Rust from G-Rump\n");
+        output.push_str("use std::time::{Duration, Instant};\n\n");
+        output.push_str("fn main() {\n");
+        output.push_str(&format!("    let mut runtime = {}::create_runtime();\n", crate_name));
+        output.push_str("    runtime.start();\n");
+        output.push_str(&format!("    let frame = Duration::from_secs_f64(1.0 / {}::FPS);\n", crate_name));
+        output.push_str("    let mut last = Instant::now();\n");
+        output.push_str("    loop {\n");
+        output.push_str("        let now = Instant::now();\n");
+        output.push_str("        runtime.update(now.duration_since(last).as_secs_f64());\n");
+        output.push_str("        last = now;\n");
+        output.push_str("        std::thread::sleep(frame);\n");
+        output.push_str("    }\n");
+        output.push_str("}\n");
+        output
+    }
+
+    /// Smoke test: build the world and run a second of frames
+    fn generate_test(&self, crate_name: &str) -> String {
+        let mut output = String::new();
+        output.push_str("// Generated Rust from G-Rump\n");
+        output.push_str(&format!("use {}::*;\n\n", crate_name));
+        output.push_str("#[test]\n");
+        output.push_str("fn game_runs_for_one_second() {\n");
+        output.push_str("    let mut runtime = create_runtime();\n");
+        output.push_str("    for _ in 0..FPS as usize {\n");
+        output.push_str("        runtime.update(1.0 / FPS);\n");
+        output.push_str("    }\n");
+        output.push_str(&format!("    assert_eq!(runtime.world.entities().len(), {});\n", self.items.entities.len()));
+        output.push_str("}\n");
+        output
+    }
+
+    fn generate_lib(&self, app: &AppInfo) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str("// Generated Rust from G-Rump\n");
+        output.push_str("#![allow(unused_mut, unused_variables, unused_imports, unused_parens, dead_code)]\n\n");
+        output.push_str("pub mod prelude;\n\n");
+        output.push_str("use grump_runtime::{Runtime, RuntimeConfig};\n");
+        output.push_str("use grump_runtime::animation::{Animation, AnimationManager, Keyframe, LoopMode};\n");
+        output.push_str("use grump_runtime::ecs::{ComponentStorage, EntityId, Query, System, World};\n");
+        output.push_str("use grump_runtime::scheduler::Script;\n");
+        output.push_str("use grump_runtime::network::{FieldKind, Schema, SyncMode};\n");
+        output.push_str("pub use prelude::*;\n\n");
+        output.push_str(&format!("pub const FPS: f64 = {:?};\n\n", app.fps));
+
+        for enum_decl in &self.items.enums {
+            self.directive(&mut output, enum_decl.span);
+            output.push_str(&self.generate_enum(enum_decl));
+        }
+        for comp in &self.items.components {
+            self.directive(&mut output, comp.span);
+            output.push_str(&self.generate_component(comp)?);
+        }
+        for func in &self.items.functions {
+            self.directive(&mut output, func.span);
+            output.push_str(&self.generate_function(func)?);
+        }
+        for entity in &self.items.entities {
+            self.directive(&mut output, entity.span);
+            output.push_str(&self.generate_entity(entity)?);
+        }
+        for system in &self.items.systems {
+            self.directive(&mut output, system.span);
+            output.push_str(&self.generate_system(system)?);
+        }
+        for scene in &self.items.scenes {
+            self.directive(&mut output, scene.span);
+            output.push_str(&self.generate_scene(scene)?);
+        }
+        for network in &self.items.networks {
+            self.directive(&mut output, network.span);
+            output.push_str(&self.generate_network(network)?);
+        }
+
+        output.push_str("/// Register every declared animation (they start stopped)\n");
+        output.push_str("pub fn register_animations(manager: &mut AnimationManager) {\n");
+        for anim in &self.items.animations {
+            self.directive(&mut output, anim.span);
+            output.push_str(&self.generate_animation(anim)?);
+        }
+        output.push_str("}\n\n");
+
+        output.push_str("/// Runtime with systems, animations and the first scene loaded\n");
+        output.push_str("pub fn create_runtime() -> Runtime {\n");
+        output.push_str("    let mut runtime = Runtime::new(RuntimeConfig { target_fps: FPS, ..RuntimeConfig::default() });\n");
+        for system in &self.items.systems {
+            output.push_str(&format!("    runtime.world.add_system(Box::new({}System));\n", pascal_case(&system.name)));
+        }
+        output.push_str("    register_animations(&mut runtime.animation_manager);\n");
+        match self.items.scenes.first() {
+            Some(scene) => output.push_str(&format!("    load_{}(&mut runtime.world);\n", snake_case(&scene.name))),
+            None => {
+                for entity in &self.items.entities {
+                    output.push_str(&format!("    spawn_{}(&mut runtime.world);\n", snake_case(&entity.name)));
+                }
+            }
+        }
+        output.push_str("    runtime\n");
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn generate_enum(&self, enum_decl: &EnumDeclaration) -> String {
+        let mut output = String::new();
+        output.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]\n");
+        output.push_str(&format!("pub enum {} {{\n", enum_decl.name));
+        for (i, variant) in enum_decl.variants.iter().enumerate() {
+            if i == 0 {
+                output.push_str("    #[default]\n");
+            }
+            output.push_str(&format!("    {},\n", variant));
+        }
+        output.push_str("}\n\n");
+        output
+    }
+
+    fn generate_component(&self, comp: &ComponentDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        if derive::has(comp, Derive::Serialize) {
+            // Missing fields load as their defaults
+            output.push_str("#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n");
+            output.push_str("#[serde(default)]\n");
+        } else {
+            output.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+        }
+        output.push_str(&format!("pub struct {} {{\n", comp.name));
+        for field in &comp.fields {
+            output.push_str(&format!("    pub {}: {},\n", field.name, self.rust_type(&field.type_)));
+        }
+        output.push_str("}\n\n");
+        output.push_str(&format!("impl Default for {} {{\n", comp.name));
+        output.push_str("    fn default() -> Self {\n");
+        output.push_str("        Self {\n");
+        for field in &comp.fields {
+            let value = match &field.default {
+                Some(default) => self.typed_expression(default, &field.type_)?,
+                None => "Default::default()".to_string(),
+            };
+            output.push_str(&format!("            {}: {},\n", field.name, value));
+        }
+        output.push_str("        }\n");
+        output.push_str("    }\n");
+        output.push_str("}\n\n");
+        output.push_str(&self.generate_derives(comp));
+        Ok(output)
+    }
+
+    /// What the component's `@derive(...)` asks for, as an `impl` block
+    fn generate_derives(&self, comp: &ComponentDeclaration) -> String {
+        let mut methods = Vec::new();
+        if derive::has(comp, Derive::Serialize) {
+            methods.push(
+                "    pub fn to_json(&self) -> String {\n        serde_json::to_string(self).unwrap_or_default()\n    }\n".to_string()
+            );
+            methods.push(
+                "    pub fn from_json(json: &str) -> serde_json::Result<Self> {\n        serde_json::from_str(json)\n    }\n".to_string()
+            );
+        }
+        if derive::has(comp, Derive::Inspect) {
+            let mut inspector = String::from("    pub const INSPECTOR: &'static [InspectorField] = &[\n");
+            for field in derive::inspector(comp, &self.items.enums) {
+                let options: Vec<String> = field.options.iter().map(|o| format!("{:?}", o)).collect();
+                inspector.push_str(&format!(
+                    "        InspectorField {{ name: {:?}, type_name: {:?}, animatable: {}, options: &[{}] }},\n",
+                    field.name, field.type_name, field.animatable, options.join(", ")
+                ));
+            }
+            inspector.push_str("    ];\n");
+            methods.push(inspector);
+        }
+        if derive::has(comp, Derive::Lerp) {
+            let fields = derive::lerp_fields(comp);
+            for field in &fields {
+                let name = &field.name;
+                let value = match &field.type_ {
+                    Type::Int | Type::Int64 => {
+                        format!("prelude::lerp(self.{} as f64, to.{} as f64, t).round() as i64", name, name)
+                    }
+                    Type::Vec2 | Type::Vec3 | Type::Vec4 | Type::Color => format!("self.{}.lerp(to.{}, t)", name, name),
+                    _ => format!("prelude::lerp(self.{}, to.{}, t)", name, name),
+                };
+                methods.push(format!(
+                    "    pub fn lerp_{}(&self, to: &Self, t: f64) -> {} {{\n        {}\n    }}\n",
+                    name, self.rust_type(&field.type_), value
+                ));
+            }
+            let mut interpolate = String::from("    /// Other fields keep `self`'s values\n");
+            interpolate.push_str("    pub fn interpolate(&self, to: &Self, t: f64) -> Self {\n");
+            interpolate.push_str("        Self {\n");
+            for field in &fields {
+                interpolate.push_str(&format!("            {}: self.lerp_{}(to, t),\n", field.name, field.name));
+            }
+            interpolate.push_str("            ..self.clone()\n");
+            interpolate.push_str("        }\n");
+            interpolate.push_str("    }\n");
+            methods.push(interpolate);
+        }
+        if methods.is_empty() {
+            return String::new();
+        }
+        format!("impl {} {{\n{}}}\n\n", comp.name, methods.join("\n"))
+    }
+
+    fn generate_function(&self, func: &FunctionDeclaration) -> GrumpResult<String> {
+        // Untyped parameters and returns are assumed numeric
+        let mut params: Vec<String> = func.params.iter()
+            .map(|p| format!("{}: {}", p.name, p.type_.as_ref().map(|t| self.rust_type(t)).unwrap_or_else(|| "f64".to_string())))
+            .collect();
+        // Async functions run as scripts on the runtime's scheduler, which
+        // their waits go through
+        if func.is_async {
+            params.insert(0, "script: &Script".to_string());
+        }
+        let return_type = match &func.return_type {
+            Some(t) => format!(" -> {}", self.rust_type(t)),
+            None if returns_value(&func.body) => " -> f64".to_string(),
+            None => String::new(),
+        };
+        let mut output = format!(
+            "pub {}fn {}({}){} {{\n",
+            if func.is_async { "async " } else { "" },
+            func.name, params.join(", "), return_type
+        );
+        output.push_str(&self.block(&func.body, 1)?);
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn generate_entity(&self, entity: &EntityDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!("pub fn spawn_{}(world: &mut World) -> EntityId {{\n", snake_case(&entity.name)));
+        output.push_str("    let entity = world.create_entity();\n");
+        for comp in &entity.components {
+            let Some(decl) = self.items.component(&comp.name) else {
+                output.push_str(&format!("    // Unknown component '{}'\n", comp.name));
+                continue;
+            };
+            // Positional args map onto the component's fields in order
+            let args: Vec<&Expression> = match comp.args.as_slice() {
+                [Expression::Tuple(values)] => values.iter().collect(),
+                args => args.iter().collect(),
+            };
+            let mut fields = Vec::new();
+            for (field, arg) in decl.fields.iter().zip(&args) {
+                fields.push(format!("{}: {}", field.name, self.typed_expression(arg, &field.type_)?));
+            }
+            if args.len() < decl.fields.len() {
+                fields.push("..Default::default()".to_string());
+            }
+            output.push_str(&format!("    world.add_component(entity, {} {{ {} }});\n", decl.name, fields.join(", ")));
+        }
+        output.push_str(&self.block(&entity.body, 1)?);
+        output.push_str("    entity\n");
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    /// Queried components are copied out, updated, and written back, so a
+    /// system can touch several components of one entity at once
+    fn generate_system(&self, system: &SystemDeclaration) -> GrumpResult<String> {
+        let name = format!("{}System", pascal_case(&system.name));
+        let components: Vec<&ComponentDeclaration> = system.query.iter()
+            .filter_map(|c| self.items.component(c))
+            .collect();
+        let query: String = components.iter().map(|c| format!(".with::<{}>()", c.name)).collect();
+
+        let mut output = String::new();
+        output.push_str(&format!("pub struct {};\n\n", name));
+        output.push_str(&format!("impl System for {} {{\n", name));
+        output.push_str("    fn update(&mut self, storage: &mut ComponentStorage, dt: f64) {\n");
+        output.push_str(&format!("        for entity in Query::new(){}.execute(storage) {{\n", query));
+        for comp in &components {
+            let binding = snake_case(&comp.name);
+            output.push_str(&format!(
+                "            let mut {} = storage.get_component::<{}>(entity).cloned().unwrap_or_default();\n",
+                binding, comp.name
+            ));
+            self.bindings.borrow_mut().push((binding, comp));
+        }
+        output.push_str(&self.block(&system.body, 3)?);
+        for comp in &components {
+            output.push_str(&format!("            storage.add_component(entity, {});\n", snake_case(&comp.name)));
+        }
+        self.bindings.borrow_mut().clear();
+        output.push_str("        }\n");
+        output.push_str("    }\n\n");
+        output.push_str("    fn query(&self) -> Option<Query> {\n");
+        output.push_str(&format!("        Some(Query::new(){})\n", query));
+        output.push_str("    }\n");
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn generate_scene(&self, scene: &SceneDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!("pub fn load_{}(world: &mut World) {{\n", snake_case(&scene.name)));
+        for entity in &self.items.entities {
+            output.push_str(&format!("    spawn_{}(world);\n", snake_case(&entity.name)));
+        }
+        output.push_str(&self.block(&scene.body, 1)?);
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn generate_animation(&self, anim: &AnimationDeclaration) -> GrumpResult<String> {
+        let constant = |expr: &Expression| -> GrumpResult<f64> {
+            match channels(expr).as_deref() {
+                Some([value]) => Ok(*value),
+                _ => Err(GrumpError::Animation {
+                    message: format!("Animation '{}' needs constant keyframe times for the Rust target", anim.name),
+                }),
+            }
+        };
+        let mut keyframes = Vec::new();
+        for kf in &anim.keyframes {
+            let values = channels(&kf.value).ok_or_else(|| GrumpError::Animation {
+                message: format!("Animation '{}' needs constant keyframe values for the Rust target", anim.name),
+            })?;
+            let values: Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+            keyframes.push(format!("Keyframe::new({:?}, vec![{}])", constant(&kf.time)?, values.join(", ")));
+        }
+        let duration = match &anim.duration {
+            Some(duration) => constant(duration)?,
+            None => anim.keyframes.iter().map(|kf| constant(&kf.time)).collect::<GrumpResult<Vec<_>>>()?
+                .into_iter().fold(0.0, f64::max),
+        };
+        let loop_mode = match &anim.loop_mode {
+            Some(LoopMode::Loop) | Some(LoopMode::Section { .. }) => "LoopMode::Loop",
+            Some(LoopMode::PingPong) => "LoopMode::PingPong",
+            Some(LoopMode::Reverse) => "LoopMode::Reverse",
+            Some(LoopMode::None) | None => "LoopMode::None",
+        };
+
+        let mut output = String::new();
+        output.push_str("    manager.add_animation(\n");
+        output.push_str(&format!(
+            "        Animation::new({}.to_string(), {:?}, {})\n",
+            serde_json::to_string(&anim.name).unwrap_or_default(), duration, loop_mode
+        ));
+        output.push_str(&format!("            .with_keyframes(vec![{}]),\n", keyframes.join(", ")));
+        output.push_str("    );\n");
+        Ok(output)
+    }
+
+    fn generate_network(&self, network: &NetworkDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!("/// Sync fields of `network {}`\n", network.name));
+        output.push_str(&format!("pub fn {}_schema() -> Schema {{\n", snake_case(&network.name)));
+        output.push_str(&format!("    Schema::new({})\n", serde_json::to_string(&network.name).unwrap_or_default()));
+        for field in &network.sync_fields {
+            let kind = match &field.type_ {
+                Type::Int | Type::Int64 => "Int",
+                Type::Float | Type::Double | Type::Angle | Type::Duration => "Float",
+                Type::Bool => "Bool",
+                Type::String => "Text",
+                Type::Vec2 => "Vec2",
+                Type::Vec3 => "Vec3",
+                Type::Color => "Color",
+                other => {
+                    return Err(GrumpError::Type {
+                        message: format!("Sync field '{}.{}' has type {:?}, which can't be replicated", network.name, field.name, other),
+                    })
+                }
+            };
+            let mode = match field.sync_mode {
+                SyncMode::Reliable => "Reliable",
+                SyncMode::Unreliable => "Unreliable",
+                SyncMode::Interpolated => "Interpolated",
+                SyncMode::Snapshot => "Snapshot",
+            };
+            output.push_str(&format!(
+                "        .field({}, FieldKind::{}, SyncMode::{})\n",
+                serde_json::to_string(&field.name).unwrap_or_default(), kind, mode
+            ));
+        }
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn directive(&self, output: &mut String, span: Span) {
+        if let Some(directive) = self.gen.line_directive(span) {
+            output.push_str(&directive);
+        }
+    }
+
+    fn block(&self, statements: &[Statement], depth: usize) -> GrumpResult<String> {
+        let mut output = String::new();
+        for stmt in statements {
+            output.push_str(&self.statement(stmt, depth)?);
+        }
+        Ok(output)
+    }
+
+    fn statement(&self, stmt: &Statement, depth: usize) -> GrumpResult<String> {
+        let indent = "    ".repeat(depth);
+        let code = match stmt {
+            Statement::Let { name, mutable, type_, value, .. } => {
+                let value = match type_ {
+                    Some(t) => self.typed_expression(value, t)?,
+                    None => self.expression(value)?,
+                };
+                format!(
+                    "{}let {}{}{} = {};\n",
+                    indent, if *mutable { "mut " } else { "" }, name,
+                    type_.as_ref().map(|t| format!(": {}", self.rust_type(t))).unwrap_or_default(), value
+                )
+            }
+            Statement::Assign { target, value, .. } => {
+                let value = if self.is_float(target) { self.float_expression(value)? } else { self.expression(value)? };
+                format!("{}{} = {};\n", indent, self.expression(target)?, value)
+            }
+            Statement::If { condition, then, else_, .. } => {
+                let mut code = format!("{}if {} {{\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(then, depth + 1)?);
+                code.push_str(&format!("{}}}", indent));
+                if let Some(else_body) = else_ {
+                    code.push_str(" else {\n");
+                    code.push_str(&self.block(else_body, depth + 1)?);
+                    code.push_str(&format!("{}}}", indent));
+                }
+                code.push('\n');
+                code
+            }
+            Statement::Match { expr, arms, .. } => {
+                let mut code = format!("{}match {} {{\n", indent, self.expression(expr)?);
+                for arm in arms {
+                    let guard = match &arm.guard {
+                        Some(guard) => format!(" if {}", self.expression(guard)?),
+                        None => String::new(),
+                    };
+                    code.push_str(&format!("{}    {}{} => {{\n", indent, self.pattern(&arm.pattern), guard));
+                    code.push_str(&self.block(&arm.body, depth + 2)?);
+                    code.push_str(&format!("{}    }}\n", indent));
+                }
+                if !self.is_exhaustive(arms) {
+                    code.push_str(&format!("{}    _ => {{}}\n", indent));
+                }
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::For { var, iter, body, .. } => {
+                let mut code = format!("{}for {} in {} {{\n", indent, var, self.expression(iter)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::While { condition, body, .. } => {
+                let mut code = format!("{}while {} {{\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::Return(Some(expr), _) => format!("{}return {};\n", indent, self.expression(expr)?),
+            Statement::Return(None, _) => format!("{}return;\n", indent),
+            Statement::Break => format!("{}break;\n", indent),
+            Statement::Continue => format!("{}continue;\n", indent),
+            Statement::Expression(expr, _) => format!("{}{};\n", indent, self.expression(expr)?),
+            Statement::Await { expr } => format!("{}{}.await;\n", indent, self.expression(expr)?),
+            Statement::Animate(_) => return Err(GrumpError::Other(anyhow::anyhow!("`animate` statements are not supported by the Rust target"))),
+            Statement::Timeline { name, .. } => {
+                return Err(GrumpError::Other(anyhow::anyhow!("Timeline '{}' is not supported by the Rust target", name)))
+            }
+            Statement::Debugger(_) => return Err(GrumpError::Other(anyhow::anyhow!("Debugger statements are not supported by the Rust target"))),
+            Statement::Network(_) => return Err(GrumpError::Other(anyhow::anyhow!("Network statements are not supported by the Rust target"))),
+        };
+        Ok(located(stmt.span(), None, code))
+    }
+
+    /// A fallback arm is needed unless some arm matches everything or every enum variant is listed
+    fn is_exhaustive(&self, arms: &[MatchArm]) -> bool {
+        let unguarded = || arms.iter().filter(|arm| arm.guard.is_none());
+        if unguarded().any(|arm| match &arm.pattern {
+            Pattern::Wildcard => true,
+            Pattern::Identifier(name) => self.items.enum_of_variant(name).is_none(),
+            _ => false,
+        }) {
+            return true;
+        }
+        let variants: Vec<&String> = unguarded()
+            .filter_map(|arm| match &arm.pattern {
+                Pattern::Identifier(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        variants.first()
+            .and_then(|first| self.items.enum_of_variant(first))
+            .is_some_and(|e| e.variants.iter().all(|v| variants.contains(&v)))
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Literal(lit) => self.literal(lit),
+            Pattern::Identifier(name) => match self.items.enum_of_variant(name) {
+                Some(enum_decl) => format!("{}::{}", enum_decl.name, name),
+                None => name.clone(),
+            },
+            Pattern::Tuple(patterns) => {
+                let patterns: Vec<String> = patterns.iter().map(|p| self.pattern(p)).collect();
+                format!("({})", patterns.join(", "))
+            }
+            Pattern::Struct { name, fields } => {
+                let mut fields: Vec<String> = fields.iter()
+                    .map(|(field, p)| format!("{}: {}", field, self.pattern(p)))
+                    .collect();
+                fields.push("..".to_string());
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+        }
+    }
+
+    /// `expr` in a context of type `type_` (integer literals widen to floats, strings are owned)
+    fn typed_expression(&self, expr: &Expression, type_: &Type) -> GrumpResult<String> {
+        match (type_, expr) {
+            (Type::Float | Type::Double | Type::Angle | Type::Duration | Type::Rotation, _) => self.float_expression(expr),
+            (Type::String, Expression::Literal(Literal::String(s))) => {
+                Ok(format!("{}.to_string()", serde_json::to_string(s).unwrap_or_default()))
+            }
+            (Type::Vec2, Expression::Tuple(values)) if values.len() == 2 => Ok(format!(
+                "Vec2::new({}, {})", self.float_expression(&values[0])?, self.float_expression(&values[1])?
+            )),
+            (Type::Array(inner), Expression::Array(values)) => {
+                let values = values.iter().map(|v| self.typed_expression(v, inner)).collect::<GrumpResult<Vec<_>>>()?;
+                Ok(format!("vec![{}]", values.join(", ")))
+            }
+            (Type::Optional(inner), _) => Ok(format!("Some({})", self.typed_expression(expr, inner)?)),
+            _ => self.expression(expr),
+        }
+    }
+
+    fn float_expression(&self, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Literal(Literal::Integer(n)) => Ok(format!("{:?}", *n as f64)),
+            Expression::Unary { op: UnaryOp::Neg, expr } => Ok(format!("-{}", self.float_expression(expr)?)),
+            other => self.expression(other),
+        }
+    }
+
+    /// Best-effort check for float-typed expressions, so integer literals next to them widen
+    fn is_float(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(Literal::Float(_) | Literal::Duration { .. } | Literal::Angle { .. }) => true,
+            Expression::Member { object, member } => match object.as_ref() {
+                Expression::Identifier(binding) => self.bindings.borrow().iter()
+                    .find(|(name, _)| name == binding)
+                    .and_then(|(_, comp)| comp.fields.iter().find(|f| &f.name == member))
+                    .is_some_and(|f| matches!(f.type_, Type::Float | Type::Double | Type::Angle | Type::Duration)),
+                _ => false,
+            },
+            Expression::Identifier(name) => name == "dt",
+            Expression::Binary { left, right, .. } => self.is_float(left) || self.is_float(right),
+            Expression::Unary { op: UnaryOp::Neg, expr } => self.is_float(expr),
+            _ => false,
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> GrumpResult<String> {
+        Ok(match expr {
+            Expression::Literal(lit) => self.literal(lit),
+            Expression::Identifier(name) => match self.items.enum_of_variant(name) {
+                Some(enum_decl) => format!("{}::{}", enum_decl.name, name),
+                None => name.clone(),
+            },
+            Expression::Binary { op, left, right } => {
+                let op = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Mod => "%",
+                    BinaryOp::Eq => "==",
+                    BinaryOp::Ne => "!=",
+                    BinaryOp::Lt => "<",
+                    BinaryOp::Gt => ">",
+                    BinaryOp::Le => "<=",
+                    BinaryOp::Ge => ">=",
+                    BinaryOp::And => "&&",
+                    BinaryOp::Or => "||",
+                    BinaryOp::Xor => "^",
+                    BinaryOp::ShiftLeft => "<<",
+                    BinaryOp::ShiftRight => ">>",
+                };
+                let (left, right) = if self.is_float(left) || self.is_float(right) {
+                    (self.float_expression(left)?, self.float_expression(right)?)
+                } else {
+                    (self.expression(left)?, self.expression(right)?)
+                };
+                format!("({} {} {})", left, op, right)
+            }
+            Expression::Unary { op, expr } => {
+                let op = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "!",
+                    UnaryOp::Deref => "*",
+                    UnaryOp::Ref => "&",
+                    UnaryOp::MutRef => "&mut ",
+                };
+                format!("{}{}", op, self.expression(expr)?)
+            }
+            Expression::Call { func, args } => self.call(func, args)?,
+            Expression::Member { object, member } => format!("{}.{}", self.expression(object)?, member),
+            Expression::Index { object, index } => format!("{}[{} as usize]", self.expression(object)?, self.expression(index)?),
+            Expression::Tuple(values) => {
+                let values = values.iter().map(|v| self.expression(v)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("({})", values.join(", "))
+            }
+            Expression::Array(values) => {
+                let values = values.iter().map(|v| self.expression(v)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("vec![{}]", values.join(", "))
+            }
+            Expression::Block(statements) => format!("{{\n{}}}", self.block(statements, 1)?),
+            Expression::If { condition, then, else_ } => format!(
+                "if {} {{ {} }} else {{ {} }}",
+                self.expression(condition)?, self.expression(then)?, self.expression(else_)?
+            ),
+            Expression::Lambda { params, body } => {
+                let params: Vec<String> = params.iter()
+                    .map(|p| match &p.type_ {
+                        Some(t) => format!("{}: {}", p.name, self.rust_type(t)),
+                        None => p.name.clone(),
+                    })
+                    .collect();
+                format!("|{}| {}", params.join(", "), self.expression(body)?)
+            }
+            Expression::Await(expr) => format!("{}.await", self.expression(expr)?),
+            Expression::AsyncBlock(statements) => format!("async {{\n{}}}", self.block(statements, 1)?),
+            Expression::MacroCall { name, args, span } => {
+                let args = args.iter().map(|arg| self.expression(arg)).collect::<GrumpResult<Vec<_>>>()?;
+                template_call(self.items.macros.iter().copied(), Target::Rust, name, &args, *span)?
+            }
+        })
+    }
+
+    /// User functions are called directly; math builtins are `f64` methods and
+    /// the rest live in the generated `prelude` module
+    fn call(&self, func: &Expression, args: &[Expression]) -> GrumpResult<String> {
+        let rendered = args.iter().map(|a| self.expression(a)).collect::<GrumpResult<Vec<_>>>()?;
+        if let Expression::Identifier(name) = func {
+            let user_defined = self.items.functions.iter().any(|f| &f.name == name);
+            if self.items.functions.iter().any(|f| &f.name == name && f.is_async) {
+                let mut rendered = rendered;
+                rendered.insert(0, "script".to_string());
+                return Ok(format!("{}({})", name, rendered.join(", ")));
+            }
+            match (name.as_str(), args) {
+                ("wait", [duration]) if !user_defined => {
+                    return Ok(format!("script.wait({})", self.float_expression(duration)?));
+                }
+                ("animation_finished", [target]) if !user_defined => {
+                    return Ok(format!("script.animation_finished({})", animation_key(&self.expression(target)?)));
+                }
+                ("event" | "emit", [event]) if !user_defined => {
+                    return Ok(format!("script.{}(&{})", name, rendered[0]));
+                }
+                ("sin" | "cos" | "sqrt" | "abs", [arg]) if !user_defined => {
+                    return Ok(format!("{}.{}()", self.float_expression(arg)?, name));
+                }
+                (builtin, _) if !user_defined && PRELUDE_FUNCTIONS.contains(&builtin) => {
+                    return Ok(format!("{}({})", builtin, rendered.join(", ")));
+                }
+                _ => {}
+            }
+        }
+        Ok(format!("{}({})", self.expression(func)?, rendered.join(", ")))
+    }
+
+    fn literal(&self, lit: &Literal) -> String {
+        match lit {
+            Literal::Integer(n) => n.to_string(),
+            Literal::Float(f) => format!("{:?}", f),
+            Literal::String(s) => serde_json::to_string(s).unwrap_or_default(),
+            Literal::Char(c) => format!("{:?}", c),
+            Literal::Bool(b) => b.to_string(),
+            Literal::Color { r, g, b, a } => format!("Color::rgba({}, {}, {}, {})", r, g, b, a),
+            Literal::Vec2 { x, y } => format!("Vec2::new({:?}, {:?})", x, y),
+            Literal::Vec3 { x, y, z } => format!("Vec3::new({:?}, {:?}, {:?})", x, y, z),
+            Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
+            Literal::Angle { value, unit } => format!("{:?}", angle_radians(*value, unit)),
+        }
+    }
+
+    fn rust_type(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Int | Type::Int64 => "i64".to_string(),
+            Type::Float | Type::Double | Type::Angle | Type::Duration | Type::Rotation => "f64".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::String => "String".to_string(),
+            Type::Char => "char".to_string(),
+            Type::Vec2 => "Vec2".to_string(),
+            Type::Vec3 => "Vec3".to_string(),
+            Type::Vec4 => "Vec4".to_string(),
+            Type::Color => "Color".to_string(),
+            Type::Transform => "Transform".to_string(),
+            Type::Optional(inner) => format!("Option<{}>", self.rust_type(inner)),
+            Type::Result { ok, err } => format!("Result<{}, {}>", self.rust_type(ok), self.rust_type(err)),
+            Type::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| self.rust_type(t)).collect();
+                format!("({})", types.join(", "))
+            }
+            Type::Array(inner) => format!("Vec<{}>", self.rust_type(inner)),
+            Type::Named(name) => name.clone(),
+        }
+    }
+}
+
+/// Constant keyframe channels: a number or a 2/3-component vector
+fn channels(expr: &Expression) -> Option<Vec<f64>> {
+    let number = |expr: &Expression| match expr {
+        Expression::Literal(Literal::Integer(n)) => Some(*n as f64),
+        Expression::Literal(Literal::Float(f)) => Some(*f),
+        Expression::Literal(Literal::Duration { value, unit }) => Some(duration_seconds(*value, unit)),
+        Expression::Literal(Literal::Angle { value, unit }) => Some(angle_radians(*value, unit)),
+        Expression::Unary { op: UnaryOp::Neg, expr } => match expr.as_ref() {
+            Expression::Literal(Literal::Integer(n)) => Some(-(*n as f64)),
+            Expression::Literal(Literal::Float(f)) => Some(-f),
+            _ => None,
+        },
+        _ => None,
+    };
+    match expr {
+        Expression::Literal(Literal::Vec2 { x, y }) => Some(vec![*x, *y]),
+        Expression::Literal(Literal::Vec3 { x, y, z }) => Some(vec![*x, *y, *z]),
+        Expression::Literal(Literal::Color { r, g, b, a }) => Some(vec![*r as f64, *g as f64, *b as f64, *a as f64]),
+        Expression::Tuple(values) | Expression::Array(values) => values.iter().map(number).collect(),
+        other => number(other).map(|n| vec![n]),
+    }
+}
+
+const PRELUDE: &str = r#"//! Value types and built-in functions for G-Rump games on the Rust runtime
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        Self::new(lerp(self.x, to.x, t), lerp(self.y, to.y, t))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        Self::new(lerp(self.x, to.x, t), lerp(self.y, to.y, t), lerp(self.z, to.z, t))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Vec4 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+
+impl Vec4 {
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        Self { x: lerp(self.x, to.x, t), y: lerp(self.y, to.y, t), z: lerp(self.z, to.z, t), w: lerp(self.w, to.w, t) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        let channel = |from: u8, to: u8| lerp(from as f64, to as f64, t).round().clamp(0.0, 255.0) as u8;
+        Self::rgba(channel(self.r, to.r), channel(self.g, to.g), channel(self.b, to.b), channel(self.a, to.a))
+    }
+}
+
+/// A component field as an editor inspector shows it (`@derive(Inspect)`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InspectorField {
+    pub name: &'static str,
+    /// As written in G-Rump: "float", "vec2", "Mood"
+    pub type_name: &'static str,
+    pub animatable: bool,
+    /// An enum field's variants
+    pub options: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Transform {
+    pub position: Vec2,
+    pub rotation: f64,
+    pub scale: Vec2,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { position: Vec2::default(), rotation: 0.0, scale: Vec2::new(1.0, 1.0) }
+    }
+}
+
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+pub fn length(v: Vec2) -> f64 {
+    v.x.hypot(v.y)
+}
+
+pub fn normalize(v: Vec2) -> Vec2 {
+    let len = length(v);
+    if len > 0.0 { Vec2::new(v.x / len, v.y / len) } else { Vec2::default() }
+}
+
+pub fn dot(a: Vec2, b: Vec2) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+pub fn ease_in_out(t: f64) -> f64 {
+    if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+}
+
+static SEED: AtomicU64 = AtomicU64::new(0x9E37_79B9_7F4A_7C15);
+
+/// Uniform value in `[min, max)` (xorshift; deterministic across runs)
+pub fn random(min: f64, max: f64) -> f64 {
+    let mut x = SEED.load(Ordering::Relaxed);
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    SEED.store(x, Ordering::Relaxed);
+    min + (x >> 11) as f64 / (1u64 << 53) as f64 * (max - min)
+}
+
+pub fn concat(a: &str, b: &str) -> String {
+    format!("{}{}", a, b)
+}
+
+pub fn substring(s: &str, start: i64, end: i64) -> String {
+    s.chars().skip(start as usize).take((end - start).max(0) as usize).collect()
+}
+
+pub fn str_length(s: &str) -> i64 {
+    s.chars().count() as i64
+}
+
+pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::rgba(r, g, b, 255)
+}
+
+pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+    Color::rgba(r, g, b, a)
+}
+
+pub fn print(value: impl std::fmt::Display) {
+    println!("{}", value);
+}
+"#;
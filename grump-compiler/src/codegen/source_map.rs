@@ -0,0 +1,358 @@
+//! Source maps from generated code back to `.grump` files
+//!
+//! Codegen records where each declaration and statement lands in the
+//! generated files, and which of the project's `.grump` files it came from.
+//! Generators tag code with [`located`] as they build it; the tags are taken
+//! out of the finished files, once line numbers are final, by
+//! [`SourceMap::take_marks`].
+//! The web target ships the result as Source Map v3 JSON (`game.js.map`) so
+//! browser stack traces show `.grump` locations; native targets also get line
+//! directives in the source itself, and `symbolicate` rewrites traces that
+//! still point at generated files.
+
+use serde_json::json;
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::Span;
+
+/// Brackets around the marks `located` leaves in generated code
+const MARK_START: char = '\u{1}';
+const MARK_END: char = '\u{2}';
+
+/// `code`, marked as coming from `span`. The mark goes after the code's
+/// indentation and is named `name`, if given.
+pub fn located(span: Option<Span>, name: Option<&str>, code: String) -> String {
+    let Some(span) = span.filter(|span| span.line != 0 && !code.trim().is_empty()) else {
+        return code;
+    };
+    let indent = code.len() - code.trim_start().len();
+    let name = name.map(|name| format!(":{}", name)).unwrap_or_default();
+    format!(
+        "{}{}{}:{}:{}{}{}{}",
+        &code[..indent], MARK_START, span.file, span.line, span.column, name, MARK_END, &code[indent..]
+    )
+}
+
+/// One output position mapped to a source position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    /// 0-based line in the generated file
+    pub generated_line: usize,
+    /// 0-based column in the generated file
+    pub generated_column: usize,
+    /// Source position (1-based); `span.file` indexes the map's sources
+    pub span: Span,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    file: String,
+    /// Source paths and, when embedded, their contents
+    sources: Vec<(String, Option<String>)>,
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Map for generated `file`, pointing into `source`
+    pub fn new(file: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            sources: vec![(source.into(), None)],
+            mappings: Vec::new(),
+        }
+    }
+
+    /// Map for generated `file`, pointing into several sources; a span's
+    /// `file` is its index in `sources`
+    pub fn with_sources(file: impl Into<String>, sources: Vec<(String, Option<String>)>) -> Self {
+        Self { file: file.into(), sources, mappings: Vec::new() }
+    }
+
+    /// Embed the original source so tools don't need the `.grump` file on disk
+    pub fn set_source_content(&mut self, content: Option<String>) {
+        if let Some(first) = self.sources.first_mut() {
+            first.1 = content;
+        }
+    }
+
+    pub fn set_file(&mut self, file: impl Into<String>) {
+        self.file = file.into();
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Path of the first source
+    pub fn source(&self) -> &str {
+        self.source_path(0)
+    }
+
+    /// Path of source `index`, as a span's `file`
+    pub fn source_path(&self, index: usize) -> &str {
+        self.sources.get(index).map_or("", |(path, _)| path.as_str())
+    }
+
+    /// Record that the text starting at the end of `output` comes from `span`
+    pub fn mark(&mut self, output: &str, span: Span, name: Option<&str>) {
+        let generated_line = output.matches('\n').count();
+        let generated_column = output.len() - output.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        self.add(generated_line, generated_column, span, name);
+    }
+
+    pub fn add(&mut self, generated_line: usize, generated_column: usize, span: Span, name: Option<&str>) {
+        // Spans are only default when the AST was built by hand
+        if span.line == 0 || span.file >= self.sources.len() {
+            return;
+        }
+        self.mappings.push(Mapping {
+            generated_line,
+            generated_column,
+            span,
+            name: name.map(str::to_string),
+        });
+    }
+
+    /// Take the marks `located` left in `text` out of it, mapping the code
+    /// after each. An unnamed (statement) mark at the start of a line gets
+    /// `directive(span)`, if there is one, on a line of its own before it.
+    pub fn take_marks(&mut self, text: &mut String, directive: impl Fn(Span) -> Option<String>) {
+        if !text.contains(MARK_START) {
+            return;
+        }
+        let mut output = String::with_capacity(text.len());
+        let (mut line, mut line_start) = (0, 0);
+        let mut rest = text.as_str();
+        while let Some((before, after)) = rest.split_once(MARK_START) {
+            let Some((mark, after)) = after.split_once(MARK_END) else {
+                break;
+            };
+            if let Some(last) = before.rfind('\n') {
+                line += before.matches('\n').count();
+                line_start = output.len() + last + 1;
+            }
+            output.push_str(before);
+            rest = after;
+
+            let fields: Vec<&str> = mark.splitn(4, ':').collect();
+            let number = |i: usize| fields.get(i).and_then(|field| field.parse().ok()).unwrap_or(0);
+            let span = Span { file: number(0), line: number(1), column: number(2) };
+            let name = fields.get(3).copied();
+            if name.is_none() && output[line_start..].trim().is_empty() {
+                if let Some(directive) = directive(span) {
+                    let indent = output.split_off(line_start);
+                    output.push_str(&directive);
+                    line += directive.matches('\n').count();
+                    line_start = output.len();
+                    output.push_str(&indent);
+                }
+            }
+            self.add(line, output.len() - line_start, span, name);
+        }
+        output.push_str(rest);
+        *text = output;
+        self.mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Source position for a 1-based generated line (the nearest mapping at or above it)
+    pub fn lookup(&self, generated_line: usize) -> Option<&Mapping> {
+        let line = generated_line.checked_sub(1)?;
+        self.mappings.iter()
+            .filter(|m| m.generated_line <= line)
+            .max_by_key(|m| (m.generated_line, m.generated_column))
+    }
+
+    /// Rewrite `file:line` references to the generated file in a stack trace
+    pub fn symbolicate(&self, trace: &str) -> String {
+        let file_name = self.file.rsplit('/').next().unwrap_or(&self.file);
+        let needle = format!("{}:", file_name);
+        let mut result = String::with_capacity(trace.len());
+        let mut rest = trace;
+
+        while let Some(index) = rest.find(&needle) {
+            let after = &rest[index + needle.len()..];
+            let digits = after.bytes().take_while(u8::is_ascii_digit).count();
+            let mapped = after[..digits].parse().ok().and_then(|line| self.lookup(line));
+            match mapped {
+                Some(mapping) => {
+                    // Replace the whole path token, not just the file name
+                    let start = rest[..index].rfind(|c: char| c.is_whitespace() || c == '(').map(|i| i + 1).unwrap_or(0);
+                    result.push_str(&rest[..start]);
+                    result.push_str(&format!("{}:{}", self.source_path(mapping.span.file), mapping.span.line));
+                    rest = &after[digits..];
+                }
+                None => {
+                    result.push_str(&rest[..index + needle.len()]);
+                    rest = after;
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Source Map revision 3 JSON
+    pub fn to_json(&self) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        for mapping in &self.mappings {
+            if let Some(name) = &mapping.name {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+
+        let mut sorted: Vec<&Mapping> = self.mappings.iter().collect();
+        sorted.sort_by_key(|m| (m.generated_line, m.generated_column));
+
+        let mut encoded = String::new();
+        let mut line = 0;
+        let mut previous_column = 0i64;
+        let mut previous_source = 0i64;
+        let mut previous_source_line = 0i64;
+        let mut previous_source_column = 0i64;
+        let mut previous_name = 0i64;
+        let mut first_in_line = true;
+
+        for mapping in sorted {
+            while line < mapping.generated_line {
+                encoded.push(';');
+                line += 1;
+                previous_column = 0;
+                first_in_line = true;
+            }
+            if !first_in_line {
+                encoded.push(',');
+            }
+            first_in_line = false;
+
+            let column = mapping.generated_column as i64;
+            let source_line = mapping.span.line as i64 - 1;
+            let source_column = mapping.span.column.saturating_sub(1) as i64;
+
+            encode_vlq(column - previous_column, &mut encoded);
+            encode_vlq(mapping.span.file as i64 - previous_source, &mut encoded);
+            encode_vlq(source_line - previous_source_line, &mut encoded);
+            encode_vlq(source_column - previous_source_column, &mut encoded);
+            if let Some(name) = &mapping.name {
+                let index = names.iter().position(|n| n == name).unwrap_or(0) as i64;
+                encode_vlq(index - previous_name, &mut encoded);
+                previous_name = index;
+            }
+
+            previous_column = column;
+            previous_source = mapping.span.file as i64;
+            previous_source_line = source_line;
+            previous_source_column = source_column;
+        }
+
+        let mut map = json!({
+            "version": 3,
+            "file": self.file,
+            "sources": self.sources.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+            "names": names,
+            "mappings": encoded,
+        });
+        if self.sources.iter().any(|(_, content)| content.is_some()) {
+            map["sourcesContent"] = json!(self.sources.iter().map(|(_, content)| content).collect::<Vec<_>>());
+        }
+        serde_json::to_string_pretty(&map).unwrap_or_default()
+    }
+
+    /// Read a map written by `to_json`
+    pub fn from_json(text: &str) -> GrumpResult<Self> {
+        let invalid = |msg: &str| GrumpError::Other(anyhow::anyhow!("Invalid source map: {}", msg));
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| invalid(&e.to_string()))?;
+        if value["version"] != 3 {
+            return Err(invalid("expected version 3"));
+        }
+        let names: Vec<&str> = value["names"].as_array()
+            .map(|names| names.iter().filter_map(|n| n.as_str()).collect())
+            .unwrap_or_default();
+        let sources = value["sources"].as_array()
+            .filter(|sources| !sources.is_empty())
+            .ok_or_else(|| invalid("missing sources"))?
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (
+                path.as_str().unwrap_or_default().to_string(),
+                value["sourcesContent"][i].as_str().map(str::to_string),
+            ))
+            .collect();
+        let mut map = SourceMap::with_sources(value["file"].as_str().unwrap_or_default(), sources);
+
+        let mappings = value["mappings"].as_str().ok_or_else(|| invalid("missing mappings"))?;
+        let (mut source, mut source_line, mut source_column, mut name) = (0i64, 0i64, 0i64, 0i64);
+        for (line, segments) in mappings.split(';').enumerate() {
+            let mut column = 0i64;
+            for segment in segments.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment).ok_or_else(|| invalid("bad VLQ segment"))?;
+                column += fields[0];
+                if fields.len() < 4 {
+                    continue;
+                }
+                source += fields[1];
+                source_line += fields[2];
+                source_column += fields[3];
+                let segment_name = if fields.len() > 4 {
+                    name += fields[4];
+                    names.get(name as usize).copied()
+                } else {
+                    None
+                };
+                map.add(
+                    line,
+                    column as usize,
+                    Span { line: source_line as usize + 1, column: source_column as usize + 1, file: source as usize },
+                    segment_name,
+                );
+            }
+        }
+        Ok(map)
+    }
+}
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 VLQ as used by the `mappings` field
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut vlq = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut values = Vec::new();
+    let mut value = 0i64;
+    let mut shift = 0;
+    for c in segment.bytes() {
+        let digit = BASE64.iter().position(|&b| b == c)? as i64;
+        value |= (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+        } else {
+            values.push(if value & 1 == 1 { -(value >> 1) } else { value >> 1 });
+            value = 0;
+            shift = 0;
+        }
+    }
+    Some(values)
+}
@@ -0,0 +1,996 @@
+//! TypeScript Code Generator
+//!
+//! Generates a typed TypeScript project from G-Rump AST: an interface per
+//! component, string-literal unions for enums, typed system functions and
+//! `const`/`readonly` for immutable bindings. The output is meant to pass
+//! `tsc --strict` with the bundled `tsconfig.json`.
+
+use std::cell::{Cell, RefCell};
+
+use crate::parser::{
+    Program, ComponentDeclaration, EntityDeclaration, SystemDeclaration, SceneDeclaration, FunctionDeclaration,
+    AnimationDeclaration, AnimateStatement, Statement, Expression, Literal, Pattern, Type,
+    BinaryOp, UnaryOp, LoopMode,
+};
+use crate::error::{GrumpError, GrumpResult};
+use crate::expander::derive::{self, Derive};
+use super::{
+    OutputTree, SourceMap, Target, angle_radians, animation_key, animation_target, duration_seconds, pascal_case,
+    returns_value, snake_case, template_call,
+};
+use super::project::{AppInfo, ProgramItems};
+use super::source_map::located;
+
+/// TypeScript version the generated `package.json` builds with
+const TYPESCRIPT_VERSION: &str = "^5.4.0";
+
+/// Everything `runtime.ts` exports; imported into `game.ts` unless shadowed
+const RUNTIME_EXPORTS: &[&str] = &[
+    "World", "Entity", "Vec2", "Vec3", "Vec4", "Color", "Transform", "Result", "LoopMode",
+    "Animatable", "Keyframe", "AnimationClip", "vec2", "vec3", "vec4", "color", "animate",
+    "sin", "cos", "sqrt", "abs", "length", "normalize", "dot", "lerp", "ease_in_out", "print",
+    "random", "concat", "substring", "str_length", "rgb", "rgba", "hsl", "translate", "rotate",
+    "scale", "now", "delta_time", "wait", "animation_finished", "InspectorField", "lerpVec2", "lerpVec3", "lerpVec4", "lerpColor",
+];
+
+pub struct TypeScriptCodegen<'a> {
+    items: ProgramItems<'a>,
+    /// Scene properties in scope (referenced through `this.`)
+    scene_props: RefCell<Vec<String>>,
+    /// Counter for `match` temporaries
+    matches: Cell<usize>,
+}
+
+impl<'a> TypeScriptCodegen<'a> {
+    /// Generate the whole TypeScript project (paths are relative to the project root)
+    pub fn generate_project(program: &'a Program, map: &mut SourceMap) -> GrumpResult<OutputTree> {
+        let codegen = Self {
+            items: ProgramItems::collect(program),
+            scene_props: RefCell::new(Vec::new()),
+            matches: Cell::new(0),
+        };
+        let app = AppInfo::from_program(program);
+
+        let mut files = OutputTree::new();
+        files.add_text("package.json", Self::generate_package(&app));
+        files.add_text("tsconfig.json", Self::generate_tsconfig());
+        files.add_text("src/runtime.ts", RUNTIME);
+        files.add_text("src/game.ts", codegen.generate_game(&app, map)?);
+        files.add_text("src/main.ts", Self::generate_main());
+        files.set_entry("src/game.ts");
+        files.set_assets_dir("assets");
+        Ok(files)
+    }
+
+    fn generate_package(app: &AppInfo) -> String {
+        let mut output = String::new();
+        output.push_str("{\n");
+        output.push_str(&format!("  \"name\": \"{}\",\n", snake_case(&app.name).replace('_', "-")));
+        output.push_str(&format!("  \"version\": \"{}\",\n", app.version));
+        output.push_str("  \"private\": true,\n");
+        output.push_str("  \"type\": \"module\",\n");
+        output.push_str("  \"scripts\": {\n");
+        output.push_str("    \"build\": \"tsc\",\n");
+        output.push_str("    \"check\": \"tsc --noEmit\"\n");
+        output.push_str("  },\n");
+        output.push_str("  \"devDependencies\": {\n");
+        output.push_str(&format!("    \"typescript\": \"{}\"\n", TYPESCRIPT_VERSION));
+        output.push_str("  }\n");
+        output.push_str("}\n");
+        output
+    }
+
+    fn generate_tsconfig() -> String {
+        let mut output = String::new();
+        output.push_str("{\n");
+        output.push_str("  \"compilerOptions\": {\n");
+        output.push_str("    \"target\": \"ES2020\",\n");
+        output.push_str("    \"module\": \"ES2020\",\n");
+        output.push_str("    \"moduleResolution\": \"bundler\",\n");
+        output.push_str("    \"lib\": [\"ES2020\", \"DOM\"],\n");
+        output.push_str("    \"strict\": true,\n");
+        output.push_str("    \"declaration\": true,\n");
+        output.push_str("    \"sourceMap\": true,\n");
+        output.push_str("    \"rootDir\": \"src\",\n");
+        output.push_str("    \"outDir\": \"dist\"\n");
+        output.push_str("  },\n");
+        output.push_str("  \"include\": [\"src\"]\n");
+        output.push_str("}\n");
+        output
+    }
+
+    fn generate_main() -> String {
+        let mut output = String::new();
+        output.push_str("// Generated TypeScript from G-Rump\n");
+        output.push_str("import { createGame } from \"./game\";\n\n");
+        output.push_str("const world = createGame();\n");
+        output.push_str("let last = performance.now();\n\n");
+        output.push_str("function frame(time: number): void {\n");
+        output.push_str("  world.update((time - last) / 1000);\n");
+        output.push_str("  last = time;\n");
+        output.push_str("  requestAnimationFrame(frame);\n");
+        output.push_str("}\n\n");
+        output.push_str("requestAnimationFrame(frame);\n");
+        output
+    }
+
+    fn generate_game(&self, app: &AppInfo, map: &mut SourceMap) -> GrumpResult<String> {
+        let declared: Vec<&str> = self.items.functions.iter().map(|f| f.name.as_str())
+            .chain(self.items.components.iter().map(|c| c.name.as_str()))
+            .chain(self.items.enums.iter().map(|e| e.name.as_str()))
+            .collect();
+        let imports: Vec<&str> = RUNTIME_EXPORTS.iter().copied()
+            .filter(|name| !declared.contains(name))
+            .collect();
+
+        let mut output = String::new();
+        output.push_str("// Generated TypeScript from G-Rump\n");
+        output.push_str(&format!("import {{ {} }} from \"./runtime\";\n\n", imports.join(", ")));
+        output.push_str(&format!("export const FPS = {};\n\n", app.fps));
+
+        for enum_decl in &self.items.enums {
+            map.mark(&output, enum_decl.span, Some(&enum_decl.name));
+            let variants: Vec<String> = enum_decl.variants.iter().map(|v| format!("\"{}\"", v)).collect();
+            output.push_str(&format!("export type {} = {};\n", enum_decl.name, variants.join(" | ")));
+            output.push_str(&format!("export const {} = {{\n", enum_decl.name));
+            for variant in &enum_decl.variants {
+                output.push_str(&format!("  {}: \"{}\",\n", variant, variant));
+            }
+            output.push_str("} as const;\n\n");
+        }
+
+        for comp in &self.items.components {
+            map.mark(&output, comp.span, Some(&comp.name));
+            output.push_str(&format!("export interface {} {{\n", comp.name));
+            for field in &comp.fields {
+                output.push_str(&format!("  {}: {};\n", field.name, self.ts_type(&field.type_)));
+            }
+            output.push_str("}\n\n");
+            output.push_str(&format!(
+                "export function create{}(init: Partial<{}> = {{}}): {} {{\n",
+                comp.name, comp.name, comp.name
+            ));
+            output.push_str("  return {\n");
+            for field in &comp.fields {
+                let value = match &field.default {
+                    Some(default) => self.expression(default)?,
+                    None => self.zero_value(&field.type_),
+                };
+                output.push_str(&format!("    {}: {},\n", field.name, value));
+            }
+            output.push_str("    ...init,\n");
+            output.push_str("  };\n");
+            output.push_str("}\n\n");
+            output.push_str(&self.generate_derives(comp));
+        }
+
+        output.push_str("/** Component name -> component type, used to type entity lookups */\n");
+        output.push_str("export interface Components {\n");
+        for comp in &self.items.components {
+            output.push_str(&format!("  {}: {};\n", comp.name, comp.name));
+        }
+        output.push_str("}\n\n");
+        output.push_str("export type GameWorld = World<Components>;\n");
+        output.push_str("export type GameEntity = Entity<Components>;\n\n");
+
+        for anim in &self.items.animations {
+            map.mark(&output, anim.span, Some(&anim.name));
+            output.push_str(&self.generate_animation(anim)?);
+        }
+        for entity in &self.items.entities {
+            map.mark(&output, entity.span, Some(&entity.name));
+            output.push_str(&self.generate_entity(entity)?);
+        }
+        for system in &self.items.systems {
+            map.mark(&output, system.span, Some(&system.name));
+            output.push_str(&self.generate_system(system)?);
+        }
+        for func in &self.items.functions {
+            map.mark(&output, func.span, Some(&func.name));
+            output.push_str(&self.generate_function(func)?);
+        }
+        for scene in &self.items.scenes {
+            map.mark(&output, scene.span, Some(&scene.name));
+            output.push_str(&self.generate_scene(scene)?);
+        }
+
+        output.push_str("export function createGame(): GameWorld {\n");
+        output.push_str("  const world: GameWorld = new World<Components>();\n");
+        for system in &self.items.systems {
+            output.push_str(&format!("  world.addSystem({}System);\n", camel_case(&system.name)));
+        }
+        match self.items.scenes.first() {
+            Some(scene) => output.push_str(&format!("  new {}Scene(world).load();\n", pascal_case(&scene.name))),
+            None => {
+                for entity in &self.items.entities {
+                    output.push_str(&format!("  spawn{}(world);\n", pascal_case(&entity.name)));
+                }
+            }
+        }
+        output.push_str("  return world;\n");
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn generate_animation(&self, anim: &AnimationDeclaration) -> GrumpResult<String> {
+        let value_type = match anim.keyframes.first().map(|kf| &kf.value) {
+            Some(Expression::Tuple(values)) | Some(Expression::Array(values)) if values.len() == 2 => "Vec2",
+            Some(Expression::Literal(Literal::Vec2 { .. })) => "Vec2",
+            Some(Expression::Literal(Literal::Integer(_))) | Some(Expression::Literal(Literal::Float(_))) => "number",
+            _ => "Animatable",
+        };
+        let mut keyframes = Vec::new();
+        for kf in &anim.keyframes {
+            keyframes.push(format!("{{ time: {}, value: {} }}", self.seconds(&kf.time)?, self.animatable(&kf.value)?));
+        }
+        let duration = match &anim.duration {
+            Some(duration) => self.seconds(duration)?,
+            None => "0".to_string(),
+        };
+
+        let mut output = String::new();
+        output.push_str(&format!(
+            "export const {}Animation: AnimationClip<{}> = {{\n",
+            camel_case(&anim.name), value_type
+        ));
+        output.push_str("  keyframes: [\n");
+        for keyframe in keyframes {
+            output.push_str(&format!("    {},\n", keyframe));
+        }
+        output.push_str("  ],\n");
+        output.push_str(&format!("  duration: {},\n", duration));
+        output.push_str(&format!("  loop: {},\n", Self::loop_mode(anim.loop_mode.as_ref())));
+        output.push_str("};\n\n");
+        Ok(output)
+    }
+
+    fn generate_entity(&self, entity: &EntityDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!(
+            "export function spawn{}(world: GameWorld): GameEntity {{\n",
+            pascal_case(&entity.name)
+        ));
+        output.push_str("  const entity = world.spawn();\n");
+        for comp in &entity.components {
+            match self.items.component(&comp.name) {
+                Some(decl) => {
+                    // Positional args map onto the component's fields in order
+                    let args: Vec<&Expression> = match comp.args.as_slice() {
+                        [Expression::Tuple(values)] => values.iter().collect(),
+                        args => args.iter().collect(),
+                    };
+                    let mut fields = Vec::new();
+                    for (field, arg) in decl.fields.iter().zip(args) {
+                        fields.push(format!("{}: {}", field.name, self.expression(arg)?));
+                    }
+                    output.push_str(&format!(
+                        "  entity.add(\"{}\", create{}({{ {} }}));\n",
+                        decl.name, decl.name, fields.join(", ")
+                    ));
+                }
+                None => output.push_str(&format!("  // Unknown component '{}'\n", comp.name)),
+            }
+        }
+        output.push_str(&self.block(&entity.body, 1)?);
+        output.push_str("  return entity;\n");
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn generate_system(&self, system: &SystemDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!(
+            "export function {}System(world: GameWorld, dt: number): void {{\n",
+            camel_case(&system.name)
+        ));
+        let query: Vec<String> = system.query.iter().map(|c| format!("\"{}\"", c)).collect();
+        output.push_str(&format!("  for (const entity of world.query({})) {{\n", query.join(", ")));
+        for comp in &system.query {
+            output.push_str(&format!("    const {} = entity.get(\"{}\")!;\n", snake_case(comp), comp));
+        }
+        output.push_str(&self.block(&system.body, 2)?);
+        output.push_str("  }\n");
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    fn generate_function(&self, func: &FunctionDeclaration) -> GrumpResult<String> {
+        let params: Vec<String> = func.params.iter()
+            .map(|p| format!("{}: {}", p.name, p.type_.as_ref().map(|t| self.ts_type(t)).unwrap_or_else(|| "any".to_string())))
+            .collect();
+        let return_type = match (&func.return_type, func.is_async) {
+            (Some(t), false) => format!(": {}", self.ts_type(t)),
+            (Some(t), true) => format!(": Promise<{}>", self.ts_type(t)),
+            (None, true) => ": Promise<void>".to_string(),
+            (None, false) if !returns_value(&func.body) => ": void".to_string(),
+            (None, false) => String::new(),
+        };
+
+        let mut output = String::new();
+        output.push_str(&format!(
+            "export {}function {}({}){} {{\n",
+            if func.is_async { "async " } else { "" },
+            func.name, params.join(", "), return_type
+        ));
+        output.push_str(&self.block(&func.body, 1)?);
+        output.push_str("}\n\n");
+        Ok(output)
+    }
+
+    /// Scenes are classes: top-level `let`s become (readonly) properties
+    fn generate_scene(&self, scene: &SceneDeclaration) -> GrumpResult<String> {
+        let mut output = String::new();
+        output.push_str(&format!("export class {}Scene {{\n", pascal_case(&scene.name)));
+
+        let mut body = Vec::new();
+        for stmt in &scene.body {
+            match stmt {
+                Statement::Let { name, mutable, type_, value, .. } => {
+                    let type_annotation = type_.as_ref().map(|t| format!(": {}", self.ts_type(t))).unwrap_or_default();
+                    output.push_str(&format!(
+                        "  {}{}{} = {};\n",
+                        if *mutable { "" } else { "readonly " },
+                        name, type_annotation, self.expression(value)?
+                    ));
+                    self.scene_props.borrow_mut().push(name.clone());
+                }
+                other => body.push(other.clone()),
+            }
+        }
+
+        output.push_str("\n  constructor(private readonly world: GameWorld) {}\n\n");
+        output.push_str("  load(): void {\n");
+        output.push_str("    const world = this.world;\n");
+        for entity in &self.items.entities {
+            output.push_str(&format!("    spawn{}(world);\n", pascal_case(&entity.name)));
+        }
+        output.push_str(&self.block(&body, 2)?);
+        output.push_str("  }\n");
+        output.push_str("}\n\n");
+        self.scene_props.borrow_mut().clear();
+        Ok(output)
+    }
+
+    fn block(&self, statements: &[Statement], depth: usize) -> GrumpResult<String> {
+        let mut output = String::new();
+        for stmt in statements {
+            output.push_str(&self.statement(stmt, depth)?);
+        }
+        Ok(output)
+    }
+
+    /// One statement, indented by `depth` levels and ending in a newline
+    fn statement(&self, stmt: &Statement, depth: usize) -> GrumpResult<String> {
+        let indent = "  ".repeat(depth);
+        let code = match stmt {
+            Statement::Let { name, mutable, type_, value, .. } => {
+                let type_annotation = type_.as_ref().map(|t| format!(": {}", self.ts_type(t))).unwrap_or_default();
+                format!(
+                    "{}{} {}{} = {};\n",
+                    indent, if *mutable { "let" } else { "const" }, name, type_annotation, self.expression(value)?
+                )
+            }
+            Statement::Assign { target, value, .. } => {
+                format!("{}{} = {};\n", indent, self.expression(target)?, self.expression(value)?)
+            }
+            Statement::If { condition, then, else_, .. } => {
+                let mut code = format!("{}if ({}) {{\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(then, depth + 1)?);
+                code.push_str(&format!("{}}}", indent));
+                if let Some(else_body) = else_ {
+                    code.push_str(" else {\n");
+                    code.push_str(&self.block(else_body, depth + 1)?);
+                    code.push_str(&format!("{}}}", indent));
+                }
+                code.push('\n');
+                code
+            }
+            Statement::Match { expr, arms, .. } => {
+                let id = self.matches.get();
+                self.matches.set(id + 1);
+                let subject = format!("match{}", id);
+                let mut code = format!("{}{}: {{\n", indent, subject);
+                code.push_str(&format!("{}  const {}Value = {};\n", indent, subject, self.expression(expr)?));
+                for arm in arms {
+                    let mut bindings = Vec::new();
+                    let condition = self.pattern(&arm.pattern, &format!("{}Value", subject), &mut bindings);
+                    code.push_str(&format!("{}  if ({}) {{\n", indent, condition));
+                    for (name, value) in &bindings {
+                        code.push_str(&format!("{}    const {} = {};\n", indent, name, value));
+                    }
+                    let (body_depth, guard_close) = match &arm.guard {
+                        Some(guard) => {
+                            code.push_str(&format!("{}    if ({}) {{\n", indent, self.expression(guard)?));
+                            (depth + 3, format!("{}    }}\n", indent))
+                        }
+                        None => (depth + 2, String::new()),
+                    };
+                    code.push_str(&self.block(&arm.body, body_depth)?);
+                    code.push_str(&format!("{}break {};\n", "  ".repeat(body_depth), subject));
+                    code.push_str(&guard_close);
+                    code.push_str(&format!("{}  }}\n", indent));
+                }
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::For { var, iter, body, .. } => {
+                let mut code = format!("{}for (const {} of {}) {{\n", indent, var, self.expression(iter)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::While { condition, body, .. } => {
+                let mut code = format!("{}while ({}) {{\n", indent, self.expression(condition)?);
+                code.push_str(&self.block(body, depth + 1)?);
+                code.push_str(&format!("{}}}\n", indent));
+                code
+            }
+            Statement::Return(Some(expr), _) => format!("{}return {};\n", indent, self.expression(expr)?),
+            Statement::Return(None, _) => format!("{}return;\n", indent),
+            Statement::Break => format!("{}break;\n", indent),
+            Statement::Continue => format!("{}continue;\n", indent),
+            Statement::Expression(expr, _) => format!("{}{};\n", indent, self.expression(expr)?),
+            Statement::Await { expr } => format!("{}await {};\n", indent, self.expression(expr)?),
+            Statement::Animate(animate) => format!("{}{};\n", indent, self.animate(animate)?),
+            Statement::Timeline { name, .. } => {
+                return Err(GrumpError::Other(anyhow::anyhow!("Timeline '{}' is not supported by the TypeScript target", name)))
+            }
+            Statement::Debugger(_) => return Err(GrumpError::Other(anyhow::anyhow!("Debugger statements are not supported by the TypeScript target"))),
+            Statement::Network(_) => return Err(GrumpError::Other(anyhow::anyhow!("Network statements are not supported by the TypeScript target"))),
+        };
+        Ok(located(stmt.span(), None, code))
+    }
+
+    /// `animate target { keyframes }` tweens the target through a setter,
+    /// keyed by the target's code for `animation_finished`
+    fn animate(&self, animate: &AnimateStatement) -> GrumpResult<String> {
+        let target = self.expression(&animate.target)?;
+        let mut keyframes = Vec::new();
+        for kf in &animate.keyframes {
+            keyframes.push(format!("{{ time: {}, value: {} }}", self.seconds(&kf.time)?, self.animatable(&kf.value)?));
+        }
+        let duration = match &animate.duration {
+            Some(duration) => self.seconds(duration)?,
+            None => "0".to_string(),
+        };
+        Ok(format!(
+            "animate((value) => {{ {} = value; }}, {{ keyframes: [{}], duration: {}, loop: \"none\" }}, {})",
+            target, keyframes.join(", "), duration, animation_key(&target)
+        ))
+    }
+
+    /// Condition testing `subject` against `pattern`; bindings are collected for the arm body
+    fn pattern(&self, pattern: &Pattern, subject: &str, bindings: &mut Vec<(String, String)>) -> String {
+        match pattern {
+            Pattern::Wildcard => "true".to_string(),
+            Pattern::Literal(lit) => format!("{} === {}", subject, self.literal(lit)),
+            Pattern::Identifier(name) => match self.items.enum_of_variant(name) {
+                Some(enum_decl) => format!("{} === {}.{}", subject, enum_decl.name, name),
+                None => {
+                    bindings.push((name.clone(), subject.to_string()));
+                    "true".to_string()
+                }
+            },
+            Pattern::Tuple(patterns) => {
+                let conditions: Vec<String> = patterns.iter().enumerate()
+                    .map(|(i, p)| self.pattern(p, &format!("{}[{}]", subject, i), bindings))
+                    .filter(|c| c != "true")
+                    .collect();
+                if conditions.is_empty() { "true".to_string() } else { conditions.join(" && ") }
+            }
+            Pattern::Struct { fields, .. } => {
+                let conditions: Vec<String> = fields.iter()
+                    .map(|(field, p)| self.pattern(p, &format!("{}.{}", subject, field), bindings))
+                    .filter(|c| c != "true")
+                    .collect();
+                if conditions.is_empty() { "true".to_string() } else { conditions.join(" && ") }
+            }
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> GrumpResult<String> {
+        Ok(match expr {
+            Expression::Literal(lit) => self.literal(lit),
+            Expression::Identifier(name) => {
+                if self.scene_props.borrow().contains(name) {
+                    format!("this.{}", name)
+                } else {
+                    name.clone()
+                }
+            }
+            Expression::Binary { op, left, right } => {
+                let op = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Mod => "%",
+                    BinaryOp::Eq => "===",
+                    BinaryOp::Ne => "!==",
+                    BinaryOp::Lt => "<",
+                    BinaryOp::Gt => ">",
+                    BinaryOp::Le => "<=",
+                    BinaryOp::Ge => ">=",
+                    BinaryOp::And => "&&",
+                    BinaryOp::Or => "||",
+                    BinaryOp::Xor => "^",
+                    BinaryOp::ShiftLeft => "<<",
+                    BinaryOp::ShiftRight => ">>",
+                };
+                format!("({} {} {})", self.expression(left)?, op, self.expression(right)?)
+            }
+            Expression::Unary { op, expr } => match op {
+                UnaryOp::Neg => format!("-{}", self.expression(expr)?),
+                UnaryOp::Not => format!("!{}", self.expression(expr)?),
+                // References are implicit in TypeScript
+                UnaryOp::Deref | UnaryOp::Ref | UnaryOp::MutRef => self.expression(expr)?,
+            },
+            Expression::Call { func, args } => {
+                if let Some(target) = animation_target(func, args) {
+                    return Ok(format!("animation_finished({})", animation_key(&self.expression(target)?)));
+                }
+                let args = args.iter().map(|a| self.expression(a)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("{}({})", self.expression(func)?, args.join(", "))
+            }
+            Expression::Member { object, member } => format!("{}.{}", self.expression(object)?, member),
+            Expression::Index { object, index } => format!("{}[{}]", self.expression(object)?, self.expression(index)?),
+            Expression::Tuple(values) | Expression::Array(values) => {
+                let values = values.iter().map(|v| self.expression(v)).collect::<GrumpResult<Vec<_>>>()?;
+                format!("[{}]", values.join(", "))
+            }
+            Expression::Block(statements) => {
+                format!("(() => {{\n{}}})()", self.block(statements, 1)?)
+            }
+            Expression::If { condition, then, else_ } => format!(
+                "({} ? {} : {})",
+                self.expression(condition)?, self.expression(then)?, self.expression(else_)?
+            ),
+            Expression::Lambda { params, body } => {
+                let params: Vec<String> = params.iter()
+                    .map(|p| format!("{}: {}", p.name, p.type_.as_ref().map(|t| self.ts_type(t)).unwrap_or_else(|| "any".to_string())))
+                    .collect();
+                format!("(({}) => {})", params.join(", "), self.expression(body)?)
+            }
+            Expression::Await(expr) => format!("(await {})", self.expression(expr)?),
+            Expression::AsyncBlock(statements) => {
+                format!("(async () => {{\n{}}})()", self.block(statements, 1)?)
+            }
+            Expression::MacroCall { name, args, span } => {
+                let args = args.iter().map(|arg| self.expression(arg)).collect::<GrumpResult<Vec<_>>>()?;
+                template_call(self.items.macros.iter().copied(), Target::TypeScript, name, &args, *span)?
+            }
+        })
+    }
+
+    fn literal(&self, lit: &Literal) -> String {
+        match lit {
+            Literal::Integer(n) => n.to_string(),
+            Literal::Float(f) => format!("{:?}", f),
+            Literal::String(s) => serde_json::to_string(s).unwrap_or_default(),
+            Literal::Char(c) => serde_json::to_string(&c.to_string()).unwrap_or_default(),
+            Literal::Bool(b) => b.to_string(),
+            Literal::Color { r, g, b, a } => format!("color({}, {}, {}, {})", r, g, b, a),
+            Literal::Vec2 { x, y } => format!("vec2({:?}, {:?})", x, y),
+            Literal::Vec3 { x, y, z } => format!("vec3({:?}, {:?}, {:?})", x, y, z),
+            Literal::Duration { value, unit } => format!("{:?}", duration_seconds(*value, unit)),
+            Literal::Angle { value, unit } => format!("{:?}", angle_radians(*value, unit)),
+        }
+    }
+
+    /// Keyframe times are seconds
+    fn seconds(&self, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Literal(Literal::Integer(n)) => Ok(format!("{:?}", *n as f64)),
+            other => self.expression(other),
+        }
+    }
+
+    /// Keyframe values: pairs become `Vec2`
+    fn animatable(&self, expr: &Expression) -> GrumpResult<String> {
+        match expr {
+            Expression::Tuple(values) | Expression::Array(values) if values.len() == 2 => {
+                Ok(format!("vec2({}, {})", self.expression(&values[0])?, self.expression(&values[1])?))
+            }
+            other => self.expression(other),
+        }
+    }
+
+    fn loop_mode(mode: Option<&LoopMode>) -> &'static str {
+        match mode {
+            Some(LoopMode::Loop) | Some(LoopMode::Section { .. }) => "\"loop\"",
+            Some(LoopMode::PingPong) => "\"pingpong\"",
+            Some(LoopMode::Reverse) => "\"reverse\"",
+            Some(LoopMode::None) | None => "\"none\"",
+        }
+    }
+
+    /// What the component's `@derive(...)` asks for
+    fn generate_derives(&self, comp: &ComponentDeclaration) -> String {
+        let name = &comp.name;
+        let mut output = String::new();
+        if derive::has(comp, Derive::Serialize) {
+            output.push_str(&format!("export function serialize{}(value: {}): string {{\n", name, name));
+            output.push_str("  return JSON.stringify(value);\n");
+            output.push_str("}\n\n");
+            output.push_str(&format!("export function deserialize{}(json: string): {} {{\n", name, name));
+            output.push_str(&format!("  return create{}(JSON.parse(json) as Partial<{}>);\n", name, name));
+            output.push_str("}\n\n");
+        }
+        if derive::has(comp, Derive::Inspect) {
+            output.push_str(&format!("export const {}Inspector: readonly InspectorField[] = [\n", name));
+            for field in derive::inspector(comp, &self.items.enums) {
+                let options: Vec<String> = field.options.iter().map(|o| format!("\"{}\"", o)).collect();
+                output.push_str(&format!(
+                    "  {{ name: \"{}\", type: \"{}\", animatable: {}, options: [{}] }},\n",
+                    field.name, field.type_name, field.animatable, options.join(", ")
+                ));
+            }
+            output.push_str("];\n\n");
+        }
+        if derive::has(comp, Derive::Lerp) {
+            let fields = derive::lerp_fields(comp);
+            output.push_str(&format!("export const {}Lerp = {{\n", name));
+            for field in &fields {
+                let (a, b) = (format!("a.{}", field.name), format!("b.{}", field.name));
+                let value = match &field.type_ {
+                    Type::Int | Type::Int64 => format!("Math.round(lerp({}, {}, t))", a, b),
+                    Type::Vec2 => format!("lerpVec2({}, {}, t)", a, b),
+                    Type::Vec3 => format!("lerpVec3({}, {}, t)", a, b),
+                    Type::Vec4 => format!("lerpVec4({}, {}, t)", a, b),
+                    Type::Color => format!("lerpColor({}, {}, t)", a, b),
+                    _ => format!("lerp({}, {}, t)", a, b),
+                };
+                output.push_str(&format!(
+                    "  {}: (a: {}, b: {}, t: number): {} => {},\n",
+                    field.name, name, name, self.ts_type(&field.type_), value
+                ));
+            }
+            output.push_str("};\n\n");
+            output.push_str("/** Other fields keep `a`'s values */\n");
+            output.push_str(&format!("export function lerp{}(a: {}, b: {}, t: number): {} {{\n", name, name, name, name));
+            output.push_str("  return {\n");
+            output.push_str("    ...a,\n");
+            for field in &fields {
+                output.push_str(&format!("    {}: {}Lerp.{}(a, b, t),\n", field.name, name, field.name));
+            }
+            output.push_str("  };\n");
+            output.push_str("}\n\n");
+        }
+        output
+    }
+
+    fn ts_type(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Int | Type::Int64 | Type::Float | Type::Double => "number".to_string(),
+            Type::Angle | Type::Duration => "number".to_string(),
+            Type::Bool => "boolean".to_string(),
+            Type::String | Type::Char => "string".to_string(),
+            Type::Vec2 => "Vec2".to_string(),
+            Type::Vec3 => "Vec3".to_string(),
+            Type::Vec4 => "Vec4".to_string(),
+            Type::Color => "Color".to_string(),
+            Type::Rotation | Type::Transform => "Transform".to_string(),
+            Type::Optional(inner) => format!("{} | undefined", self.ts_type(inner)),
+            Type::Result { ok, err } => format!("Result<{}, {}>", self.ts_type(ok), self.ts_type(err)),
+            Type::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| self.ts_type(t)).collect();
+                format!("[{}]", types.join(", "))
+            }
+            Type::Array(inner) => match inner.as_ref() {
+                Type::Optional(_) => format!("({})[]", self.ts_type(inner)),
+                _ => format!("{}[]", self.ts_type(inner)),
+            },
+            Type::Named(name) => name.clone(),
+        }
+    }
+
+    /// Value for a component field without a default
+    fn zero_value(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Int | Type::Int64 | Type::Float | Type::Double | Type::Angle | Type::Duration => "0".to_string(),
+            Type::Bool => "false".to_string(),
+            Type::String | Type::Char => "\"\"".to_string(),
+            Type::Vec2 => "vec2(0, 0)".to_string(),
+            Type::Vec3 => "vec3(0, 0, 0)".to_string(),
+            Type::Vec4 => "vec4(0, 0, 0, 0)".to_string(),
+            Type::Color => "color(0, 0, 0, 255)".to_string(),
+            Type::Rotation | Type::Transform => "translate(0, 0)".to_string(),
+            Type::Optional(_) => "undefined".to_string(),
+            Type::Tuple(types) => {
+                let values: Vec<String> = types.iter().map(|t| self.zero_value(t)).collect();
+                format!("[{}]", values.join(", "))
+            }
+            Type::Array(_) => "[]".to_string(),
+            Type::Named(name) => {
+                if let Some(enum_decl) = self.items.enums.iter().find(|e| &e.name == name) {
+                    format!("{}.{}", enum_decl.name, enum_decl.variants[0])
+                } else if self.items.component(name).is_some() {
+                    format!("create{}()", name)
+                } else {
+                    format!("({{}} as {})", name)
+                }
+            }
+            Type::Result { err, .. } => format!("{{ err: {} }}", self.zero_value(err)),
+        }
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+const RUNTIME: &str = r#"// G-Rump runtime support for TypeScript
+
+export interface Vec2 { x: number; y: number; }
+export interface Vec3 { x: number; y: number; z: number; }
+export interface Vec4 { x: number; y: number; z: number; w: number; }
+export interface Color { r: number; g: number; b: number; a: number; }
+export interface Transform { position: Vec2; rotation: number; scale: Vec2; }
+
+export type Result<T, E> = { ok: T } | { err: E };
+export type LoopMode = "none" | "loop" | "pingpong" | "reverse";
+export type Animatable = number | Vec2;
+
+/** A component field as an editor inspector shows it (`@derive(Inspect)`) */
+export interface InspectorField {
+  name: string;
+  /** As written in G-Rump: "float", "vec2", "Mood" */
+  type: string;
+  animatable: boolean;
+  /** An enum field's variants */
+  options: readonly string[];
+}
+
+export interface Keyframe<T extends Animatable> {
+  time: number;
+  value: T;
+}
+
+export interface AnimationClip<T extends Animatable> {
+  keyframes: Keyframe<T>[];
+  /** Seconds; 0 means "until the last keyframe" */
+  duration: number;
+  loop: LoopMode;
+}
+
+export function vec2(x: number, y: number): Vec2 { return { x, y }; }
+export function vec3(x: number, y: number, z: number): Vec3 { return { x, y, z }; }
+export function vec4(x: number, y: number, z: number, w: number): Vec4 { return { x, y, z, w }; }
+export function color(r: number, g: number, b: number, a: number = 255): Color { return { r, g, b, a }; }
+
+export class Entity<C> {
+  private readonly components = new Map<keyof C, unknown>();
+
+  constructor(readonly id: number) {}
+
+  add<K extends keyof C>(name: K, component: C[K]): this {
+    this.components.set(name, component);
+    return this;
+  }
+
+  get<K extends keyof C>(name: K): C[K] | undefined {
+    return this.components.get(name) as C[K] | undefined;
+  }
+
+  has(name: keyof C): boolean {
+    return this.components.has(name);
+  }
+
+  remove(name: keyof C): void {
+    this.components.delete(name);
+  }
+}
+
+export type System<C> = (world: World<C>, dt: number) => void;
+
+export class World<C> {
+  readonly entities: Entity<C>[] = [];
+  private readonly systems: System<C>[] = [];
+  private nextId = 0;
+
+  spawn(): Entity<C> {
+    const entity = new Entity<C>(this.nextId++);
+    this.entities.push(entity);
+    return entity;
+  }
+
+  despawn(entity: Entity<C>): void {
+    const index = this.entities.indexOf(entity);
+    if (index >= 0) {
+      this.entities.splice(index, 1);
+    }
+  }
+
+  query(...names: (keyof C)[]): Entity<C>[] {
+    return this.entities.filter((entity) => names.every((name) => entity.has(name)));
+  }
+
+  addSystem(system: System<C>): void {
+    this.systems.push(system);
+  }
+
+  update(dt: number): void {
+    deltaTime = dt;
+    elapsed += dt;
+    tickTweens(dt);
+    tickWaiters();
+    for (const system of this.systems) {
+      system(this, dt);
+    }
+  }
+}
+
+let deltaTime = 0;
+let elapsed = 0;
+
+interface Tween {
+  clip: AnimationClip<Animatable>;
+  set: (value: Animatable) => void;
+  time: number;
+  key?: string;
+}
+
+const tweens: Tween[] = [];
+
+/** Play `clip`, feeding each sampled value to `set` once per frame */
+export function animate<T extends Animatable>(set: (value: T) => void, clip: AnimationClip<T>, key?: string): void {
+  tweens.push({
+    clip: clip as AnimationClip<Animatable>,
+    set: set as (value: Animatable) => void,
+    time: 0,
+    key,
+  });
+}
+
+interface Waiter {
+  ready: () => boolean;
+  resume: () => void;
+}
+
+const waiters: Waiter[] = [];
+
+/** Resume the waiters that are ready, after the frame's tweens have run */
+function tickWaiters(): void {
+  for (let i = waiters.length - 1; i >= 0; i--) {
+    if (waiters[i].ready()) {
+      waiters[i].resume();
+      waiters.splice(i, 1);
+    }
+  }
+}
+
+function until(ready: () => boolean): Promise<void> {
+  return ready() ? Promise.resolve() : new Promise((resume) => waiters.push({ ready, resume }));
+}
+
+/** Resolves on the first frame `seconds` of game time from now */
+export function wait(seconds: number): Promise<void> {
+  const end = elapsed + seconds;
+  return until(() => elapsed >= end);
+}
+
+/** Resolves once no `animate` of the target keyed `key` is running */
+export function animation_finished(key: string): Promise<void> {
+  return until(() => !tweens.some((tween) => tween.key === key));
+}
+
+function clipLength(clip: AnimationClip<Animatable>): number {
+  const last = clip.keyframes[clip.keyframes.length - 1];
+  return clip.duration > 0 ? clip.duration : last ? last.time : 0;
+}
+
+function mix(a: Animatable, b: Animatable, t: number): Animatable {
+  if (typeof a === "number" && typeof b === "number") {
+    return a + (b - a) * t;
+  }
+  if (typeof a !== "number" && typeof b !== "number") {
+    return { x: a.x + (b.x - a.x) * t, y: a.y + (b.y - a.y) * t };
+  }
+  return t < 1 ? a : b;
+}
+
+function sample(clip: AnimationClip<Animatable>, time: number): Animatable | undefined {
+  const frames = clip.keyframes;
+  const length = clipLength(clip);
+  let t = time;
+  switch (clip.loop) {
+    case "loop":
+      t = length > 0 ? time % length : 0;
+      break;
+    case "pingpong": {
+      const cycle = length > 0 ? time % (2 * length) : 0;
+      t = cycle > length ? 2 * length - cycle : cycle;
+      break;
+    }
+    case "reverse":
+      t = Math.max(length - time, 0);
+      break;
+    case "none":
+      t = Math.min(time, length);
+      break;
+  }
+  if (frames.length === 0) {
+    return undefined;
+  }
+  let previous = frames[0];
+  for (const frame of frames) {
+    if (frame.time >= t) {
+      const span = frame.time - previous.time;
+      return span > 0 ? mix(previous.value, frame.value, (t - previous.time) / span) : frame.value;
+    }
+    previous = frame;
+  }
+  return previous.value;
+}
+
+function tickTweens(dt: number): void {
+  for (let i = tweens.length - 1; i >= 0; i--) {
+    const tween = tweens[i];
+    tween.time += dt;
+    const value = sample(tween.clip, tween.time);
+    if (value !== undefined) {
+      tween.set(value);
+    }
+    const finite = tween.clip.loop === "none" || tween.clip.loop === "reverse";
+    if (finite && tween.time >= clipLength(tween.clip)) {
+      tweens.splice(i, 1);
+    }
+  }
+}
+
+// Built-in functions
+
+export const sin = Math.sin;
+export const cos = Math.cos;
+export const sqrt = Math.sqrt;
+export const abs = Math.abs;
+
+export function length(v: Vec2): number { return Math.hypot(v.x, v.y); }
+export function normalize(v: Vec2): Vec2 {
+  const len = length(v);
+  return len > 0 ? vec2(v.x / len, v.y / len) : vec2(0, 0);
+}
+export function dot(a: Vec2, b: Vec2): number { return a.x * b.x + a.y * b.y; }
+export function lerp(a: number, b: number, t: number): number { return a + (b - a) * t; }
+export function lerpVec2(a: Vec2, b: Vec2, t: number): Vec2 { return vec2(lerp(a.x, b.x, t), lerp(a.y, b.y, t)); }
+export function lerpVec3(a: Vec3, b: Vec3, t: number): Vec3 {
+  return vec3(lerp(a.x, b.x, t), lerp(a.y, b.y, t), lerp(a.z, b.z, t));
+}
+export function lerpVec4(a: Vec4, b: Vec4, t: number): Vec4 {
+  return vec4(lerp(a.x, b.x, t), lerp(a.y, b.y, t), lerp(a.z, b.z, t), lerp(a.w, b.w, t));
+}
+export function lerpColor(a: Color, b: Color, t: number): Color {
+  const channel = (from: number, to: number) => Math.round(lerp(from, to, t));
+  return color(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b), channel(a.a, b.a));
+}
+export function ease_in_out(t: number): number { return t < 0.5 ? 2 * t * t : 1 - Math.pow(-2 * t + 2, 2) / 2; }
+export function print(message: string): void { console.log(message); }
+export function random(min: number, max: number): number { return min + Math.random() * (max - min); }
+export function concat(a: string, b: string): string { return a + b; }
+export function substring(s: string, start: number, end: number): string { return s.substring(start, end); }
+export function str_length(s: string): number { return s.length; }
+export function rgb(r: number, g: number, b: number): Color { return color(r, g, b); }
+export function rgba(r: number, g: number, b: number, a: number): Color { return color(r, g, b, a); }
+export function hsl(h: number, s: number, l: number): Color {
+  const k = (n: number): number => (n + h / 30) % 12;
+  const a = s * Math.min(l, 1 - l);
+  const f = (n: number): number => l - a * Math.max(-1, Math.min(k(n) - 3, Math.min(9 - k(n), 1)));
+  return color(Math.round(f(0) * 255), Math.round(f(8) * 255), Math.round(f(4) * 255));
+}
+export function translate(x: number, y: number): Transform {
+  return { position: vec2(x, y), rotation: 0, scale: vec2(1, 1) };
+}
+export function rotate(angle: number): Transform {
+  return { position: vec2(0, 0), rotation: angle, scale: vec2(1, 1) };
+}
+export function scale(x: number, y: number): Transform {
+  return { position: vec2(0, 0), rotation: 0, scale: vec2(x, y) };
+}
+export function now(): number { return elapsed; }
+export function delta_time(): number { return deltaTime; }
+"#;
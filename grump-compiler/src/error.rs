@@ -35,6 +35,37 @@ pub enum GrumpError {
         message: String,
     },
     
+    #[error("Module error in {file} at {line}:{column}: {message}")]
+    Module {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    
+    #[error("Macro error at {line}:{column}: {message}")]
+    Macro {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    
+    #[error("Package error: {message}")]
+    Package {
+        message: String,
+    },
+    
+    #[error("Plugin error in {plugin}: {message}")]
+    Plugin {
+        plugin: String,
+        message: String,
+    },
+    
+    #[error("Runtime error: {message}")]
+    Runtime {
+        message: String,
+    },
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
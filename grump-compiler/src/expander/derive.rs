@@ -0,0 +1,151 @@
+//! Derive attributes
+//!
+//! `@derive(...)` on a component asks each target to generate code from its
+//! fields:
+//!
+//! ```text
+//! @derive(Serialize, Inspect, Lerp)
+//! component Sprite {
+//!     position: vec2 = (0.0, 0.0);
+//!     tint: color;
+//!     frame: int = 0;
+//!     label: string = "";
+//! }
+//! ```
+//!
+//! - `Serialize`: save and load a component as text
+//! - `Inspect`: a list of the fields for an editor inspector, with their
+//!   types, whether they animate and an enum's variants
+//! - `Lerp`: an interpolation function for each animatable field (numbers,
+//!   vectors, colors, angles and durations), and one for the whole
+//!   component that keeps the other fields of the start value
+//!
+//! This module decides what each derive covers; the TypeScript, Godot and
+//! Rust generators write it in their language.
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::{ComponentDeclaration, EnumDeclaration, Field, Type};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    Serialize,
+    Inspect,
+    Lerp,
+}
+
+impl Derive {
+    pub const ALL: [Derive; 3] = [Derive::Serialize, Derive::Inspect, Derive::Lerp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Derive::Serialize => "Serialize",
+            Derive::Inspect => "Inspect",
+            Derive::Lerp => "Lerp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Derive> {
+        Self::ALL.into_iter().find(|derive| derive.name() == name)
+    }
+}
+
+/// A field as the inspector shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectorField<'a> {
+    pub name: &'a str,
+    /// As written in G-Rump: `float`, `vec2`, `Mood`
+    pub type_name: String,
+    pub animatable: bool,
+    /// The variants of an enum field
+    pub options: Vec<String>,
+}
+
+/// The derives a component asks for; unknown or repeated names are errors
+pub fn derives(comp: &ComponentDeclaration) -> GrumpResult<Vec<Derive>> {
+    let mut derives = Vec::new();
+    for name in &comp.derives {
+        let Some(derive) = Derive::from_name(name) else {
+            let known: Vec<&str> = Derive::ALL.iter().map(|d| d.name()).collect();
+            return Err(error(comp, format!("derives `{}`, which isn't one of {}", name, known.join(", "))));
+        };
+        if derives.contains(&derive) {
+            return Err(error(comp, format!("derives `{}` twice", name)));
+        }
+        if derive == Derive::Lerp && lerp_fields(comp).is_empty() {
+            return Err(error(comp, "derives `Lerp`, but has no animatable fields".to_string()));
+        }
+        derives.push(derive);
+    }
+    Ok(derives)
+}
+
+/// Whether `comp` derives `derive`; call [`derives`] first to report errors
+pub fn has(comp: &ComponentDeclaration, derive: Derive) -> bool {
+    comp.derives.iter().any(|name| name == derive.name())
+}
+
+/// Types `Lerp` interpolates
+pub fn is_animatable(type_: &Type) -> bool {
+    matches!(
+        type_,
+        Type::Int | Type::Int64 | Type::Float | Type::Double | Type::Angle | Type::Rotation | Type::Duration
+            | Type::Vec2 | Type::Vec3 | Type::Vec4 | Type::Color
+    )
+}
+
+pub fn lerp_fields(comp: &ComponentDeclaration) -> Vec<&Field> {
+    comp.fields.iter().filter(|field| is_animatable(&field.type_)).collect()
+}
+
+pub fn inspector<'a>(comp: &'a ComponentDeclaration, enums: &[&EnumDeclaration]) -> Vec<InspectorField<'a>> {
+    comp.fields.iter()
+        .map(|field| InspectorField {
+            name: &field.name,
+            type_name: type_name(&field.type_),
+            animatable: is_animatable(&field.type_),
+            options: match &field.type_ {
+                Type::Named(name) => enums.iter()
+                    .find(|e| &e.name == name)
+                    .map(|e| e.variants.clone())
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            },
+        })
+        .collect()
+}
+
+pub fn type_name(type_: &Type) -> String {
+    match type_ {
+        Type::Int => "int".to_string(),
+        Type::Int64 => "int64".to_string(),
+        Type::Float => "float".to_string(),
+        Type::Double => "double".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::String => "string".to_string(),
+        Type::Char => "char".to_string(),
+        Type::Vec2 => "vec2".to_string(),
+        Type::Vec3 => "vec3".to_string(),
+        Type::Vec4 => "vec4".to_string(),
+        Type::Color => "color".to_string(),
+        Type::Angle => "angle".to_string(),
+        Type::Rotation => "rotation".to_string(),
+        Type::Transform => "transform".to_string(),
+        Type::Duration => "duration".to_string(),
+        Type::Optional(inner) => format!("{}?", type_name(inner)),
+        Type::Result { ok, err } => format!("Result<{}, {}>", type_name(ok), type_name(err)),
+        Type::Tuple(types) => {
+            let types: Vec<String> = types.iter().map(type_name).collect();
+            format!("({})", types.join(", "))
+        }
+        Type::Array(inner) => format!("[{}]", type_name(inner)),
+        Type::Named(name) => name.clone(),
+    }
+}
+
+fn error(comp: &ComponentDeclaration, message: String) -> GrumpError {
+    GrumpError::Macro {
+        line: comp.span.line,
+        column: comp.span.column,
+        message: format!("Component `{}` {}", comp.name, message),
+    }
+}
@@ -0,0 +1,652 @@
+//! Macro expansion
+//!
+//! Runs between parsing and analysis, replacing every `name!(args)` call
+//! with the body of the `macro` it names:
+//!
+//! ```text
+//! macro repeat(count: int, body: block) {
+//!     let mut i = 0;
+//!     while (i < count) {
+//!         body(i);
+//!         i = i + 1;
+//!     }
+//! }
+//!
+//! repeat!(3) { |n| print(n); }
+//! ```
+//!
+//! - Each argument is evaluated once, into a fresh local the body sees as
+//!   its parameter (typed, if the parameter is)
+//! - A variadic parameter (`items...`) gets the remaining arguments as a list
+//! - A block parameter takes the `{ ... }` after a call statement; the body
+//!   runs it with `body;`, or `body(i);` to bind the block's `|n|`
+//! - Expansion is hygienic: every local the body declares is renamed
+//!   (`i__repeat3`, or `i__repeat3_1` if the program already uses that),
+//!   so it can't capture or clobber the caller's variables, while the
+//!   caller's arguments and blocks keep their names
+//!
+//! A call statement is replaced by the body's statements; a call inside an
+//! expression by a block whose value is the body's last expression (or
+//! `return`). Each run of a block gets a scope of its own, so its `|n|`
+//! and `let`s end with it. Errors point at the call, and calls made by expanded code
+//! at the outermost call that led to them.
+//!
+//! Calls to template macros, whose bodies are target code, are left for
+//! the code generators; see [`template`]. `@derive(...)` attributes on
+//! components are checked here too; see [`derive`].
+//!
+//! Each body it walks also gets its reassigned plain `let`s marked `mut`,
+//! which is how the code generators see them.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::extensions::{MacroBody, MacroDeclaration, NetworkStatement};
+use crate::parser::{Expression, Item, Literal, Pattern, Program, Span, Statement};
+use crate::query::deps;
+
+pub mod derive;
+pub mod template;
+
+/// Deepest nesting of macro calls in expanded code, which stops a macro
+/// that expands into itself
+const MAX_DEPTH: usize = 32;
+
+pub struct Expander {
+    macros: HashMap<String, MacroDeclaration>,
+    /// Leave calls to macros it doesn't know, for a later pass that sees
+    /// more files, instead of failing
+    lenient: bool,
+    next_id: usize,
+    depth: usize,
+    /// Names the program uses, and the fresh names handed out so far
+    taken: BTreeSet<String>,
+}
+
+/// A block argument: the statements and the names its `|a, b|` binds
+struct Block {
+    params: Vec<String>,
+    body: Vec<Statement>,
+}
+
+impl Expander {
+    /// An expander for the macros `program` declares
+    pub fn new(program: &Program) -> Self {
+        Self::with_macros(program.all_items())
+    }
+
+    pub fn with_macros<'a>(items: impl IntoIterator<Item = &'a Item>) -> Self {
+        let macros = items.into_iter()
+            .filter_map(|item| match item {
+                Item::Macro(decl) => Some((decl.name.clone(), decl.clone())),
+                _ => None,
+            })
+            .collect();
+        Self { macros, lenient: false, next_id: 0, depth: 0, taken: BTreeSet::new() }
+    }
+
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Expand every macro call in `program`
+    pub fn expand(&mut self, program: &mut Program) -> GrumpResult<()> {
+        used_names(&mut program.items, &mut self.taken);
+        self.expand_items(&mut program.items)
+    }
+
+    fn expand_items(&mut self, items: &mut [Item]) -> GrumpResult<()> {
+        for item in items {
+            match item {
+                Item::App(app) => self.expand_items(&mut app.body)?,
+                Item::Module(module) => self.expand_items(&mut module.items)?,
+                Item::Component(comp) => {
+                    derive::derives(comp)?;
+                }
+                Item::Function(func) => self.expand_body(&mut func.body)?,
+                Item::Scene(scene) => self.expand_body(&mut scene.body)?,
+                Item::Entity(entity) => self.expand_body(&mut entity.body)?,
+                Item::System(system) => self.expand_body(&mut system.body)?,
+                Item::Test(test) => self.expand_body(&mut test.body)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn expand_body(&mut self, body: &mut Vec<Statement>) -> GrumpResult<()> {
+        let mut expanded = Vec::with_capacity(body.len());
+        for mut stmt in std::mem::take(body) {
+            if let Statement::Expression(Expression::MacroCall { name, args, span }, _) = &mut stmt {
+                for arg in args.iter_mut() {
+                    self.expand_expression(arg)?;
+                }
+                if let Some(statements) = self.expand_call(name, args, *span)? {
+                    expanded.extend(statements);
+                    continue;
+                }
+            } else {
+                self.expand_statement(&mut stmt)?;
+            }
+            expanded.push(stmt);
+        }
+        *body = expanded;
+        mark_reassigned(body);
+        Ok(())
+    }
+
+    fn expand_statement(&mut self, stmt: &mut Statement) -> GrumpResult<()> {
+        for body in bodies_mut(stmt) {
+            self.expand_body(body)?;
+        }
+        for expr in expressions_mut(stmt) {
+            self.expand_expression(expr)?;
+        }
+        Ok(())
+    }
+
+    fn expand_expression(&mut self, expr: &mut Expression) -> GrumpResult<()> {
+        match expr {
+            Expression::MacroCall { name, args, span } => {
+                for arg in args.iter_mut() {
+                    self.expand_expression(arg)?;
+                }
+                if let Some(statements) = self.expand_call(name, args, *span)? {
+                    *expr = Expression::Block(statements);
+                }
+            }
+            Expression::Block(body) | Expression::AsyncBlock(body) => self.expand_body(body)?,
+            _ => {
+                for child in children_mut(expr) {
+                    self.expand_expression(child)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The statements a call expands to, themselves expanded; `None` for a
+    /// macro this (lenient) expander doesn't know
+    fn expand_call(&mut self, name: &str, args: &[Expression], span: Span) -> GrumpResult<Option<Vec<Statement>>> {
+        let Some(decl) = self.macros.get(name).cloned() else {
+            if self.lenient {
+                return Ok(None);
+            }
+            return Err(error(span, format!("Undefined macro `{}!`", name)));
+        };
+        validate(&decl).map_err(|e| at(e, span, name))?;
+        let MacroBody::Code(body) = &decl.body else {
+            // Template macros are filled in by the code generators
+            return Ok(None);
+        };
+        if self.depth >= MAX_DEPTH {
+            return Err(error(span, format!("`{}!` expands into itself more than {} levels deep", name, MAX_DEPTH)));
+        }
+
+        self.next_id += 1;
+
+        // Bind the arguments
+        let fixed = decl.params.iter().filter(|p| !p.is_variadic).count();
+        if let Some(block) = decl.params.last().filter(|p| p.is_block && args.len() + 1 == fixed) {
+            return Err(block_expected(span, name, &block.name));
+        }
+        if let Some(message) = arity_error(&decl, args.len()) {
+            return Err(error(span, message));
+        }
+        let mut renames: HashMap<String, String> = HashMap::new();
+        let mut blocks: HashMap<String, Block> = HashMap::new();
+        let mut expanded = Vec::new();
+        let extra = args.len() - fixed;
+        let mut args = args.iter().cloned();
+        for param in &decl.params {
+            if param.is_variadic {
+                let rest = args.by_ref().take(extra).collect();
+                let local = self.fresh(&param.name, name);
+                renames.insert(param.name.clone(), local.clone());
+                expanded.push(Statement::Let {
+                    name: local,
+                    mutable: false,
+                    type_: None,
+                    value: Expression::Array(rest),
+                    span,
+                });
+            } else if param.is_block {
+                let block = match args.next() {
+                    Some(Expression::Block(body)) => Block { params: Vec::new(), body },
+                    Some(Expression::Lambda { params, body }) => match *body {
+                        Expression::Block(body) => Block { params: params.into_iter().map(|p| p.name).collect(), body },
+                        _ => return Err(block_expected(span, name, &param.name)),
+                    },
+                    _ => return Err(block_expected(span, name, &param.name)),
+                };
+                blocks.insert(param.name.clone(), block);
+            } else if let Some(value) = args.next() {
+                let local = self.fresh(&param.name, name);
+                renames.insert(param.name.clone(), local.clone());
+                expanded.push(Statement::Let {
+                    name: local,
+                    mutable: false,
+                    type_: param.type_.clone(),
+                    value,
+                    span,
+                });
+            }
+        }
+
+        // Rename the body's own locals, then run the caller's blocks where it asks
+        let mut body = body.clone();
+        let mut locals = BTreeSet::new();
+        bound_in(&mut body, &mut locals);
+        for local in locals {
+            if !blocks.contains_key(&local) && !renames.contains_key(&local) {
+                let fresh = self.fresh(&local, name);
+                renames.insert(local, fresh);
+            }
+        }
+        rename_body(&mut body, &renames);
+        // Its statements map back to the call
+        respan(&mut body, &|_| span);
+        let body = splice_blocks(body, &blocks).map_err(|message| error(span, format!("In `{}!`: {}", name, message)))?;
+        expanded.extend(body);
+
+        // Calls the body makes are reported at this call
+        self.depth += 1;
+        let result = self.expand_body(&mut expanded).map_err(|e| at(e, span, name));
+        self.depth -= 1;
+        result?;
+        Ok(Some(expanded))
+    }
+
+    /// A name for `local` in this expansion of macro `name` that nothing
+    /// else in the program uses
+    fn fresh(&mut self, local: &str, name: &str) -> String {
+        let base = format!("{}__{}{}", local, name, self.next_id);
+        let mut fresh = base.clone();
+        let mut n = 0;
+        while !self.taken.insert(fresh.clone()) {
+            n += 1;
+            fresh = format!("{}_{}", base, n);
+        }
+        fresh
+    }
+}
+
+/// Every name `items` declare, bind or refer to
+fn used_names(items: &mut [Item], names: &mut BTreeSet<String>) {
+    for item in items {
+        names.insert(item.name().to_string());
+        names.extend(deps::references(item));
+        match item {
+            Item::App(app) => used_names(&mut app.body, names),
+            Item::Module(module) => used_names(&mut module.items, names),
+            Item::Function(func) => {
+                names.extend(func.params.iter().map(|p| p.name.clone()));
+                bound_in(&mut func.body, names);
+            }
+            Item::Scene(scene) => bound_in(&mut scene.body, names),
+            Item::Entity(entity) => bound_in(&mut entity.body, names),
+            Item::System(system) => bound_in(&mut system.body, names),
+            Item::Test(test) => bound_in(&mut test.body, names),
+            _ => {}
+        }
+    }
+}
+
+/// Check a declaration: distinct parameter names, at most one variadic
+/// parameter, and variadic parameters aren't blocks
+pub fn validate(decl: &MacroDeclaration) -> GrumpResult<()> {
+    let mut seen = BTreeSet::new();
+    for param in &decl.params {
+        if !seen.insert(param.name.as_str()) {
+            return Err(error(decl.span, format!("Macro `{}` has two parameters named `{}`", decl.name, param.name)));
+        }
+        if param.is_variadic && param.is_block {
+            return Err(error(decl.span, format!("Macro `{}`'s parameter `{}` can't be both variadic and a block", decl.name, param.name)));
+        }
+    }
+    if decl.params.iter().filter(|p| p.is_variadic).count() > 1 {
+        return Err(error(decl.span, format!("Macro `{}` has more than one variadic parameter", decl.name)));
+    }
+    if let MacroBody::Template(templates) = &decl.body {
+        template::validate(decl, templates)?;
+    }
+    Ok(())
+}
+
+/// Why a call with `args` arguments doesn't fit `decl`, if it doesn't
+pub fn arity_error(decl: &MacroDeclaration, args: usize) -> Option<String> {
+    let fixed = decl.params.iter().filter(|p| !p.is_variadic).count();
+    let variadic = decl.params.iter().any(|p| p.is_variadic);
+    if args < fixed || (!variadic && args > fixed) {
+        let at_least = if variadic { "at least " } else { "" };
+        return Some(format!("`{}!` takes {}{} arguments, got {}", decl.name, at_least, fixed, args));
+    }
+    None
+}
+
+fn block_expected(span: Span, name: &str, param: &str) -> GrumpError {
+    error(span, format!("`{}!` takes a block for `{}`: `{}!(...) {{ ... }}`", name, param, name))
+}
+
+/// Replace `block;` and `block(args);` statements with the caller's block,
+/// its `|params|` bound to the arguments; any other use of a block is an error
+fn splice_blocks(body: Vec<Statement>, blocks: &HashMap<String, Block>) -> Result<Vec<Statement>, String> {
+    if blocks.is_empty() {
+        return Ok(body);
+    }
+    let mut spliced = Vec::with_capacity(body.len());
+    for mut stmt in body {
+        let run = match &stmt {
+            Statement::Expression(Expression::Identifier(name), _) => blocks.get(name).map(|block| (name, block, Vec::new())),
+            Statement::Expression(Expression::Call { func, args }, _) => match func.as_ref() {
+                Expression::Identifier(name) => blocks.get(name).map(|block| (name, block, args.clone())),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some((name, block, args)) = run {
+            let span = stmt.span().unwrap_or_default();
+            if args.len() != block.params.len() {
+                return Err(format!("`{}` is run with {} arguments, but the block takes {}", name, args.len(), block.params.len()));
+            }
+            let mut scope: Vec<Statement> = block.params.iter().zip(args)
+                .map(|(param, value)| Statement::Let { name: param.clone(), mutable: false, type_: None, value, span })
+                .collect();
+            scope.extend(block.body.iter().cloned());
+            spliced.push(scoped(scope, span));
+            continue;
+        }
+
+        for body in bodies_mut(&mut stmt) {
+            *body = splice_blocks(std::mem::take(body), blocks)?;
+        }
+        for expr in expressions_mut(&mut stmt) {
+            if let Some(name) = block_used(expr, blocks) {
+                return Err(format!("`{}` is a block; run it as a statement, `{};`", name, name));
+            }
+        }
+        spliced.push(stmt);
+    }
+    Ok(spliced)
+}
+
+/// `body` in a scope of its own. It's an `if (true)` since that's the one
+/// block every target, the interpreter and scripts already scope.
+fn scoped(body: Vec<Statement>, span: Span) -> Statement {
+    Statement::If { condition: Expression::Literal(Literal::Bool(true)), then: body, else_: None, span }
+}
+
+/// A block parameter used as a value somewhere in `expr`
+fn block_used(expr: &mut Expression, blocks: &HashMap<String, Block>) -> Option<String> {
+    match expr {
+        Expression::Identifier(name) if blocks.contains_key(name) => Some(name.clone()),
+        Expression::Block(body) | Expression::AsyncBlock(body) => {
+            let mut found = None;
+            for stmt in body.iter_mut() {
+                for expr in expressions_mut(stmt) {
+                    found = found.or_else(|| block_used(expr, blocks));
+                }
+            }
+            found
+        }
+        _ => children_mut(expr).into_iter().find_map(|child| block_used(child, blocks)),
+    }
+}
+
+/// Names a macro body binds itself: `let`s, loop variables, match bindings
+/// and lambda parameters
+fn bound_in(body: &mut [Statement], names: &mut BTreeSet<String>) {
+    for stmt in body {
+        match stmt {
+            Statement::Let { name, .. } => {
+                names.insert(name.clone());
+            }
+            Statement::For { var, .. } => {
+                names.insert(var.clone());
+            }
+            Statement::Match { arms, .. } => {
+                for arm in arms.iter() {
+                    bound_by(&arm.pattern, names);
+                }
+            }
+            _ => {}
+        }
+        for inner in bodies_mut(stmt) {
+            bound_in(inner, names);
+        }
+        for expr in expressions_mut(stmt) {
+            bound_in_expression(expr, names);
+        }
+    }
+}
+
+fn bound_in_expression(expr: &mut Expression, names: &mut BTreeSet<String>) {
+    match expr {
+        Expression::Lambda { params, body } => {
+            names.extend(params.iter().map(|p| p.name.clone()));
+            bound_in_expression(body, names);
+        }
+        Expression::Block(body) | Expression::AsyncBlock(body) => bound_in(body, names),
+        _ => {
+            for child in children_mut(expr) {
+                bound_in_expression(child, names);
+            }
+        }
+    }
+}
+
+fn bound_by(pattern: &Pattern, names: &mut BTreeSet<String>) {
+    match pattern {
+        Pattern::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        Pattern::Tuple(patterns) => patterns.iter().for_each(|p| bound_by(p, names)),
+        Pattern::Struct { fields, .. } => fields.iter().for_each(|(_, p)| bound_by(p, names)),
+        Pattern::Literal(_) | Pattern::Wildcard => {}
+    }
+}
+
+fn rename_body(body: &mut [Statement], renames: &HashMap<String, String>) {
+    for stmt in body {
+        match stmt {
+            Statement::Let { name, .. } => rename(name, renames),
+            Statement::For { var, .. } => rename(var, renames),
+            Statement::Match { arms, .. } => {
+                for arm in arms.iter_mut() {
+                    rename_pattern(&mut arm.pattern, renames);
+                }
+            }
+            _ => {}
+        }
+        for inner in bodies_mut(stmt) {
+            rename_body(inner, renames);
+        }
+        for expr in expressions_mut(stmt) {
+            rename_expression(expr, renames);
+        }
+    }
+}
+
+/// Rewrite the span of every statement in `body`, nested ones included
+pub(crate) fn respan(body: &mut [Statement], f: &impl Fn(Span) -> Span) {
+    for stmt in body {
+        if let Some(span) = stmt.span_mut() {
+            *span = f(*span);
+        }
+        for inner in bodies_mut(stmt) {
+            respan(inner, f);
+        }
+        for expr in expressions_mut(stmt) {
+            respan_expression(expr, f);
+        }
+    }
+}
+
+fn respan_expression(expr: &mut Expression, f: &impl Fn(Span) -> Span) {
+    match expr {
+        Expression::Block(body) | Expression::AsyncBlock(body) => respan(body, f),
+        _ => {
+            for child in children_mut(expr) {
+                respan_expression(child, f);
+            }
+        }
+    }
+}
+
+fn rename_expression(expr: &mut Expression, renames: &HashMap<String, String>) {
+    match expr {
+        Expression::Identifier(name) => rename(name, renames),
+        Expression::Lambda { params, body } => {
+            for param in params.iter_mut() {
+                rename(&mut param.name, renames);
+            }
+            rename_expression(body, renames);
+        }
+        Expression::Block(body) | Expression::AsyncBlock(body) => rename_body(body, renames),
+        _ => {
+            for child in children_mut(expr) {
+                rename_expression(child, renames);
+            }
+        }
+    }
+}
+
+fn rename_pattern(pattern: &mut Pattern, renames: &HashMap<String, String>) {
+    match pattern {
+        Pattern::Identifier(name) => rename(name, renames),
+        Pattern::Tuple(patterns) => patterns.iter_mut().for_each(|p| rename_pattern(p, renames)),
+        Pattern::Struct { fields, .. } => fields.iter_mut().for_each(|(_, p)| rename_pattern(p, renames)),
+        Pattern::Literal(_) | Pattern::Wildcard => {}
+    }
+}
+
+fn rename(name: &mut String, renames: &HashMap<String, String>) {
+    if let Some(renamed) = renames.get(name.as_str()) {
+        *name = renamed.clone();
+    }
+}
+
+/// Make the plain `let`s in `body` that are assigned again later `let mut`.
+/// Reassigning a `let` is allowed; this keeps targets whose bindings are
+/// immutable by default (`const`, `val`) from rejecting it.
+fn mark_reassigned(body: &mut [Statement]) {
+    for i in 0..body.len() {
+        let (head, rest) = body.split_at_mut(i + 1);
+        if let Statement::Let { name, mutable, .. } = &mut head[i] {
+            if !*mutable && assigns(rest, name) {
+                *mutable = true;
+            }
+        }
+    }
+}
+
+/// Does anything in `body`, nested statements and blocks included, assign `name`?
+fn assigns(body: &mut [Statement], name: &str) -> bool {
+    body.iter_mut().any(|stmt| {
+        matches!(stmt, Statement::Assign { target: Expression::Identifier(target), .. } if target == name)
+            || bodies_mut(stmt).into_iter().any(|body| assigns(body, name))
+            || expressions_mut(stmt).into_iter().any(|expr| expression_assigns(expr, name))
+    })
+}
+
+fn expression_assigns(expr: &mut Expression, name: &str) -> bool {
+    match expr {
+        Expression::Block(body) | Expression::AsyncBlock(body) => assigns(body, name),
+        _ => children_mut(expr).into_iter().any(|child| expression_assigns(child, name)),
+    }
+}
+
+/// The statement lists directly inside `stmt`
+fn bodies_mut(stmt: &mut Statement) -> Vec<&mut Vec<Statement>> {
+    match stmt {
+        Statement::If { then, else_, .. } => std::iter::once(then).chain(else_.as_mut()).collect(),
+        Statement::For { body, .. } | Statement::While { body, .. } => vec![body],
+        Statement::Match { arms, .. } => arms.iter_mut().map(|arm| &mut arm.body).collect(),
+        Statement::Network(NetworkStatement::Receive { body, .. }) => vec![body],
+        _ => Vec::new(),
+    }
+}
+
+/// The expressions directly in `stmt`, outside its statement lists
+fn expressions_mut(stmt: &mut Statement) -> Vec<&mut Expression> {
+    match stmt {
+        Statement::Let { value, .. } => vec![value],
+        Statement::Assign { target, value, .. } => vec![target, value],
+        Statement::If { condition, .. } | Statement::While { condition, .. } => vec![condition],
+        Statement::For { iter, .. } => vec![iter],
+        Statement::Match { expr, arms, .. } => std::iter::once(expr)
+            .chain(arms.iter_mut().filter_map(|arm| arm.guard.as_mut()))
+            .collect(),
+        Statement::Return(expr, _) => expr.iter_mut().collect(),
+        Statement::Expression(expr, _) => vec![expr],
+        Statement::Await { expr } => vec![expr.as_mut()],
+        Statement::Animate(animate) => {
+            let mut exprs = vec![&mut animate.target];
+            for keyframe in animate.keyframes.iter_mut() {
+                exprs.push(&mut keyframe.time);
+                exprs.push(&mut keyframe.value);
+                exprs.extend(keyframe.ease_in.as_mut());
+                exprs.extend(keyframe.ease_out.as_mut());
+            }
+            exprs.extend(animate.duration.as_mut());
+            exprs.extend(animate.ease.as_mut());
+            if let Some(spring) = animate.spring.as_mut() {
+                exprs.extend(spring.stiffness.as_mut());
+                exprs.extend(spring.damping.as_mut());
+                exprs.extend(spring.mass.as_mut());
+            }
+            exprs
+        }
+        Statement::Timeline { entries, .. } => {
+            let mut exprs = Vec::new();
+            for (time, targets) in entries.iter_mut() {
+                exprs.push(time);
+                for (target, properties) in targets.iter_mut() {
+                    exprs.push(target);
+                    exprs.extend(properties.iter_mut().map(|(_, value)| value));
+                }
+            }
+            exprs
+        }
+        Statement::Network(network) => match network {
+            NetworkStatement::Sync { value, .. } => vec![value],
+            NetworkStatement::Send { args, .. } => args.iter_mut().collect(),
+            NetworkStatement::Connect { address } => vec![address],
+            NetworkStatement::Receive { .. } | NetworkStatement::Disconnect => Vec::new(),
+        },
+        Statement::Break | Statement::Continue | Statement::Debugger(_) => Vec::new(),
+    }
+}
+
+/// The expressions directly inside `expr`, except the statements of blocks
+fn children_mut(expr: &mut Expression) -> Vec<&mut Expression> {
+    match expr {
+        Expression::Binary { left, right, .. } => vec![left.as_mut(), right.as_mut()],
+        Expression::Unary { expr, .. } | Expression::Await(expr) => vec![expr.as_mut()],
+        Expression::Call { func, args } => std::iter::once(func.as_mut()).chain(args.iter_mut()).collect(),
+        Expression::Member { object, .. } => vec![object.as_mut()],
+        Expression::Index { object, index } => vec![object.as_mut(), index.as_mut()],
+        Expression::Tuple(items) | Expression::Array(items) => items.iter_mut().collect(),
+        Expression::If { condition, then, else_ } => vec![condition.as_mut(), then.as_mut(), else_.as_mut()],
+        Expression::Lambda { body, .. } => vec![body.as_mut()],
+        Expression::MacroCall { args, .. } => args.iter_mut().collect(),
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::Block(_) | Expression::AsyncBlock(_) => Vec::new(),
+    }
+}
+
+/// An error from expanding code a call produced, moved to the call
+fn at(error: GrumpError, span: Span, name: &str) -> GrumpError {
+    match error {
+        GrumpError::Macro { message, .. } => {
+            let context = format!("In `{}!`: ", name);
+            let message = if message.starts_with(&context) { message } else { context + &message };
+            GrumpError::Macro { line: span.line, column: span.column, message }
+        }
+        other => other,
+    }
+}
+
+fn error(span: Span, message: String) -> GrumpError {
+    GrumpError::Macro { line: span.line, column: span.column, message }
+}
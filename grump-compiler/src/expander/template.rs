@@ -0,0 +1,130 @@
+//! Template macros
+//!
+//! A macro whose body is target code, one template per target, is an
+//! escape hatch for platform APIs G-Rump doesn't model:
+//!
+//! ```text
+//! macro vibrate(ms: int) {
+//!     @ios "AudioServicesPlaySystemSound(kSystemSoundID_Vibrate)"
+//!     @android "vibrator.vibrate(($ms).toLong())"
+//!     @web "navigator.vibrate($ms)"
+//! }
+//! ```
+//!
+//! Expansion leaves calls to them in place; the analyzer checks their
+//! arguments and each code generator replaces a call with its target's
+//! template:
+//!
+//! - `$name` is the code for parameter `name`'s argument; a variadic
+//!   parameter's arguments are joined with `, `, and `$$` is a `$`
+//! - `\"` in a template is a `"`; everything else is copied as written
+//! - The TypeScript target uses `@web` unless there's a `@typescript`
+//! - A call on a target without a template is an error
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::codegen::Target;
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::extensions::{MacroBody, MacroDeclaration};
+use crate::parser::Span;
+
+/// Check a template macro's declaration: templates for distinct, known
+/// targets, no block parameters, and every `$name` a parameter
+pub fn validate(decl: &MacroDeclaration, templates: &[(String, String)]) -> GrumpResult<()> {
+    let error = |message: String| GrumpError::Macro { line: decl.span.line, column: decl.span.column, message };
+    if let Some(block) = decl.params.iter().find(|p| p.is_block) {
+        return Err(error(format!("Macro `{}` is a template, so its parameter `{}` can't be a block", decl.name, block.name)));
+    }
+    let mut targets = BTreeSet::new();
+    for (target, code) in templates {
+        let Ok(parsed) = Target::from_name(target) else {
+            let known: Vec<&str> = Target::ALL.iter().map(|t| t.name()).collect();
+            return Err(error(format!(
+                "Macro `{}` has a template for `@{}`, which isn't a target; use one of {}",
+                decl.name, target, known.join(", ")
+            )));
+        };
+        if !targets.insert(parsed.name()) {
+            return Err(error(format!("Macro `{}` has two `@{}` templates", decl.name, parsed.name())));
+        }
+        let mut unknown = None;
+        fill(code, |name| {
+            if !decl.params.iter().any(|p| p.name == name) {
+                unknown.get_or_insert_with(|| name.to_string());
+            }
+            None
+        });
+        if let Some(name) = unknown {
+            return Err(error(format!("Macro `{}`'s `@{}` template uses `${}`, which isn't a parameter", decl.name, target, name)));
+        }
+    }
+    Ok(())
+}
+
+/// The code for a call at `span`, given the code for its arguments; the
+/// analyzer has checked their number
+pub fn instantiate(decl: &MacroDeclaration, target: Target, args: &[String], span: Span) -> GrumpResult<String> {
+    let error = |message: String| GrumpError::Macro { line: span.line, column: span.column, message };
+    let MacroBody::Template(templates) = &decl.body else {
+        return Err(error(format!("`{}!` isn't a template macro, so it should have been expanded", decl.name)));
+    };
+    let Some(template) = template_for(templates, target) else {
+        return Err(error(format!("`{}!` has no `@{}` template", decl.name, target.name())));
+    };
+
+    let fixed = decl.params.iter().filter(|p| !p.is_variadic).count();
+    let extra = args.len().saturating_sub(fixed);
+    let mut args = args.iter();
+    let mut values = HashMap::new();
+    for param in &decl.params {
+        let value = if param.is_variadic {
+            args.by_ref().take(extra).cloned().collect::<Vec<_>>().join(", ")
+        } else {
+            args.next().cloned().unwrap_or_default()
+        };
+        values.insert(param.name.as_str(), value);
+    }
+    Ok(fill(template, |name| values.get(name).cloned()))
+}
+
+fn template_for(templates: &[(String, String)], target: Target) -> Option<&str> {
+    let find = |target: Target| templates.iter()
+        .find(|(name, _)| Target::from_name(name).ok() == Some(target))
+        .map(|(_, code)| code.as_str());
+    find(target).or_else(|| match target {
+        Target::TypeScript => find(Target::Web),
+        _ => None,
+    })
+}
+
+/// `template` with each `$name` replaced by `value(name)`; a name without a
+/// value, or a `$` not followed by one (`$0`, `${`), stays as written
+fn fill(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(at) = rest.find('$') {
+        filled.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            filled.push('$');
+            rest = after;
+            continue;
+        }
+        let len = if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len())
+        } else {
+            0
+        };
+        let name = &rest[..len];
+        match if name.is_empty() { None } else { value(name) } {
+            Some(code) => filled.push_str(&code),
+            None => {
+                filled.push('$');
+                filled.push_str(name);
+            }
+        }
+        rest = &rest[len..];
+    }
+    filled.push_str(rest);
+    filled
+}
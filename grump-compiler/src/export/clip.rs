@@ -0,0 +1,466 @@
+//! Exportable animation clips
+//!
+//! Exporters don't work on the AST directly: `animation` declarations,
+//! `animate` statements and `timeline`s are first lowered to `Clip`s, with
+//! constant keyframe values, times in seconds and per-segment cubic-bezier
+//! easing.
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::lexer::Unit;
+use crate::parser::{
+    AnimateStatement, AnimationDeclaration, Expression, Item, Keyframe, Literal, LoopMode, Program,
+    SceneDeclaration, Statement, UnaryOp,
+};
+
+/// A parsed `timeline` entry: its time, and each target's property values
+type TimelineEntry = (Expression, Vec<(Expression, Vec<(String, Expression)>)>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    /// Seconds
+    pub duration: f64,
+    pub loop_mode: ClipLoop,
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipLoop {
+    Once,
+    Loop,
+    PingPong,
+    Reverse,
+    /// Loop between two times (seconds)
+    Section { start: f64, end: f64 },
+}
+
+/// One animated property of one target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    /// Target path, outermost first (`hero.eyes` -> `["hero", "eyes"]`)
+    pub target: Vec<String>,
+    pub property: String,
+    pub keyframes: Vec<ClipKeyframe>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipKeyframe {
+    /// Seconds
+    pub time: f64,
+    pub value: Value,
+    /// Curve of the segment that starts at this keyframe
+    pub easing: Easing,
+}
+
+/// Constant keyframe value: one channel per component (`x`, `y`, ...)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub channels: Vec<f64>,
+    /// Unit written in source, if any
+    pub unit: Option<Unit>,
+}
+
+/// Cubic-bezier timing curve, as in CSS `cubic-bezier(x1, y1, x2, y2)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Easing {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+impl Easing {
+    pub const LINEAR: Easing = Easing { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 };
+
+    /// Named curve (`ease_in`, `smooth`, ...) or `cubic_bezier(x1, y1, x2, y2)`
+    pub fn from_expression(expr: &Expression) -> Option<Easing> {
+        match expr {
+            Expression::Identifier(name) | Expression::Literal(Literal::String(name)) => Self::named(name),
+            Expression::Call { func, args } => match (func.as_ref(), args.as_slice()) {
+                (Expression::Identifier(name), [x1, y1, x2, y2]) if name == "cubic_bezier" => Some(Easing {
+                    x1: number(x1)?,
+                    y1: number(y1)?,
+                    x2: number(x2)?,
+                    y2: number(y2)?,
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Easing> {
+        let (x1, y1, x2, y2) = match name {
+            "linear" => (0.0, 0.0, 1.0, 1.0),
+            "ease" => (0.25, 0.1, 0.25, 1.0),
+            "ease_in" | "in" => (0.42, 0.0, 1.0, 1.0),
+            "ease_out" | "out" => (0.0, 0.0, 0.58, 1.0),
+            "ease_in_out" | "in_out" | "smooth" => (0.42, 0.0, 0.58, 1.0),
+            "snappy" => (0.2, 0.0, 0.0, 1.0),
+            // Overshoots past the target and settles back
+            "bounce" | "back" => (0.68, -0.55, 0.265, 1.55),
+            _ => return None,
+        };
+        Some(Easing { x1, y1, x2, y2 })
+    }
+
+    /// The same curve traversed backwards in time
+    pub fn reversed(&self) -> Easing {
+        Easing { x1: 1.0 - self.x2, y1: 1.0 - self.y2, x2: 1.0 - self.x1, y2: 1.0 - self.y1 }
+    }
+
+    pub fn is_linear(&self) -> bool {
+        self.x1 == self.y1 && self.x2 == self.y2
+    }
+
+    /// Eased progress for linear progress `t` in `[0, 1]`
+    pub fn apply(&self, t: f64) -> f64 {
+        if self.is_linear() {
+            return t;
+        }
+        let bezier = |a: f64, b: f64, s: f64| {
+            let u = 1.0 - s;
+            3.0 * u * u * s * a + 3.0 * u * s * s * b + s * s * s
+        };
+        // Solve x(s) = t by bisection; x is monotonic for x1, x2 in [0, 1]
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..40 {
+            let mid = (low + high) / 2.0;
+            if bezier(self.x1, self.x2, mid) < t {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        bezier(self.y1, self.y2, (low + high) / 2.0)
+    }
+}
+
+impl Clip {
+    /// Every clip in the program: `animation` items, then `animate` statements
+    /// and `timeline`s in declaration order
+    pub fn collect(program: &Program) -> GrumpResult<Vec<Clip>> {
+        let mut clips = Vec::new();
+        for item in &program.items {
+            collect_item(item, &mut clips)?;
+        }
+        // `animate` statements are named after their target; keep names unique
+        let mut seen: Vec<String> = Vec::new();
+        for clip in &mut clips {
+            let base = clip.name.clone();
+            let mut n = 1;
+            while seen.contains(&clip.name) {
+                n += 1;
+                clip.name = format!("{}_{}", base, n);
+            }
+            seen.push(clip.name.clone());
+        }
+        Ok(clips)
+    }
+
+    /// Bare declarations have no target, so they animate `value` on a target named after the clip
+    pub fn from_declaration(anim: &AnimationDeclaration) -> GrumpResult<Clip> {
+        let keyframes = lower_keyframes(&anim.name, &anim.keyframes, None)?;
+        let duration = match &anim.duration {
+            Some(duration) => seconds(&anim.name, duration)?,
+            None => last_time(&keyframes),
+        };
+        let loop_mode = match &anim.loop_mode {
+            Some(LoopMode::Loop) => ClipLoop::Loop,
+            Some(LoopMode::PingPong) => ClipLoop::PingPong,
+            Some(LoopMode::Reverse) => ClipLoop::Reverse,
+            Some(LoopMode::Section { start, end }) => ClipLoop::Section {
+                start: seconds(&anim.name, start)?,
+                end: seconds(&anim.name, end)?,
+            },
+            Some(LoopMode::None) | None => ClipLoop::Once,
+        };
+        Ok(Clip {
+            name: anim.name.clone(),
+            duration,
+            loop_mode,
+            tracks: vec![Track { target: vec![anim.name.clone()], property: "value".to_string(), keyframes }],
+        })
+    }
+
+    /// `animate hero.position { ... }` animates `position` on `hero`
+    pub fn from_animate(animate: &AnimateStatement) -> GrumpResult<Clip> {
+        let mut path = target_path(&animate.target).ok_or_else(|| GrumpError::Animation {
+            message: "Only `name` or `object.property` targets can be exported".to_string(),
+        })?;
+        let name = path.join("_");
+        let property = if path.len() > 1 { path.pop().unwrap_or_default() } else { "value".to_string() };
+        let default_ease = animate.ease.as_ref().and_then(Easing::from_expression);
+        let keyframes = lower_keyframes(&name, &animate.keyframes, default_ease)?;
+        let duration = match &animate.duration {
+            Some(duration) => seconds(&name, duration)?,
+            None => last_time(&keyframes),
+        };
+        Ok(Clip {
+            name,
+            duration,
+            loop_mode: ClipLoop::Once,
+            tracks: vec![Track { target: path, property, keyframes }],
+        })
+    }
+
+    /// `timeline intro { 0s { hero { x: 0 } } 1s { hero { x: 100 } } }`:
+    /// one track per target property, keyed at each entry's time
+    pub fn from_timeline(name: &str, entries: &[TimelineEntry]) -> GrumpResult<Clip> {
+        let mut tracks: Vec<Track> = Vec::new();
+        for (time, targets) in entries {
+            let time = seconds(name, time)?;
+            for (target, properties) in targets {
+                let path = target_path(target).ok_or_else(|| GrumpError::Animation {
+                    message: format!("Timeline '{}' targets must be names or `object.property` paths", name),
+                })?;
+                for (property, value) in properties {
+                    let keyframe = ClipKeyframe { time, value: constant(name, value)?, easing: Easing::LINEAR };
+                    match tracks.iter_mut().find(|t| t.target == path && &t.property == property) {
+                        Some(track) => track.keyframes.push(keyframe),
+                        None => tracks.push(Track { target: path.clone(), property: property.clone(), keyframes: vec![keyframe] }),
+                    }
+                }
+            }
+        }
+        for track in &mut tracks {
+            track.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        let duration = tracks.iter().map(|t| last_time(&t.keyframes)).fold(0.0, f64::max);
+        Ok(Clip { name: name.to_string(), duration, loop_mode: ClipLoop::Once, tracks })
+    }
+
+    /// What a scene plays when it starts: its `animate` blocks and timelines,
+    /// all running together from time zero
+    pub fn from_scene(scene: &SceneDeclaration) -> GrumpResult<Clip> {
+        let mut clips = Vec::new();
+        collect_statements(&scene.body, &mut clips)?;
+        let duration = clips.iter().map(|c| c.duration).fold(0.0, f64::max);
+        let tracks = clips.into_iter().flat_map(|c| c.tracks).collect();
+        Ok(Clip { name: scene.name.clone(), duration, loop_mode: ClipLoop::Once, tracks })
+    }
+
+    /// Bake `Reverse` and `PingPong` into the keyframes, for formats whose
+    /// players only know "play once" and "loop"
+    pub fn unrolled(&self) -> Clip {
+        let (tracks, duration, loop_mode) = match self.loop_mode {
+            ClipLoop::Reverse => (
+                self.tracks.iter().map(|t| t.reversed(self.duration)).collect(),
+                self.duration,
+                ClipLoop::Once,
+            ),
+            ClipLoop::PingPong => (
+                self.tracks.iter().map(|t| t.ping_pong(self.duration)).collect(),
+                self.duration * 2.0,
+                ClipLoop::Loop,
+            ),
+            other => (self.tracks.clone(), self.duration, other),
+        };
+        Clip { name: self.name.clone(), duration, loop_mode, tracks }
+    }
+
+    /// Targets in first-use order, each listed after its parent path
+    pub fn targets(&self) -> Vec<Vec<String>> {
+        let mut targets: Vec<Vec<String>> = Vec::new();
+        for track in &self.tracks {
+            for depth in 1..=track.target.len() {
+                let path = track.target[..depth].to_vec();
+                if !targets.contains(&path) {
+                    targets.push(path);
+                }
+            }
+        }
+        targets
+    }
+}
+
+impl Track {
+    /// Keyframes mirrored around the middle of `duration`
+    pub fn reversed(&self, duration: f64) -> Track {
+        let mut keyframes: Vec<ClipKeyframe> = self.keyframes.iter().rev()
+            .map(|k| ClipKeyframe { time: duration - k.time, value: k.value.clone(), easing: Easing::LINEAR })
+            .collect();
+        // Segment i of the reversed track is segment n - 2 - i of the original, played backwards
+        let n = self.keyframes.len();
+        for (i, keyframe) in keyframes.iter_mut().enumerate().take(n.saturating_sub(1)) {
+            keyframe.easing = self.keyframes[n - 2 - i].easing.reversed();
+        }
+        Track { target: self.target.clone(), property: self.property.clone(), keyframes }
+    }
+
+    /// Channels at `time`, eased between the surrounding keyframes and held
+    /// before the first and after the last
+    pub fn sample(&self, time: f64) -> Vec<f64> {
+        let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec::new(),
+        };
+        if time <= first.time {
+            return first.value.channels.clone();
+        }
+        if time >= last.time {
+            return last.value.channels.clone();
+        }
+        let segment = self.keyframes.windows(2).find(|pair| time < pair[1].time).unwrap_or(&self.keyframes[..2]);
+        let (from, to) = (&segment[0], &segment[1]);
+        let span = to.time - from.time;
+        let t = if span > 0.0 { from.easing.apply((time - from.time) / span) } else { 1.0 };
+        from.value.channels.iter().zip(&to.value.channels).map(|(a, b)| a + (b - a) * t).collect()
+    }
+
+    /// The part between `start` and `end`, retimed to begin at zero; segments
+    /// cut at either end are sampled and keep their curve
+    pub fn section(&self, start: f64, end: f64) -> Track {
+        let unit = self.keyframes.first().and_then(|k| k.value.unit.clone());
+        let at = |time: f64, easing: Easing| ClipKeyframe {
+            time: time - start,
+            value: Value { channels: self.sample(time), unit: unit.clone() },
+            easing,
+        };
+        let easing_at = |time: f64| {
+            self.keyframes.iter().rev().find(|k| k.time <= time).map_or(Easing::LINEAR, |k| k.easing)
+        };
+        let mut keyframes = vec![at(start, easing_at(start))];
+        for keyframe in self.keyframes.iter().filter(|k| k.time > start && k.time < end) {
+            keyframes.push(ClipKeyframe { time: keyframe.time - start, ..keyframe.clone() });
+        }
+        keyframes.push(at(end, Easing::LINEAR));
+        Track { target: self.target.clone(), property: self.property.clone(), keyframes }
+    }
+
+    /// Forward, then backward: twice as long, ending where it started
+    pub fn ping_pong(&self, duration: f64) -> Track {
+        let mut keyframes = self.keyframes.clone();
+        let back = self.reversed(duration);
+        if let (Some(last), Some(first_back)) = (keyframes.last_mut(), back.keyframes.first()) {
+            // The turnaround keyframe starts the way back
+            last.easing = first_back.easing;
+        }
+        keyframes.extend(back.keyframes.into_iter().skip(1).map(|k| ClipKeyframe { time: k.time + duration, ..k }));
+        Track { target: self.target.clone(), property: self.property.clone(), keyframes }
+    }
+}
+
+fn collect_item(item: &Item, clips: &mut Vec<Clip>) -> GrumpResult<()> {
+    match item {
+        Item::App(app) => {
+            for nested in &app.body {
+                collect_item(nested, clips)?;
+            }
+        }
+        Item::Module(module) => {
+            for nested in &module.items {
+                collect_item(nested, clips)?;
+            }
+        }
+        Item::Animation(anim) => clips.push(Clip::from_declaration(anim)?),
+        Item::Scene(scene) => collect_statements(&scene.body, clips)?,
+        Item::Entity(entity) => collect_statements(&entity.body, clips)?,
+        Item::System(system) => collect_statements(&system.body, clips)?,
+        Item::Function(func) => collect_statements(&func.body, clips)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn collect_statements(statements: &[Statement], clips: &mut Vec<Clip>) -> GrumpResult<()> {
+    for stmt in statements {
+        match stmt {
+            Statement::Animate(animate) => clips.push(Clip::from_animate(animate)?),
+            Statement::Timeline { name, entries } => clips.push(Clip::from_timeline(name, entries)?),
+            Statement::If { then, else_, .. } => {
+                collect_statements(then, clips)?;
+                if let Some(else_body) = else_ {
+                    collect_statements(else_body, clips)?;
+                }
+            }
+            Statement::For { body, .. } | Statement::While { body, .. } => collect_statements(body, clips)?,
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    collect_statements(&arm.body, clips)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Keyframes sorted by time; each segment eases out of its start and into its end
+fn lower_keyframes(clip: &str, keyframes: &[Keyframe], default_ease: Option<Easing>) -> GrumpResult<Vec<ClipKeyframe>> {
+    let mut lowered: Vec<(f64, Value, &Keyframe)> = Vec::new();
+    for kf in keyframes {
+        lowered.push((seconds(clip, &kf.time)?, constant(clip, &kf.value)?, kf));
+    }
+    lowered.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let curve = |expr: &Option<Expression>| expr.as_ref().and_then(Easing::from_expression);
+    let mut result = Vec::new();
+    for (i, (time, value, kf)) in lowered.iter().enumerate() {
+        let fallback = default_ease.unwrap_or(Easing::LINEAR);
+        let out = curve(&kf.ease_out).unwrap_or(fallback);
+        let into = lowered.get(i + 1).and_then(|(_, _, next)| curve(&next.ease_in)).unwrap_or(fallback);
+        result.push(ClipKeyframe {
+            time: *time,
+            value: value.clone(),
+            easing: Easing { x1: out.x1, y1: out.y1, x2: into.x2, y2: into.y2 },
+        });
+    }
+    Ok(result)
+}
+
+fn last_time(keyframes: &[ClipKeyframe]) -> f64 {
+    keyframes.iter().map(|k| k.time).fold(0.0, f64::max)
+}
+
+fn target_path(expr: &Expression) -> Option<Vec<String>> {
+    match expr {
+        Expression::Identifier(name) => Some(vec![name.clone()]),
+        Expression::Member { object, member } => {
+            let mut path = target_path(object)?;
+            path.push(member.clone());
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
+fn number(expr: &Expression) -> Option<f64> {
+    match expr {
+        Expression::Literal(Literal::Integer(n)) => Some(*n as f64),
+        Expression::Literal(Literal::Float(f)) => Some(*f),
+        Expression::Literal(Literal::Duration { value, .. }) | Expression::Literal(Literal::Angle { value, .. }) => Some(*value),
+        Expression::Unary { op: UnaryOp::Neg, expr } => number(expr).map(|n| -n),
+        _ => None,
+    }
+}
+
+/// Keyframe times in seconds
+fn seconds(clip: &str, expr: &Expression) -> GrumpResult<f64> {
+    match expr {
+        Expression::Literal(Literal::Duration { value, unit }) if unit == "ms" => Ok(value / 1000.0),
+        other => number(other).ok_or_else(|| GrumpError::Animation {
+            message: format!("Animation '{}' needs constant keyframe times to be exported", clip),
+        }),
+    }
+}
+
+fn constant(clip: &str, expr: &Expression) -> GrumpResult<Value> {
+    let unit = match expr {
+        Expression::Literal(Literal::Duration { unit, .. }) | Expression::Literal(Literal::Angle { unit, .. }) => Unit::from_suffix(unit),
+        _ => None,
+    };
+    let channels = match expr {
+        Expression::Literal(Literal::Vec2 { x, y }) => Some(vec![*x, *y]),
+        Expression::Literal(Literal::Vec3 { x, y, z }) => Some(vec![*x, *y, *z]),
+        Expression::Literal(Literal::Color { r, g, b, a }) => Some(vec![*r as f64, *g as f64, *b as f64, *a as f64]),
+        Expression::Literal(Literal::Bool(b)) => Some(vec![if *b { 1.0 } else { 0.0 }]),
+        Expression::Tuple(values) | Expression::Array(values) => values.iter().map(number).collect(),
+        other => number(other).map(|n| vec![n]),
+    };
+    channels
+        .map(|channels| Value { channels, unit })
+        .ok_or_else(|| GrumpError::Animation {
+            message: format!("Animation '{}' needs constant keyframe values to be exported", clip),
+        })
+}
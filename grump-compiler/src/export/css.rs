@@ -0,0 +1,282 @@
+//! CSS `@keyframes` export
+//!
+//! Every track becomes its own `@keyframes` rule, so tracks with different
+//! curves never fight over one keyframe's `animation-timing-function`. The
+//! tracks of one target are combined into a single `animation` list on a
+//! class named after the clip and target (`.wave__hero-arm`; a bare
+//! `animation` declaration is just `.wave`).
+//!
+//! Transforms use the individual `translate`, `rotate` and `scale`
+//! properties, `x`/`y` map to `left`/`top`, and anything CSS has no property
+//! for becomes a registered custom property (`--wobble`) the page can read
+//! with `var()`. Values keep the unit they were written with; otherwise each
+//! property gets its natural one (px for lengths, deg for angles).
+
+use crate::codegen::{pascal_case, snake_case};
+use crate::error::{GrumpError, GrumpResult};
+use crate::lexer::Unit;
+use super::clip::{Clip, ClipLoop, Easing, Track};
+
+/// How a track is written in CSS
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CssProperty {
+    /// CSS name (`translate`, `background-color`, `--wobble`)
+    pub name: String,
+    kind: Kind,
+    /// `@property` syntax for custom properties, which must be registered to animate
+    pub syntax: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Translate,
+    Length,
+    Angle,
+    Scale,
+    Number,
+    Color,
+}
+
+impl CssProperty {
+    pub(super) fn for_track(track: &Track) -> GrumpResult<CssProperty> {
+        let first = track.keyframes.first();
+        let channels = first.map_or(1, |k| k.value.channels.len());
+        let unit = first.and_then(|k| k.value.unit.clone());
+        let property = track.property.as_str();
+        let (name, kind) = match (property, channels) {
+            ("position" | "pos" | "offset" | "translate", 1 | 2) | ("value", 2) => ("translate", Kind::Translate),
+            ("x" | "left", 1) => ("left", Kind::Length),
+            ("y" | "top", 1) => ("top", Kind::Length),
+            ("width" | "height", 1) => (property, Kind::Length),
+            ("rotation" | "rotate" | "angle", 1) => ("rotate", Kind::Angle),
+            ("scale", 1 | 2) => ("scale", Kind::Scale),
+            ("opacity" | "alpha", 1) => ("opacity", Kind::Number),
+            ("color" | "tint", 3 | 4) => ("color", Kind::Color),
+            ("background" | "background_color", 3 | 4) => ("background-color", Kind::Color),
+            ("fill", 3 | 4) => ("fill", Kind::Color),
+            (_, 1) => {
+                let kind = match unit {
+                    Some(Unit::Pixels | Unit::Points) => Kind::Length,
+                    Some(Unit::Degrees | Unit::Radians) => Kind::Angle,
+                    _ => Kind::Number,
+                };
+                return Ok(CssProperty::custom(track, kind));
+            }
+            (_, 3 | 4) => return Ok(CssProperty::custom(track, Kind::Color)),
+            (_, n) => {
+                return Err(GrumpError::Animation {
+                    message: format!("Property '{}' has {} channels and no CSS equivalent", track.property, n),
+                });
+            }
+        };
+        Ok(CssProperty { name: name.to_string(), kind, syntax: None })
+    }
+
+    /// Bare `value` tracks are named after their target (`--bounce`)
+    fn custom(track: &Track, kind: Kind) -> CssProperty {
+        let base = match track.property.as_str() {
+            "value" => track.target.last().map_or("value", String::as_str),
+            other => other,
+        };
+        let syntax = match kind {
+            Kind::Length => "<length>",
+            Kind::Angle => "<angle>",
+            Kind::Color => "<color>",
+            _ => "<number>",
+        };
+        CssProperty { name: format!("--{}", kebab_case(base)), kind, syntax: Some(syntax) }
+    }
+
+    /// CSS value text for one keyframe
+    pub(super) fn value(&self, channels: &[f64], unit: Option<&Unit>) -> String {
+        let with_unit = |v: f64, fallback: Unit| {
+            // Time units make no sense on a length or angle; fall back to the natural unit
+            let unit = match unit {
+                Some(u @ (Unit::Pixels | Unit::Points)) if fallback == Unit::Pixels => u,
+                Some(u @ (Unit::Degrees | Unit::Radians)) if fallback == Unit::Degrees => u,
+                _ => &fallback,
+            };
+            format!("{}{}", number(v), unit.suffix())
+        };
+        match self.kind {
+            Kind::Translate => {
+                let y = channels.get(1).copied().unwrap_or(0.0);
+                format!("{} {}", with_unit(channels[0], Unit::Pixels), with_unit(y, Unit::Pixels))
+            }
+            Kind::Length => with_unit(channels[0], Unit::Pixels),
+            Kind::Angle => with_unit(channels[0], Unit::Degrees),
+            Kind::Scale => channels.iter().map(|v| number(*v)).collect::<Vec<_>>().join(" "),
+            Kind::Number => number(channels[0]),
+            // Channels are 0-255, alpha included
+            Kind::Color => format!(
+                "rgba({}, {}, {}, {})",
+                number(channels[0].round()),
+                number(channels[1].round()),
+                number(channels[2].round()),
+                number(channels.get(3).map_or(1.0, |a| a / 255.0)),
+            ),
+        }
+    }
+
+    /// Initial value for an `@property` registration
+    pub(super) fn initial_value(&self) -> &'static str {
+        match self.kind {
+            Kind::Length => "0px",
+            Kind::Angle => "0deg",
+            Kind::Color => "transparent",
+            _ => "0",
+        }
+    }
+}
+
+/// One `@keyframes` rule and how it is played
+#[derive(Debug, Clone)]
+pub(super) struct Run {
+    pub keyframes_name: String,
+    /// Class the run is applied to (no leading dot)
+    pub class: String,
+    pub target: Vec<String>,
+    pub property: CssProperty,
+    /// (offset in `[0, 1]`, CSS value, curve of the segment starting here)
+    pub keyframes: Vec<(f64, String, Easing)>,
+    /// Seconds
+    pub duration: f64,
+    /// Seconds
+    pub delay: f64,
+    /// `None` repeats forever
+    pub iterations: Option<u32>,
+    pub direction: &'static str,
+}
+
+/// The runs that play `clip`. Loop modes map onto `animation-direction` and
+/// `animation-iteration-count`; a looped section is the clip played once up
+/// to the section's end, followed by the section repeating forever.
+pub(super) fn runs(clip: &Clip) -> GrumpResult<Vec<Run>> {
+    let mut runs = Vec::new();
+    for track in &clip.tracks {
+        let property = CssProperty::for_track(track)?;
+        let class = class_name(clip, &track.target);
+        let keyframes_name = format!("{}-{}", class.replace("__", "-"), kebab_case(&track.property));
+        let run = |keyframes_name: String, track: &Track, duration: f64, delay: f64, iterations, direction| Run {
+            keyframes_name,
+            class: class.clone(),
+            target: track.target.clone(),
+            property: property.clone(),
+            keyframes: offsets(track, &property, duration),
+            duration,
+            delay,
+            iterations,
+            direction,
+        };
+        match clip.loop_mode {
+            ClipLoop::Once => runs.push(run(keyframes_name, track, clip.duration, 0.0, Some(1), "normal")),
+            ClipLoop::Loop => runs.push(run(keyframes_name, track, clip.duration, 0.0, None, "normal")),
+            ClipLoop::PingPong => runs.push(run(keyframes_name, track, clip.duration, 0.0, None, "alternate")),
+            ClipLoop::Reverse => runs.push(run(keyframes_name, track, clip.duration, 0.0, Some(1), "reverse")),
+            ClipLoop::Section { start, end } => {
+                let intro = track.section(0.0, end);
+                let section = track.section(start, end);
+                runs.push(run(keyframes_name.clone(), &intro, end, 0.0, Some(1), "normal"));
+                runs.push(run(format!("{}-loop", keyframes_name), &section, end - start, end, None, "normal"));
+            }
+        }
+    }
+    Ok(runs)
+}
+
+/// Stylesheet for `clip`
+pub fn export(clip: &Clip) -> GrumpResult<String> {
+    let runs = runs(clip)?;
+    let mut css = String::new();
+    css.push_str(&format!("/* Generated by G-Rump from `{}` */\n", clip.name));
+
+    let mut registered: Vec<&str> = Vec::new();
+    for run in &runs {
+        if let Some(syntax) = run.property.syntax {
+            if !registered.contains(&run.property.name.as_str()) {
+                registered.push(&run.property.name);
+                css.push_str(&format!(
+                    "\n@property {} {{\n  syntax: '{}';\n  inherits: false;\n  initial-value: {};\n}}\n",
+                    run.property.name, syntax, run.property.initial_value()
+                ));
+            }
+        }
+    }
+
+    for run in &runs {
+        css.push_str(&format!("\n@keyframes {} {{\n", run.keyframes_name));
+        let last = run.keyframes.len().saturating_sub(1);
+        for (i, (offset, value, easing)) in run.keyframes.iter().enumerate() {
+            css.push_str(&format!("  {}% {{ {}: {};", number(offset * 100.0), run.property.name, value));
+            if i < last && !easing.is_linear() {
+                css.push_str(&format!(" animation-timing-function: {};", cubic_bezier(easing)));
+            }
+            css.push_str(" }\n");
+        }
+        css.push_str("}\n");
+    }
+
+    let mut classes: Vec<&str> = Vec::new();
+    for run in &runs {
+        if !classes.contains(&run.class.as_str()) {
+            classes.push(&run.class);
+        }
+    }
+    for class in classes {
+        let animations: Vec<String> = runs.iter().filter(|r| r.class == class).map(|run| {
+            let iterations = run.iterations.map_or("infinite".to_string(), |n| n.to_string());
+            format!(
+                "{} {}s linear {}s {} {} both",
+                run.keyframes_name, number(run.duration), number(run.delay), iterations, run.direction
+            )
+        }).collect();
+        css.push_str(&format!("\n.{} {{\n  animation:\n    {};\n}}\n", class, animations.join(",\n    ")));
+    }
+    Ok(css)
+}
+
+/// `cubic-bezier(x1, y1, x2, y2)` for CSS and the Web Animations API
+pub(super) fn cubic_bezier(easing: &Easing) -> String {
+    format!(
+        "cubic-bezier({}, {}, {}, {})",
+        number(easing.x1), number(easing.y1), number(easing.x2), number(easing.y2)
+    )
+}
+
+/// `.wave__hero-arm`, or `.wave` when the clip animates itself
+pub(super) fn class_name(clip: &Clip, target: &[String]) -> String {
+    let clip_class = kebab_case(&clip.name);
+    if target.len() == 1 && target[0] == clip.name {
+        clip_class
+    } else {
+        format!("{}__{}", clip_class, kebab_case(&target.join("_")))
+    }
+}
+
+fn offsets(track: &Track, property: &CssProperty, duration: f64) -> Vec<(f64, String, Easing)> {
+    let duration = if duration > 0.0 { duration } else { 1.0 };
+    track.keyframes.iter().map(|k| {
+        let offset = (k.time / duration).clamp(0.0, 1.0);
+        (offset, property.value(&k.value.channels, k.value.unit.as_ref()), k.easing)
+    }).collect()
+}
+
+pub(super) fn kebab_case(name: &str) -> String {
+    snake_case(name).replace('_', "-")
+}
+
+pub(super) fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Shortest decimal that round-trips at four places (`0.5`, `10`, `-2.25`)
+pub(super) fn number(value: f64) -> String {
+    let rounded = (value * 10_000.0).round() / 10_000.0;
+    // Avoid printing `-0`
+    format!("{}", rounded + 0.0)
+}
@@ -0,0 +1,180 @@
+//! Animated GIF export
+//!
+//! Frames from the rasterizer are quantized to one global palette (median
+//! cut over every frame, so colors don't flicker between frames) and
+//! written by the `gif` crate. Pixels less than half opaque become the
+//! transparent index.
+//! Frame delays come from `FrameRateNormalization::gif_delay`, and the clip
+//! is re-rendered at the matching rate so the GIF runs at authored speed;
+//! identical consecutive frames are merged into one longer frame.
+//!
+//! Loop modes: looping and ping-pong clips loop forever, clips that play
+//! once (forwards or reversed) stop on their last frame, and a looped
+//! section exports just the section, looping.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::animation::CrossPlatformConsistency;
+use crate::error::{GrumpError, GrumpResult};
+use super::clip::{Clip, ClipLoop};
+use super::raster::{Canvas, Renderer};
+
+/// Palette entries available to opaque colors (one is kept for transparency)
+const MAX_COLORS: usize = 255;
+
+/// Delay (centiseconds) and source rate for a GIF authored at `fps`
+pub(super) fn timing(fps: f64) -> (u16, f64) {
+    let delay = CrossPlatformConsistency::default().frame_rate.gif_delay(fps);
+    (delay, 100.0 / delay as f64)
+}
+
+/// GIF of `clip`
+pub fn export(renderer: &Renderer, clip: &Clip, fps: f64) -> GrumpResult<Vec<u8>> {
+    let (delay, fps) = timing(fps);
+    let (frames, looping) = match clip.loop_mode {
+        ClipLoop::Section { start, end } => {
+            let section = Clip {
+                name: clip.name.clone(),
+                duration: end - start,
+                loop_mode: ClipLoop::Loop,
+                tracks: clip.tracks.iter().map(|t| t.section(start, end)).collect(),
+            };
+            (renderer.frames(&section, fps), true)
+        }
+        ClipLoop::Loop | ClipLoop::PingPong => (renderer.frames(clip, fps), true),
+        ClipLoop::Once | ClipLoop::Reverse => (renderer.frames(clip, fps), false),
+    };
+    encode(&frames, delay, looping)
+}
+
+/// GIF of `count` frames of a running scene, looping forever
+pub fn record(renderer: &Renderer, scene: &Clip, fps: f64, count: usize) -> GrumpResult<Vec<u8>> {
+    let (delay, fps) = timing(fps);
+    encode(&renderer.record(scene, fps, count), delay, true)
+}
+
+/// Encode `frames`, each shown for `delay` centiseconds
+pub fn encode(frames: &[Canvas], delay: u16, looping: bool) -> GrumpResult<Vec<u8>> {
+    let first = frames.first().ok_or_else(|| GrumpError::Animation {
+        message: "A GIF needs at least one frame".to_string(),
+    })?;
+    let (width, height) = (first.width, first.height);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(GrumpError::Animation { message: format!("{}x{} is too large for a GIF", width, height) });
+    }
+
+    let palette = Palette::median_cut(frames);
+    let colors: Vec<u8> = palette.colors.iter().flatten().copied().collect();
+    let mut encoder = gif::Encoder::new(Vec::new(), width as u16, height as u16, &colors).map_err(encoding)?;
+    if looping {
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(encoding)?;
+    }
+
+    // Merge runs of identical frames into one longer frame
+    let mut runs: Vec<(Vec<u8>, u32)> = Vec::new();
+    for frame in frames {
+        let indices = palette.map(frame);
+        match runs.last_mut() {
+            Some((last, frames_shown)) if *last == indices => *frames_shown += 1,
+            _ => runs.push((indices, 1)),
+        }
+    }
+
+    for (indices, frames_shown) in runs {
+        let frame = gif::Frame {
+            delay: (delay as u32 * frames_shown).min(u16::MAX as u32) as u16,
+            // Restore to background between frames; index 0 is transparent
+            dispose: gif::DisposalMethod::Background,
+            transparent: Some(0),
+            width: width as u16,
+            height: height as u16,
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(encoding)?;
+    }
+    encoder.into_inner().map_err(encoding)
+}
+
+fn encoding(error: impl std::fmt::Display) -> GrumpError {
+    GrumpError::Animation { message: format!("Could not encode the GIF: {}", error) }
+}
+
+/// Index 0 is transparent; opaque colors follow
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    fn median_cut(frames: &[Canvas]) -> Palette {
+        let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+        for pixel in frames.iter().flat_map(|f| &f.pixels).filter(|p| p[3] >= 128) {
+            *counts.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+        }
+        let mut boxes: Vec<Vec<([u8; 3], u32)>> = vec![counts.into_iter().collect()];
+        boxes.retain(|b| !b.is_empty());
+
+        // Split the box with the widest weighted channel range at its weighted median
+        while boxes.len() < MAX_COLORS {
+            let widest = boxes.iter().enumerate()
+                .filter(|(_, b)| b.len() > 1)
+                .map(|(i, b)| {
+                    let (channel, range) = widest_channel(b);
+                    let weight: u64 = b.iter().map(|(_, n)| *n as u64).sum();
+                    (i, channel, range as u64 * weight)
+                })
+                .max_by_key(|(_, _, score)| *score);
+            let (index, channel, _) = match widest {
+                Some(found) => found,
+                None => break,
+            };
+            let mut colors = boxes.swap_remove(index);
+            colors.sort_by_key(|(color, _)| color[channel]);
+            let half: u64 = colors.iter().map(|(_, n)| *n as u64).sum::<u64>() / 2;
+            let mut seen = 0;
+            let split = colors.iter().position(|(_, n)| {
+                seen += *n as u64;
+                seen > half
+            }).unwrap_or(0).clamp(1, colors.len() - 1);
+            let upper = colors.split_off(split);
+            boxes.push(colors);
+            boxes.push(upper);
+        }
+
+        let mut palette = Palette { colors: vec![[0, 0, 0]], lookup: HashMap::new() };
+        for colors in boxes {
+            let weight: u64 = colors.iter().map(|(_, n)| *n as u64).sum();
+            let mut average = [0u8; 3];
+            for (channel, slot) in average.iter_mut().enumerate() {
+                let total: u64 = colors.iter().map(|(c, n)| c[channel] as u64 * *n as u64).sum();
+                *slot = ((total + weight / 2) / weight.max(1)) as u8;
+            }
+            let index = palette.colors.len() as u8;
+            palette.colors.push(average);
+            for (color, _) in colors {
+                palette.lookup.insert(color, index);
+            }
+        }
+        palette
+    }
+
+    fn map(&self, frame: &Canvas) -> Vec<u8> {
+        frame.pixels.iter().map(|p| {
+            if p[3] < 128 {
+                0
+            } else {
+                self.lookup.get(&[p[0], p[1], p[2]]).copied().unwrap_or(0)
+            }
+        }).collect()
+    }
+}
+
+fn widest_channel(colors: &[([u8; 3], u32)]) -> (usize, u8) {
+    (0..3).map(|channel| {
+        let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+        let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+        (channel, max - min)
+    }).max_by_key(|(_, range)| *range).unwrap_or((0, 0))
+}
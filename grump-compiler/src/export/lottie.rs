@@ -0,0 +1,228 @@
+//! Lottie (Bodymovin) export
+//!
+//! Each clip becomes one composition. Every target path gets a null layer,
+//! parented to its enclosing target (`hero.eyes` hangs off `hero`), so art
+//! attached in After Effects or a Lottie editor follows the motion.
+//! Transform properties map onto the layer transform; anything else becomes
+//! an expression control effect (slider, point or color) with the property's
+//! name. Lottie players only loop or play once, so reverse and ping-pong are
+//! baked into the keyframes and looping is recorded as a `loop` marker.
+
+use serde_json::{json, Value as Json};
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::lexer::Unit;
+use super::clip::{Clip, ClipKeyframe, ClipLoop, Track};
+
+/// Bodymovin schema version the output follows
+const BODYMOVIN_VERSION: &str = "5.7.4";
+
+#[derive(Debug, Clone)]
+pub struct LottieOptions {
+    pub fps: f64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for LottieOptions {
+    fn default() -> Self {
+        Self { fps: 60.0, width: 512, height: 512 }
+    }
+}
+
+/// Layer transform slot for a property name
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Position,
+    PositionX,
+    PositionY,
+    Anchor,
+    Rotation,
+    Scale,
+    Opacity,
+    Effect,
+}
+
+fn slot(track: &Track) -> Slot {
+    let channels = track.keyframes.first().map_or(1, |k| k.value.channels.len());
+    match (track.property.as_str(), channels) {
+        ("position" | "pos" | "offset", _) => Slot::Position,
+        ("x", 1) => Slot::PositionX,
+        ("y", 1) => Slot::PositionY,
+        ("anchor" | "pivot", _) => Slot::Anchor,
+        ("rotation" | "rotate" | "angle", 1) => Slot::Rotation,
+        ("scale", _) => Slot::Scale,
+        ("opacity" | "alpha", 1) => Slot::Opacity,
+        // Bare `animation` declarations: moving points read best as motion
+        ("value", 2) => Slot::Position,
+        _ => Slot::Effect,
+    }
+}
+
+/// Lottie composition JSON for `clip`
+pub fn export(clip: &Clip, options: &LottieOptions) -> GrumpResult<String> {
+    let clip = clip.unrolled();
+    let fr = options.fps;
+    let op = (clip.duration * fr).round().max(1.0);
+
+    let targets = clip.targets();
+    let mut layers = Vec::new();
+    for (index, path) in targets.iter().enumerate() {
+        let parent = targets.iter().position(|p| p.len() + 1 == path.len() && path.starts_with(p));
+        let tracks: Vec<&Track> = clip.tracks.iter().filter(|t| &t.target == path).collect();
+
+        let mut layer = json!({
+            "ddd": 0,
+            "ind": index + 1,
+            "ty": 3,
+            "nm": path.last().cloned().unwrap_or_default(),
+            "sr": 1,
+            "ks": transform(&tracks, fr)?,
+            "ao": 0,
+            "ip": 0,
+            "op": op,
+            "st": 0,
+            "bm": 0,
+        });
+        if let Some(parent) = parent {
+            layer["parent"] = json!(parent + 1);
+        }
+        let effects = effects(&tracks, fr)?;
+        if !effects.is_empty() {
+            layer["ef"] = Json::Array(effects);
+        }
+        layers.push(layer);
+    }
+
+    let markers = match clip.loop_mode {
+        ClipLoop::Loop => vec![json!({ "tm": 0, "cm": "loop", "dr": op })],
+        ClipLoop::Section { start, end } => vec![json!({
+            "tm": (start * fr).round(),
+            "cm": "loop",
+            "dr": ((end - start) * fr).round(),
+        })],
+        _ => Vec::new(),
+    };
+
+    let composition = json!({
+        "v": BODYMOVIN_VERSION,
+        "fr": fr,
+        "ip": 0,
+        "op": op,
+        "w": options.width,
+        "h": options.height,
+        "nm": clip.name,
+        "ddd": 0,
+        "assets": [],
+        "layers": layers,
+        "markers": markers,
+    });
+    serde_json::to_string_pretty(&composition).map_err(|e| GrumpError::Other(e.into()))
+}
+
+fn transform(tracks: &[&Track], fr: f64) -> GrumpResult<Json> {
+    let find = |wanted: Slot| tracks.iter().copied().find(|t| slot(t) == wanted);
+
+    let position = match (find(Slot::PositionX), find(Slot::PositionY), find(Slot::Position)) {
+        (None, None, None) => json!({ "a": 0, "k": [0, 0, 0] }),
+        (None, None, Some(track)) => property(&track.keyframes, fr, |c| vec![c[0], c.get(1).copied().unwrap_or(0.0), 0.0]),
+        // Separate x/y tracks use Lottie's split position
+        (x, y, _) => json!({
+            "s": true,
+            "x": x.map_or(json!({ "a": 0, "k": 0 }), |t| property(&t.keyframes, fr, |c| vec![c[0]])),
+            "y": y.map_or(json!({ "a": 0, "k": 0 }), |t| property(&t.keyframes, fr, |c| vec![c[0]])),
+        }),
+    };
+    let anchor = match find(Slot::Anchor) {
+        Some(track) => property(&track.keyframes, fr, |c| vec![c[0], c.get(1).copied().unwrap_or(0.0), 0.0]),
+        None => json!({ "a": 0, "k": [0, 0, 0] }),
+    };
+    let rotation = match find(Slot::Rotation) {
+        Some(track) => {
+            let radians = track.keyframes.first().and_then(|k| k.value.unit.as_ref()) == Some(&Unit::Radians);
+            property(&track.keyframes, fr, |c| vec![if radians { c[0].to_degrees() } else { c[0] }])
+        }
+        None => json!({ "a": 0, "k": 0 }),
+    };
+    // Lottie scale and opacity are percentages
+    let scale = match find(Slot::Scale) {
+        Some(track) => property(&track.keyframes, fr, |c| {
+            let y = c.get(1).copied().unwrap_or(c[0]);
+            vec![c[0] * 100.0, y * 100.0, 100.0]
+        }),
+        None => json!({ "a": 0, "k": [100, 100, 100] }),
+    };
+    let opacity = match find(Slot::Opacity) {
+        Some(track) => property(&track.keyframes, fr, |c| vec![c[0] * 100.0]),
+        None => json!({ "a": 0, "k": 100 }),
+    };
+
+    Ok(json!({ "o": opacity, "r": rotation, "p": position, "a": anchor, "s": scale }))
+}
+
+/// Expression controls for properties Lottie has no transform slot for
+fn effects(tracks: &[&Track], fr: f64) -> GrumpResult<Vec<Json>> {
+    let mut effects = Vec::new();
+    for track in tracks.iter().filter(|t| slot(t) == Slot::Effect) {
+        let channels = track.keyframes.first().map_or(1, |k| k.value.channels.len());
+        let (control, inner_type, inner_name, value) = match channels {
+            1 => ("ADBE Slider Control", 0, "Slider", property(&track.keyframes, fr, |c| vec![c[0]])),
+            2 => ("ADBE Point Control", 3, "Point", property(&track.keyframes, fr, |c| vec![c[0], c[1]])),
+            // Colors are stored 0-255 and exported as 0-1 RGBA
+            3 | 4 => ("ADBE Color Control", 2, "Color", property(&track.keyframes, fr, |c| {
+                vec![c[0] / 255.0, c[1] / 255.0, c[2] / 255.0, c.get(3).map_or(1.0, |a| a / 255.0)]
+            })),
+            n => {
+                return Err(GrumpError::Animation {
+                    message: format!("Property '{}' has {} channels; Lottie controls take 1, 2 or 4", track.property, n),
+                });
+            }
+        };
+        effects.push(json!({
+            "ty": 5,
+            "nm": track.property,
+            "np": 3,
+            "mn": control,
+            "ix": effects.len() + 1,
+            "en": 1,
+            "ef": [{
+                "ty": inner_type,
+                "nm": inner_name,
+                "mn": format!("{}-0001", control),
+                "ix": 1,
+                "v": value,
+            }],
+        }));
+    }
+    Ok(effects)
+}
+
+/// Static (`a: 0`) or keyframed (`a: 1`) property; easing tangents come from
+/// each segment's cubic-bezier (`o` leaves this keyframe, `i` enters the next)
+fn property(keyframes: &[ClipKeyframe], fr: f64, map: impl Fn(&[f64]) -> Vec<f64>) -> Json {
+    let value = |k: &ClipKeyframe| map(&k.value.channels);
+    if keyframes.len() < 2 {
+        let v = keyframes.first().map(value).unwrap_or_default();
+        return match v.as_slice() {
+            [single] => json!({ "a": 0, "k": single }),
+            _ => json!({ "a": 0, "k": v }),
+        };
+    }
+
+    let mut k = Vec::new();
+    for (i, keyframe) in keyframes.iter().enumerate() {
+        let t = (keyframe.time * fr).round();
+        if i + 1 == keyframes.len() {
+            k.push(json!({ "t": t, "s": value(keyframe) }));
+        } else {
+            let easing = keyframe.easing;
+            k.push(json!({
+                "t": t,
+                "s": value(keyframe),
+                "o": { "x": [easing.x1], "y": [easing.y1] },
+                "i": { "x": [easing.x2], "y": [easing.y2] },
+            }));
+        }
+    }
+    json!({ "a": 1, "k": k })
+}
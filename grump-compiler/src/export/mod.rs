@@ -0,0 +1,142 @@
+//! Animation export
+//!
+//! Renders the program's animations, `animate` blocks and timelines to
+//! formats that live outside a game build (Lottie for designers and web
+//! players, CSS and the Web Animations API for web pages, rendered frames
+//! and sprite sheets for devices too slow to animate live, GIFs for bug
+//! reports). Every exporter works from the same `Clip` model.
+
+pub mod clip;
+pub mod lottie;
+pub mod css;
+pub mod web_animations;
+pub mod raster;
+pub mod png;
+pub mod sprite_sheet;
+pub mod gif;
+
+pub use crate::animation::ExportFormat;
+pub use clip::{Clip, ClipKeyframe, ClipLoop, Easing, Track, Value};
+
+use std::path::PathBuf;
+
+use crate::codegen::OutputTree;
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::{Item, Program, SceneDeclaration};
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Frame rate; defaults to the app's `@fps`, then 60
+    pub fps: Option<f64>,
+    /// Only export the clip with this name
+    pub only: Option<String>,
+    /// Frame size for rendered formats; defaults to 256x256
+    pub size: Option<(u32, u32)>,
+    /// Directory sprites are loaded from
+    pub assets: Option<PathBuf>,
+    /// Record this scene running instead of exporting clips one by one
+    pub scene: Option<String>,
+    /// Frames to record; defaults to the scene's longest animation (at least a second)
+    pub frames: Option<usize>,
+}
+
+/// Export every clip in `program` as `format`
+pub fn export(program: &Program, format: &ExportFormat, options: &ExportOptions) -> GrumpResult<OutputTree> {
+    let fps = options.fps.unwrap_or_else(|| app_fps(program));
+    if let Some(scene) = &options.scene {
+        return record(program, scene, format, fps, options);
+    }
+    let mut clips = Clip::collect(program)?;
+    if let Some(only) = &options.only {
+        clips.retain(|clip| &clip.name == only);
+        if clips.is_empty() {
+            return Err(GrumpError::Animation {
+                message: format!("No animation or timeline named '{}'", only),
+            });
+        }
+    }
+    if clips.is_empty() {
+        return Err(GrumpError::Animation { message: "Nothing to export: no animations or timelines found".to_string() });
+    }
+
+    let mut tree = OutputTree::new();
+    match format {
+        ExportFormat::Lottie => {
+            let lottie_options = lottie::LottieOptions { fps, ..Default::default() };
+            for clip in &clips {
+                tree.add_text(format!("lottie/{}.json", clip.name), lottie::export(clip, &lottie_options)?);
+            }
+        }
+        ExportFormat::Css => {
+            for clip in &clips {
+                tree.add_text(format!("css/{}.css", clip.name), css::export(clip)?);
+            }
+        }
+        ExportFormat::WebAnimations => {
+            for clip in &clips {
+                tree.add_text(format!("js/{}.js", clip.name), web_animations::export(clip)?);
+            }
+        }
+        ExportFormat::SpriteSheet => {
+            let (width, height) = options.size.unwrap_or((256, 256));
+            let renderer = raster::Renderer::from_program(program, width, height, options.assets.as_deref());
+            for clip in &clips {
+                let frames = renderer.frames(clip, fps);
+                for (index, frame) in frames.iter().enumerate() {
+                    let name = sprite_sheet::frame_name(&clip.name, index);
+                    tree.add_binary(format!("frames/{}/{}", clip.name, name), png::encode(frame)?);
+                }
+                let image_name = format!("{}.png", clip.name);
+                let sheet = sprite_sheet::pack(clip, &frames, fps, &image_name)?;
+                tree.add_binary(format!("spritesheet/{}", image_name), png::encode(&sheet.image)?);
+                tree.add_text(format!("spritesheet/{}.json", clip.name), sheet.json);
+            }
+        }
+        ExportFormat::Gif => {
+            let (width, height) = options.size.unwrap_or((256, 256));
+            let renderer = raster::Renderer::from_program(program, width, height, options.assets.as_deref());
+            for clip in &clips {
+                tree.add_binary(format!("gif/{}.gif", clip.name), gif::export(&renderer, clip, fps)?);
+            }
+        }
+        other => {
+            return Err(GrumpError::Other(anyhow::anyhow!("Export to {} is not supported yet", other.name())));
+        }
+    }
+    Ok(tree)
+}
+
+/// `frames` frames of `scene` running, with every entity on stage
+fn record(program: &Program, scene: &str, format: &ExportFormat, fps: f64, options: &ExportOptions) -> GrumpResult<OutputTree> {
+    if *format != ExportFormat::Gif {
+        return Err(GrumpError::Other(anyhow::anyhow!("Scenes can only be recorded as gif, not {}", format.name())));
+    }
+    let declaration = find_scene(&program.items, scene).ok_or_else(|| GrumpError::Animation {
+        message: format!("No scene named '{}'", scene),
+    })?;
+    let clip = Clip::from_scene(declaration)?;
+    let (_, gif_fps) = gif::timing(fps);
+    let count = options.frames.unwrap_or_else(|| (clip.duration.max(1.0) * gif_fps).round() as usize).max(1);
+
+    let (width, height) = options.size.unwrap_or((256, 256));
+    let renderer = raster::Renderer::from_program(program, width, height, options.assets.as_deref());
+    let mut tree = OutputTree::new();
+    tree.add_binary(format!("gif/{}.gif", scene), gif::record(&renderer, &clip, fps, count)?);
+    Ok(tree)
+}
+
+fn find_scene<'a>(items: &'a [Item], name: &str) -> Option<&'a SceneDeclaration> {
+    items.iter().find_map(|item| match item {
+        Item::Scene(scene) if scene.name == name => Some(scene),
+        Item::App(app) => find_scene(&app.body, name),
+        Item::Module(module) => find_scene(&module.items, name),
+        _ => None,
+    })
+}
+
+fn app_fps(program: &Program) -> f64 {
+    program.items.iter().find_map(|item| match item {
+        Item::App(app) => app.fps,
+        _ => None,
+    }).unwrap_or(60.0)
+}
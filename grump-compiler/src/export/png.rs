@@ -0,0 +1,55 @@
+//! PNG encoding and decoding
+//!
+//! The frame renderer writes 8-bit RGBA images and reads sprites in any
+//! color type and bit depth, through the `png` crate. Decoding is capped at
+//! `MAX_DECODED_BYTES` so a crafted header can't ask for more memory than a
+//! sprite sheet would ever need.
+
+use crate::error::{GrumpError, GrumpResult};
+use super::raster::Canvas;
+
+/// Largest image, in RGBA bytes, `decode` will expand
+const MAX_DECODED_BYTES: usize = 64 * 1024 * 1024;
+
+/// 8-bit RGBA PNG of `canvas`
+pub fn encode(canvas: &Canvas) -> GrumpResult<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, canvas.width, canvas.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> = canvas.pixels.iter().flatten().copied().collect();
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(|e| GrumpError::Other(anyhow::anyhow!("Could not encode a PNG: {}", e)))?;
+    Ok(png)
+}
+
+/// Decode a PNG into RGBA pixels
+pub fn decode(data: &[u8]) -> GrumpResult<Canvas> {
+    let mut decoder = png::Decoder::new_with_limits(data, png::Limits { bytes: MAX_DECODED_BYTES });
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid(&e.to_string()))?;
+    let (width, height) = reader.info().size();
+    let size = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4));
+    if size.is_none_or(|size| size > MAX_DECODED_BYTES) {
+        return Err(invalid(&format!("{}x{} is too large", width, height)));
+    }
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(|e| invalid(&e.to_string()))?;
+    let channels = frame.color_type.samples();
+    let pixels = buffer[..frame.buffer_size()]
+        .chunks_exact(channels)
+        .map(|px| match frame.color_type {
+            png::ColorType::Grayscale => [px[0], px[0], px[0], 255],
+            png::ColorType::GrayscaleAlpha => [px[0], px[0], px[0], px[1]],
+            png::ColorType::Rgb => [px[0], px[1], px[2], 255],
+            _ => [px[0], px[1], px[2], px[3]],
+        })
+        .collect();
+    Ok(Canvas { width, height, pixels })
+}
+
+fn invalid(reason: &str) -> GrumpError {
+    GrumpError::Other(anyhow::anyhow!("Invalid PNG: {}", reason))
+}
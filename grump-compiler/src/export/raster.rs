@@ -0,0 +1,421 @@
+//! Headless CPU rasterizer
+//!
+//! Renders clips frame by frame without a GPU or windowing system, for
+//! sprite sheets, frame sequences and GIFs. Each animated target is a node:
+//! an entity from the program (its `position`, `size`, `color`, `shape` or
+//! `sprite` components give the rest pose) or, for targets no entity
+//! declares, a circle in the middle of the frame. Clip tracks then override
+//! the pose at each sampled time. Child targets (`hero.eyes`) are positioned
+//! relative to their parent and inherit its rotation, scale and opacity.
+//!
+//! Shapes are antialiased by supersampling; sprites are sampled nearest
+//! neighbour so pixel art stays crisp.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::lexer::Unit;
+use crate::parser::{Expression, Item, Literal, Program, UnaryOp};
+use super::clip::{Clip, ClipLoop};
+use super::png;
+
+/// Samples per pixel along each axis
+const SUPERSAMPLE: usize = 4;
+
+/// Straight-alpha RGBA image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, top row first
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: [u8; 4]) -> Canvas {
+        Canvas { width, height, pixels: vec![background; width as usize * height as usize] }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Copy `source` with its top-left corner at (`x`, `y`)
+    pub fn blit(&mut self, source: &Canvas, x: u32, y: u32) {
+        for row in 0..source.height.min(self.height.saturating_sub(y)) {
+            for col in 0..source.width.min(self.width.saturating_sub(x)) {
+                self.pixels[((y + row) * self.width + x + col) as usize] = source.get(col, row);
+            }
+        }
+    }
+
+    /// Source-over blend of `color` at `coverage` (0-1)
+    fn blend(&mut self, x: usize, y: usize, color: [u8; 4], coverage: f64) {
+        let index = y * self.width as usize + x;
+        let dst = self.pixels[index];
+        let src_a = color[3] as f64 / 255.0 * coverage;
+        let dst_a = dst[3] as f64 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a <= 0.0 {
+            return;
+        }
+        let mut out = [0u8; 4];
+        for c in 0..3 {
+            let value = (color[c] as f64 * src_a + dst[c] as f64 * dst_a * (1.0 - src_a)) / out_a;
+            out[c] = value.round().clamp(0.0, 255.0) as u8;
+        }
+        out[3] = (out_a * 255.0).round() as u8;
+        self.pixels[index] = out;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect,
+    Circle,
+    /// Path relative to the assets directory
+    Sprite(String),
+}
+
+/// Where and how a node is drawn; position is its center, relative to the
+/// parent node (or the frame's top-left corner for top-level nodes)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: (f64, f64),
+    pub size: (f64, f64),
+    /// Degrees, clockwise
+    pub rotation: f64,
+    pub scale: (f64, f64),
+    pub opacity: f64,
+    pub color: [u8; 4],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub path: Vec<String>,
+    pub shape: Shape,
+    pub pose: Pose,
+}
+
+pub struct Renderer {
+    pub width: u32,
+    pub height: u32,
+    pub background: [u8; 4],
+    /// Declared nodes, drawn in order when a clip targets them
+    pub nodes: Vec<Node>,
+    sprites: HashMap<String, Canvas>,
+}
+
+const DEFAULT_COLOR: [u8; 4] = [255, 140, 0, 255];
+const DEFAULT_SIZE: f64 = 32.0;
+
+impl Renderer {
+    /// Empty stage with a transparent background
+    pub fn new(width: u32, height: u32) -> Renderer {
+        Renderer { width, height, background: [0, 0, 0, 0], nodes: Vec::new(), sprites: HashMap::new() }
+    }
+
+    /// Stage with one node per entity; sprites are read from `assets`
+    /// (missing or unreadable sprites are drawn as rectangles)
+    pub fn from_program(program: &Program, width: u32, height: u32, assets: Option<&Path>) -> Renderer {
+        let mut renderer = Renderer::new(width, height);
+        for item in &program.items {
+            renderer.add_item(item, assets);
+        }
+        renderer
+    }
+
+    fn add_item(&mut self, item: &Item, assets: Option<&Path>) {
+        match item {
+            Item::App(app) => {
+                for nested in &app.body {
+                    self.add_item(nested, assets);
+                }
+            }
+            Item::Module(module) => {
+                for nested in &module.items {
+                    self.add_item(nested, assets);
+                }
+            }
+            Item::Entity(entity) => {
+                let mut node = self.default_node(vec![entity.name.clone()]);
+                for component in &entity.components {
+                    let numbers: Vec<f64> = component.args.iter().filter_map(number).collect();
+                    match (component.name.as_str(), component.args.first()) {
+                        ("sprite", Some(Expression::Literal(Literal::String(path)))) => {
+                            if let Some(sprite) = self.load_sprite(path, assets) {
+                                node.pose.size = (sprite.width as f64, sprite.height as f64);
+                                node.shape = Shape::Sprite(path.clone());
+                            } else {
+                                node.shape = Shape::Rect;
+                            }
+                        }
+                        ("shape", Some(Expression::Identifier(shape) | Expression::Literal(Literal::String(shape)))) => {
+                            node.shape = match shape.as_str() {
+                                "rect" | "rectangle" | "square" | "box" => Shape::Rect,
+                                _ => Shape::Circle,
+                            };
+                        }
+                        ("color" | "tint" | "fill", Some(Expression::Literal(Literal::String(hex)))) => {
+                            if let Some(color) = parse_hex(hex) {
+                                node.pose.color = color;
+                            }
+                        }
+                        (property, _) if !numbers.is_empty() => apply(&mut node.pose, property, &numbers, None),
+                        _ => {}
+                    }
+                }
+                self.nodes.push(node);
+            }
+            _ => {}
+        }
+    }
+
+    fn load_sprite(&mut self, path: &str, assets: Option<&Path>) -> Option<Canvas> {
+        if let Some(sprite) = self.sprites.get(path) {
+            return Some(sprite.clone());
+        }
+        let bytes = std::fs::read(assets?.join(path)).ok()?;
+        let sprite = png::decode(&bytes).ok()?;
+        self.sprites.insert(path.to_string(), sprite.clone());
+        Some(sprite)
+    }
+
+    /// Circle at the center of the frame (top-level) or of its parent
+    fn default_node(&self, path: Vec<String>) -> Node {
+        let position = if path.len() == 1 { (self.width as f64 / 2.0, self.height as f64 / 2.0) } else { (0.0, 0.0) };
+        Node {
+            path,
+            shape: Shape::Circle,
+            pose: Pose {
+                position,
+                size: (DEFAULT_SIZE, DEFAULT_SIZE),
+                rotation: 0.0,
+                scale: (1.0, 1.0),
+                opacity: 1.0,
+                color: DEFAULT_COLOR,
+            },
+        }
+    }
+
+    /// The clip's targets, posed at `time` (seconds)
+    pub fn pose(&self, clip: &Clip, time: f64) -> Vec<Node> {
+        self.pose_paths(clip.targets(), clip, time)
+    }
+
+    fn pose_paths(&self, paths: Vec<Vec<String>>, clip: &Clip, time: f64) -> Vec<Node> {
+        paths.into_iter().map(|path| {
+            let mut node = self.nodes.iter().find(|n| n.path == path).cloned()
+                .unwrap_or_else(|| self.default_node(path.clone()));
+            let rest = node.pose;
+            for track in clip.tracks.iter().filter(|t| t.target == path) {
+                let unit = track.keyframes.first().and_then(|k| k.value.unit.clone());
+                let channels = track.sample(time);
+                if channels.is_empty() {
+                    continue;
+                }
+                match (track.property.as_str(), channels.len()) {
+                    // A bare numeric `animation` lifts its target (positive is up)
+                    ("value", 1) => node.pose.position.1 = rest.position.1 - channels[0],
+                    ("value", _) => apply(&mut node.pose, "position", &channels, unit.as_ref()),
+                    (property, _) => apply(&mut node.pose, property, &channels, unit.as_ref()),
+                }
+            }
+            node
+        }).collect()
+    }
+
+    /// One frame per `1 / fps` seconds. Reverse and ping-pong are baked in;
+    /// looping clips stop one frame short so the sequence wraps seamlessly.
+    pub fn frames(&self, clip: &Clip, fps: f64) -> Vec<Canvas> {
+        let clip = clip.unrolled();
+        let count = (clip.duration * fps).round().max(1.0) as usize;
+        let count = if clip.loop_mode == ClipLoop::Loop { count } else { count + 1 };
+        (0..count).map(|i| self.render(&self.pose(&clip, i as f64 / fps))).collect()
+    }
+
+    /// `count` frames of a running scene: every declared node is drawn, and
+    /// the clip's tracks move them as time passes
+    pub fn record(&self, clip: &Clip, fps: f64, count: usize) -> Vec<Canvas> {
+        let mut paths: Vec<Vec<String>> = self.nodes.iter().map(|n| n.path.clone()).collect();
+        for path in clip.targets() {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        (0..count).map(|i| self.render(&self.pose_paths(paths.clone(), clip, i as f64 / fps))).collect()
+    }
+
+    pub fn render(&self, nodes: &[Node]) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height, self.background);
+        for node in nodes {
+            // Parents come first in clip target order, so their transforms are known
+            let (transform, opacity) = world(nodes, node);
+            self.draw(&mut canvas, node, &transform, opacity);
+        }
+        canvas
+    }
+
+    fn draw(&self, canvas: &mut Canvas, node: &Node, transform: &Affine, opacity: f64) {
+        let (w, h) = node.pose.size;
+        if w <= 0.0 || h <= 0.0 || opacity <= 0.0 {
+            return;
+        }
+        let inverse = match transform.inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        let sprite = match &node.shape {
+            Shape::Sprite(path) => self.sprites.get(path),
+            _ => None,
+        };
+
+        // Bounding box of the transformed shape, clipped to the canvas
+        let corners = [(-w / 2.0, -h / 2.0), (w / 2.0, -h / 2.0), (-w / 2.0, h / 2.0), (w / 2.0, h / 2.0)]
+            .map(|(x, y)| transform.apply(x, y));
+        let min_x = corners.iter().map(|c| c.0).fold(f64::MAX, f64::min).floor().max(0.0) as usize;
+        let min_y = corners.iter().map(|c| c.1).fold(f64::MAX, f64::min).floor().max(0.0) as usize;
+        let max_x = corners.iter().map(|c| c.0).fold(f64::MIN, f64::max).ceil().min(canvas.width as f64) as usize;
+        let max_y = corners.iter().map(|c| c.1).fold(f64::MIN, f64::max).ceil().min(canvas.height as f64) as usize;
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                if let Some(sprite) = sprite {
+                    // Sample the pixel center
+                    let (u, v) = inverse.apply(px as f64 + 0.5, py as f64 + 0.5);
+                    let (sx, sy) = ((u / w + 0.5) * sprite.width as f64, (v / h + 0.5) * sprite.height as f64);
+                    if sx >= 0.0 && sy >= 0.0 && (sx as u32) < sprite.width && (sy as u32) < sprite.height {
+                        canvas.blend(px, py, sprite.get(sx as u32, sy as u32), opacity);
+                    }
+                    continue;
+                }
+                let mut inside = 0;
+                for sy in 0..SUPERSAMPLE {
+                    for sx in 0..SUPERSAMPLE {
+                        let x = px as f64 + (sx as f64 + 0.5) / SUPERSAMPLE as f64;
+                        let y = py as f64 + (sy as f64 + 0.5) / SUPERSAMPLE as f64;
+                        let (u, v) = inverse.apply(x, y);
+                        let hit = match node.shape {
+                            Shape::Circle => (u / (w / 2.0)).powi(2) + (v / (h / 2.0)).powi(2) <= 1.0,
+                            _ => u.abs() <= w / 2.0 && v.abs() <= h / 2.0,
+                        };
+                        if hit {
+                            inside += 1;
+                        }
+                    }
+                }
+                if inside > 0 {
+                    let coverage = inside as f64 / (SUPERSAMPLE * SUPERSAMPLE) as f64;
+                    canvas.blend(px, py, node.pose.color, coverage * opacity);
+                }
+            }
+        }
+    }
+}
+
+/// Set a pose property from sampled channels; unknown properties are ignored
+fn apply(pose: &mut Pose, property: &str, channels: &[f64], unit: Option<&Unit>) {
+    let first = channels[0];
+    let second = channels.get(1).copied();
+    match property {
+        "position" | "pos" | "offset" | "translate" => pose.position = (first, second.unwrap_or(pose.position.1)),
+        "x" | "left" => pose.position.0 = first,
+        "y" | "top" => pose.position.1 = first,
+        "size" => pose.size = (first, second.unwrap_or(first)),
+        "width" => pose.size.0 = first,
+        "height" => pose.size.1 = first,
+        "rotation" | "rotate" | "angle" => {
+            pose.rotation = if unit == Some(&Unit::Radians) { first.to_degrees() } else { first };
+        }
+        "scale" => pose.scale = (first, second.unwrap_or(first)),
+        "opacity" | "alpha" => pose.opacity = first.clamp(0.0, 1.0),
+        "color" | "tint" | "fill" if channels.len() >= 3 => {
+            let byte = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+            pose.color = [byte(channels[0]), byte(channels[1]), byte(channels[2]), channels.get(3).map_or(255, |a| byte(*a))];
+        }
+        _ => {}
+    }
+}
+
+/// Transform and opacity of `node` including every ancestor in `nodes`
+fn world(nodes: &[Node], node: &Node) -> (Affine, f64) {
+    let mut transform = Affine::local(&node.pose);
+    let mut opacity = node.pose.opacity;
+    for depth in (1..node.path.len()).rev() {
+        if let Some(parent) = nodes.iter().find(|n| n.path[..] == node.path[..depth]) {
+            transform = Affine::local(&parent.pose).then(&transform);
+            opacity *= parent.pose.opacity;
+        }
+    }
+    (transform, opacity)
+}
+
+/// 2D affine transform: (x, y) -> (a x + c y + e, b x + d y + f)
+#[derive(Debug, Clone, Copy)]
+struct Affine {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Affine {
+    /// Scale, then rotate, then move to the pose's position
+    fn local(pose: &Pose) -> Affine {
+        let (sin, cos) = pose.rotation.to_radians().sin_cos();
+        let (sx, sy) = pose.scale;
+        Affine { a: cos * sx, b: sin * sx, c: -sin * sy, d: cos * sy, e: pose.position.0, f: pose.position.1 }
+    }
+
+    /// `self` applied after `inner`
+    fn then(&self, inner: &Affine) -> Affine {
+        Affine {
+            a: self.a * inner.a + self.c * inner.b,
+            b: self.b * inner.a + self.d * inner.b,
+            c: self.a * inner.c + self.c * inner.d,
+            d: self.b * inner.c + self.d * inner.d,
+            e: self.a * inner.e + self.c * inner.f + self.e,
+            f: self.b * inner.e + self.d * inner.f + self.f,
+        }
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    fn inverse(&self) -> Option<Affine> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Affine { a, b, c, d, e: -(a * self.e + c * self.f), f: -(b * self.e + d * self.f) })
+    }
+}
+
+fn number(expr: &Expression) -> Option<f64> {
+    match expr {
+        Expression::Literal(Literal::Integer(n)) => Some(*n as f64),
+        Expression::Literal(Literal::Float(f)) => Some(*f),
+        Expression::Literal(Literal::Angle { value, .. }) => Some(*value),
+        Expression::Unary { op: UnaryOp::Neg, expr } => number(expr).map(|n| -n),
+        _ => None,
+    }
+}
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`
+fn parse_hex(hex: &str) -> Option<[u8; 4]> {
+    let digits = hex.strip_prefix('#')?;
+    let channel = |i: usize, len: usize| u8::from_str_radix(digits.get(i..i + len)?, 16).ok();
+    match digits.len() {
+        3 => {
+            let expand = |i| channel(i, 1).map(|v| v * 17);
+            Some([expand(0)?, expand(1)?, expand(2)?, 255])
+        }
+        6 => Some([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?, 255]),
+        8 => Some([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?, channel(6, 2)?]),
+        _ => None,
+    }
+}
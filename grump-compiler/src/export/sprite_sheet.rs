@@ -0,0 +1,79 @@
+//! Sprite sheet packing
+//!
+//! Frames are packed row by row into a near-square grid and described in
+//! TexturePacker's "JSON (Hash)" format, which Phaser, PixiJS, Cocos and
+//! most engines load directly. Each frame carries its duration, and the
+//! `animations` map lists the frame names in play order (plus a `_loop`
+//! sequence for clips that loop a section).
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::error::{GrumpError, GrumpResult};
+use super::clip::{Clip, ClipLoop};
+use super::raster::Canvas;
+
+/// A packed sheet: the atlas image and its TexturePacker metadata
+pub struct SpriteSheet {
+    pub image: Canvas,
+    pub json: String,
+}
+
+/// `bounce_0003.png`
+pub fn frame_name(clip: &str, index: usize) -> String {
+    format!("{}_{:04}.png", clip, index)
+}
+
+/// Pack `frames` (rendered at `fps`) of `clip`; `image_name` is the file the
+/// atlas is saved as, which the metadata points at
+pub fn pack(clip: &Clip, frames: &[Canvas], fps: f64, image_name: &str) -> GrumpResult<SpriteSheet> {
+    let first = frames.first().ok_or_else(|| GrumpError::Animation {
+        message: format!("Animation '{}' rendered no frames", clip.name),
+    })?;
+    let (w, h) = (first.width, first.height);
+    let columns = (frames.len() as f64).sqrt().ceil() as u32;
+    let rows = (frames.len() as u32).div_ceil(columns);
+    let mut image = Canvas::new(w * columns, h * rows, [0, 0, 0, 0]);
+
+    let duration = (1000.0 / fps).round() as u32;
+    let mut entries = Map::new();
+    let mut names = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let (x, y) = ((index as u32 % columns) * w, (index as u32 / columns) * h);
+        image.blit(frame, x, y);
+        let name = frame_name(&clip.name, index);
+        entries.insert(name.clone(), json!({
+            "frame": { "x": x, "y": y, "w": w, "h": h },
+            "rotated": false,
+            "trimmed": false,
+            "spriteSourceSize": { "x": 0, "y": 0, "w": w, "h": h },
+            "sourceSize": { "w": w, "h": h },
+            "duration": duration,
+        }));
+        names.push(name);
+    }
+
+    let mut animations = Map::new();
+    animations.insert(clip.name.clone(), json!(names));
+    if let ClipLoop::Section { start, end } = clip.loop_mode {
+        let from = ((start * fps).round() as usize).min(names.len());
+        let to = ((end * fps).round() as usize).clamp(from, names.len());
+        animations.insert(format!("{}_loop", clip.name), json!(names[from..to]));
+    }
+
+    let sheet = json!({
+        "frames": Json::Object(entries),
+        "animations": Json::Object(animations),
+        "meta": {
+            "app": "G-Rump",
+            "version": "1.0",
+            "image": image_name,
+            "format": "RGBA8888",
+            "size": { "w": image.width, "h": image.height },
+            "scale": "1",
+            "frameRate": fps,
+            "loop": clip.loop_mode != ClipLoop::Once && clip.loop_mode != ClipLoop::Reverse,
+        },
+    });
+    let json = serde_json::to_string_pretty(&sheet).map_err(|e| GrumpError::Other(e.into()))?;
+    Ok(SpriteSheet { image, json })
+}
@@ -0,0 +1,103 @@
+//! Web Animations API export
+//!
+//! The same runs as the CSS export, as an ES module: one function per clip
+//! that takes the elements to animate, keyed by target path
+//! (`wave({ hero: el, "hero.arm": arm })`), starts every track with
+//! `Element.animate()` and returns the `Animation`s so the page can pause,
+//! seek or await them.
+
+use crate::error::GrumpResult;
+use super::clip::Clip;
+use super::css::{self, camel_case, number, Run};
+
+/// ES module for `clip`
+pub fn export(clip: &Clip) -> GrumpResult<String> {
+    let runs = css::runs(clip)?;
+    let mut js = String::new();
+    js.push_str(&format!("// Generated by G-Rump from `{}`\n", clip.name));
+
+    let mut registered: Vec<&str> = Vec::new();
+    for run in &runs {
+        if let Some(syntax) = run.property.syntax {
+            if registered.is_empty() {
+                js.push_str("\n// Custom properties only interpolate once registered\n");
+                js.push_str("function register(name, syntax, initialValue) {\n");
+                js.push_str("  try {\n");
+                js.push_str("    CSS.registerProperty({ name, syntax, inherits: false, initialValue });\n");
+                js.push_str("  } catch (_) {\n");
+                js.push_str("    // Already registered (e.g. by the exported stylesheet)\n");
+                js.push_str("  }\n");
+                js.push_str("}\n\n");
+            }
+            if !registered.contains(&run.property.name.as_str()) {
+                registered.push(&run.property.name);
+                js.push_str(&format!(
+                    "register({:?}, {:?}, {:?});\n",
+                    run.property.name, syntax, run.property.initial_value()
+                ));
+            }
+        }
+    }
+
+    js.push_str(&format!(
+        "\n/**\n * Plays `{}` on `targets` ({{ \"path.to.target\": element }}).\n * @returns {{Animation[]}}\n */\n",
+        clip.name
+    ));
+    js.push_str(&format!("export function {}(targets) {{\n", function_name(&clip.name)));
+    js.push_str("  const animations = [];\n");
+    for run in &runs {
+        js.push_str(&format!("  if (targets[{:?}]) {{\n", run.target.join(".")));
+        js.push_str(&format!("    animations.push(targets[{:?}].animate(\n", run.target.join(".")));
+        js.push_str(&keyframes(run));
+        js.push_str(&format!("      {},\n", options(run)));
+        js.push_str("    ));\n");
+        js.push_str("  }\n");
+    }
+    js.push_str("  return animations;\n");
+    js.push_str("}\n");
+    Ok(js)
+}
+
+fn keyframes(run: &Run) -> String {
+    // Element.animate() takes camelCase names; custom properties keep their dashes
+    let key = if run.property.name.starts_with("--") {
+        format!("{:?}", run.property.name)
+    } else {
+        camel_case(&run.property.name)
+    };
+    let last = run.keyframes.len().saturating_sub(1);
+    let mut out = String::from("      [\n");
+    for (i, (offset, value, easing)) in run.keyframes.iter().enumerate() {
+        out.push_str(&format!("        {{ offset: {}, {}: {:?}", number(*offset), key, value));
+        if i < last && !easing.is_linear() {
+            out.push_str(&format!(", easing: {:?}", css::cubic_bezier(easing)));
+        }
+        out.push_str(" },\n");
+    }
+    out.push_str("      ],\n");
+    out
+}
+
+fn options(run: &Run) -> String {
+    let iterations = run.iterations.map_or("Infinity".to_string(), |n| n.to_string());
+    let mut options = format!(
+        "{{ duration: {}, iterations: {}, direction: {:?}, fill: \"both\"",
+        number(run.duration * 1000.0), iterations, run.direction
+    );
+    if run.delay > 0.0 {
+        options.push_str(&format!(", delay: {}", number(run.delay * 1000.0)));
+    }
+    options.push_str(" }");
+    options
+}
+
+/// Clip names may collide with JS keywords (`animate hero.delete`)
+fn function_name(clip: &str) -> String {
+    let name = camel_case(clip);
+    match name.as_str() {
+        "" => "play".to_string(),
+        "delete" | "default" | "function" | "new" | "return" | "switch" | "this" | "var" | "class" => format!("{}Animation", name),
+        _ if name.starts_with(|c: char| c.is_ascii_digit()) => format!("play{}", name),
+        _ => name,
+    }
+}
@@ -0,0 +1,191 @@
+//! Built-in functions
+//!
+//! The analyzer's built-ins, with the semantics of the generated runtimes
+//! (see `codegen::rust`'s prelude).
+
+use crate::error::GrumpResult;
+use super::{error, Interpreter, Value};
+
+/// Built-ins and how many arguments they take
+const BUILTINS: &[(&str, usize)] = &[
+    ("sin", 1), ("cos", 1), ("sqrt", 1), ("abs", 1),
+    ("length", 1), ("normalize", 1), ("dot", 2),
+    ("lerp", 3), ("ease_in_out", 1),
+    ("print", 1), ("random", 2),
+    ("concat", 2), ("substring", 3), ("str_length", 1),
+    ("rgb", 3), ("rgba", 4), ("hsl", 3),
+    ("translate", 2), ("rotate", 1), ("scale", 2),
+    ("now", 0), ("delta_time", 0),
+    ("wait", 1), ("animation_finished", 1), ("event", 1), ("emit", 1),
+    ("spawn", 1), ("despawn", 1),
+    // For tests
+    ("assert", 1), ("assert_eq", 2), ("simulate", 1),
+];
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.iter().any(|(n, _)| *n == name)
+}
+
+/// Call built-in `name`, or `None` if there is no such built-in
+pub fn call(interpreter: &mut Interpreter, name: &str, args: &[Value]) -> Option<GrumpResult<Value>> {
+    let (_, arity) = BUILTINS.iter().find(|(n, _)| *n == name)?;
+    if args.len() != *arity {
+        return Some(Err(error(format!("'{}' takes {} arguments, got {}", name, arity, args.len()))));
+    }
+    Some(dispatch(interpreter, name, args))
+}
+
+fn dispatch(interpreter: &mut Interpreter, name: &str, args: &[Value]) -> GrumpResult<Value> {
+    let number = |index: usize| -> GrumpResult<f64> {
+        args[index].as_number()
+            .ok_or_else(|| error(format!("Argument {} to '{}' must be a number, got {}", index, name, args[index].type_name())))
+    };
+    let text = |index: usize| -> GrumpResult<&str> {
+        match &args[index] {
+            Value::String(s) => Ok(s),
+            other => Err(error(format!("Argument {} to '{}' must be a string, got {}", index, name, other.type_name()))),
+        }
+    };
+    let vector = |index: usize| -> GrumpResult<(f64, f64)> {
+        match &args[index] {
+            Value::Vec2 { x, y } => Ok((*x, *y)),
+            other => Err(error(format!("Argument {} to '{}' must be a vec2, got {}", index, name, other.type_name()))),
+        }
+    };
+    let byte = |index: usize| -> GrumpResult<u8> { Ok(number(index)?.round().clamp(0.0, 255.0) as u8) };
+
+    let value = match name {
+        "sin" => Value::Float(number(0)?.sin()),
+        "cos" => Value::Float(number(0)?.cos()),
+        "sqrt" => Value::Float(number(0)?.sqrt()),
+        "abs" => match &args[0] {
+            Value::Int(n) => Value::Int(n.abs()),
+            _ => Value::Float(number(0)?.abs()),
+        },
+        "length" => {
+            let (x, y) = vector(0)?;
+            Value::Float(x.hypot(y))
+        }
+        "normalize" => {
+            let (x, y) = vector(0)?;
+            let length = x.hypot(y);
+            if length > 0.0 {
+                Value::Vec2 { x: x / length, y: y / length }
+            } else {
+                Value::Vec2 { x: 0.0, y: 0.0 }
+            }
+        }
+        "dot" => {
+            let ((ax, ay), (bx, by)) = (vector(0)?, vector(1)?);
+            Value::Float(ax * bx + ay * by)
+        }
+        "lerp" => {
+            let (a, b, t) = (number(0)?, number(1)?, number(2)?);
+            Value::Float(a + (b - a) * t)
+        }
+        "ease_in_out" => {
+            let t = number(0)?;
+            Value::Float(if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 })
+        }
+        "print" => {
+            interpreter.output.push(args[0].as_text());
+            Value::Unit
+        }
+        "random" => {
+            let (min, max) = (number(0)?, number(1)?);
+            let mut x = interpreter.seed;
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            interpreter.seed = x;
+            Value::Float(min + (x >> 11) as f64 / (1u64 << 53) as f64 * (max - min))
+        }
+        "concat" => Value::String(format!("{}{}", text(0)?, text(1)?)),
+        "substring" => {
+            let (start, end) = (number(1)? as i64, number(2)? as i64);
+            let s: String = text(0)?.chars().skip(start.max(0) as usize).take((end - start).max(0) as usize).collect();
+            Value::String(s)
+        }
+        "str_length" => Value::Int(text(0)?.chars().count() as i64),
+        "rgb" => Value::Color { r: byte(0)?, g: byte(1)?, b: byte(2)?, a: 255 },
+        "rgba" => Value::Color { r: byte(0)?, g: byte(1)?, b: byte(2)?, a: byte(3)? },
+        "hsl" => {
+            let (h, s, l) = (number(0)?, number(1)?, number(2)?);
+            let k = |n: f64| (n + h / 30.0) % 12.0;
+            let a = s * l.min(1.0 - l);
+            let f = |n: f64| l - a * (k(n) - 3.0).min(9.0 - k(n)).clamp(-1.0, 1.0);
+            let channel = |n: f64| (f(n) * 255.0).round().clamp(0.0, 255.0) as u8;
+            Value::Color { r: channel(0.0), g: channel(8.0), b: channel(4.0), a: 255 }
+        }
+        "translate" => transform((number(0)?, number(1)?), 0.0, (1.0, 1.0)),
+        "rotate" => transform((0.0, 0.0), number(0)?, (1.0, 1.0)),
+        "scale" => transform((0.0, 0.0), 0.0, (number(0)?, number(1)?)),
+        "now" => Value::Float(interpreter.time),
+        "delta_time" => Value::Float(interpreter.delta),
+        // Outside a script async code runs to completion, so waiting runs the frames it waits for
+        "wait" => {
+            let frames = (number(0)? / interpreter.delta).ceil().max(0.0) as u64;
+            interpreter.simulate(frames)?;
+            Value::Unit
+        }
+        // Outside a script an `animate` can't be told from its target's
+        // value, so there's nothing to wait for
+        "animation_finished" => Value::Unit,
+        "event" => return Err(error("Only scripts can wait for events; start the function with `Interpreter::start`")),
+        "emit" => {
+            interpreter.emit(text(0)?);
+            Value::Unit
+        }
+        "assert" => match &args[0] {
+            Value::Bool(true) => Value::Unit,
+            Value::Bool(false) => return Err(error("Assertion failed")),
+            other => return Err(error(format!("assert takes a bool, got {}", other.type_name()))),
+        },
+        "assert_eq" => {
+            if !roughly_equal(&args[0], &args[1]) {
+                return Err(error(format!("Assertion failed: {} != {}", args[0], args[1])));
+            }
+            Value::Unit
+        }
+        "simulate" => {
+            let frames = match &args[0] {
+                Value::Int(n) if *n >= 0 => *n as u64,
+                other => return Err(error(format!("simulate takes a frame count, got {}", other))),
+            };
+            interpreter.simulate(frames)?;
+            Value::Unit
+        }
+        "spawn" => {
+            let name = text(0)?.to_string();
+            interpreter.spawn(&name)?
+        }
+        "despawn" => {
+            interpreter.despawn(&args[0])?;
+            Value::Unit
+        }
+        _ => unreachable!("'{}' is listed in BUILTINS", name),
+    };
+    Ok(value)
+}
+
+/// `equals`, except floats only need to agree to within rounding error
+/// (sixty frames of `dt` don't add up to exactly 1.0)
+fn roughly_equal(a: &Value, b: &Value) -> bool {
+    match (a, b, a.as_number(), b.as_number()) {
+        (Value::Float(_), _, Some(x), Some(y)) | (_, Value::Float(_), Some(x), Some(y)) => {
+            (x - y).abs() <= 1e-9 * x.abs().max(y.abs()).max(1.0)
+        }
+        _ => a.equals(b),
+    }
+}
+
+fn transform(position: (f64, f64), rotation: f64, scale: (f64, f64)) -> Value {
+    Value::Struct {
+        name: "Transform".to_string(),
+        fields: vec![
+            ("position".to_string(), Value::Vec2 { x: position.0, y: position.1 }),
+            ("rotation".to_string(), Value::Float(rotation)),
+            ("scale".to_string(), Value::Vec2 { x: scale.0, y: scale.1 }),
+        ],
+    }
+}
@@ -0,0 +1,914 @@
+//! Tree-walking interpreter
+//!
+//! Runs checked code straight from the AST, for the REPL. Values are copied
+//! on assignment, so `hero.position.x = 10.0` updates the binding `hero` in
+//! place. `animate`, `timeline`, network and debugger statements do nothing
+//! here: playing them back is the runtime's job. An `animate` only notes when
+//! it would end, for scripts awaiting `animation_finished`.
+//!
+//! Tests run here too. `simulate(frames)` steps every system over the
+//! entities held in the variables a test can see, so a spawned entity lives
+//! in its binding like any other value, then resumes the scripts that are
+//! due (see `scheduler`).
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::plugin::PluginHost;
+use crate::parser::{
+    AnimationDeclaration, BinaryOp, ComponentDeclaration, EntityDeclaration, Expression, FunctionDeclaration,
+    Item, Literal, Pattern, Program, SceneDeclaration, Statement, SystemDeclaration, TestDeclaration, Type, UnaryOp,
+};
+
+pub mod builtins;
+pub mod scheduler;
+pub mod value;
+
+pub use scheduler::TaskId;
+pub use value::Value;
+
+/// Deepest call nesting before giving up on runaway recursion
+const MAX_DEPTH: usize = 100;
+/// Loop iterations and calls allowed per top-level evaluation
+const MAX_STEPS: u64 = 1_000_000;
+
+/// How a statement finished
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// One step of an assignment target (`hero.position.x`, `items[2]`)
+enum Step {
+    Member(String),
+    Index(Value),
+}
+
+struct Closure {
+    params: Vec<String>,
+    body: Expression,
+    captured: HashMap<String, Value>,
+}
+
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    /// Block scopes of the running function (or top-level block), innermost last
+    locals: Vec<HashMap<String, Value>>,
+    functions: HashMap<String, Rc<FunctionDeclaration>>,
+    components: HashMap<String, Rc<ComponentDeclaration>>,
+    entities: HashMap<String, Rc<EntityDeclaration>>,
+    scenes: HashMap<String, Rc<SceneDeclaration>>,
+    /// In declaration order, which is the order they run each frame
+    systems: Vec<Rc<SystemDeclaration>>,
+    enums: HashMap<String, Vec<String>>,
+    animations: HashMap<String, AnimationDeclaration>,
+    closures: Vec<Rc<Closure>>,
+    /// Lines written by `print`, until taken
+    output: Vec<String>,
+    next_entity: u64,
+    /// Entities taken out of the world, which systems and scripts skip
+    despawned: HashSet<u64>,
+    depth: usize,
+    steps: u64,
+    /// State of `random`'s xorshift generator
+    seed: u64,
+    /// Seconds since start and since the last frame, for `now()` and `delta_time()`
+    time: f64,
+    delta: f64,
+    /// Built-in functions added by plugins
+    plugins: PluginHost,
+    /// Scripts waiting to resume, and the last one's ID
+    tasks: Vec<scheduler::Task>,
+    next_task: TaskId,
+    in_task: bool,
+    /// Events emitted since scripts last resumed, and how many times they have
+    events: Vec<String>,
+    frame: u64,
+    /// When the latest `animate` of each variable or field ends
+    animating: HashMap<String, f64>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            globals: HashMap::new(),
+            locals: Vec::new(),
+            functions: HashMap::new(),
+            components: HashMap::new(),
+            entities: HashMap::new(),
+            scenes: HashMap::new(),
+            systems: Vec::new(),
+            enums: HashMap::new(),
+            animations: HashMap::new(),
+            closures: Vec::new(),
+            output: Vec::new(),
+            next_entity: 0,
+            despawned: HashSet::new(),
+            depth: 0,
+            steps: 0,
+            seed: 0x9E37_79B9_7F4A_7C15,
+            time: 0.0,
+            delta: 1.0 / 60.0,
+            plugins: PluginHost::default(),
+            tasks: Vec::new(),
+            next_task: 0,
+            in_task: false,
+            events: Vec::new(),
+            frame: 0,
+            animating: HashMap::new(),
+        }
+    }
+
+    /// Declare every item in the program
+    pub fn load(&mut self, program: &Program) {
+        for item in &program.items {
+            self.declare(item);
+        }
+    }
+
+    /// Make an item callable, spawnable or playable; apps and modules
+    /// declare their contents
+    pub fn declare(&mut self, item: &Item) {
+        match item {
+            Item::App(app) => app.body.iter().for_each(|item| self.declare(item)),
+            Item::Module(module) => module.items.iter().for_each(|item| self.declare(item)),
+            Item::Function(func) => {
+                self.functions.insert(func.name.clone(), Rc::new(func.clone()));
+            }
+            Item::Component(comp) => {
+                self.components.insert(comp.name.clone(), Rc::new(comp.clone()));
+            }
+            Item::Entity(entity) => {
+                self.entities.insert(entity.name.clone(), Rc::new(entity.clone()));
+            }
+            Item::Scene(scene) => {
+                self.scenes.insert(scene.name.clone(), Rc::new(scene.clone()));
+            }
+            Item::System(system) => {
+                self.systems.retain(|s| s.name != system.name);
+                self.systems.push(Rc::new(system.clone()));
+            }
+            Item::Enum(decl) => {
+                self.enums.insert(decl.name.clone(), decl.variants.clone());
+            }
+            Item::Animation(anim) => {
+                self.animations.insert(anim.name.clone(), anim.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Make the plugins' built-in functions callable
+    pub fn use_plugins(&mut self, plugins: PluginHost) {
+        self.plugins = plugins;
+    }
+
+    /// Whether `name` is a declared function
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&AnimationDeclaration> {
+        self.animations.get(name)
+    }
+
+    pub fn has_entity(&self, name: &str) -> bool {
+        self.entities.contains_key(name)
+    }
+
+    /// A top-level variable
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Bind (or rebind) a top-level variable
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.to_string(), value);
+    }
+
+    /// Set the clock `now()` and `delta_time()` read
+    pub fn set_clock(&mut self, time: f64, delta: f64) {
+        self.time = time;
+        self.delta = delta;
+    }
+
+    /// Lines printed since the last call
+    pub fn take_output(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Run a top-level statement. Expression statements produce their value,
+    /// other statements `Value::Unit`.
+    pub fn execute(&mut self, stmt: &Statement) -> GrumpResult<Value> {
+        self.steps = 0;
+        if let Statement::Expression(expr, _) = stmt {
+            return self.eval(expr);
+        }
+        match self.exec(stmt)? {
+            Flow::Next => Ok(Value::Unit),
+            Flow::Return(value) => Ok(value),
+            Flow::Break | Flow::Continue => Err(error("`break` and `continue` only work inside loops")),
+        }
+    }
+
+    /// Evaluate an expression in the top-level scope
+    pub fn evaluate(&mut self, expr: &Expression) -> GrumpResult<Value> {
+        self.steps = 0;
+        self.eval(expr)
+    }
+
+    /// Call a declared or built-in function
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> GrumpResult<Value> {
+        self.steps = 0;
+        self.call_named(name, args)
+    }
+
+    /// Run a test's body in a frame of its own; a failed assertion is an error
+    pub fn run_test(&mut self, test: &TestDeclaration) -> GrumpResult<()> {
+        self.steps = 0;
+        self.invoke(HashMap::new(), |interpreter| {
+            interpreter.run(&test.body)?;
+            Ok(Value::Unit)
+        })?;
+        Ok(())
+    }
+
+    /// Advance the clock `frames` frames, running every system once per
+    /// frame over each visible entity that has the components it queries,
+    /// then the scripts that are due
+    pub fn simulate(&mut self, frames: u64) -> GrumpResult<()> {
+        for _ in 0..frames {
+            for system in self.systems.clone() {
+                for name in self.entity_bindings() {
+                    self.tick()?;
+                    self.run_system(&system, &name)?;
+                }
+            }
+            self.time += self.delta;
+            self.run_tasks()?;
+        }
+        Ok(())
+    }
+
+    /// Visible variables holding entities that haven't been despawned,
+    /// innermost first
+    fn entity_bindings(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for scope in self.locals.iter().rev().chain(std::iter::once(&self.globals)) {
+            for (name, value) in scope {
+                let live = matches!(value, Value::Entity { id, .. } if !self.despawned.contains(id));
+                if live && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+
+    /// Run `system` on the entity in variable `name`, with its queried
+    /// components bound by lowercase name and `dt` the frame's length
+    fn run_system(&mut self, system: &SystemDeclaration, name: &str) -> GrumpResult<()> {
+        let components = match self.variable_mut(name) {
+            Some(Value::Entity { components, .. }) => components.clone(),
+            _ => return Ok(()),
+        };
+        let has = |wanted: &String| components.iter().any(|(n, _)| n == wanted);
+        if !system.query.iter().all(has) {
+            return Ok(());
+        }
+
+        let mut scope: HashMap<String, Value> = components.iter()
+            .filter(|(n, _)| system.query.contains(n))
+            .map(|(n, v)| (n.to_lowercase(), v.clone()))
+            .collect();
+        scope.insert("dt".to_string(), Value::Float(self.delta));
+        let saved = std::mem::replace(&mut self.locals, vec![scope]);
+        let result = self.run(&system.body);
+        let mut scope = std::mem::replace(&mut self.locals, saved).pop().unwrap_or_default();
+        result?;
+
+        if let Some(Value::Entity { components, .. }) = self.variable_mut(name) {
+            for (component, value) in components.iter_mut().filter(|(n, _)| system.query.contains(n)) {
+                if let Some(updated) = scope.remove(&component.to_lowercase()) {
+                    *value = updated;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an instance of a declared entity: its components are built from
+    /// their declarations and arguments, then its body runs with each
+    /// component bound by its lowercase name
+    pub fn spawn(&mut self, name: &str) -> GrumpResult<Value> {
+        self.steps = 0;
+        let entity = self.entities.get(name).cloned()
+            .ok_or_else(|| error(format!("Undefined entity: {}", name)))?;
+        let mut components = Vec::new();
+        for instance in &entity.components {
+            let args = instance.args.iter().map(|arg| self.eval(arg)).collect::<GrumpResult<Vec<_>>>()?;
+            components.push((instance.name.clone(), self.instantiate(&instance.name, args)?));
+        }
+
+        let scope = components.iter().map(|(n, v)| (n.to_lowercase(), v.clone())).collect();
+        let saved = std::mem::replace(&mut self.locals, vec![scope]);
+        let result = self.run(&entity.body);
+        let mut scope = std::mem::replace(&mut self.locals, saved).pop().unwrap_or_default();
+        result?;
+        for (name, value) in &mut components {
+            if let Some(updated) = scope.remove(&name.to_lowercase()) {
+                *value = updated;
+            }
+        }
+
+        self.next_entity += 1;
+        Ok(Value::Entity { id: self.next_entity, name: name.to_string(), components })
+    }
+
+    /// A component with its fields' defaults, overridden in order by `args`.
+    /// Undeclared components (`sprite: "hero.png"`) are just their arguments.
+    fn instantiate(&mut self, name: &str, mut args: Vec<Value>) -> GrumpResult<Value> {
+        let comp = match self.components.get(name).cloned() {
+            Some(comp) => comp,
+            None if args.len() == 1 => return Ok(args.remove(0)),
+            None => return Ok(Value::Tuple(args)),
+        };
+        if args.len() > comp.fields.len() {
+            return Err(error(format!("{} has {} fields, got {} values", name, comp.fields.len(), args.len())));
+        }
+        let mut args = args.into_iter();
+        let mut fields = Vec::new();
+        for field in &comp.fields {
+            let value = match (args.next(), &field.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.eval(default)?,
+                (None, None) => default_value(&field.type_),
+            };
+            fields.push((field.name.clone(), value));
+        }
+        Ok(Value::Struct { name: name.to_string(), fields })
+    }
+
+    fn run(&mut self, body: &[Statement]) -> GrumpResult<Flow> {
+        for stmt in body {
+            match self.exec(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Run `body` in a new block scope (with `bindings` in it)
+    fn block(&mut self, body: &[Statement], bindings: HashMap<String, Value>) -> GrumpResult<Flow> {
+        self.locals.push(bindings);
+        let result = self.run(body);
+        self.locals.pop();
+        result
+    }
+
+    fn exec(&mut self, stmt: &Statement) -> GrumpResult<Flow> {
+        match stmt {
+            Statement::Let { name, value, .. } => {
+                let value = self.eval(value)?;
+                self.bind(name, value);
+            }
+            Statement::Assign { target, value, .. } => {
+                let value = self.eval(value)?;
+                self.assign(target, value)?;
+            }
+            Statement::If { condition, then, else_, .. } => {
+                if self.condition(condition)? {
+                    return self.block(then, HashMap::new());
+                } else if let Some(else_) = else_ {
+                    return self.block(else_, HashMap::new());
+                }
+            }
+            Statement::Match { expr, arms, .. } => {
+                let value = self.eval(expr)?;
+                for arm in arms {
+                    let mut bindings = HashMap::new();
+                    if !self.matches(&arm.pattern, &value, &mut bindings) {
+                        continue;
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.locals.push(bindings.clone());
+                        let passed = self.condition(guard);
+                        self.locals.pop();
+                        if !passed? {
+                            continue;
+                        }
+                    }
+                    return self.block(&arm.body, bindings);
+                }
+            }
+            Statement::For { var, iter, body, .. } => {
+                let items = match self.eval(iter)? {
+                    Value::List(items) | Value::Tuple(items) => items,
+                    Value::String(s) => s.chars().map(Value::Char).collect(),
+                    other => return Err(error(format!("Cannot iterate over {}", other.type_name()))),
+                };
+                for item in items {
+                    self.tick()?;
+                    match self.block(body, HashMap::from([(var.clone(), item)]))? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                while self.condition(condition)? {
+                    self.tick()?;
+                    match self.block(body, HashMap::new())? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Statement::Return(expr, _) => {
+                let value = match expr {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Unit,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Expression(expr, _) => {
+                self.eval(expr)?;
+            }
+            Statement::Await { expr } => {
+                self.eval(expr)?;
+            }
+            Statement::Animate(animate) => self.track_animation(animate)?,
+            Statement::Timeline { .. } | Statement::Debugger(_) | Statement::Network(_) => {}
+        }
+        Ok(Flow::Next)
+    }
+
+    fn eval(&mut self, expr: &Expression) -> GrumpResult<Value> {
+        match expr {
+            Expression::Literal(lit) => Ok(literal(lit)),
+            Expression::Identifier(name) => self.lookup(name),
+            Expression::Binary { op: BinaryOp::And, left, right } => {
+                Ok(Value::Bool(self.condition(left)? && self.condition(right)?))
+            }
+            Expression::Binary { op: BinaryOp::Or, left, right } => {
+                Ok(Value::Bool(self.condition(left)? || self.condition(right)?))
+            }
+            Expression::Binary { op, left, right } => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                binary(op, left, right)
+            }
+            Expression::Unary { op, expr } => {
+                let value = self.eval(expr)?;
+                unary(op, value)
+            }
+            Expression::Call { func, args } => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<GrumpResult<Vec<_>>>()?;
+                match func.as_ref() {
+                    // Variables holding functions shadow declarations of the same name
+                    Expression::Identifier(name) if self.local(name).is_none() && !self.globals.contains_key(name) => {
+                        self.call_named(name, args)
+                    }
+                    other => {
+                        let callee = self.eval(other)?;
+                        self.call_value(callee, args)
+                    }
+                }
+            }
+            Expression::Member { object, member } => {
+                let object = self.eval(object)?;
+                self::member(&object, member, &self.enums)
+            }
+            Expression::Index { object, index } => {
+                let object = self.eval(object)?;
+                let index = self.eval(index)?;
+                match object {
+                    Value::List(items) | Value::Tuple(items) => Ok(items[position(&index, items.len())?].clone()),
+                    Value::String(s) => {
+                        let chars: Vec<char> = s.chars().collect();
+                        Ok(Value::Char(chars[position(&index, chars.len())?]))
+                    }
+                    other => Err(error(format!("Cannot index {}", other.type_name()))),
+                }
+            }
+            Expression::Tuple(items) => Ok(Value::Tuple(self.eval_all(items)?)),
+            Expression::Array(items) => Ok(Value::List(self.eval_all(items)?)),
+            // Async blocks run to completion right away
+            Expression::Block(body) | Expression::AsyncBlock(body) => {
+                let (last, rest) = match body.split_last() {
+                    Some((Statement::Expression(last, _), rest)) => (Some(last), rest),
+                    _ => (None, body.as_slice()),
+                };
+                self.locals.push(HashMap::new());
+                let result = self.run(rest).and_then(|flow| match (flow, last) {
+                    (Flow::Return(value), _) => Ok(value),
+                    (_, Some(last)) => self.eval(last),
+                    (_, None) => Ok(Value::Unit),
+                });
+                self.locals.pop();
+                result
+            }
+            Expression::If { condition, then, else_ } => {
+                if self.condition(condition)? {
+                    self.eval(then)
+                } else {
+                    self.eval(else_)
+                }
+            }
+            Expression::Lambda { params, body } => {
+                let mut captured = self.globals.clone();
+                for scope in &self.locals {
+                    captured.extend(scope.iter().map(|(n, v)| (n.clone(), v.clone())));
+                }
+                let params = params.iter().map(|p| p.name.clone()).collect();
+                self.closures.push(Rc::new(Closure { params, body: body.as_ref().clone(), captured }));
+                Ok(Value::Lambda(self.closures.len() - 1))
+            }
+            Expression::Await(expr) => self.eval(expr),
+            Expression::MacroCall { name, .. } => Err(error(format!("Macro {}! must be expanded before it can run; template macros only run in generated code", name))),
+        }
+    }
+
+    fn eval_all(&mut self, exprs: &[Expression]) -> GrumpResult<Vec<Value>> {
+        exprs.iter().map(|expr| self.eval(expr)).collect()
+    }
+
+    fn condition(&mut self, expr: &Expression) -> GrumpResult<bool> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(error(format!("Expected a bool condition, got {}", other.type_name()))),
+        }
+    }
+
+    fn call_named(&mut self, name: &str, args: Vec<Value>) -> GrumpResult<Value> {
+        if let Some(func) = self.functions.get(name).cloned() {
+            if func.params.len() != args.len() {
+                return Err(error(format!("'{}' takes {} arguments, got {}", name, func.params.len(), args.len())));
+            }
+            let scope = func.params.iter().map(|p| p.name.clone()).zip(args).collect();
+            return self.invoke(scope, |interpreter| match interpreter.run(&func.body)? {
+                Flow::Return(value) => Ok(value),
+                _ => Ok(Value::Unit),
+            });
+        }
+        match builtins::call(self, name, &args).or_else(|| self.plugins.call(name, &args)) {
+            Some(result) => result,
+            None => Err(error(format!("Undefined function: {}", name))),
+        }
+    }
+
+    fn call_value(&mut self, callee: Value, args: Vec<Value>) -> GrumpResult<Value> {
+        match callee {
+            Value::Function(name) => self.call_named(&name, args),
+            Value::Lambda(index) => {
+                let closure = self.closures[index].clone();
+                if closure.params.len() != args.len() {
+                    return Err(error(format!("Lambda takes {} arguments, got {}", closure.params.len(), args.len())));
+                }
+                let mut scope = closure.captured.clone();
+                scope.extend(closure.params.iter().cloned().zip(args));
+                self.invoke(scope, |interpreter| interpreter.eval(&closure.body))
+            }
+            other => Err(error(format!("Cannot call {}", other.type_name()))),
+        }
+    }
+
+    /// Run `f` in a fresh call frame holding `scope`
+    fn invoke(&mut self, scope: HashMap<String, Value>, f: impl FnOnce(&mut Self) -> GrumpResult<Value>) -> GrumpResult<Value> {
+        if self.depth >= MAX_DEPTH {
+            return Err(error("Stack overflow: too much recursion"));
+        }
+        self.tick()?;
+        let saved = std::mem::replace(&mut self.locals, vec![scope]);
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        self.locals = saved;
+        result
+    }
+
+    fn tick(&mut self) -> GrumpResult<()> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(error(format!("Gave up after {} steps (infinite loop?)", MAX_STEPS)));
+        }
+        Ok(())
+    }
+
+    fn local(&self, name: &str) -> Option<&Value> {
+        self.locals.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup(&self, name: &str) -> GrumpResult<Value> {
+        if let Some(value) = self.local(name).or_else(|| self.globals.get(name)) {
+            return Ok(value.clone());
+        }
+        if self.functions.contains_key(name) || builtins::is_builtin(name) || self.plugins.has_function(name) {
+            return Ok(Value::Function(name.to_string()));
+        }
+        if self.enums.contains_key(name) {
+            return Ok(Value::Enum(name.to_string()));
+        }
+        Err(error(format!("Undefined variable: {}", name)))
+    }
+
+    /// `let`: into the innermost block, or a global at top level
+    fn bind(&mut self, name: &str, value: Value) {
+        match self.locals.last_mut() {
+            Some(scope) => scope.insert(name.to_string(), value),
+            None => self.globals.insert(name.to_string(), value),
+        };
+    }
+
+    fn variable_mut(&mut self, name: &str) -> Option<&mut Value> {
+        for scope in self.locals.iter_mut().rev() {
+            if let Some(value) = scope.get_mut(name) {
+                return Some(value);
+            }
+        }
+        self.globals.get_mut(name)
+    }
+
+    fn assign(&mut self, target: &Expression, value: Value) -> GrumpResult<()> {
+        let mut steps = Vec::new();
+        let mut current = target;
+        let root = loop {
+            match current {
+                Expression::Identifier(name) => break name,
+                Expression::Member { object, member } => {
+                    steps.push(Step::Member(member.clone()));
+                    current = object;
+                }
+                Expression::Index { object, index } => {
+                    steps.push(Step::Index(self.eval(index)?));
+                    current = object;
+                }
+                _ => return Err(error("Can only assign to variables, fields and list elements")),
+            }
+        };
+        steps.reverse();
+        let slot = self.variable_mut(root).ok_or_else(|| error(format!("Undefined variable: {}", root)))?;
+        store(slot, &steps, value)
+    }
+
+    /// Whether `value` matches `pattern`, collecting the names it binds
+    fn matches(&self, pattern: &Pattern, value: &Value, bindings: &mut HashMap<String, Value>) -> bool {
+        match pattern {
+            Pattern::Wildcard => true,
+            Pattern::Literal(lit) => literal(lit).equals(value),
+            Pattern::Identifier(name) => {
+                if let Some(enum_name) = self.enums.iter().find(|(_, variants)| variants.contains(name)).map(|(e, _)| e) {
+                    return *value == Value::Variant { enum_name: enum_name.clone(), variant: name.clone() };
+                }
+                bindings.insert(name.clone(), value.clone());
+                true
+            }
+            Pattern::Tuple(patterns) => match value {
+                Value::Tuple(items) | Value::List(items) => {
+                    items.len() == patterns.len()
+                        && patterns.iter().zip(items).all(|(p, v)| self.matches(p, v, bindings))
+                }
+                _ => false,
+            },
+            Pattern::Struct { name, fields } => match value {
+                Value::Struct { name: actual, fields: values } if actual == name => fields.iter().all(|(field, p)| {
+                    values.iter().find(|(n, _)| n == field).is_some_and(|(_, v)| self.matches(p, v, bindings))
+                }),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn error(message: impl Into<String>) -> GrumpError {
+    GrumpError::Runtime { message: message.into() }
+}
+
+fn literal(lit: &Literal) -> Value {
+    match lit {
+        Literal::Integer(n) => Value::Int(*n),
+        Literal::Float(f) => Value::Float(*f),
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Char(c) => Value::Char(*c),
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::Color { r, g, b, a } => Value::Color { r: *r, g: *g, b: *b, a: *a },
+        Literal::Vec2 { x, y } => Value::Vec2 { x: *x, y: *y },
+        Literal::Vec3 { x, y, z } => Value::Vec3 { x: *x, y: *y, z: *z },
+        Literal::Duration { value, unit } if unit == "ms" => Value::Duration(value / 1000.0),
+        Literal::Duration { value, .. } => Value::Duration(*value),
+        Literal::Angle { value, unit } if unit == "rad" => Value::Angle(value.to_degrees()),
+        Literal::Angle { value, unit } if unit == "turn" => Value::Angle(value * 360.0),
+        Literal::Angle { value, .. } => Value::Angle(*value),
+    }
+}
+
+/// Zero value of a component field without a default
+fn default_value(type_: &Type) -> Value {
+    match type_ {
+        Type::Int | Type::Int64 => Value::Int(0),
+        Type::Float | Type::Double => Value::Float(0.0),
+        Type::Bool => Value::Bool(false),
+        Type::String => Value::String(String::new()),
+        Type::Char => Value::Char('\0'),
+        Type::Vec2 => Value::Vec2 { x: 0.0, y: 0.0 },
+        Type::Vec3 | Type::Vec4 => Value::Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Type::Color => Value::Color { r: 0, g: 0, b: 0, a: 255 },
+        Type::Angle | Type::Rotation => Value::Angle(0.0),
+        Type::Duration => Value::Duration(0.0),
+        Type::Array(_) => Value::List(Vec::new()),
+        Type::Tuple(types) => Value::Tuple(types.iter().map(default_value).collect()),
+        Type::Transform | Type::Optional(_) | Type::Result { .. } | Type::Named(_) => Value::Unit,
+    }
+}
+
+fn binary(op: &BinaryOp, left: Value, right: Value) -> GrumpResult<Value> {
+    use Value::*;
+    let mismatch = |left: &Value, right: &Value| {
+        error(format!("Cannot apply {:?} to {} and {}", op, left.type_name(), right.type_name()))
+    };
+    match op {
+        BinaryOp::Eq => return Ok(Bool(left.equals(&right))),
+        BinaryOp::Ne => return Ok(Bool(!left.equals(&right))),
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge => {
+            let ordering = match (&left, &right) {
+                (String(a), String(b)) => a.partial_cmp(b),
+                _ => match (left.as_number(), right.as_number()) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => return Err(mismatch(&left, &right)),
+                },
+            };
+            let result = ordering.is_some_and(|o| match op {
+                BinaryOp::Lt => o.is_lt(),
+                BinaryOp::Gt => o.is_gt(),
+                BinaryOp::Le => o.is_le(),
+                _ => o.is_ge(),
+            });
+            return Ok(Bool(result));
+        }
+        _ => {}
+    }
+    match (&left, &right) {
+        (Int(a), Int(b)) => {
+            let (a, b) = (*a, *b);
+            let result = match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Sub => a.checked_sub(b),
+                BinaryOp::Mul => a.checked_mul(b),
+                BinaryOp::Div if b == 0 => return Err(error("Division by zero")),
+                BinaryOp::Div => a.checked_div(b),
+                BinaryOp::Mod if b == 0 => return Err(error("Division by zero")),
+                BinaryOp::Mod => a.checked_rem(b),
+                BinaryOp::ShiftLeft => a.checked_shl(b as u32),
+                BinaryOp::ShiftRight => a.checked_shr(b as u32),
+                BinaryOp::Xor => Some(a ^ b),
+                _ => return Err(mismatch(&left, &right)),
+            };
+            result.map(Int).ok_or_else(|| error(format!("Integer overflow in {:?}", op)))
+        }
+        (Bool(a), Bool(b)) if matches!(op, BinaryOp::Xor) => Ok(Bool(a ^ b)),
+        (Vec2 { x: ax, y: ay }, Vec2 { x: bx, y: by }) => match (arithmetic(op, *ax, *bx), arithmetic(op, *ay, *by)) {
+            (Some(x), Some(y)) => Ok(Vec2 { x, y }),
+            _ => Err(mismatch(&left, &right)),
+        },
+        (Vec3 { x: ax, y: ay, z: az }, Vec3 { x: bx, y: by, z: bz }) => {
+            match (arithmetic(op, *ax, *bx), arithmetic(op, *ay, *by), arithmetic(op, *az, *bz)) {
+                (Some(x), Some(y), Some(z)) => Ok(Vec3 { x, y, z }),
+                _ => Err(mismatch(&left, &right)),
+            }
+        }
+        _ => match (left.as_number(), right.as_number()) {
+            (Some(a), Some(b)) => arithmetic(op, a, b).map(Float).ok_or_else(|| mismatch(&left, &right)),
+            _ => Err(mismatch(&left, &right)),
+        },
+    }
+}
+
+fn arithmetic(op: &BinaryOp, a: f64, b: f64) -> Option<f64> {
+    match op {
+        BinaryOp::Add => Some(a + b),
+        BinaryOp::Sub => Some(a - b),
+        BinaryOp::Mul => Some(a * b),
+        BinaryOp::Div => Some(a / b),
+        BinaryOp::Mod => Some(a % b),
+        _ => None,
+    }
+}
+
+fn unary(op: &UnaryOp, value: Value) -> GrumpResult<Value> {
+    match (op, value) {
+        (UnaryOp::Neg, Value::Int(n)) => n.checked_neg().map(Value::Int).ok_or_else(|| error("Integer overflow in Neg")),
+        (UnaryOp::Neg, Value::Float(f)) => Ok(Value::Float(-f)),
+        (UnaryOp::Neg, Value::Duration(f)) => Ok(Value::Duration(-f)),
+        (UnaryOp::Neg, Value::Angle(f)) => Ok(Value::Angle(-f)),
+        (UnaryOp::Neg, Value::Vec2 { x, y }) => Ok(Value::Vec2 { x: -x, y: -y }),
+        (UnaryOp::Neg, Value::Vec3 { x, y, z }) => Ok(Value::Vec3 { x: -x, y: -y, z: -z }),
+        (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        // References are plain copies here
+        (UnaryOp::Deref | UnaryOp::Ref | UnaryOp::MutRef, value) => Ok(value),
+        (op, value) => Err(error(format!("Cannot apply {:?} to {}", op, value.type_name()))),
+    }
+}
+
+fn member(object: &Value, member: &str, enums: &HashMap<String, Vec<String>>) -> GrumpResult<Value> {
+    let found = match object {
+        Value::Enum(name) => enums.get(name)
+            .filter(|variants| variants.iter().any(|v| v == member))
+            .map(|_| Value::Variant { enum_name: name.clone(), variant: member.to_string() }),
+        Value::Struct { fields, .. } => fields.iter().find(|(n, _)| n == member).map(|(_, v)| v.clone()),
+        Value::Entity { components, .. } => components.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(member))
+            .map(|(_, v)| v.clone()),
+        Value::Color { r, g, b, a } => match member {
+            "r" => Some(Value::Int(*r as i64)),
+            "g" => Some(Value::Int(*g as i64)),
+            "b" => Some(Value::Int(*b as i64)),
+            "a" => Some(Value::Int(*a as i64)),
+            _ => None,
+        },
+        Value::List(items) if member == "length" => Some(Value::Int(items.len() as i64)),
+        vector => channel(vector, member).map(Value::Float),
+    };
+    found.ok_or_else(|| error(format!("{} has no member '{}'", object.type_name(), member)))
+}
+
+/// `x`, `y` or `z` of a vector
+fn channel(value: &Value, member: &str) -> Option<f64> {
+    match (value, member) {
+        (Value::Vec2 { x, .. } | Value::Vec3 { x, .. }, "x") => Some(*x),
+        (Value::Vec2 { y, .. } | Value::Vec3 { y, .. }, "y") => Some(*y),
+        (Value::Vec3 { z, .. }, "z") => Some(*z),
+        _ => None,
+    }
+}
+
+fn channel_mut<'a>(value: &'a mut Value, member: &str) -> Option<&'a mut f64> {
+    match (value, member) {
+        (Value::Vec2 { x, .. } | Value::Vec3 { x, .. }, "x") => Some(x),
+        (Value::Vec2 { y, .. } | Value::Vec3 { y, .. }, "y") => Some(y),
+        (Value::Vec3 { z, .. }, "z") => Some(z),
+        _ => None,
+    }
+}
+
+/// Write `value` at `steps` below `slot`
+fn store(slot: &mut Value, steps: &[Step], value: Value) -> GrumpResult<()> {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => {
+            *slot = value;
+            return Ok(());
+        }
+    };
+    let type_name = slot.type_name();
+    match step {
+        Step::Member(member) => {
+            if rest.is_empty() {
+                if let Some(channel) = channel_mut(slot, member) {
+                    *channel = value.as_number()
+                        .ok_or_else(|| error(format!("Cannot store {} in {}.{}", value.type_name(), type_name, member)))?;
+                    return Ok(());
+                }
+            }
+            let field = match slot {
+                Value::Struct { fields, .. } => fields.iter_mut().find(|(n, _)| n == member).map(|(_, v)| v),
+                Value::Entity { components, .. } => components.iter_mut()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(member))
+                    .map(|(_, v)| v),
+                _ => None,
+            };
+            let field = field.ok_or_else(|| error(format!("{} has no member '{}'", type_name, member)))?;
+            store(field, rest, value)
+        }
+        Step::Index(index) => match slot {
+            Value::List(items) => {
+                let position = position(index, items.len())?;
+                store(&mut items[position], rest, value)
+            }
+            _ => Err(error(format!("Cannot index {}", type_name))),
+        },
+    }
+}
+
+/// A checked list index
+fn position(index: &Value, len: usize) -> GrumpResult<usize> {
+    match index {
+        Value::Int(i) if *i >= 0 && (*i as usize) < len => Ok(*i as usize),
+        Value::Int(i) => Err(error(format!("Index {} out of bounds for length {}", i, len))),
+        other => Err(error(format!("Index must be int, got {}", other.type_name()))),
+    }
+}
@@ -0,0 +1,453 @@
+//! Scripts
+//!
+//! `start` runs an async function as a script that `simulate` resumes once
+//! per frame, so cutscenes and other long sequences interleave with the
+//! systems and with each other. A script keeps its own stack of blocks
+//! instead of the Rust stack, so it can suspend at a statement-level
+//! `await` (`await wait(1s);`, `let x = await f();`, `x = await f();`)
+//! anywhere in its function or in `if`, `while` and `for` bodies,
+//! including in the async functions it awaits. Other `await`s run to
+//! completion.
+//!
+//! A scene's body runs as a script too, started by `enter`, so a cutscene
+//! is just a scene that awaits.
+//!
+//! A script started for an entity is cancelled when the entity is
+//! despawned, or once no visible variable holds it.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::GrumpResult;
+use crate::parser::{AnimateStatement, Expression, Statement};
+use super::{error, Flow, Interpreter, Value};
+
+/// Script ID
+pub type TaskId = u64;
+
+/// How far the clock can drift below a wait's end and still count, since
+/// summing frame times rounds
+const EPSILON: f64 = 1e-9;
+
+pub(super) struct Task {
+    id: TaskId,
+    /// Entity whose disappearance cancels the script
+    owner: Option<u64>,
+    frames: Vec<Frame>,
+    /// Block scopes, innermost last; in `Interpreter::locals` while it runs
+    locals: Vec<HashMap<String, Value>>,
+    waiting: Option<Wait>,
+}
+
+/// A block the script is part way through
+struct Frame {
+    body: Rc<Vec<Statement>>,
+    next: usize,
+    kind: FrameKind,
+}
+
+enum FrameKind {
+    /// An `if` branch, in a scope of its own
+    Block,
+    While(Expression),
+    /// Items still to come, last first
+    For { var: String, rest: Vec<Value> },
+    /// A function body, which has the locals to itself; returning restores
+    /// the caller's and stores the result in `result`
+    Call { caller: Vec<HashMap<String, Value>>, result: Option<Store> },
+}
+
+/// Where an awaited value goes
+#[derive(Clone)]
+enum Store {
+    Let(String),
+    Assign(Expression),
+}
+
+enum Wait {
+    Until(f64),
+    Animation(String),
+    /// An event, and the frame the wait began in; events delivered that
+    /// frame went out before it
+    Event(String, u64),
+}
+
+impl Interpreter {
+    /// Start function `name` as a script; it runs up to its first
+    /// suspension right away
+    pub fn start(&mut self, name: &str, args: Vec<Value>) -> GrumpResult<TaskId> {
+        self.start_task(None, name, args)
+    }
+
+    /// Start a script that's cancelled when `entity` is gone
+    pub fn start_for(&mut self, entity: &Value, name: &str, args: Vec<Value>) -> GrumpResult<TaskId> {
+        let Value::Entity { id, .. } = entity else {
+            return Err(error(format!("Scripts belong to entities, not to {}", entity.type_name())));
+        };
+        self.start_task(Some(*id), name, args)
+    }
+
+    /// Run scene `name`'s body as a script, in a scope of its own
+    pub fn enter(&mut self, name: &str) -> GrumpResult<TaskId> {
+        let scene = self.scenes.get(name).cloned()
+            .ok_or_else(|| error(format!("Undefined scene: {}", name)))?;
+        self.spawn_task(None, |this, task| {
+            this.locals.push(HashMap::new());
+            task.frames.push(Frame {
+                body: Rc::new(scene.body.clone()),
+                next: 0,
+                kind: FrameKind::Call { caller: Vec::new(), result: None },
+            });
+            Ok(())
+        })
+    }
+
+    /// Deliver an event to the scripts waiting for it, on the next frame
+    pub fn emit(&mut self, event: &str) {
+        self.events.push(event.to_string());
+    }
+
+    /// Stop a script; it won't resume again
+    pub fn cancel(&mut self, id: TaskId) {
+        self.tasks.retain(|task| task.id != id);
+    }
+
+    /// Take `entity` out of the world: systems skip it from now on, and its
+    /// scripts are cancelled
+    pub fn despawn(&mut self, entity: &Value) -> GrumpResult<()> {
+        let Value::Entity { id, .. } = entity else {
+            return Err(error(format!("Only entities can be despawned, not {}", entity.type_name())));
+        };
+        self.despawned.insert(*id);
+        self.tasks.retain(|task| task.owner != Some(*id));
+        Ok(())
+    }
+
+    pub fn is_running(&self, id: TaskId) -> bool {
+        self.tasks.iter().any(|task| task.id == id)
+    }
+
+    fn start_task(&mut self, owner: Option<u64>, name: &str, args: Vec<Value>) -> GrumpResult<TaskId> {
+        self.spawn_task(owner, |this, task| this.call_frame(task, name, args, None))
+    }
+
+    /// A script with the frame `begin` pushes, run up to its first suspension
+    fn spawn_task(
+        &mut self,
+        owner: Option<u64>,
+        begin: impl FnOnce(&mut Self, &mut Task) -> GrumpResult<()>,
+    ) -> GrumpResult<TaskId> {
+        self.steps = 0;
+        self.next_task += 1;
+        let id = self.next_task;
+        let mut task = Task { id, owner, frames: Vec::new(), locals: Vec::new(), waiting: None };
+        let saved = std::mem::take(&mut self.locals);
+        let result = begin(self, &mut task).and_then(|()| self.resume(&mut task, &[]));
+        task.locals = std::mem::replace(&mut self.locals, saved);
+        if !result? {
+            self.tasks.push(task);
+        }
+        Ok(id)
+    }
+
+    /// Resume every script whose wait is over, with the events emitted
+    /// since the last frame. Scripts of entities that are gone are dropped.
+    pub(super) fn run_tasks(&mut self) -> GrumpResult<()> {
+        // A script's `simulate` doesn't resume the scripts again
+        if self.in_task {
+            return Ok(());
+        }
+        let live: Vec<u64> = self.entity_bindings().iter()
+            .filter_map(|name| match self.lookup(name) {
+                Ok(Value::Entity { id, .. }) => Some(id),
+                _ => None,
+            })
+            .collect();
+        self.frame += 1;
+        let fired = std::mem::take(&mut self.events);
+        let mut pending = std::mem::take(&mut self.tasks).into_iter()
+            .filter(|task| task.owner.is_none_or(|owner| live.contains(&owner)));
+
+        let saved = std::mem::take(&mut self.locals);
+        self.in_task = true;
+        let mut result = Ok(());
+        let mut kept = Vec::new();
+        for mut task in pending.by_ref() {
+            // Despawned by a script that ran before it this frame
+            if task.owner.is_some_and(|owner| self.despawned.contains(&owner)) {
+                continue;
+            }
+            self.steps = 0;
+            self.locals = std::mem::take(&mut task.locals);
+            let finished = self.resume(&mut task, &fired);
+            task.locals = std::mem::take(&mut self.locals);
+            match finished {
+                Ok(true) => {}
+                Ok(false) => kept.push(task),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.in_task = false;
+        self.locals = saved;
+        // Scripts started by scripts run from the next frame
+        kept.extend(pending);
+        kept.append(&mut self.tasks);
+        kept.retain(|task| !task.owner.is_some_and(|owner| self.despawned.contains(&owner)));
+        self.tasks = kept;
+        result
+    }
+
+    /// Run `task` until it suspends (`false`) or returns (`true`)
+    fn resume(&mut self, task: &mut Task, fired: &[String]) -> GrumpResult<bool> {
+        loop {
+            if let Some(wait) = &task.waiting {
+                let ready = match wait {
+                    Wait::Until(end) => self.time + EPSILON >= *end,
+                    Wait::Animation(key) => self.animating.get(key).is_none_or(|end| self.time + EPSILON >= *end),
+                    Wait::Event(name, since) => *since < self.frame && fired.contains(name),
+                };
+                if !ready {
+                    return Ok(false);
+                }
+                task.waiting = None;
+            }
+            let Some(frame) = task.frames.last_mut() else {
+                return Ok(true);
+            };
+            if frame.next == frame.body.len() {
+                self.end_frame(task)?;
+                continue;
+            }
+            let body = frame.body.clone();
+            let stmt = &body[frame.next];
+            frame.next += 1;
+            self.tick()?;
+            self.step(task, stmt)?;
+        }
+    }
+
+    fn step(&mut self, task: &mut Task, stmt: &Statement) -> GrumpResult<()> {
+        match stmt {
+            Statement::Expression(Expression::Await(expr), _) | Statement::Await { expr } => self.suspend(task, expr, None),
+            Statement::Let { name, value: Expression::Await(expr), .. } => self.suspend(task, expr, Some(Store::Let(name.clone()))),
+            Statement::Assign { target, value: Expression::Await(expr), .. } => {
+                self.suspend(task, expr, Some(Store::Assign(target.clone())))
+            }
+            Statement::If { condition, then, else_, .. } if suspends(then) || else_.as_deref().is_some_and(suspends) => {
+                let branch = if self.condition(condition)? { Some(then) } else { else_.as_ref() };
+                if let Some(branch) = branch {
+                    self.push_frame(task, branch, FrameKind::Block, HashMap::new());
+                }
+                Ok(())
+            }
+            Statement::While { condition, body, .. } if suspends(body) => {
+                if self.condition(condition)? {
+                    self.push_frame(task, body, FrameKind::While(condition.clone()), HashMap::new());
+                }
+                Ok(())
+            }
+            Statement::For { var, iter, body, .. } if suspends(body) => {
+                let mut rest = match self.eval(iter)? {
+                    Value::List(items) | Value::Tuple(items) => items,
+                    Value::String(s) => s.chars().map(Value::Char).collect(),
+                    other => return Err(error(format!("Cannot iterate over {}", other.type_name()))),
+                };
+                rest.reverse();
+                if let Some(item) = rest.pop() {
+                    let scope = HashMap::from([(var.clone(), item)]);
+                    self.push_frame(task, body, FrameKind::For { var: var.clone(), rest }, scope);
+                }
+                Ok(())
+            }
+            _ => {
+                let flow = self.exec(stmt)?;
+                self.unwind(task, flow)
+            }
+        }
+    }
+
+    /// `await expr` as a statement: waits suspend the script and calls to
+    /// async functions run in it; anything else is awaited right away
+    fn suspend(&mut self, task: &mut Task, expr: &Expression, result: Option<Store>) -> GrumpResult<()> {
+        if let Expression::Call { func, args } = expr {
+            if let Expression::Identifier(name) = func.as_ref() {
+                let shadowed = self.local(name).is_some() || self.globals.contains_key(name);
+                let wait = match (name.as_str(), args.as_slice()) {
+                    _ if shadowed => None,
+                    ("wait", [duration]) => {
+                        let seconds = self.eval(duration)?.as_number()
+                            .ok_or_else(|| error("'wait' takes a duration"))?;
+                        Some(Wait::Until(self.time + seconds))
+                    }
+                    ("animation_finished", [target]) => animation_key(target).map(Wait::Animation),
+                    ("event", [event]) => match self.eval(event)? {
+                        Value::String(event) => Some(Wait::Event(event, self.frame)),
+                        other => return Err(error(format!("'event' takes a string, got {}", other.type_name()))),
+                    },
+                    _ if self.functions.get(name).is_some_and(|func| func.is_async) => {
+                        let args = args.iter().map(|arg| self.eval(arg)).collect::<GrumpResult<Vec<_>>>()?;
+                        return self.call_frame(task, name, args, result);
+                    }
+                    _ => None,
+                };
+                if let Some(wait) = wait {
+                    task.waiting = Some(wait);
+                    return self.store(result, Value::Unit);
+                }
+            }
+        }
+        let value = self.eval(expr)?;
+        self.store(result, value)
+    }
+
+    fn store(&mut self, result: Option<Store>, value: Value) -> GrumpResult<()> {
+        match result {
+            Some(Store::Let(name)) => self.bind(&name, value),
+            Some(Store::Assign(target)) => self.assign(&target, value)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Enter function `name`; the script continues in its body
+    fn call_frame(&mut self, task: &mut Task, name: &str, args: Vec<Value>, result: Option<Store>) -> GrumpResult<()> {
+        let func = self.functions.get(name).cloned()
+            .ok_or_else(|| error(format!("Undefined function: {}", name)))?;
+        if func.params.len() != args.len() {
+            return Err(error(format!("'{}' takes {} arguments, got {}", name, func.params.len(), args.len())));
+        }
+        if task.frames.len() >= super::MAX_DEPTH {
+            return Err(error("Stack overflow: too much recursion"));
+        }
+        let scope = func.params.iter().map(|p| p.name.clone()).zip(args).collect();
+        let caller = std::mem::replace(&mut self.locals, vec![scope]);
+        task.frames.push(Frame { body: Rc::new(func.body.clone()), next: 0, kind: FrameKind::Call { caller, result } });
+        Ok(())
+    }
+
+    fn push_frame(&mut self, task: &mut Task, body: &[Statement], kind: FrameKind, scope: HashMap<String, Value>) {
+        self.locals.push(scope);
+        task.frames.push(Frame { body: Rc::new(body.to_vec()), next: 0, kind });
+    }
+
+    /// The end of the innermost block: loops go round again, calls return
+    fn end_frame(&mut self, task: &mut Task) -> GrumpResult<()> {
+        let Some(mut frame) = task.frames.pop() else {
+            return Ok(());
+        };
+        match &mut frame.kind {
+            FrameKind::Block => {
+                self.locals.pop();
+            }
+            FrameKind::While(condition) => {
+                self.locals.pop();
+                let condition = condition.clone();
+                if self.condition(&condition)? {
+                    self.locals.push(HashMap::new());
+                    frame.next = 0;
+                    task.frames.push(frame);
+                }
+            }
+            FrameKind::For { var, rest } => {
+                self.locals.pop();
+                if let Some(item) = rest.pop() {
+                    self.locals.push(HashMap::from([(var.clone(), item)]));
+                    frame.next = 0;
+                    task.frames.push(frame);
+                }
+            }
+            FrameKind::Call { .. } => self.return_from(frame, Value::Unit)?,
+        }
+        Ok(())
+    }
+
+    /// Carry out a `break`, `continue` or `return` from a statement the
+    /// script ran directly
+    fn unwind(&mut self, task: &mut Task, flow: Flow) -> GrumpResult<()> {
+        let continuing = matches!(flow, Flow::Continue);
+        let value = match flow {
+            Flow::Next => return Ok(()),
+            Flow::Break | Flow::Continue => None,
+            Flow::Return(value) => Some(value),
+        };
+        while let Some(mut frame) = task.frames.pop() {
+            match (&frame.kind, &value) {
+                (FrameKind::Call { .. }, Some(value)) => return self.return_from(frame, value.clone()),
+                (FrameKind::Call { .. }, None) => {
+                    return Err(error("`break` and `continue` only work inside loops"));
+                }
+                (FrameKind::While(_) | FrameKind::For { .. }, None) => {
+                    if continuing {
+                        // Go round again from the end of the body
+                        frame.next = frame.body.len();
+                        task.frames.push(frame);
+                    } else {
+                        self.locals.pop();
+                    }
+                    return Ok(());
+                }
+                _ => {
+                    self.locals.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn return_from(&mut self, frame: Frame, value: Value) -> GrumpResult<()> {
+        match frame.kind {
+            FrameKind::Call { caller, result } => {
+                self.locals = caller;
+                self.store(result, value)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Note when an `animate` of a variable or field started by a script
+    /// ends, for `animation_finished`
+    pub(super) fn track_animation(&mut self, animate: &AnimateStatement) -> GrumpResult<()> {
+        let Some(key) = animation_key(&animate.target) else {
+            return Ok(());
+        };
+        // Its duration, or else its last keyframe's time
+        let mut seconds = 0.0_f64;
+        match &animate.duration {
+            Some(duration) => seconds = self.eval(duration)?.as_number().unwrap_or(0.0),
+            None => {
+                for kf in &animate.keyframes {
+                    seconds = seconds.max(self.eval(&kf.time)?.as_number().unwrap_or(0.0));
+                }
+            }
+        }
+        let end = self.time + seconds;
+        let entry = self.animating.entry(key).or_insert(end);
+        *entry = entry.max(end);
+        Ok(())
+    }
+}
+
+/// Whether `body` has a statement a script can suspend at
+fn suspends(body: &[Statement]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Statement::Await { .. } => true,
+        Statement::Expression(Expression::Await(_), _)
+        | Statement::Let { value: Expression::Await(_), .. }
+        | Statement::Assign { value: Expression::Await(_), .. } => true,
+        Statement::If { then, else_, .. } => suspends(then) || else_.as_deref().is_some_and(suspends),
+        Statement::While { body, .. } | Statement::For { body, .. } => suspends(body),
+        _ => false,
+    })
+}
+
+/// `hero.position.x` for an animated variable or field
+fn animation_key(target: &Expression) -> Option<String> {
+    match target {
+        Expression::Identifier(name) => Some(name.clone()),
+        Expression::Member { object, member } => animation_key(object).map(|object| format!("{}.{}", object, member)),
+        _ => None,
+    }
+}
@@ -0,0 +1,129 @@
+//! Runtime values
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// What statements and functions without a return value produce
+    Unit,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Char(char),
+    Vec2 { x: f64, y: f64 },
+    Vec3 { x: f64, y: f64, z: f64 },
+    Color { r: u8, g: u8, b: u8, a: u8 },
+    /// Seconds
+    Duration(f64),
+    /// Degrees
+    Angle(f64),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// An enum used as a value, to reach its variants (`Direction.Up`)
+    Enum(String),
+    Variant { enum_name: String, variant: String },
+    /// A component instance, or a built-in record like `Transform`
+    Struct { name: String, fields: Vec<(String, Value)> },
+    /// A spawned entity with its components in declaration order
+    Entity { id: u64, name: String, components: Vec<(String, Value)> },
+    /// A declared or built-in function, by name
+    Function(String),
+    /// A lambda, by its index in the interpreter's closures
+    Lambda(usize),
+}
+
+impl Value {
+    /// Name of the value's type, as written in source
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Unit => "()".to_string(),
+            Value::Int(_) => "int".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::String(_) => "string".to_string(),
+            Value::Char(_) => "char".to_string(),
+            Value::Vec2 { .. } => "vec2".to_string(),
+            Value::Vec3 { .. } => "vec3".to_string(),
+            Value::Color { .. } => "color".to_string(),
+            Value::Duration(_) => "duration".to_string(),
+            Value::Angle(_) => "angle".to_string(),
+            Value::List(_) => "list".to_string(),
+            Value::Tuple(_) => "tuple".to_string(),
+            Value::Enum(name) => name.clone(),
+            Value::Variant { enum_name, .. } => enum_name.clone(),
+            Value::Struct { name, .. } | Value::Entity { name, .. } => name.clone(),
+            Value::Function(_) | Value::Lambda(_) => "fn".to_string(),
+        }
+    }
+
+    /// Numbers (and durations and angles) as floats
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(f) | Value::Duration(f) | Value::Angle(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// What `print` shows: strings without quotes, everything else as displayed
+    pub fn as_text(&self) -> String {
+        match self {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Equality with ints and floats compared by value
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => self.as_number() == other.as_number(),
+            (Value::List(a), Value::List(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.equals(b))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Vec2 { x, y } => write!(f, "vec2({:?}, {:?})", x, y),
+            Value::Vec3 { x, y, z } => write!(f, "vec3({:?}, {:?}, {:?})", x, y, z),
+            Value::Color { r, g, b, a: 255 } => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Value::Color { r, g, b, a } => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            Value::Duration(seconds) => write!(f, "{}s", seconds),
+            Value::Angle(degrees) => write!(f, "{}deg", degrees),
+            Value::List(items) => write!(f, "[{}]", list(items)),
+            Value::Tuple(items) => write!(f, "({})", list(items)),
+            Value::Enum(name) => write!(f, "{}", name),
+            Value::Variant { enum_name, variant } => write!(f, "{}.{}", enum_name, variant),
+            Value::Struct { name, fields } => {
+                let fields: Vec<String> = fields.iter().map(|(n, v)| format!("{}: {}", n, v)).collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Value::Entity { id, name, components } => {
+                let components: Vec<String> = components.iter()
+                    .map(|(n, v)| match v {
+                        Value::Struct { name, .. } if name == n => v.to_string(),
+                        _ => format!("{}: {}", n, v),
+                    })
+                    .collect();
+                write!(f, "{}#{} {{ {} }}", name, id, components.join(", "))
+            }
+            Value::Function(name) => write!(f, "fn {}", name),
+            Value::Lambda(_) => write!(f, "fn <lambda>"),
+        }
+    }
+}
+
+fn list(items: &[Value]) -> String {
+    items.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
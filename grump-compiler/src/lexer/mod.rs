@@ -27,6 +27,8 @@ pub enum Token {
     Component,
     #[token("system")]
     System,
+    #[token("enum")]
+    Enum,
     #[token("fn")]
     Fn,
     #[token("let")]
@@ -162,6 +164,10 @@ pub enum Token {
     #[token("dependencies")]
     Dependencies,
     
+    // Test keywords
+    #[token("test")]
+    Test,
+    
     // Type keywords
     #[token("int")]
     Int,
@@ -372,10 +378,39 @@ pub enum Unit {
     // TODO: Add more units (%, vw, vh, frames, beats, etc.)
 }
 
+impl Unit {
+    /// Unit for a suffix as written in source (`px`, `ms`, `deg`, ...)
+    pub fn from_suffix(suffix: &str) -> Option<Unit> {
+        match suffix {
+            "px" => Some(Unit::Pixels),
+            "pt" => Some(Unit::Points),
+            "s" => Some(Unit::Seconds),
+            "ms" => Some(Unit::Milliseconds),
+            "deg" => Some(Unit::Degrees),
+            "rad" => Some(Unit::Radians),
+            _ => None,
+        }
+    }
+
+    /// Source suffix; every unit G-Rump knows is spelled the same in CSS
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Pixels => "px",
+            Unit::Points => "pt",
+            Unit::Seconds => "s",
+            Unit::Milliseconds => "ms",
+            Unit::Degrees => "deg",
+            Unit::Radians => "rad",
+        }
+    }
+}
+
 pub struct Lexer<'source> {
     inner: logos::Lexer<'source, Token>,
     line: usize,
     column: usize,
+    /// Tokens lexed ahead of time (`Lexer::replay`), served instead of `inner`
+    replay: Option<std::vec::IntoIter<(Token, usize, usize)>>,
 }
 
 impl<'source> Lexer<'source> {
@@ -384,10 +419,24 @@ impl<'source> Lexer<'source> {
             inner: Token::lexer(source),
             line: 1,
             column: 1,
+            replay: None,
+        }
+    }
+    
+    /// Serve tokens that were already lexed, with their positions as given
+    pub fn replay(tokens: Vec<(Token, usize, usize)>) -> Lexer<'static> {
+        Lexer {
+            inner: Token::lexer(""),
+            line: 1,
+            column: 1,
+            replay: Some(tokens.into_iter()),
         }
     }
     
     pub fn next_token(&mut self) -> GrumpResult<Option<(Token, usize, usize)>> {
+        if let Some(tokens) = &mut self.replay {
+            return Ok(tokens.next());
+        }
         let token = self.inner.next();
         
         match token {
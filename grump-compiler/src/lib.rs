@@ -4,12 +4,23 @@
 
 pub mod lexer;
 pub mod parser;
+pub mod expander;
 pub mod analyzer;
 pub mod optimizer;
 pub mod codegen;
 pub mod runtime;
 pub mod error;
 pub mod animation;
+pub mod manifest;
+pub mod export;
+pub mod loader;
+pub mod package;
+pub mod plugin;
+pub mod lsp;
+pub mod query;
+pub mod interpreter;
+pub mod repl;
+pub mod testing;
 
 pub use error::{GrumpError, GrumpResult};
 
@@ -91,14 +102,17 @@ fn compile_internal(source: &str, target_platform: &str) -> SerializableCompilat
         "ios" => codegen::Target::Ios,
         "android" => codegen::Target::Android,
         "flutter" => codegen::Target::Flutter,
+        "typescript" | "ts" => codegen::Target::TypeScript,
+        "godot" => codegen::Target::Godot,
+        "rust" => codegen::Target::Rust,
         _ => codegen::Target::Web, // Default to Web
     };
 
     let mut codegen = codegen::CodeGenerator::new(target_enum);
     match codegen.generate(&program) {
-        Ok(code) => SerializableCompilationResult {
+        Ok(tree) => SerializableCompilationResult {
             success: true,
-            output: Some(code),
+            output: tree.entry_source().map(str::to_string),
             error: None,
             target: target_platform.to_string(),
         },
@@ -0,0 +1,527 @@
+//! Module loader for multi-file projects
+//!
+//! A project's .grump files live under `src/`, and each is a module named by
+//! its path: `src/physics/forces.grump` is `physics::forces`. `use` makes
+//! another module's items visible, all of them (`use physics::forces;`) or
+//! one (`use physics::forces::gravity;`). The names every file declares go
+//! into one symbol table, `use`s are resolved against it (cycles and names
+//! used without being imported are errors), and the files are merged into a
+//! single program for analysis and code generation.
+//!
+//! Dependencies in the project's `grump.lock` are loaded too, each as a
+//! module named after its package: `grump-ui`'s `src/lib.grump` (or
+//! `main.grump`) is `grump_ui`, its `src/widgets.grump` is
+//! `grump_ui::widgets`. So are source plugins, each as a module named
+//! after the plugin.
+//!
+//! Macro calls a file's parse left alone, to macros declared elsewhere, are
+//! expanded once the `use`s are resolved: a file sees the macros it declares
+//! and the ones it imports.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::expander::Expander;
+use crate::package::Lockfile;
+use crate::parser::{Item, ModuleDeclaration, Parser, Pattern, Program, Span, Statement, UseDeclaration};
+use crate::query::deps;
+use crate::query::items::relocate;
+
+/// Where a project directory keeps its sources
+pub const SOURCE_DIR: &str = "src";
+/// The module whose items stay at the top level of the merged program
+pub const ENTRY_MODULE: &str = "main";
+/// Directories never searched for sources (build output, dependencies)
+const SKIPPED_DIRS: &[&str] = &["build", "target", "node_modules"];
+
+/// One parsed file
+#[derive(Debug)]
+pub struct SourceFile {
+    /// Module path, e.g. `physics::forces`
+    pub module: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub program: Program,
+}
+
+impl SourceFile {
+    fn parse(module: String, path: PathBuf, source: String) -> GrumpResult<Self> {
+        let program = Parser::new(&source).parse().map_err(|e| in_file(e, &path))?;
+        Ok(Self { module, path, source, program })
+    }
+
+    fn read(module: String, path: PathBuf) -> GrumpResult<Self> {
+        let source = std::fs::read_to_string(&path)?;
+        Self::parse(module, path, source)
+    }
+
+    fn uses(&self) -> impl Iterator<Item = &UseDeclaration> {
+        self.program.items.iter().filter_map(|item| match item {
+            Item::Use(use_) => Some(use_),
+            _ => None,
+        })
+    }
+
+    fn error(&self, span: Span, message: String) -> GrumpError {
+        GrumpError::Module { file: self.path.display().to_string(), line: span.line, column: span.column, message }
+    }
+}
+
+/// Where a top-level name is declared
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub module: String,
+    pub keyword: &'static str,
+    pub span: Span,
+}
+
+/// A project's files, linked
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    /// Every module comes after the modules it uses
+    pub files: Vec<SourceFile>,
+    /// Every top-level name in every file
+    pub symbols: BTreeMap<String, Symbol>,
+    entry: Option<String>,
+}
+
+impl Project {
+    /// Load a project directory (its `src/`, if it has one), or a single
+    /// file and the files next to it that its `use`s reach, along with the
+    /// dependencies in the governing `grump.lock`
+    pub fn load(input: &Path) -> GrumpResult<Self> {
+        if input.is_dir() {
+            let root = source_root(input);
+            let mut files = Vec::new();
+            for path in source_files(&root) {
+                files.push(SourceFile::read(module_name(&root, &path), path)?);
+            }
+            for (module, path) in dependency_modules(input)? {
+                files.push(SourceFile::read(module, path)?);
+            }
+            for (module, path) in plugin_modules(&files, input) {
+                if !files.iter().any(|f| f.path == path) {
+                    files.push(SourceFile::read(module, path)?);
+                }
+            }
+            return Self::link(root, files, None);
+        }
+
+        let root = match input.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut dependencies: BTreeMap<String, PathBuf> = match Lockfile::find(input) {
+            Some(project) => dependency_modules(&project)?.into_iter().collect(),
+            None => BTreeMap::new(),
+        };
+        let entry = SourceFile::read(module_name(&root, input), input.to_path_buf())?;
+        let entry_module = entry.module.clone();
+        let mut files = vec![entry];
+        let mut next = 0;
+        while next < files.len() {
+            dependencies.extend(plugin_modules(&files[next..=next], &root));
+            let wanted: Vec<(String, PathBuf)> = files[next].uses()
+                .filter_map(|use_| {
+                    dependency_file(&dependencies, &use_.path)
+                        .or_else(|| module_file(&root, &use_.path).map(|path| (module_name(&root, &path), path)))
+                })
+                .collect();
+            for (module, path) in wanted {
+                if !files.iter().any(|f| f.path == path) {
+                    files.push(SourceFile::read(module, path)?);
+                }
+            }
+            next += 1;
+        }
+        Self::link(root, files, Some(entry_module))
+    }
+
+    /// Link files already read; `sources` are paths under `root` and their text
+    pub fn from_sources(root: &Path, sources: Vec<(PathBuf, String)>) -> GrumpResult<Self> {
+        let files = sources.into_iter()
+            .map(|(path, source)| SourceFile::parse(module_name(root, &path), path, source))
+            .collect::<GrumpResult<Vec<_>>>()?;
+        Self::link(root.to_path_buf(), files, None)
+    }
+
+    fn link(root: PathBuf, mut files: Vec<SourceFile>, entry: Option<String>) -> GrumpResult<Self> {
+        files.sort_by(|a, b| a.module.cmp(&b.module));
+        let symbols = symbol_table(&files)?;
+        let index: HashMap<&str, usize> = files.iter().enumerate().map(|(i, f)| (f.module.as_str(), i)).collect();
+
+        // What each file imports, and which files it depends on
+        let mut imports: Vec<BTreeSet<String>> = vec![BTreeSet::new(); files.len()];
+        let mut edges: Vec<Vec<(usize, Span)>> = vec![Vec::new(); files.len()];
+        for (i, file) in files.iter().enumerate() {
+            for use_ in file.uses() {
+                let path = use_.path_string();
+                let target = if let Some(&target) = index.get(path.as_str()) {
+                    let names = symbols.iter().filter(|(_, s)| s.module == path).map(|(n, _)| n.clone());
+                    imports[i].extend(names);
+                    target
+                } else {
+                    let (module, name) = path.rsplit_once("::").unwrap_or(("", &path));
+                    let target = *index.get(module).ok_or_else(|| {
+                        file.error(use_.span, format!("Unresolved use `{}`: there is no module `{}`", path, if module.is_empty() { &path } else { module }))
+                    })?;
+                    if symbols.get(name).is_none_or(|s| s.module != module) {
+                        return Err(file.error(use_.span, format!("Module `{}` has no item `{}`", module, name)));
+                    }
+                    imports[i].insert(name.to_string());
+                    target
+                };
+                if target != i {
+                    edges[i].push((target, use_.span));
+                }
+            }
+        }
+
+        let order = dependency_order(&files, &edges)?;
+        check_visibility(&files, &symbols, &imports)?;
+
+        // Expand the macros each file declares or imports
+        let macros: Vec<(String, Item)> = files.iter()
+            .flat_map(|file| file.program.all_items().into_iter()
+                .filter(|item| matches!(item, Item::Macro(_)))
+                .map(|item| (file.module.clone(), item.clone())))
+            .collect();
+        for (i, file) in files.iter_mut().enumerate() {
+            let visible = macros.iter()
+                .filter(|(module, item)| *module == file.module || imports[i].contains(item.name()))
+                .map(|(_, item)| item);
+            Expander::with_macros(visible).expand(&mut file.program).map_err(|e| match e {
+                GrumpError::Macro { line, column, message } => file.error(Span { line, column, file: 0 }, message),
+                other => other,
+            })?;
+        }
+
+        let mut slots: Vec<Option<SourceFile>> = files.into_iter().map(Some).collect();
+        let files = order.into_iter().filter_map(|i| slots[i].take()).collect::<Vec<_>>();
+        let entry = entry
+            .or_else(|| files.iter().find(|f| f.module == ENTRY_MODULE).map(|f| f.module.clone()))
+            .or_else(|| {
+                let mut apps = files.iter().filter(|f| f.program.items.iter().any(|item| matches!(item, Item::App(_))));
+                match (apps.next(), apps.next()) {
+                    (Some(only), None) => Some(only.module.clone()),
+                    _ => None,
+                }
+            });
+        Ok(Self { root, files, symbols, entry })
+    }
+
+    /// The file whose items are the program's top level (`main`, the file
+    /// given on the command line, or the only one declaring an app)
+    pub fn entry(&self) -> Option<&SourceFile> {
+        let entry = self.entry.as_ref()?;
+        self.files.iter().find(|f| &f.module == entry)
+    }
+
+    /// Every file, the entry (or else the first) first; an item's
+    /// `Span::file` in `program` indexes this
+    pub fn sources(&self) -> Vec<&SourceFile> {
+        let entry = self.entry();
+        entry.into_iter()
+            .chain(self.files.iter().filter(|f| !entry.is_some_and(|entry| std::ptr::eq(entry, *f))))
+            .collect()
+    }
+
+    /// Every file as one program: the entry file's items at the top level,
+    /// every other file a module of its own before them. Spans point into
+    /// the file each item came from.
+    pub fn program(&self) -> Program {
+        let sources = self.sources();
+        let mut items = Vec::new();
+        let mut top = Vec::new();
+        for file in &self.files {
+            let index = sources.iter().position(|source| std::ptr::eq(*source, file)).unwrap_or(0);
+            let mut file_items = file.program.items.clone();
+            if index != 0 {
+                relocate(&mut file_items, &|span| Span { file: index, ..span });
+            }
+            if Some(&file.module) == self.entry.as_ref() {
+                top.extend(file_items);
+            } else {
+                items.push(Item::Module(ModuleDeclaration {
+                    name: file.module.clone(),
+                    items: file_items,
+                    span: Span { file: index, ..Span::default() },
+                }));
+            }
+        }
+        items.extend(top);
+        Program { items }
+    }
+}
+
+/// Where a project (or package) directory keeps its sources: `src/` if it
+/// has one, otherwise the directory itself
+pub fn source_root(dir: &Path) -> PathBuf {
+    let src = dir.join(SOURCE_DIR);
+    if src.is_dir() { src } else { dir.to_path_buf() }
+}
+
+/// The .grump files under `root` (or `root` itself if it's a file), sorted
+pub fn source_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            let skipped = name.starts_with('.') || (entry.file_type().is_dir() && SKIPPED_DIRS.contains(&name.as_ref()));
+            entry.depth() == 0 || !skipped
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "grump"))
+        .map(|entry| entry.into_path())
+        .collect();
+    files.sort();
+    files
+}
+
+/// `src/physics/forces.grump` under `src` is `physics::forces`
+fn module_name(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path).with_extension("");
+    let parts: Vec<String> = relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .filter(|part| part != ".")
+        .collect();
+    parts.join("::")
+}
+
+/// Every file of every package in `project`'s `grump.lock`, with its module
+fn dependency_modules(project: &Path) -> GrumpResult<Vec<(String, PathBuf)>> {
+    let Some(lock) = Lockfile::read(project)? else {
+        return Ok(Vec::new());
+    };
+    let mut modules = Vec::new();
+    for package in &lock.packages {
+        let root = source_root(&project.join(package.dir()));
+        if !root.is_dir() {
+            return Err(GrumpError::Package {
+                message: format!("`{}` isn't fetched; run `grump fetch`", package.name),
+            });
+        }
+        modules.extend(package_files(&package.module(), &root));
+    }
+    Ok(modules)
+}
+
+/// Every file of the source plugins declared in `files`, with its module;
+/// plugin paths are relative to `project`
+fn plugin_modules(files: &[SourceFile], project: &Path) -> Vec<(String, PathBuf)> {
+    let mut modules = Vec::new();
+    for file in files {
+        for item in file.program.all_items() {
+            if let Item::Plugin(plugin) = item {
+                if let Some(dir) = crate::plugin::source_dir(plugin, project) {
+                    modules.extend(package_files(&plugin.name, &source_root(&dir)));
+                }
+            }
+        }
+    }
+    modules
+}
+
+/// The files under a package's source `root`: `lib.grump` (or `main.grump`)
+/// is `module` itself, the rest are inside it
+fn package_files(module: &str, root: &Path) -> Vec<(String, PathBuf)> {
+    source_files(root).into_iter()
+        .map(|path| {
+            let module = match module_name(root, &path).as_str() {
+                "lib" | ENTRY_MODULE => module.to_string(),
+                inner => format!("{}::{}", module, inner),
+            };
+            (module, path)
+        })
+        .collect()
+}
+
+/// The dependency file holding the module `path` names, or the longest
+/// prefix of it that is one
+fn dependency_file(dependencies: &BTreeMap<String, PathBuf>, path: &[String]) -> Option<(String, PathBuf)> {
+    (1..=path.len()).rev()
+        .map(|len| path[..len].join("::"))
+        .find_map(|module| dependencies.get(&module).map(|file| (module, file.clone())))
+}
+
+/// The file holding the module `path` names, or the longest prefix of it
+/// that is a module (`a::b::item` can be in `a/b.grump`)
+fn module_file(root: &Path, path: &[String]) -> Option<PathBuf> {
+    (1..=path.len()).rev()
+        .map(|len| root.join(path[..len].join("/")).with_extension("grump"))
+        .find(|file| file.is_file())
+}
+
+/// Name -> declaring module, refusing a name declared in two files
+fn symbol_table(files: &[SourceFile]) -> GrumpResult<BTreeMap<String, Symbol>> {
+    let mut symbols: BTreeMap<String, Symbol> = BTreeMap::new();
+    for file in files {
+        for item in file.program.all_items() {
+            if matches!(item, Item::Test(_) | Item::Use(_)) {
+                continue;
+            }
+            match symbols.get(item.name()) {
+                Some(existing) if existing.module != file.module => {
+                    return Err(file.error(
+                        item.span(),
+                        format!("`{}` is already declared in module `{}`", item.name(), existing.module),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    let symbol = Symbol { module: file.module.clone(), keyword: item.keyword(), span: item.span() };
+                    symbols.insert(item.name().to_string(), symbol);
+                }
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+/// Files in an order where each comes after those it uses; a cycle of
+/// `use`s is an error at the `use` that closes it
+fn dependency_order(files: &[SourceFile], edges: &[Vec<(usize, Span)>]) -> GrumpResult<Vec<usize>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        New,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        node: usize,
+        files: &[SourceFile],
+        edges: &[Vec<(usize, Span)>],
+        state: &mut [State],
+        stack: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> GrumpResult<()> {
+        state[node] = State::Visiting;
+        stack.push(node);
+        for &(next, span) in &edges[node] {
+            match state[next] {
+                State::Done => {}
+                State::Visiting => {
+                    let start = stack.iter().position(|&n| n == next).unwrap_or(0);
+                    let mut cycle: Vec<&str> = stack[start..].iter().map(|&n| files[n].module.as_str()).collect();
+                    cycle.push(&files[next].module);
+                    return Err(files[node].error(span, format!("Cyclic use: {}", cycle.join(" -> "))));
+                }
+                State::New => visit(next, files, edges, state, stack, order)?,
+            }
+        }
+        stack.pop();
+        state[node] = State::Done;
+        order.push(node);
+        Ok(())
+    }
+
+    let mut state = vec![State::New; files.len()];
+    let mut order = Vec::new();
+    for node in 0..files.len() {
+        if state[node] == State::New {
+            visit(node, files, edges, &mut state, &mut Vec::new(), &mut order)?;
+        }
+    }
+    Ok(order)
+}
+
+/// Every name an item uses from another module must be imported
+fn check_visibility(files: &[SourceFile], symbols: &BTreeMap<String, Symbol>, imports: &[BTreeSet<String>]) -> GrumpResult<()> {
+    for (file, imported) in files.iter().zip(imports) {
+        for item in file.program.all_items() {
+            let bound = locals(item);
+            for name in deps::references(item) {
+                let symbol = match symbols.get(&name) {
+                    Some(symbol) if symbol.module != file.module => symbol,
+                    _ => continue,
+                };
+                if !bound.contains(&name) && !imported.contains(&name) {
+                    return Err(file.error(
+                        item.span(),
+                        format!("`{}` is declared in module `{}`; add `use {}::{};`", name, symbol.module, symbol.module, name),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Names an item binds itself (parameters, `let`s, loop variables), which
+/// may shadow names from other modules
+fn locals(item: &Item) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let body = match item {
+        Item::Function(func) => {
+            names.extend(func.params.iter().map(|p| p.name.clone()));
+            &func.body
+        }
+        Item::Scene(scene) => &scene.body,
+        Item::Entity(entity) => &entity.body,
+        Item::System(system) => &system.body,
+        Item::Test(test) => &test.body,
+        _ => return names,
+    };
+    bound_in(body, &mut names);
+    names
+}
+
+fn bound_in(body: &[Statement], names: &mut BTreeSet<String>) {
+    for stmt in body {
+        match stmt {
+            Statement::Let { name, .. } => {
+                names.insert(name.clone());
+            }
+            Statement::For { var, body, .. } => {
+                names.insert(var.clone());
+                bound_in(body, names);
+            }
+            Statement::If { then, else_, .. } => {
+                bound_in(then, names);
+                if let Some(else_) = else_ {
+                    bound_in(else_, names);
+                }
+            }
+            Statement::While { body, .. } => bound_in(body, names),
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    bound_by(&arm.pattern, names);
+                    bound_in(&arm.body, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn bound_by(pattern: &Pattern, names: &mut BTreeSet<String>) {
+    match pattern {
+        Pattern::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        Pattern::Tuple(patterns) => patterns.iter().for_each(|p| bound_by(p, names)),
+        Pattern::Struct { fields, .. } => fields.iter().for_each(|(_, p)| bound_by(p, names)),
+        Pattern::Literal(_) | Pattern::Wildcard => {}
+    }
+}
+
+/// Say which file a parse error is in
+pub(crate) fn in_file(error: GrumpError, path: &Path) -> GrumpError {
+    match error {
+        GrumpError::Lexer { line, column, message } => {
+            GrumpError::Lexer { line, column, message: format!("{} (in {})", message, path.display()) }
+        }
+        GrumpError::Parser { line, column, message } => {
+            GrumpError::Parser { line, column, message: format!("{} (in {})", message, path.display()) }
+        }
+        GrumpError::Macro { line, column, message } => {
+            GrumpError::Macro { line, column, message: format!("{} (in {})", message, path.display()) }
+        }
+        other => other,
+    }
+}
@@ -0,0 +1,406 @@
+//! Language features for one open document
+//!
+//! Positions are LSP positions: 0-based lines and characters counted in
+//! UTF-16 code units. Compiler spans count chars, from 1, so positions are
+//! converted on the way in (`char_index`) and out (`position`). Spans only
+//! mark where an item's name starts, so an item is taken to run until the
+//! next one begins.
+
+use serde_json::{json, Value as Json};
+
+use crate::analyzer::Analyzer;
+use crate::analyzer::types::{ast_type_to_type, FunctionSignature, Type};
+use crate::parser::{Item, Program, Span};
+use crate::query::{Analysis, ItemResult, Problem};
+
+const KEYWORDS: &[&str] = &[
+    "app", "scene", "entity", "component", "system", "enum", "fn", "let", "mut",
+    "if", "else", "match", "for", "while", "loop", "return", "break", "continue",
+    "use", "pub", "in", "true", "false", "animate", "timeline", "keyframes",
+    "ease", "spring", "animation", "async", "await", "behavior_tree", "selector",
+    "sequence", "condition", "action", "shader", "network", "macro", "plugin",
+    "package", "test",
+];
+
+/// An open .grump file as of its last analysis
+pub struct Document<'a> {
+    text: &'a str,
+    /// The last version that parsed, kept so hover and completion keep
+    /// working while the user is mid-edit
+    program: Option<&'a Program>,
+    /// One per `program.all_items()`
+    items: &'a [ItemResult],
+}
+
+impl<'a> Document<'a> {
+    pub fn new(analysis: Analysis<'a>) -> Self {
+        Document {
+            text: analysis.text,
+            program: analysis.program,
+            items: analysis.items,
+        }
+    }
+
+    /// `Diagnostic` for a problem the database found
+    pub fn diagnostic(&self, problem: &Problem) -> Json {
+        // Problems at EOF come without a position
+        let (line, character) = if problem.line == 0 {
+            let last = self.text.lines().count().saturating_sub(1);
+            (last, self.line(last).chars().count())
+        } else {
+            (problem.line - 1, problem.column.saturating_sub(1))
+        };
+        json!({
+            "range": self.token_range(line, character),
+            "severity": 1,
+            "source": "grump",
+            "message": problem.message,
+        })
+    }
+
+    /// `Hover` for the word under the cursor
+    pub fn hover(&self, line: usize, character: usize) -> Option<Json> {
+        let (word, object) = self.word_at(line, character)?;
+        let text = match object {
+            Some(object) => self.describe_member(&object, &word, line)?,
+            None => self.describe(&word, line)?,
+        };
+        Some(json!({
+            "contents": { "kind": "markdown", "value": format!("```grump\n{}\n```", text) },
+        }))
+    }
+
+    /// `Location` of the item named under the cursor (behavior-tree actions
+    /// resolve to the function of the same name)
+    pub fn definition(&self, uri: &str, line: usize, character: usize) -> Option<Json> {
+        let (word, _) = self.word_at(line, character)?;
+        let item = self.find_item(&word)?;
+        Some(json!({ "uri": uri, "range": self.name_range(item) }))
+    }
+
+    /// `CompletionItem[]` at the cursor: members after `object.`, otherwise
+    /// locals, items, built-in functions and keywords
+    pub fn completion(&self, line: usize, character: usize) -> Json {
+        let before: String = self.line(line).chars().take(character).collect();
+        let before = before.trim_end_matches(is_ident_char);
+        if let Some(object) = before.strip_suffix('.') {
+            let start = object.trim_end_matches(is_ident_char).len();
+            return json!(self.members(&object[start..], line));
+        }
+
+        let mut items = Vec::new();
+        if let Some((item, analysis)) = self.containing(line) {
+            if let Item::Function(func) = item {
+                for param in &func.params {
+                    let detail = param.type_.as_ref().map(|t| ast_type_to_type(t).to_string());
+                    items.push(completion(&param.name, 6, detail));
+                }
+            }
+            for (name, type_) in &analysis.bindings {
+                items.push(completion(name, 6, Some(type_.to_string())));
+            }
+        }
+        if let Some(program) = self.program {
+            // Tests have no name to refer to, and uses name items declared elsewhere
+            for item in program.all_items().into_iter().filter(|item| !matches!(item, Item::Test(_) | Item::Use(_))) {
+                items.push(completion(item.name(), completion_kind(item), Some(item.keyword().to_string())));
+            }
+        }
+        for (name, sig) in Analyzer::new().context().functions() {
+            items.push(completion(name, 3, Some(signature(name, sig))));
+        }
+        for keyword in KEYWORDS {
+            items.push(completion(keyword, 14, None));
+        }
+        json!(items)
+    }
+
+    /// `DocumentSymbol[]`, nested the way items are
+    pub fn symbols(&self) -> Json {
+        match self.program {
+            Some(program) => {
+                let last = self.text.lines().count().saturating_sub(1);
+                json!(self.symbol_list(&program.items, (last, self.line(last).chars().count())))
+            }
+            None => json!([]),
+        }
+    }
+
+    fn symbol_list(&self, items: &[Item], end: (usize, usize)) -> Vec<Json> {
+        items.iter().enumerate().map(|(i, item)| {
+            let start = item.span().line.saturating_sub(1);
+            // Runs until the line before the next sibling
+            let item_end = match items.get(i + 1) {
+                Some(next) => {
+                    let line = next.span().line.saturating_sub(2).max(start);
+                    (line, self.line(line).chars().count())
+                }
+                None => end,
+            };
+            let range = json!({
+                "start": self.position(start, 0),
+                "end": self.position(item_end.0, item_end.1),
+            });
+            let selection = self.name_range(item);
+            let leaf = |name: &str, kind: u32, detail: String| json!({
+                "name": name, "kind": kind, "detail": detail,
+                "range": range, "selectionRange": selection,
+            });
+            let children = match item {
+                Item::App(app) => self.symbol_list(&app.body, item_end),
+                Item::Module(module) => self.symbol_list(&module.items, item_end),
+                Item::Component(comp) => comp.fields.iter()
+                    .map(|f| leaf(&f.name, 8, ast_type_to_type(&f.type_).to_string()))
+                    .collect(),
+                Item::Enum(decl) => decl.variants.iter()
+                    .map(|v| leaf(v, 22, decl.name.clone()))
+                    .collect(),
+                Item::Entity(entity) => entity.components.iter()
+                    .map(|c| leaf(&c.name, 7, "component".to_string()))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            json!({
+                "name": item.name(),
+                "kind": symbol_kind(item),
+                "detail": item.keyword(),
+                "range": range,
+                "selectionRange": selection,
+                "children": children,
+            })
+        }).collect()
+    }
+
+    fn describe(&self, word: &str, line: usize) -> Option<String> {
+        if let Some((item, analysis)) = self.containing(line) {
+            if let Some((_, type_)) = analysis.bindings.iter().rev().find(|(name, _)| name == word) {
+                return Some(format!("let {}: {}", word, type_));
+            }
+            if let Item::Function(func) = item {
+                if let Some(param) = func.params.iter().find(|p| p.name == word) {
+                    let type_ = param.type_.as_ref().map_or(Type::Unknown, ast_type_to_type);
+                    return Some(format!("{}: {}", word, type_));
+                }
+            }
+        }
+        if let Some(item) = self.find_item(word) {
+            return Some(declaration(item));
+        }
+        let analyzer = Analyzer::new();
+        analyzer.context().get_function(word).map(|sig| format!("{} // built-in", signature(word, sig)))
+    }
+
+    fn describe_member(&self, object: &str, member: &str, line: usize) -> Option<String> {
+        self.members(object, line).into_iter()
+            .find(|c| c["label"] == member)
+            .map(|c| format!("{}.{}: {}", object, member, c["detail"].as_str().unwrap_or("_")))
+    }
+
+    /// Completions for `object.`: component fields (matched case-insensitively,
+    /// so `position.` finds `Position`), enum variants, or vector components
+    fn members(&self, object: &str, line: usize) -> Vec<Json> {
+        let local = self.containing(line).and_then(|(item, analysis)| {
+            analysis.bindings.iter().rev().find(|(name, _)| name == object).map(|(_, t)| t.clone())
+                .or_else(|| match item {
+                    Item::Function(func) => func.params.iter()
+                        .find(|p| p.name == object)
+                        .and_then(|p| p.type_.as_ref().map(ast_type_to_type)),
+                    _ => None,
+                })
+        });
+        let type_name = match &local {
+            Some(Type::Vec2) => return vector_members(2),
+            Some(Type::Vec3) => return vector_members(3),
+            Some(Type::Vec4) => return vector_members(4),
+            Some(Type::Named(name)) => Type::Named(name.clone()).to_string(),
+            _ => object.to_string(),
+        };
+
+        let program = match self.program {
+            Some(program) => program,
+            None => return Vec::new(),
+        };
+        for item in program.all_items() {
+            match item {
+                Item::Component(comp) if comp.name.eq_ignore_ascii_case(&type_name) => {
+                    return comp.fields.iter()
+                        .map(|f| completion(&f.name, 5, Some(ast_type_to_type(&f.type_).to_string())))
+                        .collect();
+                }
+                Item::Enum(decl) if decl.name == type_name => {
+                    return decl.variants.iter()
+                        .map(|v| completion(v, 20, Some(decl.name.clone())))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        Vec::new()
+    }
+
+    /// The innermost item starting at or before `line`, with its analysis
+    fn containing(&self, line: usize) -> Option<(&'a Item, &'a ItemResult)> {
+        let program = self.program?;
+        program.all_items().into_iter().zip(self.items)
+            .rfind(|(item, _)| item.span().line.saturating_sub(1) <= line)
+    }
+
+    fn find_item(&self, name: &str) -> Option<&'a Item> {
+        self.program?.all_items().into_iter().find(|item| item.name() == name)
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.text.lines().nth(line).unwrap_or("")
+    }
+
+    /// Char index on `line` of an LSP `character`
+    pub fn char_index(&self, line: usize, character: usize) -> usize {
+        let mut units = 0;
+        self.line(line).chars()
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= character
+            })
+            .count()
+    }
+
+    /// LSP `Position` of char index `character` on `line`; past the end of
+    /// the line, one unit per char
+    fn position(&self, line: usize, character: usize) -> Json {
+        let text = self.line(line);
+        let units: usize = text.chars().take(character).map(char::len_utf16).sum();
+        let past_end = character.saturating_sub(text.chars().count());
+        json!({ "line": line, "character": units + past_end })
+    }
+
+    fn range(&self, line: usize, start: usize, end: usize) -> Json {
+        json!({ "start": self.position(line, start), "end": self.position(line, end) })
+    }
+
+    fn name_range(&self, item: &Item) -> Json {
+        let Span { line, column, .. } = item.span();
+        let start = column.saturating_sub(1);
+        self.range(line.saturating_sub(1), start, start + item.name().chars().count())
+    }
+
+    /// Identifier under the cursor, and the identifier before it if it is
+    /// accessed as a member (`pos.x` gives `("x", Some("pos"))`)
+    fn word_at(&self, line: usize, character: usize) -> Option<(String, Option<String>)> {
+        let chars: Vec<char> = self.line(line).chars().collect();
+        let mut start = character.min(chars.len());
+        while start > 0 && is_ident_char(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = character.min(chars.len());
+        while end < chars.len() && is_ident_char(chars[end]) {
+            end += 1;
+        }
+        if start == end {
+            return None;
+        }
+        let word: String = chars[start..end].iter().collect();
+        let object = if start > 0 && chars[start - 1] == '.' {
+            let mut object_start = start - 1;
+            while object_start > 0 && is_ident_char(chars[object_start - 1]) {
+                object_start -= 1;
+            }
+            Some(chars[object_start..start - 1].iter().collect::<String>()).filter(|o| !o.is_empty())
+        } else {
+            None
+        };
+        Some((word, object))
+    }
+
+    /// Range of the token starting at a position (at least one character)
+    fn token_range(&self, line: usize, character: usize) -> Json {
+        let chars: Vec<char> = self.line(line).chars().collect();
+        let mut end = character;
+        while end < chars.len() && is_ident_char(chars[end]) {
+            end += 1;
+        }
+        self.range(line, character, end.max(character + 1))
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn completion(label: &str, kind: u32, detail: Option<String>) -> Json {
+    match detail {
+        Some(detail) => json!({ "label": label, "kind": kind, "detail": detail }),
+        None => json!({ "label": label, "kind": kind }),
+    }
+}
+
+fn vector_members(size: usize) -> Vec<Json> {
+    ["x", "y", "z", "w"][..size].iter()
+        .map(|axis| completion(axis, 5, Some("float".to_string())))
+        .collect()
+}
+
+/// `fn lerp(a: float, b: float, t: float) -> float`
+fn signature(name: &str, sig: &FunctionSignature) -> String {
+    let params: Vec<String> = sig.params.iter().map(|(n, t)| format!("{}: {}", n, t)).collect();
+    match sig.return_type {
+        Type::Never => format!("fn {}({})", name, params.join(", ")),
+        ref ret => format!("fn {}({}) -> {}", name, params.join(", "), ret),
+    }
+}
+
+/// Hover text for an item
+fn declaration(item: &Item) -> String {
+    match item {
+        Item::Component(comp) => {
+            let fields: Vec<String> = comp.fields.iter()
+                .map(|f| format!("    {}: {},\n", f.name, ast_type_to_type(&f.type_)))
+                .collect();
+            format!("component {} {{\n{}}}", comp.name, fields.concat())
+        }
+        Item::Entity(entity) => {
+            let components: Vec<&str> = entity.components.iter().map(|c| c.name.as_str()).collect();
+            format!("entity {} [{}]", entity.name, components.join(", "))
+        }
+        Item::Enum(decl) => format!("enum {} {{ {} }}", decl.name, decl.variants.join(", ")),
+        Item::System(system) => format!("system {} [{}]", system.name, system.query.join(", ")),
+        Item::Function(func) => {
+            let sig = FunctionSignature {
+                params: func.params.iter()
+                    .map(|p| (p.name.clone(), p.type_.as_ref().map_or(Type::Unknown, ast_type_to_type)))
+                    .collect(),
+                return_type: func.return_type.as_ref().map_or(Type::Never, ast_type_to_type),
+            };
+            let prefix = if func.is_async { "async " } else { "" };
+            format!("{}{}", prefix, signature(&func.name, &sig))
+        }
+        other => format!("{} {}", other.keyword(), other.name()),
+    }
+}
+
+/// LSP `SymbolKind`
+fn symbol_kind(item: &Item) -> u32 {
+    match item {
+        Item::App(_) | Item::Package(_) => 4,
+        Item::Module(_) | Item::Plugin(_) | Item::Use(_) => 2,
+        Item::Scene(_) => 3,
+        Item::Entity(_) => 19,
+        Item::Component(_) | Item::Network(_) => 23,
+        Item::Enum(_) => 10,
+        Item::System(_) => 6,
+        Item::Function(_) | Item::Macro(_) | Item::Test(_) => 12,
+        Item::Animation(_) => 24,
+        Item::Shader(_) | Item::BehaviorTree(_) => 5,
+    }
+}
+
+/// LSP `CompletionItemKind`
+fn completion_kind(item: &Item) -> u32 {
+    match item {
+        Item::Component(_) => 22,
+        Item::Entity(_) => 7,
+        Item::Enum(_) => 13,
+        Item::Function(_) | Item::System(_) | Item::Macro(_) => 3,
+        Item::Animation(_) => 23,
+        _ => 9,
+    }
+}
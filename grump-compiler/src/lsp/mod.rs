@@ -0,0 +1,231 @@
+//! Language server for G-Rump (`grump lsp`)
+//!
+//! Speaks the Language Server Protocol over stdio: diagnostics from the
+//! parser and analyzer, hover with analyzer types, go-to-definition,
+//! completion and document symbols. Documents are synced in full; the
+//! query database works out how little of each needs re-checking.
+//! Positions are in UTF-16 code units, the protocol's default encoding.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value as Json};
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::query::Database;
+
+mod document;
+
+pub use document::Document;
+
+/// Protocol state: the open documents (keyed by URI) and what is known about them
+#[derive(Default)]
+pub struct Server {
+    db: Database,
+    open: HashSet<String>,
+    shutting_down: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle one message from the client, returning the messages to send back
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) if !method.is_empty() => id.clone(),
+            // Notifications (and responses to requests we never send)
+            _ => return self.notification(method, params),
+        };
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "positionEncoding": "utf-16",
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "grump", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shutting_down = true;
+                Json::Null
+            }
+            "textDocument/hover" => self.at_position(params, |doc, _, line, character| {
+                doc.hover(line, character)
+            }),
+            "textDocument/definition" => self.at_position(params, |doc, uri, line, character| {
+                doc.definition(uri, line, character)
+            }),
+            "textDocument/completion" => self.at_position(params, |doc, _, line, character| {
+                Some(doc.completion(line, character))
+            }),
+            "textDocument/documentSymbol" => self.at_document(params, |doc, _| Some(doc.symbols())),
+            _ => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("Unsupported method '{}'", method) },
+                })];
+            }
+        };
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    /// Whether the client has asked the server to shut down
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.db.set_source(&uri, text);
+                self.open.insert(uri.clone());
+                vec![self.publish(&uri)]
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let text = params["contentChanges"].as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match text {
+                    Some(text) if self.open.contains(&uri) => {
+                        self.db.set_source(&uri, text);
+                        vec![self.publish(&uri)]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.open.remove(&uri);
+                self.db.remove(&uri);
+                vec![publish_diagnostics(&uri, &[])]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn publish(&mut self, uri: &str) -> Json {
+        let problems = self.db.diagnostics(uri);
+        let diagnostics: Vec<Json> = match self.db.analyze(uri) {
+            Some(analysis) => {
+                let doc = Document::new(analysis);
+                problems.iter().map(|problem| doc.diagnostic(problem)).collect()
+            }
+            None => Vec::new(),
+        };
+        publish_diagnostics(uri, &diagnostics)
+    }
+
+    fn at_document(&mut self, params: &Json, feature: impl Fn(&Document, &str) -> Option<Json>) -> Json {
+        let uri = match params["textDocument"]["uri"].as_str() {
+            Some(uri) if self.open.contains(uri) => uri,
+            _ => return Json::Null,
+        };
+        let found = self.db.analyze(uri).and_then(|analysis| feature(&Document::new(analysis), uri));
+        found.unwrap_or(Json::Null)
+    }
+
+    fn at_position(&mut self, params: &Json, feature: impl Fn(&Document, &str, usize, usize) -> Option<Json>) -> Json {
+        let line = params["position"]["line"].as_u64();
+        let character = params["position"]["character"].as_u64();
+        match (line, character) {
+            (Some(line), Some(character)) => self.at_document(params, |doc, uri| {
+                let line = line as usize;
+                feature(doc, uri, line, doc.char_index(line, character as usize))
+            }),
+            _ => Json::Null,
+        }
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[Json]) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Read one `Content-Length`-framed message; `None` at end of input
+pub fn read_message(reader: &mut impl BufRead) -> GrumpResult<Option<Json>> {
+    match read_body(reader)? {
+        Some(body) => Ok(Some(serde_json::from_slice(&body).map_err(|e| GrumpError::Other(e.into()))?)),
+        None => Ok(None),
+    }
+}
+
+/// The body of one `Content-Length`-framed message; `None` at end of input
+fn read_body(reader: &mut impl BufRead) -> GrumpResult<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| GrumpError::Other(anyhow::anyhow!("LSP message without Content-Length")))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// Write one message with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &Json) -> GrumpResult<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Serve on stdin/stdout until the client sends `exit`
+pub fn run() -> GrumpResult<()> {
+    serve(&mut std::io::stdin().lock(), &mut std::io::stdout().lock())
+}
+
+/// Serve messages from `input` until the client sends `exit`. A body that
+/// isn't JSON gets a parse error back, and the server carries on.
+pub fn serve(input: &mut impl BufRead, output: &mut impl Write) -> GrumpResult<()> {
+    let mut server = Server::new();
+    while let Some(body) = read_body(input)? {
+        let message: Json = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                write_message(output, &json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+                }))?;
+                continue;
+            }
+        };
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(output, &reply)?;
+        }
+    }
+    if !server.is_shutting_down() {
+        return Err(GrumpError::Other(anyhow::anyhow!("Client exited without shutting the server down")));
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Project manifest (`grump.manifest`)
+//!
+//! Written by `grump init`; lists the targets a project builds for and its
+//! default frame rate. The `@target [...]` and `@fps` attributes in source are
+//! reconciled against it before a build.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::codegen::Target;
+use crate::error::{GrumpError, GrumpResult};
+use crate::parser::{Item, Program};
+
+pub const MANIFEST_FILE: &str = "grump.manifest";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default = "default_version")]
+    pub version: String,
+    #[serde(default)]
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+}
+
+fn default_version() -> String {
+    "0.1.0".to_string()
+}
+
+impl Manifest {
+    /// A manifest listing no targets, so builds follow the app's `@target` list
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: default_version(),
+            targets: Vec::new(),
+            fps: Some(60.0),
+        }
+    }
+
+    pub fn load(path: &Path) -> GrumpResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| {
+            GrumpError::Other(anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))
+        })
+    }
+
+    /// Find the manifest governing `input` by walking up from it
+    pub fn find(input: &Path) -> Option<PathBuf> {
+        let start = if input.is_dir() { Some(input) } else { input.parent() };
+        let start = start.map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })?;
+        let start = start.canonicalize().ok()?;
+        start.ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Targets listed in the manifest, rejecting unknown names
+    pub fn parsed_targets(&self) -> GrumpResult<Vec<Target>> {
+        self.targets.iter().map(|name| Target::from_name(name)).collect()
+    }
+}
+
+/// What to build, after reconciling the CLI, the manifest and the source
+#[derive(Debug, Clone)]
+pub struct BuildPlan {
+    pub targets: Vec<Target>,
+    pub fps: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+impl BuildPlan {
+    /// Decide which targets to build.
+    ///
+    /// `requested` is the `--target` value: a target name, `all`, or `None` to
+    /// use the manifest. `all` means every manifest target (every known target
+    /// without a manifest). If the source declares `@target [...]`, it narrows
+    /// `all`; explicitly requesting a target the source excludes is an error.
+    /// With neither a target nor manifest targets, the app's `@target` list is
+    /// built, or iOS if it has none.
+    pub fn resolve(requested: Option<&str>, manifest: Option<&Manifest>, program: &Program) -> GrumpResult<Self> {
+        let mut warnings = Vec::new();
+
+        let manifest_targets = match manifest {
+            Some(m) => m.parsed_targets()?,
+            None => Vec::new(),
+        };
+        let app = program.items.iter().find_map(|item| match item {
+            Item::App(app) => Some(app),
+            _ => None,
+        });
+        let source_targets = match app {
+            Some(app) => app.targets.iter().map(|name| Target::from_name(name)).collect::<GrumpResult<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        let explicit = match requested {
+            Some("all") => None,
+            Some(name) => Some(vec![Target::from_name(name)?]),
+            None if manifest_targets.is_empty() && source_targets.is_empty() => Some(vec![Target::Ios]),
+            None if manifest_targets.is_empty() => Some(source_targets.clone()),
+            None => None,
+        };
+
+        let mut targets = match explicit {
+            Some(targets) => {
+                for target in &targets {
+                    if !source_targets.is_empty() && !source_targets.contains(target) {
+                        return Err(GrumpError::Other(anyhow::anyhow!(
+                            "Target '{}' is not in the app's @target list [{}]",
+                            target.name(),
+                            join_names(&source_targets),
+                        )));
+                    }
+                }
+                targets
+            }
+            None => {
+                let mut targets = if manifest_targets.is_empty() {
+                    Target::ALL.to_vec()
+                } else {
+                    manifest_targets.clone()
+                };
+                if !source_targets.is_empty() {
+                    for target in targets.iter().filter(|t| !source_targets.contains(t)) {
+                        warnings.push(format!(
+                            "Skipping '{}': listed in {} but not in the app's @target list",
+                            target.name(), MANIFEST_FILE,
+                        ));
+                    }
+                    targets.retain(|t| source_targets.contains(t));
+                    if manifest.is_some() {
+                        for target in source_targets.iter().filter(|t| !manifest_targets.contains(t)) {
+                            warnings.push(format!(
+                                "'{}' is in the app's @target list but not in {}; add it to build it",
+                                target.name(), MANIFEST_FILE,
+                            ));
+                        }
+                    }
+                }
+                targets
+            }
+        };
+        let mut seen = HashSet::new();
+        targets.retain(|target| seen.insert(*target));
+
+        if targets.is_empty() {
+            return Err(GrumpError::Other(anyhow::anyhow!(
+                "Nothing to build: {} and the app's @target list have no targets in common",
+                MANIFEST_FILE,
+            )));
+        }
+
+        let source_fps = app.and_then(|a| a.fps);
+        let manifest_fps = manifest.and_then(|m| m.fps);
+        if let (Some(source), Some(manifest)) = (source_fps, manifest_fps) {
+            if source != manifest {
+                warnings.push(format!(
+                    "@fps {} overrides fps {} from {}", source, manifest, MANIFEST_FILE,
+                ));
+            }
+        }
+
+        Ok(Self {
+            targets,
+            fps: source_fps.or(manifest_fps),
+            warnings,
+        })
+    }
+
+    /// Give the app the manifest's frame rate when the source doesn't set one
+    pub fn apply(&self, program: &mut Program) {
+        for item in &mut program.items {
+            if let Item::App(app) = item {
+                if app.fps.is_none() {
+                    app.fps = self.fps;
+                }
+            }
+        }
+    }
+}
+
+fn join_names(targets: &[Target]) -> String {
+    targets.iter().map(|t| t.name()).collect::<Vec<_>>().join(", ")
+}
@@ -78,13 +78,13 @@ impl Optimizer {
             crate::parser::Statement::Let { value, .. } => {
                 *value = self.fold_expression(value)?;
             }
-            crate::parser::Statement::Return(Some(expr)) => {
+            crate::parser::Statement::Return(Some(expr), _) => {
                 *expr = self.fold_expression(expr)?;
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 *expr = self.fold_expression(expr)?;
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 *condition = self.fold_expression(condition)?;
                 for stmt in then {
                     self.fold_statement(stmt)?;
@@ -272,10 +272,10 @@ impl Optimizer {
                 self.collect_expression_usage(value, functions, variables);
                 variables.insert(name.clone());
             }
-            crate::parser::Statement::Expression(expr) => {
+            crate::parser::Statement::Expression(expr, _) => {
                 self.collect_expression_usage(expr, functions, variables);
             }
-            crate::parser::Statement::If { condition, then, else_ } => {
+            crate::parser::Statement::If { condition, then, else_, .. } => {
                 self.collect_expression_usage(condition, functions, variables);
                 for stmt in then {
                     self.collect_statement_usage(stmt, functions, variables);
@@ -286,7 +286,7 @@ impl Optimizer {
                     }
                 }
             }
-            crate::parser::Statement::Return(Some(expr)) => {
+            crate::parser::Statement::Return(Some(expr), _) => {
                 self.collect_expression_usage(expr, functions, variables);
             }
             crate::parser::Statement::For { iter, body, .. } => {
@@ -295,7 +295,7 @@ impl Optimizer {
                     self.collect_statement_usage(stmt, functions, variables);
                 }
             }
-            crate::parser::Statement::While { condition, body } => {
+            crate::parser::Statement::While { condition, body, .. } => {
                 self.collect_expression_usage(condition, functions, variables);
                 for stmt in body {
                     self.collect_statement_usage(stmt, functions, variables);
@@ -0,0 +1,104 @@
+//! The git commands fetching runs
+//!
+//! Relative repository paths are relative to the project, so every command
+//! runs there. Only local remotes are fetched from: a path, or a `file://`
+//! URL.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use semver::Version;
+
+use crate::error::{GrumpError, GrumpResult};
+
+fn git(cwd: &Path, args: &[&str]) -> GrumpResult<String> {
+    let output = Command::new("git")
+        .current_dir(cwd)
+        .args(args)
+        .output()
+        .map_err(|e| GrumpError::Package { message: format!("Couldn't run git: {}", e) })?;
+    if !output.status.success() {
+        return Err(GrumpError::Package {
+            message: format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Check `url` is a local remote. Other transports can run commands
+/// (`ext::`), and a leading `-` would be read as an option.
+pub fn check_remote(url: &str) -> GrumpResult<()> {
+    let local = match url.strip_prefix("file://") {
+        Some(path) => !path.is_empty(),
+        // `host:path` is ssh, unless the colon is a drive letter's or comes after a `/`
+        None => !url.contains("://") && match url.find(':') {
+            Some(colon) => url[..colon].contains('/') || (colon == 1 && url.starts_with(|c: char| c.is_ascii_alphabetic())),
+            None => true,
+        },
+    };
+    if url.is_empty() || url.starts_with('-') || !local {
+        return Err(GrumpError::Package {
+            message: format!("git source \"{}\" must be a local path or a file:// URL", url),
+        });
+    }
+    Ok(())
+}
+
+/// Tags of `url` that are versions (`1.2.0` or `v1.2.0`) and the commits
+/// they name, oldest version first
+pub fn tags(cwd: &Path, url: &str) -> GrumpResult<Vec<(Version, String)>> {
+    let mut tags: BTreeMap<Version, String> = BTreeMap::new();
+    let mut peeled: BTreeMap<Version, String> = BTreeMap::new();
+    check_remote(url)?;
+    for line in git(cwd, &["ls-remote", "--tags", "--", url])?.lines() {
+        let Some((commit, name)) = line.split_once('\t') else { continue };
+        let Some(name) = name.strip_prefix("refs/tags/") else { continue };
+        // Annotated tags are listed again as `name^{}` with the commit itself
+        let (name, target) = match name.strip_suffix("^{}") {
+            Some(name) => (name, &mut peeled),
+            None => (name, &mut tags),
+        };
+        if let Ok(version) = Version::parse(name.strip_prefix('v').unwrap_or(name)) {
+            target.insert(version, commit.to_string());
+        }
+    }
+    tags.extend(peeled);
+    Ok(tags.into_iter().collect())
+}
+
+/// The commit `branch` (or the default branch) of `url` is at
+pub fn head(cwd: &Path, url: &str, branch: Option<&str>) -> GrumpResult<String> {
+    check_remote(url)?;
+    let reference = branch.map_or("HEAD".to_string(), |b| format!("refs/heads/{}", b));
+    let listing = git(cwd, &["ls-remote", "--", url, &reference])?;
+    listing.lines()
+        .find_map(|line| line.split_once('\t').map(|(commit, _)| commit.to_string()))
+        .ok_or_else(|| GrumpError::Package {
+            message: match branch {
+                Some(branch) => format!("{} has no branch `{}`", url, branch),
+                None => format!("{} has no commits", url),
+            },
+        })
+}
+
+/// Check `commit` of `url` out into `dir`, unless it already is
+pub fn checkout(cwd: &Path, url: &str, commit: &str, dir: &Path) -> GrumpResult<()> {
+    check_remote(url)?;
+    if commit.is_empty() || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(GrumpError::Package { message: format!("\"{}\" isn't a commit hash", commit) });
+    }
+    if dir.join(".git").exists() && git(dir, &["rev-parse", "HEAD"]).is_ok_and(|head| head.trim() == commit) {
+        return Ok(());
+    }
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    if let Some(parent) = dir.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let target = dir.to_string_lossy();
+    git(cwd, &["clone", "--quiet", "--no-checkout", "--", url, &target])?;
+    git(dir, &["checkout", "--quiet", "--detach", commit])?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! `grump.lock`: what a fetch picked for every dependency
+//!
+//! Packages are sorted by name and their lists by value, so the same
+//! resolution always writes the same file.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GrumpError, GrumpResult};
+
+use super::DEPS_DIR;
+
+pub const LOCK_FILE: &str = "grump.lock";
+/// Bumped when the format changes
+pub const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub source: LockedSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Names of the packages it depends on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LockedSource {
+    /// A directory, relative to the project
+    Path { path: String },
+    Git {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        commit: String,
+    },
+    /// A directory registry, by absolute path
+    Registry { registry: String, checksum: String },
+}
+
+impl Lockfile {
+    pub fn new(mut packages: Vec<LockedPackage>) -> Self {
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        for package in &mut packages {
+            package.features.sort();
+            package.features.dedup();
+            package.dependencies.sort();
+            package.dependencies.dedup();
+        }
+        Self { version: LOCK_VERSION, packages }
+    }
+
+    /// The lock in `project`, if it has one
+    pub fn read(project: &Path) -> GrumpResult<Option<Self>> {
+        let path = project.join(LOCK_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)?;
+        let lock: Self = serde_json::from_str(&text).map_err(|e| GrumpError::Package {
+            message: format!("Invalid {}: {}", path.display(), e),
+        })?;
+        if lock.version != LOCK_VERSION {
+            return Err(GrumpError::Package {
+                message: format!("{} is version {}; this grump reads version {}", path.display(), lock.version, LOCK_VERSION),
+            });
+        }
+        Ok(Some(lock))
+    }
+
+    pub fn write(&self, project: &Path) -> GrumpResult<()> {
+        std::fs::write(project.join(LOCK_FILE), self.to_json())?;
+        Ok(())
+    }
+
+    /// The directory holding the lock governing `input`, walking up from it
+    pub fn find(input: &Path) -> Option<PathBuf> {
+        let start = if input.is_dir() { Some(input) } else { input.parent() };
+        let start = start.map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })?;
+        let start = start.canonicalize().ok()?;
+        start.ancestors()
+            .find(|dir| dir.join(LOCK_FILE).is_file())
+            .map(Path::to_path_buf)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+}
+
+impl LockedPackage {
+    /// Where its sources are, relative to the project
+    pub fn dir(&self) -> PathBuf {
+        match &self.source {
+            LockedSource::Path { path } => PathBuf::from(path),
+            LockedSource::Git { commit, .. } => checkout_dir(&self.name, commit),
+            LockedSource::Registry { .. } => release_dir(&self.name, &self.version),
+        }
+    }
+
+    /// The module its files are under: `grump-ui` is `grump_ui`
+    pub fn module(&self) -> String {
+        module_name(&self.name)
+    }
+}
+
+impl std::fmt::Display for LockedSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LockedSource::Path { path } => write!(f, "path+{}", path),
+            LockedSource::Git { url, commit, .. } => write!(f, "git+{}#{}", url, short(commit)),
+            LockedSource::Registry { registry, .. } => write!(f, "registry+{}", registry),
+        }
+    }
+}
+
+/// Where a git dependency is checked out, relative to the project
+pub fn checkout_dir(name: &str, commit: &str) -> PathBuf {
+    Path::new(DEPS_DIR).join(format!("{}-{}", name, short(commit)))
+}
+
+/// Where a registry release of a dependency is unpacked, relative to the
+/// project
+pub fn release_dir(name: &str, version: &str) -> PathBuf {
+    Path::new(DEPS_DIR).join(format!("{}-{}", name, version))
+}
+
+/// A package name as a module name
+pub fn module_name(package: &str) -> String {
+    package.replace('-', "_")
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
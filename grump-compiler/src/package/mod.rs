@@ -0,0 +1,552 @@
+//! Package manager
+//!
+//! A project's `package` declaration lists its dependencies. `grump fetch`
+//! resolves them: a path dependency is used where it is, a git dependency
+//! is checked out under `.grump/deps` at the newest version tag meeting its
+//! requirements (or at its branch, if it names one), and a dependency with
+//! only a version comes from a directory registry (see `registry`).
+//! Dependencies' own packages are resolved the same way, and what was
+//! picked goes into `grump.lock`; a later fetch keeps a locked git commit
+//! or release while it still meets every requirement. The loader reads the
+//! lock to add dependency sources to a project.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+use semver::{Version, VersionReq};
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::lexer::Token;
+use crate::loader;
+use crate::parser::extensions::{Dependency, PackageDeclaration};
+use crate::parser::{Item, Parser};
+use crate::query::{Lexeme, Tokens};
+
+mod git;
+pub mod lock;
+pub mod registry;
+
+pub use lock::{LockedPackage, LockedSource, Lockfile, LOCK_FILE};
+pub use registry::{Registry, Release};
+
+/// Where git and registry dependencies are put, under the project
+pub const DEPS_DIR: &str = ".grump/deps";
+
+/// Resolve `project`'s dependencies, check out the git ones, unpack the
+/// registry ones and write `grump.lock`. Without a `registry`, the one
+/// already in the lock (if any) is used.
+pub fn fetch(project: &Path, registry: Option<&Path>) -> GrumpResult<Lockfile> {
+    let (_, package) = find_package(project)?.ok_or_else(|| no_package(project))?;
+    validate(&package)?;
+    let previous = Lockfile::read(project)?;
+    let lock = Resolver::new(project, previous.as_ref(), registry)?.resolve(&package)?;
+    lock.write(project)?;
+    Ok(lock)
+}
+
+/// Add `dependency` to `project`'s package declaration (replacing one of
+/// the same name) and fetch. The declaration is put back if the fetch fails.
+pub fn add(project: &Path, dependency: &Dependency, dev: bool, registry: Option<&Path>) -> GrumpResult<Lockfile> {
+    let (path, _) = find_package(project)?.ok_or_else(|| no_package(project))?;
+    let original = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, add_dependency(&original, dependency, dev)?)?;
+    match fetch(project, registry) {
+        Ok(lock) => Ok(lock),
+        Err(e) => {
+            std::fs::write(&path, &original)?;
+            Err(e)
+        }
+    }
+}
+
+/// Publish the package in `project` to the registry at `registry`,
+/// creating the registry if needed
+pub fn publish(project: &Path, registry: &Path) -> GrumpResult<Release> {
+    Registry::create(registry)?.publish(project)
+}
+
+/// A project's `package` declaration and the file it's in
+pub fn find_package(project: &Path) -> GrumpResult<Option<(PathBuf, PackageDeclaration)>> {
+    for path in loader::source_files(&loader::source_root(project)) {
+        let source = std::fs::read_to_string(&path)?;
+        if package_token(Tokens::new(&source).lexemes()).is_none() {
+            continue;
+        }
+        let program = Parser::new(&source).parse().map_err(|e| loader::in_file(e, &path))?;
+        let package = program.items.into_iter().find_map(|item| match item {
+            Item::Package(package) => Some(package),
+            _ => None,
+        });
+        if let Some(package) = package {
+            return Ok(Some((path, package)));
+        }
+    }
+    Ok(None)
+}
+
+/// Check a declaration's names, version, requirements and sources
+pub fn validate(package: &PackageDeclaration) -> GrumpResult<()> {
+    validate_name(&package.name)?;
+    parse_version(&package.name, &package.version)?;
+    for dependency in package.dependencies.iter().chain(&package.dev_dependencies) {
+        validate_name(&dependency.name)?;
+        if let Some(url) = &dependency.git {
+            git::check_remote(url)?;
+        }
+        if let Some(version) = &dependency.version {
+            parse_requirement(&dependency.name, version)?;
+        }
+        if dependency.path.is_some() && dependency.git.is_some() {
+            return Err(error(format!("`{}` has both a path and a git source", dependency.name)));
+        }
+        if dependency.branch.is_some() && dependency.git.is_none() {
+            return Err(error(format!("`{}` names a branch but no git source", dependency.name)));
+        }
+    }
+    Ok(())
+}
+
+/// Package names are identifiers, except that they can use `-` for `_`
+/// (`grump-ui` is imported as `grump_ui`). They name directories, so
+/// nothing else is allowed.
+pub fn validate_name(name: &str) -> GrumpResult<()> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(error(format!(
+            "`{}` isn't a valid package name; use letters, digits, `_` and `-`, starting with a letter or `_`",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// `source` with `dependency` added to the `dependencies` (or
+/// `dev_dependencies`) block of its package declaration
+pub fn add_dependency(source: &str, dependency: &Dependency, dev: bool) -> GrumpResult<String> {
+    let tokens = Tokens::new(source);
+    let lexemes = tokens.lexemes();
+    let package = package_token(lexemes).ok_or_else(|| error("No `package` declaration".to_string()))?;
+    // `package name {`
+    let open = package + 2;
+    if lexemes.get(open).map(|l| &l.token) != Some(&Token::LeftBrace) {
+        return Err(error("Malformed `package` declaration".to_string()));
+    }
+    let close = closing(lexemes, open)?;
+    let key = if dev { "dev_dependencies" } else { "dependencies" };
+
+    let mut block = None;
+    let mut i = open + 1;
+    while i < close {
+        let is_key = match &lexemes[i].token {
+            Token::Dependencies => !dev,
+            Token::Identifier(name) => name == key,
+            _ => false,
+        };
+        if is_key && lexemes.get(i + 2).is_some_and(|l| l.token == Token::LeftBrace) {
+            block = Some((i, i + 2));
+            break;
+        }
+        i = next(lexemes, i)?;
+    }
+
+    let mut out = source.to_string();
+    let Some((key_at, block_open)) = block else {
+        let indent = format!("{}    ", indent_of(source, lexemes[package].start));
+        let entry = render(dependency, &format!("{}    ", indent));
+        insert_before(&mut out, source, lexemes[close].start, &format!("{}{}: {{\n{}{}}}\n", indent, key, entry, indent));
+        return Ok(out);
+    };
+
+    let indent = format!("{}    ", indent_of(source, lexemes[key_at].start));
+    let entry = render(dependency, &indent);
+    let block_close = closing(lexemes, block_open)?;
+    let mut i = block_open + 1;
+    while i < block_close {
+        let existing = lexemes[i].token == Token::StringLiteral(dependency.name.clone())
+            && lexemes.get(i + 2).is_some_and(|l| l.token == Token::LeftBrace);
+        if existing {
+            let entry_close = closing(lexemes, i + 2)?;
+            let mut end = lexemes[entry_close].end;
+            if lexemes.get(entry_close + 1).is_some_and(|l| l.token == Token::Comma) {
+                end = lexemes[entry_close + 1].end;
+            }
+            let rest = &source[end..];
+            let trailing = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            if rest[trailing..].starts_with('\n') {
+                end += trailing + 1;
+            }
+            let line = line_start(source, lexemes[i].start);
+            let start = if source[line..lexemes[i].start].trim().is_empty() { line } else { lexemes[i].start };
+            out.replace_range(start..end, &entry);
+            return Ok(out);
+        }
+        i = next(lexemes, i)?;
+    }
+    insert_before(&mut out, source, lexemes[block_close].start, &entry);
+    Ok(out)
+}
+
+/// Where a dependency comes from, as declared
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// A directory, relative to the project
+    Path(PathBuf),
+    Git { url: String, branch: Option<String> },
+    /// The resolver's registry, at this path
+    Registry(PathBuf),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Source::Path(path) => write!(f, "path {}", slashed(path)),
+            Source::Git { url, branch: Some(branch) } => write!(f, "git {} (branch {})", url, branch),
+            Source::Git { url, branch: None } => write!(f, "git {}", url),
+            Source::Registry(root) => write!(f, "registry {}", root.display()),
+        }
+    }
+}
+
+struct Resolver<'a> {
+    project: &'a Path,
+    previous: Option<&'a Lockfile>,
+    registry: Option<Registry>,
+    sources: BTreeMap<String, Source>,
+    /// Every version requirement on a package, and who made it
+    requirements: BTreeMap<String, Vec<(VersionReq, String)>>,
+    picked: BTreeMap<String, LockedPackage>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(project: &'a Path, previous: Option<&'a Lockfile>, registry: Option<&Path>) -> GrumpResult<Self> {
+        let locked_registry = previous.into_iter()
+            .flat_map(|lock| &lock.packages)
+            .find_map(|package| match &package.source {
+                LockedSource::Registry { registry, .. } => Some(Path::new(registry)),
+                _ => None,
+            });
+        let registry = match registry.or(locked_registry) {
+            Some(root) => Some(Registry::open(root)?),
+            None => None,
+        };
+        Ok(Self {
+            project,
+            previous,
+            registry,
+            sources: BTreeMap::new(),
+            requirements: BTreeMap::new(),
+            picked: BTreeMap::new(),
+        })
+    }
+
+    /// Pick a version of every package reachable from `package`. A new
+    /// requirement on a package already picked re-picks it if it isn't met.
+    fn resolve(mut self, package: &PackageDeclaration) -> GrumpResult<Lockfile> {
+        let mut queue: VecDeque<(Dependency, PathBuf, String)> = package.dependencies.iter()
+            .chain(&package.dev_dependencies)
+            .map(|dependency| (dependency.clone(), PathBuf::new(), package.name.clone()))
+            .collect();
+
+        while let Some((dependency, base, by)) = queue.pop_front() {
+            let name = dependency.name.clone();
+            let source = self.source_of(&dependency, &base)?;
+            match self.sources.get(&name) {
+                Some(existing) if *existing != source => {
+                    return Err(error(format!("`{}` is required from two sources: {} and {}", name, existing, source)));
+                }
+                _ => {
+                    self.sources.insert(name.clone(), source.clone());
+                }
+            }
+            if let Some(version) = &dependency.version {
+                let requirement = parse_requirement(&name, version)?;
+                self.requirements.entry(name.clone()).or_default().push((requirement, by));
+            }
+
+            let mut features = dependency.features.clone();
+            if let Some(picked) = self.picked.get(&name) {
+                features.extend(picked.features.iter().cloned());
+                let met = Version::parse(&picked.version).is_ok_and(|v| self.unmet(&name, &v).is_none());
+                if met {
+                    self.picked.get_mut(&name).unwrap().features = features;
+                    continue;
+                }
+            }
+
+            let (mut picked, declaration) = self.pick(&name, &source)?;
+            validate(&declaration)?;
+            let dir = picked.dir();
+            picked.features = features;
+            picked.dependencies = declaration.dependencies.iter().map(|d| d.name.clone()).collect();
+            for dependency in declaration.dependencies {
+                queue.push_back((dependency, dir.clone(), name.clone()));
+            }
+            self.picked.insert(name, picked);
+        }
+        Ok(Lockfile::new(self.picked.into_values().collect()))
+    }
+
+    /// A version of `name` meeting its requirements so far, with the
+    /// declaration found there
+    fn pick(&self, name: &str, source: &Source) -> GrumpResult<(LockedPackage, PackageDeclaration)> {
+        let (version, source, declaration) = match source {
+            Source::Path(dir) => {
+                let declaration = self.declaration(name, dir)?;
+                let version = parse_version(name, &declaration.version)?;
+                (version, LockedSource::Path { path: slashed(dir) }, declaration)
+            }
+            Source::Git { url, branch } => {
+                let locked = self.locked(name).and_then(|(version, source)| match source {
+                    LockedSource::Git { url: locked_url, branch: locked_branch, commit }
+                        if locked_url == url && locked_branch == branch => Some((version, commit.clone())),
+                    _ => None,
+                });
+                let (version, commit) = match locked {
+                    Some((version, commit)) => (Some(version), commit),
+                    None if branch.is_none() && self.requirements.contains_key(name) => {
+                        let (version, commit) = self.newest_tag(name, url)?;
+                        (Some(version), commit)
+                    }
+                    None => (None, git::head(self.project, url, branch.as_deref())?),
+                };
+                let dir = lock::checkout_dir(name, &commit);
+                git::checkout(self.project, url, &commit, &self.project.join(&dir))?;
+                let declaration = self.declaration(name, &dir)?;
+                let version = match version {
+                    Some(version) => version,
+                    None => parse_version(name, &declaration.version)?,
+                };
+                (version, LockedSource::Git { url: url.clone(), branch: branch.clone(), commit }, declaration)
+            }
+            Source::Registry(root) => {
+                let registry = self.registry.as_ref().ok_or_else(|| error(format!("`{}` needs a registry", name)))?;
+                let registry_name = root.display().to_string();
+                let locked = self.locked(name).and_then(|(version, source)| match source {
+                    LockedSource::Registry { registry, checksum } if *registry == registry_name => {
+                        Some((version, checksum.clone()))
+                    }
+                    _ => None,
+                });
+                let (version, checksum) = match locked {
+                    Some(locked) => locked,
+                    None => self.newest_release(name, registry)?,
+                };
+                let dir = lock::release_dir(name, &version.to_string());
+                registry.unpack(name, &version.to_string(), &checksum, &self.project.join(&dir))?;
+                let declaration = self.declaration(name, &dir)?;
+                (version, LockedSource::Registry { registry: registry_name, checksum }, declaration)
+            }
+        };
+
+        if let Some((requirement, by)) = self.unmet(name, &version) {
+            return Err(error(format!(
+                "`{}` {} ({}) doesn't meet `{}`, required by `{}`",
+                name, version, source, requirement, by
+            )));
+        }
+        let package = LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source,
+            features: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        Ok((package, declaration))
+    }
+
+    /// What `grump.lock` has for `name`, if it still meets every
+    /// requirement; callers check it's from the same place
+    fn locked(&self, name: &str) -> Option<(Version, &'a LockedSource)> {
+        let locked = self.previous?.get(name)?;
+        let version = Version::parse(&locked.version).ok()?;
+        self.unmet(name, &version).is_none().then_some((version, &locked.source))
+    }
+
+    fn newest_tag(&self, name: &str, url: &str) -> GrumpResult<(Version, String)> {
+        let tags = git::tags(self.project, url)?;
+        if let Some(tag) = tags.iter().rev().find(|(version, _)| self.unmet(name, version).is_none()) {
+            return Ok(tag.clone());
+        }
+        Err(self.no_match(&format!("version tag of {}", url), name, tags.iter().map(|(v, _)| v.to_string()).collect()))
+    }
+
+    fn newest_release(&self, name: &str, registry: &Registry) -> GrumpResult<(Version, String)> {
+        let releases: Vec<(Version, String)> = registry.releases(name)?.into_iter()
+            .filter_map(|release| Some((Version::parse(&release.version).ok()?, release.checksum)))
+            .collect();
+        let newest = releases.iter()
+            .filter(|(version, _)| self.unmet(name, version).is_none())
+            .max_by(|a, b| a.0.cmp(&b.0));
+        if let Some(release) = newest {
+            return Ok(release.clone());
+        }
+        let found = releases.iter().map(|(v, _)| v.to_string()).collect();
+        Err(self.no_match(&format!("release of `{}` in {}", name, registry.root.display()), name, found))
+    }
+
+    fn no_match(&self, what: &str, name: &str, found: Vec<String>) -> GrumpError {
+        let wanted: Vec<String> = self.requirements.get(name).into_iter().flatten()
+            .map(|(r, by)| format!("`{}` (from `{}`)", r, by))
+            .collect();
+        error(format!(
+            "No {} meets {}; it has {}",
+            what,
+            if wanted.is_empty() { "any version".to_string() } else { wanted.join(" and ") },
+            if found.is_empty() { "none".to_string() } else { found.join(", ") }
+        ))
+    }
+
+    /// The package declaration in a dependency's directory
+    fn declaration(&self, name: &str, dir: &Path) -> GrumpResult<PackageDeclaration> {
+        let full = self.project.join(dir);
+        if !full.is_dir() {
+            return Err(error(format!("`{}`: {} isn't a directory", name, slashed(dir))));
+        }
+        let (_, declaration) = find_package(&full)?
+            .ok_or_else(|| error(format!("`{}`: {} has no `package` declaration", name, slashed(dir))))?;
+        if lock::module_name(&declaration.name) != lock::module_name(name) {
+            return Err(error(format!("{} holds package `{}`, not `{}`", slashed(dir), declaration.name, name)));
+        }
+        Ok(declaration)
+    }
+
+    /// A requirement on `name` that `version` doesn't meet
+    fn unmet(&self, name: &str, version: &Version) -> Option<&(VersionReq, String)> {
+        self.requirements.get(name)?.iter().find(|(requirement, _)| !requirement.matches(version))
+    }
+
+    /// Paths are relative to `base`, the directory of the package
+    /// declaring the dependency
+    fn source_of(&self, dependency: &Dependency, base: &Path) -> GrumpResult<Source> {
+        match (&dependency.path, &dependency.git) {
+            (Some(path), None) => Ok(Source::Path(normalize(&base.join(path)))),
+            (None, Some(url)) => Ok(Source::Git { url: url.clone(), branch: dependency.branch.clone() }),
+            (Some(_), Some(_)) => Err(error(format!("`{}` has both a path and a git source", dependency.name))),
+            (None, None) => match &self.registry {
+                Some(registry) => Ok(Source::Registry(registry.root.clone())),
+                None => Err(error(format!(
+                    "`{}` has no `path` or `git`, and no registry was given to fetch it from",
+                    dependency.name
+                ))),
+            },
+        }
+    }
+}
+
+fn parse_version(name: &str, version: &str) -> GrumpResult<Version> {
+    Version::parse(version).map_err(|e| error(format!("`{}` has version \"{}\": {}", name, version, e)))
+}
+
+/// `"1.2"` means `^1.2`, as in Cargo
+fn parse_requirement(name: &str, requirement: &str) -> GrumpResult<VersionReq> {
+    VersionReq::parse(requirement).map_err(|e| error(format!("`{}` requires version \"{}\": {}", name, requirement, e)))
+}
+
+/// Drop `.` and resolve `..` against the components before it
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(out.components().next_back(), Some(Component::Normal(_))) => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn slashed(path: &Path) -> String {
+    let text = path.display().to_string().replace('\\', "/");
+    if text.is_empty() { ".".to_string() } else { text }
+}
+
+fn no_package(project: &Path) -> GrumpError {
+    error(format!("No `package` declaration in {}", project.display()))
+}
+
+fn error(message: String) -> GrumpError {
+    GrumpError::Package { message }
+}
+
+fn package_token(lexemes: &[Lexeme]) -> Option<usize> {
+    lexemes.iter().position(|l| l.token == Token::Package)
+}
+
+/// The bracket closing the one at `open`
+fn closing(lexemes: &[Lexeme], open: usize) -> GrumpResult<usize> {
+    let mut depth = 0usize;
+    for (i, lexeme) in lexemes.iter().enumerate().skip(open) {
+        match lexeme.token {
+            Token::LeftBrace | Token::LeftBracket | Token::LeftParen => depth += 1,
+            Token::RightBrace | Token::RightBracket | Token::RightParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(error(format!("Unclosed bracket at {}:{}", lexemes[open].line, lexemes[open].column)))
+}
+
+/// The token after the one at `i`, skipping whatever it opens
+fn next(lexemes: &[Lexeme], i: usize) -> GrumpResult<usize> {
+    match lexemes[i].token {
+        Token::LeftBrace | Token::LeftBracket | Token::LeftParen => Ok(closing(lexemes, i)? + 1),
+        _ => Ok(i + 1),
+    }
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn indent_of(source: &str, offset: usize) -> String {
+    let line = &source[line_start(source, offset)..];
+    line[..line.len() - line.trim_start_matches([' ', '\t']).len()].to_string()
+}
+
+/// Insert whole lines before the bracket at `at`, giving it a line of its
+/// own if it shares one with something else
+fn insert_before(out: &mut String, source: &str, at: usize, lines: &str) {
+    let line = line_start(source, at);
+    if source[line..at].trim().is_empty() {
+        out.insert_str(line, lines);
+    } else {
+        out.insert_str(at, &format!("\n{}{}", lines, indent_of(source, at)));
+    }
+}
+
+/// One dependency entry, as lines indented by `indent`
+fn render(dependency: &Dependency, indent: &str) -> String {
+    let mut properties = Vec::new();
+    let strings = [
+        ("version", &dependency.version),
+        ("path", &dependency.path),
+        ("git", &dependency.git),
+        ("branch", &dependency.branch),
+    ];
+    for (key, value) in strings {
+        if let Some(value) = value {
+            properties.push(format!("{}: \"{}\"", key, value));
+        }
+    }
+    if !dependency.features.is_empty() {
+        let features: Vec<String> = dependency.features.iter().map(|f| format!("\"{}\"", f)).collect();
+        properties.push(format!("features: [{}]", features.join(", ")));
+    }
+    if properties.is_empty() {
+        return format!("{}\"{}\": {{}}\n", indent, dependency.name);
+    }
+    let mut entry = format!("{}\"{}\": {{\n", indent, dependency.name);
+    for property in properties {
+        entry.push_str(&format!("{}    {}\n", indent, property));
+    }
+    entry.push_str(&format!("{}}}\n", indent));
+    entry
+}
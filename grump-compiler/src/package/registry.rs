@@ -0,0 +1,250 @@
+//! Directory registries
+//!
+//! A registry is a directory that can live on a shared drive or behind a
+//! static file server:
+//!
+//! ```text
+//! index/<name>.json                      every release of a package
+//! archives/<name>/<name>-<version>.tar   its sources
+//! ```
+//!
+//! Archives are written deterministically (sorted, no timestamps or
+//! owners), so the SHA-256 checksum in the index identifies a release's
+//! contents. Names are module names: `grump-ui` is listed as `grump_ui`, so
+//! only one of the two can be published. Publishing holds
+//! `index/<name>.json.lock` while it adds the release.
+
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{GrumpError, GrumpResult};
+use crate::loader;
+
+use super::lock::module_name;
+use super::{error, find_package, parse_version, validate, validate_name};
+
+pub const INDEX_DIR: &str = "index";
+pub const ARCHIVE_DIR: &str = "archives";
+/// Written next to unpacked sources, so they're only unpacked once
+const CHECKSUM_FILE: &str = ".grump-checksum";
+/// How long a publish waits for another to release an index: 100 tries, 50ms apart
+const LOCK_ATTEMPTS: u32 = 100;
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// `index/<name>.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Name the package was published under
+    pub name: String,
+    /// Oldest first
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// SHA-256 of the archive, in hex
+    pub checksum: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<ReleaseDependency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseDependency {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+pub struct Registry {
+    pub root: PathBuf,
+}
+
+impl Registry {
+    /// A registry that must already exist
+    pub fn open(root: &Path) -> GrumpResult<Self> {
+        let root = root.canonicalize()
+            .ok()
+            .filter(|root| root.join(INDEX_DIR).is_dir())
+            .ok_or_else(|| error(format!("No registry at {} (it has no {}/ directory)", root.display(), INDEX_DIR)))?;
+        Ok(Self { root })
+    }
+
+    /// A registry, created if it doesn't exist yet
+    pub fn create(root: &Path) -> GrumpResult<Self> {
+        std::fs::create_dir_all(root.join(INDEX_DIR))?;
+        std::fs::create_dir_all(root.join(ARCHIVE_DIR))?;
+        Self::open(root)
+    }
+
+    /// Every release of `name`; none if it was never published
+    pub fn releases(&self, name: &str) -> GrumpResult<Vec<Release>> {
+        Ok(self.index(name)?.releases)
+    }
+
+    fn index(&self, name: &str) -> GrumpResult<IndexEntry> {
+        let path = self.index_path(name)?;
+        if !path.is_file() {
+            return Ok(IndexEntry { name: name.to_string(), releases: Vec::new() });
+        }
+        let text = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&text).map_err(|e| error(format!("Invalid registry index {}: {}", path.display(), e)))
+    }
+
+    fn index_path(&self, name: &str) -> GrumpResult<PathBuf> {
+        validate_name(name)?;
+        Ok(self.root.join(INDEX_DIR).join(format!("{}.json", module_name(name))))
+    }
+
+    pub fn archive_path(&self, name: &str, version: &str) -> GrumpResult<PathBuf> {
+        validate_name(name)?;
+        parse_version(name, version)?;
+        let name = module_name(name);
+        Ok(self.root.join(ARCHIVE_DIR).join(&name).join(format!("{}-{}.tar", name, version)))
+    }
+
+    /// Archive the package in `project` and add it to the index. Its
+    /// declaration must be valid, its version new, and its dependencies
+    /// registry versions (dev dependencies aren't published).
+    pub fn publish(&self, project: &Path) -> GrumpResult<Release> {
+        let (_, package) = find_package(project)?
+            .ok_or_else(|| error(format!("No `package` declaration in {}", project.display())))?;
+        validate(&package)?;
+        for dependency in &package.dependencies {
+            if dependency.path.is_some() || dependency.git.is_some() {
+                return Err(error(format!(
+                    "`{}` can't be published: its dependency `{}` isn't from a registry",
+                    package.name, dependency.name
+                )));
+            }
+            if dependency.version.is_none() {
+                return Err(error(format!(
+                    "`{}` can't be published: its dependency `{}` has no version requirement",
+                    package.name, dependency.name
+                )));
+            }
+        }
+
+        // Readers never see half an index: it's replaced whole, and only
+        // one publish rewrites it at a time
+        let path = self.index_path(&package.name)?;
+        let _lock = IndexLock::acquire(path.with_extension("json.lock"))?;
+        let mut index = self.index(&package.name)?;
+        if !index.releases.is_empty() && index.name != package.name {
+            return Err(error(format!(
+                "`{}` can't be published: `{}` is already in the registry, and both are module `{}`",
+                package.name, index.name, module_name(&package.name)
+            )));
+        }
+        if index.releases.iter().any(|r| r.version == package.version) {
+            return Err(error(format!("`{}` {} is already in the registry", package.name, package.version)));
+        }
+
+        let bytes = archive(project)?;
+        let release = Release {
+            version: package.version.clone(),
+            checksum: checksum(&bytes),
+            dependencies: package.dependencies.iter()
+                .map(|d| ReleaseDependency {
+                    name: d.name.clone(),
+                    version: d.version.clone().unwrap_or_default(),
+                    features: d.features.clone(),
+                })
+                .collect(),
+        };
+        let archive_path = self.archive_path(&package.name, &package.version)?;
+        std::fs::create_dir_all(archive_path.parent().unwrap_or(&self.root))?;
+        std::fs::write(&archive_path, &bytes)?;
+
+        index.name = package.name.clone();
+        index.releases.push(release.clone());
+        index.releases.sort_by_key(|r| Version::parse(&r.version).ok());
+        let mut json = serde_json::to_string_pretty(&index).unwrap_or_default();
+        json.push('\n');
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, json)?;
+        if let Err(e) = std::fs::rename(&partial, &path) {
+            std::fs::remove_file(&partial).ok();
+            return Err(e.into());
+        }
+        Ok(release)
+    }
+
+    /// Unpack a release into `dir`, checking its archive against the
+    /// `expected` checksum first
+    pub fn unpack(&self, name: &str, version: &str, expected: &str, dir: &Path) -> GrumpResult<()> {
+        let marker = dir.join(CHECKSUM_FILE);
+        if std::fs::read_to_string(&marker).is_ok_and(|existing| existing == expected) {
+            return Ok(());
+        }
+        let path = self.archive_path(name, version)?;
+        let bytes = std::fs::read(&path).map_err(|e| error(format!("Can't read {}: {}", path.display(), e)))?;
+        if checksum(&bytes) != expected {
+            return Err(error(format!("{} doesn't match its checksum; the registry copy has changed", path.display())));
+        }
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        std::fs::create_dir_all(dir)?;
+        // `unpack` refuses entries that would land outside `dir`
+        tar::Archive::new(bytes.as_slice()).unpack(dir)?;
+        std::fs::write(marker, expected)?;
+        Ok(())
+    }
+}
+
+/// Held while an index is rewritten; removes its lock file when dropped
+struct IndexLock(PathBuf);
+
+impl IndexLock {
+    fn acquire(path: PathBuf) -> GrumpResult<Self> {
+        for _ in 0..LOCK_ATTEMPTS {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => std::thread::sleep(LOCK_RETRY),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(error(format!(
+            "{} is held by another publish; delete it if none is running",
+            path.display()
+        )))
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// A package's sources as a tarball, paths relative to `project`
+pub fn archive(project: &Path) -> GrumpResult<Vec<u8>> {
+    let files = loader::source_files(&loader::source_root(project));
+    if files.is_empty() {
+        return Err(error(format!("{} has no .grump files to publish", project.display())));
+    }
+    let mut builder = tar::Builder::new(Vec::new());
+    for path in files {
+        let data = std::fs::read(&path)?;
+        let name = path.strip_prefix(project).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        builder.append_data(&mut header, name, data.as_slice())?;
+    }
+    builder.into_inner().map_err(GrumpError::from)
+}
+
+/// SHA-256, in hex
+pub fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
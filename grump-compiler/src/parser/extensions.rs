@@ -9,7 +9,7 @@
 //! - Debugger
 //! - Package management
 
-use crate::parser::{Expression, Statement, Type, Parameter};
+use crate::parser::{Expression, Statement, Type, Parameter, Span};
 
 // ============================================================================
 // SHADER SYSTEM
@@ -22,6 +22,7 @@ pub struct ShaderDeclaration {
     pub vertex_code: Option<String>,  // Shader code as string for now
     pub fragment_code: Option<String>,
     pub compute_code: Option<String>,  // For compute shaders
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +40,7 @@ pub struct Uniform {
 pub struct BehaviorTreeDeclaration {
     pub name: String,
     pub root: BehaviorNode,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +72,7 @@ pub struct NetworkDeclaration {
     pub name: String,
     pub sync_fields: Vec<SyncField>,
     pub rpc_functions: Vec<RpcFunction>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -121,11 +124,13 @@ pub struct MacroDeclaration {
     pub name: String,
     pub params: Vec<MacroParam>,
     pub body: MacroBody,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct MacroParam {
     pub name: String,
+    pub type_: Option<Type>,  // `count: int`; none for blocks
     pub is_variadic: bool,  // For ...args
     pub is_block: bool,  // For block parameters
 }
@@ -133,7 +138,7 @@ pub struct MacroParam {
 #[derive(Debug, Clone)]
 pub enum MacroBody {
     Code(Vec<Statement>),  // Regular macro expansion
-    Template(String),  // String template for code generation
+    Template(Vec<(String, String)>),  // (target, code) pairs; `$param` marks an argument
 }
 
 // ============================================================================
@@ -148,6 +153,7 @@ pub struct PluginDeclaration {
     pub url: Option<String>,  // Remote URL
     pub dependencies: Vec<String>,
     pub exports: Vec<String>,  // What this plugin exports
+    pub span: Span,
 }
 
 // ============================================================================
@@ -173,6 +179,7 @@ pub struct PackageDeclaration {
     pub version: String,
     pub dependencies: Vec<Dependency>,
     pub dev_dependencies: Vec<Dependency>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -181,6 +188,7 @@ pub struct Dependency {
     pub version: Option<String>,  // Version constraint
     pub path: Option<String>,  // Local path
     pub git: Option<String>,  // Git URL
+    pub branch: Option<String>,  // Git branch, when not pinned by version
     pub features: Vec<String>,  // Optional features
 }
 
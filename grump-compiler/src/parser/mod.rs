@@ -13,17 +13,49 @@ use extensions::*;
 mod parse_extensions;
 use parse_extensions::*;
 
+/// Position of a construct in the source (1-based, like lexer positions)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    /// Which of a project's files the construct is in, as an index into
+    /// `Project::sources`; 0 for the file being compiled
+    #[serde(default)]
+    pub file: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Program {
     pub items: Vec<Item>,
 }
 
+impl Program {
+    /// Every item in source order, including those nested in apps and modules
+    pub fn all_items(&self) -> Vec<&Item> {
+        let mut items = Vec::new();
+        collect_items(&self.items, &mut items);
+        items
+    }
+}
+
+fn collect_items<'a>(items: &'a [Item], out: &mut Vec<&'a Item>) {
+    for item in items {
+        out.push(item);
+        match item {
+            Item::App(app) => collect_items(&app.body, out),
+            Item::Module(module) => collect_items(&module.items, out),
+            _ => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Item {
     App(AppDeclaration),
     Scene(SceneDeclaration),
     Entity(EntityDeclaration),
     Component(ComponentDeclaration),
+    Enum(EnumDeclaration),
     System(SystemDeclaration),
     Function(FunctionDeclaration),
     Animation(AnimationDeclaration),
@@ -35,6 +67,79 @@ pub enum Item {
     Macro(MacroDeclaration),
     Plugin(PluginDeclaration),
     Package(PackageDeclaration),
+    Test(TestDeclaration),
+    Use(UseDeclaration),
+}
+
+impl Item {
+    /// Declared name of the item
+    pub fn name(&self) -> &str {
+        match self {
+            Item::App(d) => &d.name,
+            Item::Scene(d) => &d.name,
+            Item::Entity(d) => &d.name,
+            Item::Component(d) => &d.name,
+            Item::Enum(d) => &d.name,
+            Item::System(d) => &d.name,
+            Item::Function(d) => &d.name,
+            Item::Animation(d) => &d.name,
+            Item::Module(d) => &d.name,
+            Item::Shader(d) => &d.name,
+            Item::BehaviorTree(d) => &d.name,
+            Item::Network(d) => &d.name,
+            Item::Macro(d) => &d.name,
+            Item::Plugin(d) => &d.name,
+            Item::Package(d) => &d.name,
+            Item::Test(d) => &d.name,
+            Item::Use(d) => d.path.last().map_or("", String::as_str),
+        }
+    }
+    
+    /// Keyword the item is declared with
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Item::App(_) => "app",
+            Item::Scene(_) => "scene",
+            Item::Entity(_) => "entity",
+            Item::Component(_) => "component",
+            Item::Enum(_) => "enum",
+            Item::System(_) => "system",
+            Item::Function(_) => "fn",
+            Item::Animation(_) => "animation",
+            Item::Module(_) => "module",
+            Item::Shader(_) => "shader",
+            Item::BehaviorTree(_) => "behavior_tree",
+            Item::Network(_) => "network",
+            Item::Macro(_) => "macro",
+            Item::Plugin(_) => "plugin",
+            Item::Package(_) => "package",
+            Item::Test(_) => "test",
+            Item::Use(_) => "use",
+        }
+    }
+    
+    /// Where the item is declared
+    pub fn span(&self) -> Span {
+        match self {
+            Item::App(d) => d.span,
+            Item::Scene(d) => d.span,
+            Item::Entity(d) => d.span,
+            Item::Component(d) => d.span,
+            Item::Enum(d) => d.span,
+            Item::System(d) => d.span,
+            Item::Function(d) => d.span,
+            Item::Animation(d) => d.span,
+            Item::Module(d) => d.span,
+            Item::Shader(d) => d.span,
+            Item::BehaviorTree(d) => d.span,
+            Item::Network(d) => d.span,
+            Item::Macro(d) => d.span,
+            Item::Plugin(d) => d.span,
+            Item::Package(d) => d.span,
+            Item::Test(d) => d.span,
+            Item::Use(d) => d.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,12 +149,14 @@ pub struct AppDeclaration {
     pub targets: Vec<String>,
     pub fps: Option<f64>,
     pub body: Vec<Item>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct SceneDeclaration {
     pub name: String,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -57,12 +164,24 @@ pub struct EntityDeclaration {
     pub name: String,
     pub components: Vec<ComponentInstance>,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ComponentDeclaration {
     pub name: String,
     pub fields: Vec<Field>,
+    /// `@derive(Serialize, Lerp)`: code generated for it on each target
+    pub derives: Vec<String>,
+    pub span: Span,
+}
+
+/// `enum Direction { Up, Down }`: a closed set of named variants
+#[derive(Debug, Clone)]
+pub struct EnumDeclaration {
+    pub name: String,
+    pub variants: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +189,7 @@ pub struct SystemDeclaration {
     pub name: String,
     pub query: Vec<String>,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +199,7 @@ pub struct FunctionDeclaration {
     pub return_type: Option<Type>,
     pub body: Vec<Statement>,
     pub is_async: bool,  // NEW: Async function support
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -87,26 +208,53 @@ pub struct AnimationDeclaration {
     pub keyframes: Vec<Keyframe>,
     pub duration: Option<Expression>,
     pub loop_mode: Option<LoopMode>,
+    pub span: Span,
+}
+
+/// `test "name" { ... }`: run by `grump test`, left out of builds
+#[derive(Debug, Clone)]
+pub struct TestDeclaration {
+    pub name: String,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+/// `use physics::gravity;` brings one item of another file into scope,
+/// `use physics;` all of them
+#[derive(Debug, Clone)]
+pub struct UseDeclaration {
+    pub path: Vec<String>,
+    pub span: Span,
+}
+
+impl UseDeclaration {
+    /// `a::b::c`
+    pub fn path_string(&self) -> String {
+        self.path.join("::")
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ModuleDeclaration {
     pub name: String,
     pub items: Vec<Item>,
+    pub span: Span,
 }
 
+/// A statement. The ones code generators emit on lines of their own carry
+/// the span they start at, for statement-level source maps.
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Let { name: String, type_: Option<Type>, value: Expression },
-    Assign { target: Expression, value: Expression },
-    If { condition: Expression, then: Vec<Statement>, else_: Option<Vec<Statement>> },
-    Match { expr: Expression, arms: Vec<MatchArm> },
-    For { var: String, iter: Expression, body: Vec<Statement> },
-    While { condition: Expression, body: Vec<Statement> },
-    Return(Option<Expression>),
+    Let { name: String, mutable: bool, type_: Option<Type>, value: Expression, span: Span },
+    Assign { target: Expression, value: Expression, span: Span },
+    If { condition: Expression, then: Vec<Statement>, else_: Option<Vec<Statement>>, span: Span },
+    Match { expr: Expression, arms: Vec<MatchArm>, span: Span },
+    For { var: String, iter: Expression, body: Vec<Statement>, span: Span },
+    While { condition: Expression, body: Vec<Statement>, span: Span },
+    Return(Option<Expression>, Span),
     Break,
     Continue,
-    Expression(Expression),
+    Expression(Expression, Span),
     Animate(AnimateStatement),
     Timeline { name: String, entries: Vec<(Expression, Vec<(Expression, Vec<(String, Expression)>)>)> },
     // NEW: Extended statements
@@ -115,6 +263,37 @@ pub enum Statement {
     Network(NetworkStatement),  // network.sync(), network.send(), etc.
 }
 
+impl Statement {
+    /// Where the statement starts, for the kinds that record it
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Statement::Let { span, .. }
+            | Statement::Assign { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Match { span, .. }
+            | Statement::For { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Return(_, span)
+            | Statement::Expression(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    pub fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            Statement::Let { span, .. }
+            | Statement::Assign { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Match { span, .. }
+            | Statement::For { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Return(_, span)
+            | Statement::Expression(_, span) => Some(span),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AnimateStatement {
     pub target: Expression,
@@ -156,7 +335,7 @@ pub enum Expression {
     // NEW: Extended expressions
     Await(Box<Expression>),  // await future
     AsyncBlock(Vec<Statement>),  // async { ... }
-    MacroCall { name: String, args: Vec<Expression> },  // macro_name!(args)
+    MacroCall { name: String, args: Vec<Expression>, span: Span },  // macro_name!(args)
 }
 
 #[derive(Debug, Clone)]
@@ -262,7 +441,15 @@ pub struct Parser<'source> {
 
 impl<'source> Parser<'source> {
     pub fn new(source: &'source str) -> Self {
-        let mut lexer = Lexer::new(source);
+        Self::with_lexer(Lexer::new(source))
+    }
+    
+    /// Parse tokens that were lexed elsewhere (see `query::Tokens`)
+    pub fn from_tokens(tokens: Vec<(Token, usize, usize)>) -> Parser<'static> {
+        Parser::with_lexer(Lexer::replay(tokens))
+    }
+    
+    fn with_lexer(mut lexer: Lexer<'source>) -> Self {
         let current = lexer.next_token().ok().flatten();
         let peek = if current.is_some() {
             lexer.next_token().ok().flatten()
@@ -284,7 +471,22 @@ impl<'source> Parser<'source> {
             items.push(self.parse_item()?);
         }
         
-        Ok(Program { items })
+        // Expand calls to the macros declared here; the loader expands the rest
+        let mut program = Program { items };
+        crate::expander::Expander::new(&program).lenient().expand(&mut program)?;
+        Ok(program)
+    }
+    
+    /// Statements up to the end of the input, as typed at the REPL; the last
+    /// one may be an expression without a semicolon
+    pub fn parse_statements(&mut self) -> GrumpResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+        
+        while self.current.is_some() {
+            statements.push(self.parse_statement()?);
+        }
+        
+        Ok(statements)
     }
     
     fn parse_item(&mut self) -> GrumpResult<Item> {
@@ -305,13 +507,37 @@ impl<'source> Parser<'source> {
                 self.advance();
                 Ok(Item::Component(self.parse_component()?))
             }
+            Some(Token::At) => {
+                self.advance();
+                let attr = self.expect_identifier()?;
+                if attr != "derive" {
+                    return Err(self.error(&format!("Unknown attribute: {}", attr)));
+                }
+                let derives = self.parse_derives()?;
+                if !self.check(Token::Component) {
+                    return Err(self.error("Expected component after @derive(...)"));
+                }
+                self.advance();
+                let mut comp = self.parse_component()?;
+                comp.derives = derives;
+                Ok(Item::Component(comp))
+            }
+            Some(Token::Enum) => {
+                self.advance();
+                Ok(Item::Enum(self.parse_enum()?))
+            }
             Some(Token::System) => {
                 self.advance();
                 Ok(Item::System(self.parse_system()?))
             }
             Some(Token::Fn) => {
                 self.advance();
-                Ok(Item::Function(self.parse_function()?))
+                Ok(Item::Function(self.parse_function(false)?))
+            }
+            Some(Token::Async) => {
+                self.advance();
+                self.expect(Token::Fn)?;
+                Ok(Item::Function(self.parse_function(true)?))
             }
             Some(Token::Animation) => {
                 self.advance();
@@ -341,6 +567,14 @@ impl<'source> Parser<'source> {
                 self.advance();
                 Ok(Item::Package(self.parse_package()?))
             }
+            Some(Token::Test) => {
+                self.advance();
+                Ok(Item::Test(self.parse_test()?))
+            }
+            Some(Token::Use) => {
+                self.advance();
+                Ok(Item::Use(self.parse_use()?))
+            }
             _ => {
                 let (token, line, col) = self.current.take().unwrap();
                 Err(GrumpError::Parser {
@@ -353,6 +587,7 @@ impl<'source> Parser<'source> {
     }
     
     fn parse_app(&mut self) -> GrumpResult<AppDeclaration> {
+        let span = self.span();
         // @app "GameName" @version "1.0.0" @target [ios, android] @fps 60
         let name = self.expect_string()?;
         
@@ -372,7 +607,12 @@ impl<'source> Parser<'source> {
                         "target" => {
                             self.expect(Token::LeftBracket)?;
                             while !self.check(Token::RightBracket) {
-                                targets.push(self.expect_string()?);
+                                // Accept both `[ios, web]` and `["ios", "web"]`
+                                if let Some((Token::StringLiteral(_), _, _)) = self.current {
+                                    targets.push(self.expect_string()?);
+                                } else {
+                                    targets.push(self.expect_identifier()?);
+                                }
                                 if !self.check(Token::RightBracket) {
                                     self.expect(Token::Comma)?;
                                 }
@@ -411,23 +651,42 @@ impl<'source> Parser<'source> {
             targets,
             fps,
             body,
+            span,
         })
     }
     
     fn parse_scene(&mut self) -> GrumpResult<SceneDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
         let mut body = Vec::new();
         while !self.check(Token::RightBrace) {
+            // `on enter { ... }` spells out what the body already is: what
+            // runs when the scene is entered
+            let on_enter = matches!(
+                (&self.current, &self.peek),
+                (Some((Token::Identifier(on), _, _)), Some((Token::Identifier(enter), _, _))) if on == "on" && enter == "enter"
+            );
+            if on_enter {
+                self.advance();
+                self.advance();
+                self.expect(Token::LeftBrace)?;
+                while !self.check(Token::RightBrace) {
+                    body.push(self.parse_statement()?);
+                }
+                self.expect(Token::RightBrace)?;
+                continue;
+            }
             body.push(self.parse_statement()?);
         }
         self.expect(Token::RightBrace)?;
         
-        Ok(SceneDeclaration { name, body })
+        Ok(SceneDeclaration { name, body, span })
     }
     
     fn parse_entity(&mut self) -> GrumpResult<EntityDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -466,6 +725,7 @@ impl<'source> Parser<'source> {
             name,
             components,
             body,
+            span,
         })
     }
     
@@ -489,6 +749,7 @@ impl<'source> Parser<'source> {
     }
     
     fn parse_component(&mut self) -> GrumpResult<ComponentDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -513,10 +774,54 @@ impl<'source> Parser<'source> {
         }
         self.expect(Token::RightBrace)?;
         
-        Ok(ComponentDeclaration { name, fields })
+        Ok(ComponentDeclaration { name, fields, derives: Vec::new(), span })
+    }
+
+    /// `(Serialize, Inspect)` after `@derive`
+    fn parse_derives(&mut self) -> GrumpResult<Vec<String>> {
+        self.expect(Token::LeftParen)?;
+        let mut derives = Vec::new();
+        while !self.check(Token::RightParen) {
+            derives.push(self.expect_identifier()?);
+            if !self.check(Token::RightParen) {
+                self.expect(Token::Comma)?;
+            }
+        }
+        self.expect(Token::RightParen)?;
+        Ok(derives)
+    }
+    
+    fn parse_enum(&mut self) -> GrumpResult<EnumDeclaration> {
+        let span = self.span();
+        let name = self.expect_identifier()?;
+        self.expect(Token::LeftBrace)?;
+        
+        let mut variants = Vec::new();
+        while !self.check(Token::RightBrace) {
+            let variant = self.expect_identifier()?;
+            if variants.contains(&variant) {
+                return Err(self.error(&format!("Duplicate variant '{}' in enum {}", variant, name)));
+            }
+            variants.push(variant);
+            if !self.check(Token::RightBrace) {
+                self.expect(Token::Comma)?;
+            }
+        }
+        self.expect(Token::RightBrace)?;
+        
+        if variants.is_empty() {
+            return Err(self.error(&format!("Enum {} needs at least one variant", name)));
+        }
+        
+        Ok(EnumDeclaration {
+            name,
+            variants,
+            span,
+        })
     }
     
     fn parse_system(&mut self) -> GrumpResult<SystemDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -548,18 +853,37 @@ impl<'source> Parser<'source> {
         }
         self.expect(Token::RightBrace)?;
         
-        Ok(SystemDeclaration { name, query, body })
+        Ok(SystemDeclaration { name, query, body, span })
     }
     
-    fn parse_function(&mut self) -> GrumpResult<FunctionDeclaration> {
-        // Check for async keyword
-        let is_async = if self.check(Token::Async) {
+    fn parse_use(&mut self) -> GrumpResult<UseDeclaration> {
+        let span = self.span();
+        let mut path = vec![self.expect_identifier()?];
+        while self.check(Token::ColonColon) {
             self.advance();
-            true
-        } else {
-            false
-        };
+            path.push(self.expect_identifier()?);
+        }
+        self.expect(Token::Semicolon)?;
         
+        Ok(UseDeclaration { path, span })
+    }
+    
+    fn parse_test(&mut self) -> GrumpResult<TestDeclaration> {
+        let span = self.span();
+        let name = self.expect_string()?;
+        self.expect(Token::LeftBrace)?;
+        
+        let mut body = Vec::new();
+        while !self.check(Token::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.expect(Token::RightBrace)?;
+        
+        Ok(TestDeclaration { name, body, span })
+    }
+    
+    fn parse_function(&mut self, is_async: bool) -> GrumpResult<FunctionDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftParen)?;
         
@@ -600,10 +924,12 @@ impl<'source> Parser<'source> {
             return_type,
             body,
             is_async,
+            span,
         })
     }
     
     fn parse_animation(&mut self) -> GrumpResult<AnimationDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -637,6 +963,7 @@ impl<'source> Parser<'source> {
             keyframes,
             duration,
             loop_mode,
+            span,
         })
     }
     
@@ -702,9 +1029,16 @@ impl<'source> Parser<'source> {
     }
     
     fn parse_statement(&mut self) -> GrumpResult<Statement> {
+        let span = self.span();
         match self.current.as_ref().map(|(t, _, _)| t) {
             Some(Token::Let) => {
                 self.advance();
+                let mutable = if self.check(Token::Mut) {
+                    self.advance();
+                    true
+                } else {
+                    false
+                };
                 let name = self.expect_identifier()?;
                 let type_ = if self.check(Token::Colon) {
                     self.advance();
@@ -715,7 +1049,7 @@ impl<'source> Parser<'source> {
                 self.expect(Token::Equals)?;
                 let value = self.parse_expression()?;
                 self.expect(Token::Semicolon)?;
-                Ok(Statement::Let { name, type_, value })
+                Ok(Statement::Let { name, mutable, type_, value, span })
             }
             Some(Token::If) => {
                 self.advance();
@@ -740,7 +1074,7 @@ impl<'source> Parser<'source> {
                 } else {
                     None
                 };
-                Ok(Statement::If { condition, then, else_ })
+                Ok(Statement::If { condition, then, else_, span })
             }
             Some(Token::Return) => {
                 self.advance();
@@ -750,7 +1084,7 @@ impl<'source> Parser<'source> {
                     None
                 };
                 self.expect(Token::Semicolon)?;
-                Ok(Statement::Return(expr))
+                Ok(Statement::Return(expr, span))
             }
             Some(Token::Animate) => {
                 self.advance();
@@ -810,7 +1144,7 @@ impl<'source> Parser<'source> {
                     body.push(self.parse_statement()?);
                 }
                 self.expect(Token::RightBrace)?;
-                Ok(Statement::For { var, iter, body })
+                Ok(Statement::For { var, iter, body, span })
             }
             Some(Token::While) => {
                 self.advance();
@@ -823,7 +1157,7 @@ impl<'source> Parser<'source> {
                     body.push(self.parse_statement()?);
                 }
                 self.expect(Token::RightBrace)?;
-                Ok(Statement::While { condition, body })
+                Ok(Statement::While { condition, body, span })
             }
             Some(Token::Break) => {
                 self.advance();
@@ -886,14 +1220,27 @@ impl<'source> Parser<'source> {
                     arms.push(crate::parser::MatchArm { pattern, guard, body });
                 }
                 self.expect(Token::RightBrace)?;
-                Ok(Statement::Match { expr, arms })
+                Ok(Statement::Match { expr, arms, span })
             }
             _ => {
-                let expr = self.parse_expression()?;
+                let mut expr = self.parse_expression()?;
+                // A macro call statement may pass a block: `repeat!(3) { |i| ... }`
+                if let Expression::MacroCall { args, .. } = &mut expr {
+                    if self.check(Token::LeftBrace) {
+                        args.push(self.parse_macro_block()?);
+                    }
+                }
+                let stmt = if self.check(Token::Equals) {
+                    self.advance();
+                    let value = self.parse_expression()?;
+                    Statement::Assign { target: expr, value, span }
+                } else {
+                    Statement::Expression(expr, span)
+                };
                 if self.check(Token::Semicolon) {
                     self.advance();
                 }
-                Ok(Statement::Expression(expr))
+                Ok(stmt)
             }
         }
     }
@@ -965,6 +1312,7 @@ impl<'source> Parser<'source> {
     }
     
     fn parse_primary(&mut self) -> GrumpResult<Expression> {
+        let start = self.span();
         let mut expr = match self.current.as_ref().map(|(t, _, _)| t) {
             Some(Token::Integer(n)) => {
                 self.advance();
@@ -992,9 +1340,23 @@ impl<'source> Parser<'source> {
             }
             Some(Token::LeftParen) => {
                 self.advance();
-                let expr = self.parse_expression()?;
-                self.expect(Token::RightParen)?;
-                expr
+                let first = self.parse_expression()?;
+                if self.check(Token::RightParen) {
+                    self.advance();
+                    first
+                } else {
+                    // `(x, y)` is a tuple; `(x)` only groups
+                    let mut elements = vec![first];
+                    while self.check(Token::Comma) {
+                        self.advance();
+                        if self.check(Token::RightParen) {
+                            break;
+                        }
+                        elements.push(self.parse_expression()?);
+                    }
+                    self.expect(Token::RightParen)?;
+                    Expression::Tuple(elements)
+                }
             }
             Some(Token::LeftBracket) => {
                 self.advance();
@@ -1054,6 +1416,22 @@ impl<'source> Parser<'source> {
                         args,
                     };
                 }
+                // `name!(args)` calls a macro
+                Some(Token::Not) if matches!(self.peek, Some((Token::LeftParen, _, _))) => {
+                    let Expression::Identifier(name) = &expr else { break };
+                    let name = name.clone();
+                    self.advance();
+                    self.advance();
+                    let mut args = Vec::new();
+                    while !self.check(Token::RightParen) {
+                        args.push(self.parse_expression()?);
+                        if !self.check(Token::RightParen) {
+                            self.expect(Token::Comma)?;
+                        }
+                    }
+                    self.expect(Token::RightParen)?;
+                    expr = Expression::MacroCall { name, args, span: start };
+                }
                 Some(Token::LeftBracket) => {
                     self.advance();
                     let index = self.parse_expression()?;
@@ -1092,7 +1470,14 @@ impl<'source> Parser<'source> {
                 self.advance();
                 Ok(Type::Named(name.clone()))
             }
-            _ => Err(self.error("Expected type")),
+            Some(token) => match builtin_type(token) {
+                Some(type_) => {
+                    self.advance();
+                    Ok(type_)
+                }
+                None => Err(self.error("Expected type")),
+            },
+            None => Err(self.error("Expected type")),
         }
     }
     
@@ -1154,6 +1539,13 @@ impl<'source> Parser<'source> {
         }
     }
     
+    /// Position of the current token
+    fn span(&self) -> Span {
+        self.current.as_ref()
+            .map(|(_, line, column)| Span { line: *line, column: *column, file: 0 })
+            .unwrap_or_default()
+    }
+    
     fn error(&self, msg: &str) -> GrumpError {
         if let Some((_, line, col)) = &self.current {
             GrumpError::Parser {
@@ -1171,6 +1563,24 @@ impl<'source> Parser<'source> {
     }
 }
 
+/// The other type keywords: `int64`, `vec2`, `color`, ...
+fn builtin_type(token: &Token) -> Option<Type> {
+    match token {
+        Token::Int64 => Some(Type::Int64),
+        Token::Double => Some(Type::Double),
+        Token::Char => Some(Type::Char),
+        Token::Vec2 => Some(Type::Vec2),
+        Token::Vec3 => Some(Type::Vec3),
+        Token::Vec4 => Some(Type::Vec4),
+        Token::Color => Some(Type::Color),
+        Token::Angle => Some(Type::Angle),
+        Token::Rotation => Some(Type::Rotation),
+        Token::Transform => Some(Type::Transform),
+        Token::Duration => Some(Type::Duration),
+        _ => None,
+    }
+}
+
 fn binary_op(token: &Token) -> Option<BinaryOp> {
     match token {
         Token::Plus => Some(BinaryOp::Add),
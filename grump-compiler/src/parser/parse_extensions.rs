@@ -9,6 +9,7 @@ use crate::parser::extensions::*;
 
 impl<'source> Parser<'source> {
     pub fn parse_shader(&mut self) -> GrumpResult<ShaderDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -72,6 +73,7 @@ impl<'source> Parser<'source> {
             vertex_code,
             fragment_code,
             compute_code,
+            span,
         })
     }
     
@@ -112,12 +114,13 @@ impl<'source> Parser<'source> {
     }
     
     pub fn parse_behavior_tree(&mut self) -> GrumpResult<BehaviorTreeDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         let root = self.parse_behavior_node()?;
         self.expect(Token::RightBrace)?;
         
-        Ok(BehaviorTreeDeclaration { name, root })
+        Ok(BehaviorTreeDeclaration { name, root, span })
     }
     
     fn parse_behavior_node(&mut self) -> GrumpResult<BehaviorNode> {
@@ -168,6 +171,7 @@ impl<'source> Parser<'source> {
     }
     
     pub fn parse_network(&mut self) -> GrumpResult<NetworkDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
@@ -180,10 +184,25 @@ impl<'source> Parser<'source> {
                 let field_name = self.expect_identifier()?;
                 self.expect(Token::Colon)?;
                 let field_type = self.parse_type()?;
+                let sync_mode = if self.check(Token::At) {
+                    self.advance();
+                    match self.expect_identifier()?.as_str() {
+                        "reliable" => SyncMode::Reliable,
+                        "unreliable" => SyncMode::Unreliable,
+                        "interpolated" => SyncMode::Interpolated,
+                        "snapshot" => SyncMode::Snapshot,
+                        other => return Err(self.error(&format!(
+                            "Unknown sync mode '{}', expected reliable, unreliable, interpolated or snapshot",
+                            other
+                        ))),
+                    }
+                } else {
+                    SyncMode::Reliable
+                };
                 sync_fields.push(SyncField {
                     name: field_name,
                     type_: field_type,
-                    sync_mode: SyncMode::Reliable,  // Default
+                    sync_mode,
                 });
             } else if self.check(Token::Rpc) {
                 self.advance();
@@ -234,10 +253,12 @@ impl<'source> Parser<'source> {
             name,
             sync_fields,
             rpc_functions,
+            span,
         })
     }
     
     pub fn parse_macro(&mut self) -> GrumpResult<MacroDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftParen)?;
         let mut params = Vec::new();
@@ -249,9 +270,21 @@ impl<'source> Parser<'source> {
             } else {
                 false
             };
-            let is_block = param_name == "block" || self.check(Token::LeftBrace);
+            // `body: block` takes a block; other annotations type the argument
+            let mut is_block = param_name == "block";
+            let mut type_ = None;
+            if self.check(Token::Colon) {
+                self.advance();
+                if matches!(&self.current, Some((Token::Identifier(name), _, _)) if name == "block") {
+                    self.advance();
+                    is_block = true;
+                } else {
+                    type_ = Some(self.parse_type()?);
+                }
+            }
             params.push(MacroParam {
                 name: param_name,
+                type_,
                 is_variadic,
                 is_block,
             });
@@ -261,30 +294,46 @@ impl<'source> Parser<'source> {
         }
         self.expect(Token::RightParen)?;
         self.expect(Token::LeftBrace)?;
-        let mut body = Vec::new();
-        while !self.check(Token::RightBrace) {
-            body.push(self.parse_statement()?);
-        }
+        // `@ios "..."` lines make a template macro; anything else is G-Rump code
+        let body = if self.check(Token::At) {
+            let mut templates = Vec::new();
+            while self.check(Token::At) {
+                self.advance();
+                let target = self.expect_identifier()?;
+                let code = self.expect_string()?.replace("\\\"", "\"");
+                templates.push((target, code));
+            }
+            MacroBody::Template(templates)
+        } else {
+            let mut body = Vec::new();
+            while !self.check(Token::RightBrace) {
+                body.push(self.parse_statement()?);
+            }
+            MacroBody::Code(body)
+        };
         self.expect(Token::RightBrace)?;
         
         Ok(MacroDeclaration {
             name,
             params,
-            body: MacroBody::Code(body),
+            body,
+            span,
         })
     }
     
     pub fn parse_plugin(&mut self) -> GrumpResult<PluginDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
         let mut version = None;
         let mut path = None;
+        let mut url = None;
         let mut dependencies = Vec::new();
         let mut exports = Vec::new();
         
         while !self.check(Token::RightBrace) {
-            let key = self.expect_identifier()?;
+            let key = self.expect_key()?;
             self.expect(Token::Colon)?;
             match key.as_str() {
                 "version" => {
@@ -293,6 +342,9 @@ impl<'source> Parser<'source> {
                 "path" => {
                     path = Some(self.expect_string()?);
                 }
+                "url" => {
+                    url = Some(self.expect_string()?);
+                }
                 "dependencies" => {
                     self.expect(Token::LeftBracket)?;
                     while !self.check(Token::RightBracket) {
@@ -322,58 +374,34 @@ impl<'source> Parser<'source> {
             name,
             version,
             path,
-            url: None,
+            url,
             dependencies,
             exports,
+            span,
         })
     }
     
     pub fn parse_package(&mut self) -> GrumpResult<PackageDeclaration> {
+        let span = self.span();
         let name = self.expect_identifier()?;
         self.expect(Token::LeftBrace)?;
         
         let mut version = None;
         let mut dependencies = Vec::new();
+        let mut dev_dependencies = Vec::new();
         
         while !self.check(Token::RightBrace) {
-            let key = self.expect_identifier()?;
+            let key = self.expect_key()?;
             self.expect(Token::Colon)?;
             match key.as_str() {
                 "version" => {
                     version = Some(self.expect_string()?);
                 }
                 "dependencies" => {
-                    self.expect(Token::LeftBrace)?;
-                    while !self.check(Token::RightBrace) {
-                        let dep_name = self.expect_string()?;
-                        self.expect(Token::Colon)?;
-                        self.expect(Token::LeftBrace)?;
-                        let mut version_req = None;
-                        let mut git_url = None;
-                        while !self.check(Token::RightBrace) {
-                            let dep_key = self.expect_identifier()?;
-                            self.expect(Token::Colon)?;
-                            match dep_key.as_str() {
-                                "version" => {
-                                    version_req = Some(self.expect_string()?);
-                                }
-                                "git" => {
-                                    git_url = Some(self.expect_string()?);
-                                }
-                                _ => return Err(self.error(&format!("Unknown dependency property: {}", dep_key))),
-                            }
-                        }
-                        self.expect(Token::RightBrace)?;
-                        dependencies.push(Dependency {
-                            name: dep_name,
-                            version: version_req,
-                            git: git_url,
-                        });
-                        if !self.check(Token::RightBrace) {
-                            self.expect(Token::Comma)?;
-                        }
-                    }
-                    self.expect(Token::RightBrace)?;
+                    dependencies = self.parse_dependencies()?;
+                }
+                "dev_dependencies" => {
+                    dev_dependencies = self.parse_dependencies()?;
                 }
                 _ => return Err(self.error(&format!("Unknown package property: {}", key))),
             }
@@ -384,7 +412,98 @@ impl<'source> Parser<'source> {
             name,
             version: version.unwrap_or_else(|| "1.0.0".to_string()),
             dependencies,
+            dev_dependencies,
+            span,
+        })
+    }
+    
+    /// `{ "name": { version: "^1.0" path: "..." } ... }`, commas optional
+    fn parse_dependencies(&mut self) -> GrumpResult<Vec<Dependency>> {
+        let mut dependencies = Vec::new();
+        self.expect(Token::LeftBrace)?;
+        while !self.check(Token::RightBrace) {
+            let mut dependency = Dependency {
+                name: self.expect_string()?,
+                version: None,
+                path: None,
+                git: None,
+                branch: None,
+                features: Vec::new(),
+            };
+            self.expect(Token::Colon)?;
+            self.expect(Token::LeftBrace)?;
+            while !self.check(Token::RightBrace) {
+                let key = self.expect_key()?;
+                self.expect(Token::Colon)?;
+                match key.as_str() {
+                    "version" => dependency.version = Some(self.expect_string()?),
+                    "path" => dependency.path = Some(self.expect_string()?),
+                    "git" => dependency.git = Some(self.expect_string()?),
+                    "branch" => dependency.branch = Some(self.expect_string()?),
+                    "features" => {
+                        self.expect(Token::LeftBracket)?;
+                        while !self.check(Token::RightBracket) {
+                            dependency.features.push(self.expect_string()?);
+                            if !self.check(Token::RightBracket) {
+                                self.expect(Token::Comma)?;
+                            }
+                        }
+                        self.expect(Token::RightBracket)?;
+                    }
+                    _ => return Err(self.error(&format!("Unknown dependency property: {}", key))),
+                }
+                if self.check(Token::Comma) {
+                    self.advance();
+                }
+            }
+            self.expect(Token::RightBrace)?;
+            dependencies.push(dependency);
+            if self.check(Token::Comma) {
+                self.advance();
+            }
+        }
+        self.expect(Token::RightBrace)?;
+        Ok(dependencies)
+    }
+    
+    /// The block passed to a macro call statement, `{ |a, b| ... }`: a
+    /// lambda if it names parameters, otherwise a block
+    pub(super) fn parse_macro_block(&mut self) -> GrumpResult<Expression> {
+        self.expect(Token::LeftBrace)?;
+        let mut params = None;
+        if self.check(Token::OrOr) {
+            self.advance();
+            params = Some(Vec::new());
+        } else if self.check(Token::Or) {
+            self.advance();
+            let mut names = Vec::new();
+            while !self.check(Token::Or) {
+                names.push(Parameter { name: self.expect_identifier()?, type_: None });
+                if !self.check(Token::Or) {
+                    self.expect(Token::Comma)?;
+                }
+            }
+            self.advance();
+            params = Some(names);
+        }
+        let mut body = Vec::new();
+        while !self.check(Token::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.expect(Token::RightBrace)?;
+        Ok(match params {
+            Some(params) => Expression::Lambda { params, body: Box::new(Expression::Block(body)) },
+            None => Expression::Block(body),
         })
     }
+
+    /// A property name; `dependencies` lexes as a keyword
+    fn expect_key(&mut self) -> GrumpResult<String> {
+        if self.check(Token::Dependencies) {
+            self.advance();
+            return Ok("dependencies".to_string());
+        }
+        self.expect_identifier()
+    }
 }
 
@@ -0,0 +1,290 @@
+//! Plugins
+//!
+//! A `plugin` declaration names a local plugin by `path`:
+//!
+//! ```text
+//! plugin noise {
+//!     version: "1.0.0"
+//!     path: "plugins/noise.wasm"
+//!     exports: ["perlin", "octaves"]
+//! }
+//! ```
+//!
+//! A path to a `.wasm` file is a WASM plugin. Each name in `exports` becomes
+//! a built-in function, for the analyzer and the interpreter alike. A path
+//! to a directory is a source plugin: its .grump files are loaded as a
+//! module named after the plugin, and `exports` must name items they
+//! declare.
+//!
+//! Either kind can also provide hooks, found by name:
+//!
+//! - `grump_lint` is called with every item's name and keyword and returns a
+//!   warning about it, or an empty string
+//! - `grump_codegen` is called with the target, path and text of every
+//!   generated text file and returns its replacement, or an empty string to
+//!   leave it alone
+//!
+//! A source plugin declares them as functions taking and returning
+//! `String`s; for WASM plugins see [`wasm`], which also describes the ABI.
+//! Plugins are loaded from the project directory; `url` plugins aren't
+//! fetched, so they're refused.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::analyzer::types::FunctionSignature;
+use crate::codegen::OutputTree;
+use crate::error::{GrumpError, GrumpResult};
+use crate::interpreter::{Interpreter, Value};
+use crate::loader;
+use crate::parser::extensions::PluginDeclaration;
+use crate::parser::{Item, Parser, Program};
+
+pub mod wasm;
+
+pub use wasm::WasmPlugin;
+
+/// Bumped when the WASM plugin ABI changes incompatibly
+pub const PLUGIN_ABI: i32 = 1;
+pub const LINT_HOOK: &str = "grump_lint";
+pub const CODEGEN_HOOK: &str = "grump_codegen";
+
+/// A warning from a plugin's lint hook
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintMessage {
+    pub plugin: String,
+    pub item: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}: `{}`: {} ({})", self.line, self.column, self.item, self.message, self.plugin)
+    }
+}
+
+/// One loaded plugin
+pub struct Plugin {
+    pub name: String,
+    /// The built-in functions it adds
+    pub functions: Vec<(String, FunctionSignature)>,
+    kind: Kind,
+}
+
+enum Kind {
+    Wasm(Box<RefCell<WasmPlugin>>),
+    /// An interpreter with the plugin's items declared, to run its hooks
+    Source(Box<RefCell<Interpreter>>),
+}
+
+/// Every plugin a program declares. Clones share the loaded plugins.
+#[derive(Clone, Default)]
+pub struct PluginHost {
+    plugins: Vec<Rc<Plugin>>,
+}
+
+impl PluginHost {
+    /// Load the plugins `program` declares, with paths relative to `project`
+    pub fn load(program: &Program, project: &Path) -> GrumpResult<Self> {
+        let declarations: Vec<&PluginDeclaration> = program.all_items().into_iter()
+            .filter_map(|item| match item {
+                Item::Plugin(declaration) => Some(declaration),
+                _ => None,
+            })
+            .collect();
+        let mut plugins = Vec::new();
+        for declaration in &declarations {
+            validate(declaration)?;
+            if let Some(missing) = declaration.dependencies.iter().find(|d| !declarations.iter().any(|p| &p.name == *d)) {
+                return Err(error(&declaration.name, format!("depends on plugin `{}`, which isn't declared", missing)));
+            }
+            plugins.push(Rc::new(Plugin::load(declaration, project)?));
+        }
+        Ok(Self { plugins })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    pub fn plugins(&self) -> impl Iterator<Item = &Plugin> {
+        self.plugins.iter().map(|plugin| plugin.as_ref())
+    }
+
+    /// Every built-in function the plugins add
+    pub fn functions(&self) -> impl Iterator<Item = &(String, FunctionSignature)> {
+        self.plugins().flat_map(|plugin| plugin.functions.iter())
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions().any(|(n, _)| n == name)
+    }
+
+    /// Call a plugin's built-in function; `None` if no plugin adds `name`
+    pub fn call(&self, name: &str, args: &[Value]) -> Option<GrumpResult<Value>> {
+        let plugin = self.plugins().find(|plugin| plugin.functions.iter().any(|(n, _)| n == name))?;
+        match &plugin.kind {
+            Kind::Wasm(module) => Some(module.borrow_mut().call(name, args)),
+            Kind::Source(_) => None,
+        }
+    }
+
+    /// Run every lint hook over every item of `program`
+    pub fn lint(&self, program: &Program) -> GrumpResult<Vec<LintMessage>> {
+        let mut messages = Vec::new();
+        for plugin in self.plugins().filter(|plugin| plugin.has_hook(LINT_HOOK)) {
+            for item in program.all_items() {
+                if matches!(item, Item::Use(_) | Item::Plugin(_)) {
+                    continue;
+                }
+                let message = match &plugin.kind {
+                    Kind::Wasm(module) => {
+                        let input = serde_json::json!({
+                            "name": item.name(),
+                            "keyword": item.keyword(),
+                            "line": item.span().line,
+                            "column": item.span().column,
+                        });
+                        module.borrow_mut().call_hook(LINT_HOOK, &input.to_string())?
+                    }
+                    Kind::Source(interpreter) => {
+                        let args = vec![Value::String(item.name().to_string()), Value::String(item.keyword().to_string())];
+                        plugin.text(interpreter.borrow_mut().call(LINT_HOOK, args))?
+                    }
+                };
+                if !message.is_empty() {
+                    messages.push(LintMessage {
+                        plugin: plugin.name.clone(),
+                        item: item.name().to_string(),
+                        line: item.span().line,
+                        column: item.span().column,
+                        message,
+                    });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Run every codegen hook over the text files of `tree`, in plugin order
+    pub fn transform(&self, target: &str, tree: &mut OutputTree) -> GrumpResult<()> {
+        for plugin in self.plugins().filter(|plugin| plugin.has_hook(CODEGEN_HOOK)) {
+            let paths: Vec<String> = tree.paths().cloned().collect();
+            for path in paths {
+                let Some(contents) = tree.get_text(&path).map(str::to_string) else {
+                    continue;
+                };
+                let replaced = match &plugin.kind {
+                    Kind::Wasm(module) => {
+                        let input = serde_json::json!({ "target": target, "path": path, "contents": contents });
+                        module.borrow_mut().call_hook(CODEGEN_HOOK, &input.to_string())?
+                    }
+                    Kind::Source(interpreter) => {
+                        let args = vec![Value::String(target.to_string()), Value::String(path.clone()), Value::String(contents)];
+                        plugin.text(interpreter.borrow_mut().call(CODEGEN_HOOK, args))?
+                    }
+                };
+                if !replaced.is_empty() {
+                    tree.add_text(&path, replaced);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Plugin {
+    fn load(declaration: &PluginDeclaration, project: &Path) -> GrumpResult<Self> {
+        let name = declaration.name.clone();
+        let path = location(declaration, project)?;
+        if let Some(dir) = source_dir(declaration, project) {
+            let program = parse_dir(&name, &dir)?;
+            let declared: Vec<&str> = program.all_items().into_iter().map(Item::name).collect();
+            if let Some(missing) = declaration.exports.iter().find(|export| !declared.contains(&export.as_str())) {
+                return Err(error(&name, format!("exports `{}`, but {} doesn't declare it", missing, dir.display())));
+            }
+            let mut interpreter = Interpreter::new();
+            interpreter.load(&program);
+            return Ok(Self { name, functions: Vec::new(), kind: Kind::Source(Box::new(RefCell::new(interpreter))) });
+        }
+
+        let bytes = std::fs::read(&path).map_err(|e| error(&name, format!("Can't read {}: {}", path.display(), e)))?;
+        let (module, functions) = WasmPlugin::load(&name, &bytes, &declaration.exports)?;
+        Ok(Self { name, functions, kind: Kind::Wasm(Box::new(RefCell::new(module))) })
+    }
+
+    pub fn has_hook(&self, hook: &str) -> bool {
+        match &self.kind {
+            Kind::Wasm(module) => module.borrow().has_hook(hook),
+            Kind::Source(interpreter) => interpreter.borrow().has_function(hook),
+        }
+    }
+
+    /// A source hook's result, which must be a string
+    fn text(&self, result: GrumpResult<Value>) -> GrumpResult<String> {
+        match result {
+            Ok(Value::String(text)) => Ok(text),
+            Ok(other) => Err(error(&self.name, format!("a hook returned {}, not a String", other))),
+            Err(e) => Err(error(&self.name, e.to_string())),
+        }
+    }
+}
+
+/// Check a declaration without loading it: a semver version, and one of
+/// `path` or `url`
+pub fn validate(declaration: &PluginDeclaration) -> GrumpResult<()> {
+    let name = &declaration.name;
+    if let Some(version) = &declaration.version {
+        if semver::Version::parse(version).is_err() {
+            return Err(error(name, format!("has version \"{}\", which isn't a semantic version like \"1.0.0\"", version)));
+        }
+    }
+    match (&declaration.path, &declaration.url) {
+        (Some(_), Some(_)) => Err(error(name, "has both a `path` and a `url`".to_string())),
+        (None, None) => Err(error(name, "needs a `path` (or a `url`)".to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// The directory of a source plugin, if `declaration` names one
+pub fn source_dir(declaration: &PluginDeclaration, project: &Path) -> Option<PathBuf> {
+    let dir = project.join(declaration.path.as_ref()?);
+    dir.is_dir().then_some(dir)
+}
+
+/// Where a plugin is; it must be local and exist
+fn location(declaration: &PluginDeclaration, project: &Path) -> GrumpResult<PathBuf> {
+    let name = &declaration.name;
+    let Some(path) = &declaration.path else {
+        return Err(match &declaration.url {
+            Some(url) => error(name, format!("only local plugins can be loaded; give a `path` instead of {}", url)),
+            None => error(name, "needs a `path`".to_string()),
+        });
+    };
+    let path = project.join(path);
+    if !path.exists() {
+        return Err(error(name, format!("{} doesn't exist", path.display())));
+    }
+    Ok(path)
+}
+
+/// A source plugin's files as one program
+fn parse_dir(name: &str, dir: &Path) -> GrumpResult<Program> {
+    let mut items = Vec::new();
+    for path in loader::source_files(&loader::source_root(dir)) {
+        let source = std::fs::read_to_string(&path)?;
+        let program = Parser::new(&source).parse().map_err(|e| loader::in_file(e, &path))?;
+        items.extend(program.items);
+    }
+    if items.is_empty() {
+        return Err(error(name, format!("{} has no .grump files", dir.display())));
+    }
+    Ok(Program { items })
+}
+
+fn error(plugin: &str, message: String) -> GrumpError {
+    GrumpError::Plugin { plugin: plugin.to_string(), message }
+}
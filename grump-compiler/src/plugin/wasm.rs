@@ -0,0 +1,195 @@
+//! WASM plugins and the plugin ABI
+//!
+//! A plugin is a WASM module, run by an interpreter in its own store. It's
+//! sandboxed: it may not import anything (so no files, clock or network),
+//! its memory is capped, and every call gets a fixed amount of fuel, so a
+//! runaway loop traps instead of hanging the build. ABI version 1:
+//!
+//! ```text
+//! grump_abi_version() -> i32      must return 1
+//! <export>(...) -> ...            each name in `exports`; f64 is float,
+//!                                 i64 int, i32 bool, and at most one result
+//! memory                          needed by hooks
+//! grump_alloc(len: i32) -> i32    needed by hooks: room for `len` bytes
+//! grump_lint(ptr, len) -> i64     optional hook
+//! grump_codegen(ptr, len) -> i64  optional hook
+//! ```
+//!
+//! A hook is given UTF-8 JSON the host wrote into a `grump_alloc`ed buffer
+//! (`{"name", "keyword", "line", "column"}` for lint, `{"target", "path",
+//! "contents"}` for codegen) and returns its UTF-8 text result packed as
+//! `ptr << 32 | len`.
+
+use wasmi::core::{ValType, F64};
+use wasmi::{Config, Engine, Func, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Val};
+
+use crate::analyzer::types::{FunctionSignature, Type};
+use crate::error::{GrumpError, GrumpResult};
+use crate::interpreter::Value;
+
+use super::{CODEGEN_HOOK, LINT_HOOK, PLUGIN_ABI};
+
+pub const ABI_EXPORT: &str = "grump_abi_version";
+pub const ALLOC_EXPORT: &str = "grump_alloc";
+/// Instructions (roughly) a single call may run
+pub const FUEL: u64 = 10_000_000;
+/// Largest linear memory a plugin may grow
+pub const MAX_MEMORY: usize = 64 << 20;
+
+pub struct WasmPlugin {
+    name: String,
+    store: Store<StoreLimits>,
+    instance: Instance,
+}
+
+impl WasmPlugin {
+    /// Instantiate a plugin's module, checking its ABI version and that it
+    /// exports each of `exports` with a type the ABI allows. Returns the
+    /// plugin and the signatures of its exports.
+    pub fn load(name: &str, bytes: &[u8], exports: &[String]) -> GrumpResult<(Self, Vec<(String, FunctionSignature)>)> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| error(name, format!("isn't a valid WASM module: {}", e)))?;
+        if let Some(import) = module.imports().next() {
+            return Err(error(name, format!(
+                "imports `{}::{}`, but plugins are sandboxed and get no imports",
+                import.module(), import.name()
+            )));
+        }
+
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL).map_err(|e| error(name, e.to_string()))?;
+        let instance = Linker::<StoreLimits>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| error(name, format!("failed to start: {}", e)))?;
+        let mut plugin = Self { name: name.to_string(), store, instance };
+
+        match plugin.call_raw(ABI_EXPORT, &[])? {
+            Some(Val::I32(version)) if version == PLUGIN_ABI => {}
+            Some(Val::I32(version)) => {
+                return Err(plugin.error(format!("is built for plugin ABI {}; this grump supports {}", version, PLUGIN_ABI)));
+            }
+            _ => return Err(plugin.error(format!("`{}` must return an i32", ABI_EXPORT))),
+        }
+
+        let mut functions = Vec::new();
+        for export in exports {
+            let func = plugin.func(export)?;
+            let ty = func.ty(&plugin.store);
+            let params = ty.params().iter().enumerate()
+                .map(|(i, ty)| Ok((format!("arg{}", i), grump_type(*ty).ok_or(())?)))
+                .collect::<Result<Vec<_>, ()>>();
+            let return_type = match ty.results() {
+                [] => Some(Type::Never),
+                [ty] => grump_type(*ty),
+                _ => None,
+            };
+            let (Ok(params), Some(return_type)) = (params, return_type) else {
+                return Err(plugin.error(format!("`{}` has type {:?}; exports take and return f64, i64 or i32", export, ty)));
+            };
+            functions.push((export.clone(), FunctionSignature { params, return_type }));
+        }
+
+        if let Some(hook) = [LINT_HOOK, CODEGEN_HOOK].into_iter().find(|hook| plugin.has_hook(hook)) {
+            if plugin.memory().is_none() || plugin.func(ALLOC_EXPORT).is_err() {
+                return Err(plugin.error(format!("has `{}`, so it must export `memory` and `{}`", hook, ALLOC_EXPORT)));
+            }
+        }
+        Ok((plugin, functions))
+    }
+
+    pub fn has_hook(&self, hook: &str) -> bool {
+        self.instance.get_func(&self.store, hook).is_some()
+    }
+
+    /// Call an exported function with interpreter values
+    pub fn call(&mut self, name: &str, args: &[Value]) -> GrumpResult<Value> {
+        let func = self.func(name)?;
+        let ty = func.ty(&self.store);
+        if ty.params().len() != args.len() {
+            return Err(self.error(format!("`{}` takes {} arguments, got {}", name, ty.params().len(), args.len())));
+        }
+        let inputs = ty.params().iter().zip(args)
+            .map(|(ty, arg)| match (ty, arg) {
+                (ValType::F64, Value::Float(x)) => Some(Val::F64(F64::from_float(*x))),
+                (ValType::F64, Value::Int(x)) => Some(Val::F64(F64::from_float(*x as f64))),
+                (ValType::I64, Value::Int(x)) => Some(Val::I64(*x)),
+                (ValType::I32, Value::Bool(b)) => Some(Val::I32(*b as i32)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| self.error(format!("`{}` was called with the wrong argument types", name)))?;
+        Ok(match self.call_raw(name, &inputs)? {
+            None => Value::Unit,
+            Some(Val::F64(x)) => Value::Float(x.to_float()),
+            Some(Val::I64(x)) => Value::Int(x),
+            Some(Val::I32(x)) => Value::Bool(x != 0),
+            Some(other) => return Err(self.error(format!("`{}` returned {:?}", name, other))),
+        })
+    }
+
+    /// Run a hook on `input`
+    pub fn call_hook(&mut self, hook: &str, input: &str) -> GrumpResult<String> {
+        let len = i32::try_from(input.len()).map_err(|_| self.error(format!("`{}` input is too large", hook)))?;
+        let Some(Val::I32(ptr)) = self.call_raw(ALLOC_EXPORT, &[Val::I32(len)])? else {
+            return Err(self.error(format!("`{}` must return an i32", ALLOC_EXPORT)));
+        };
+        let memory = self.memory().ok_or_else(|| self.error("exports no `memory`".to_string()))?;
+        memory.write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+            .map_err(|_| self.error(format!("`{}` returned a buffer outside its memory", ALLOC_EXPORT)))?;
+
+        let Some(Val::I64(packed)) = self.call_raw(hook, &[Val::I32(ptr), Val::I32(len)])? else {
+            return Err(self.error(format!("`{}` must return an i64", hook)));
+        };
+        let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+        // Check the length against the memory before allocating for it
+        let size = memory.data(&self.store).len().min(MAX_MEMORY);
+        if ptr.checked_add(len).is_none_or(|end| end > size) {
+            return Err(self.error(format!("`{}` returned a buffer outside its memory", hook)));
+        }
+        let mut output = vec![0; len];
+        memory.read(&self.store, ptr, &mut output)
+            .map_err(|_| self.error(format!("`{}` returned a buffer outside its memory", hook)))?;
+        String::from_utf8(output).map_err(|_| self.error(format!("`{}` returned text that isn't UTF-8", hook)))
+    }
+
+    /// Call an export with a fresh tank of fuel; traps are errors
+    fn call_raw(&mut self, name: &str, inputs: &[Val]) -> GrumpResult<Option<Val>> {
+        let func = self.func(name)?;
+        let mut outputs = vec![Val::I32(0); func.ty(&self.store).results().len()];
+        self.store.set_fuel(FUEL).map_err(|e| self.error(e.to_string()))?;
+        func.call(&mut self.store, inputs, &mut outputs)
+            .map_err(|e| self.error(format!("`{}` trapped: {}", name, e)))?;
+        Ok(outputs.into_iter().next())
+    }
+
+    fn func(&self, name: &str) -> GrumpResult<Func> {
+        self.instance.get_func(&self.store, name)
+            .ok_or_else(|| self.error(format!("doesn't export a function `{}`", name)))
+    }
+
+    fn memory(&self) -> Option<Memory> {
+        self.instance.get_memory(&self.store, "memory")
+    }
+
+    fn error(&self, message: String) -> GrumpError {
+        error(&self.name, message)
+    }
+}
+
+fn grump_type(ty: ValType) -> Option<Type> {
+    match ty {
+        ValType::F64 => Some(Type::Float),
+        ValType::I64 => Some(Type::Int),
+        ValType::I32 => Some(Type::Bool),
+        _ => None,
+    }
+}
+
+fn error(plugin: &str, message: String) -> GrumpError {
+    GrumpError::Plugin { plugin: plugin.to_string(), message }
+}
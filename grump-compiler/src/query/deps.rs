@@ -0,0 +1,290 @@
+//! Name dependency graph
+//!
+//! An item's analysis only reads the declarations of the names it uses
+//! (function signatures, enum variants, component and entity types), so a
+//! cached analysis stays valid until the item itself or the declaration of
+//! one of those names changes. Editing a function's body leaves its
+//! signature, and so every caller, untouched.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::parser::extensions::{BehaviorNode, DecoratorType, NetworkStatement};
+use crate::parser::{AnimateStatement, Expression, Item, Pattern, Statement, Type};
+use super::items::fnv1a;
+
+/// Fingerprint of what `item` declares for other items, if anything
+pub fn interface(item: &Item) -> Option<u64> {
+    let declared = match item {
+        Item::Function(func) => format!("{:?} {:?} {}", func.params, func.return_type, func.is_async),
+        Item::Component(comp) => format!("{:?} {:?}", comp.fields, comp.derives),
+        Item::Enum(decl) => format!("{:?}", decl.variants),
+        Item::Entity(_) => String::new(),
+        Item::Macro(decl) => format!("{:?} {:?}", decl.params, decl.body),
+        _ => return None,
+    };
+    Some(fnv1a(format!("{} {}", item.keyword(), declared).as_bytes()))
+}
+
+/// Interfaces by name; names declared twice combine their fingerprints
+pub fn interfaces(items: &[&Item]) -> HashMap<String, u64> {
+    let mut interfaces = HashMap::new();
+    for item in items {
+        if let Some(fingerprint) = interface(item) {
+            interfaces.entry(item.name().to_string())
+                .and_modify(|existing: &mut u64| *existing = existing.rotate_left(7) ^ fingerprint)
+                .or_insert(fingerprint);
+        }
+    }
+    interfaces
+}
+
+/// Every name `item` refers to
+pub fn references(item: &Item) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    match item {
+        Item::Scene(scene) => statements(&scene.body, &mut names),
+        Item::Entity(entity) => {
+            for component in &entity.components {
+                names.insert(component.name.clone());
+                expressions(&component.args, &mut names);
+            }
+            statements(&entity.body, &mut names);
+        }
+        Item::Component(comp) => {
+            for field in &comp.fields {
+                type_names(&field.type_, &mut names);
+                if let Some(default) = &field.default {
+                    expression(default, &mut names);
+                }
+            }
+        }
+        Item::System(system) => {
+            names.extend(system.query.iter().cloned());
+            statements(&system.body, &mut names);
+        }
+        Item::Function(func) => {
+            for param in &func.params {
+                if let Some(type_) = &param.type_ {
+                    type_names(type_, &mut names);
+                }
+            }
+            if let Some(return_type) = &func.return_type {
+                type_names(return_type, &mut names);
+            }
+            statements(&func.body, &mut names);
+        }
+        Item::Animation(animation) => {
+            for keyframe in &animation.keyframes {
+                expression(&keyframe.time, &mut names);
+                expression(&keyframe.value, &mut names);
+            }
+            if let Some(duration) = &animation.duration {
+                expression(duration, &mut names);
+            }
+        }
+        Item::BehaviorTree(tree) => behavior_node(&tree.root, &mut names),
+        Item::Test(test) => statements(&test.body, &mut names),
+        _ => {}
+    }
+    names
+}
+
+fn statements(body: &[Statement], names: &mut BTreeSet<String>) {
+    for stmt in body {
+        statement(stmt, names);
+    }
+}
+
+fn statement(stmt: &Statement, names: &mut BTreeSet<String>) {
+    match stmt {
+        Statement::Let { type_, value, .. } => {
+            if let Some(type_) = type_ {
+                type_names(type_, names);
+            }
+            expression(value, names);
+        }
+        Statement::Assign { target, value, .. } => {
+            expression(target, names);
+            expression(value, names);
+        }
+        Statement::If { condition, then, else_, .. } => {
+            expression(condition, names);
+            statements(then, names);
+            if let Some(else_) = else_ {
+                statements(else_, names);
+            }
+        }
+        Statement::Match { expr, arms, .. } => {
+            expression(expr, names);
+            for arm in arms {
+                pattern(&arm.pattern, names);
+                if let Some(guard) = &arm.guard {
+                    expression(guard, names);
+                }
+                statements(&arm.body, names);
+            }
+        }
+        Statement::For { iter, body, .. } => {
+            expression(iter, names);
+            statements(body, names);
+        }
+        Statement::While { condition, body, .. } => {
+            expression(condition, names);
+            statements(body, names);
+        }
+        Statement::Return(Some(expr), _) | Statement::Expression(expr, _) => expression(expr, names),
+        Statement::Animate(animate) => animate_statement(animate, names),
+        Statement::Timeline { entries, .. } => {
+            for (time, targets) in entries {
+                expression(time, names);
+                for (target, properties) in targets {
+                    expression(target, names);
+                    for (_, value) in properties {
+                        expression(value, names);
+                    }
+                }
+            }
+        }
+        Statement::Await { expr } => expression(expr, names),
+        Statement::Network(network) => match network {
+            NetworkStatement::Sync { value, .. } => expression(value, names),
+            NetworkStatement::Send { function, args, .. } => {
+                names.insert(function.clone());
+                expressions(args, names);
+            }
+            NetworkStatement::Receive { body, .. } => statements(body, names),
+            NetworkStatement::Connect { address } => expression(address, names),
+            NetworkStatement::Disconnect => {}
+        },
+        Statement::Return(None, _) | Statement::Break | Statement::Continue | Statement::Debugger(_) => {}
+    }
+}
+
+fn animate_statement(animate: &AnimateStatement, names: &mut BTreeSet<String>) {
+    expression(&animate.target, names);
+    for keyframe in &animate.keyframes {
+        expression(&keyframe.time, names);
+        expression(&keyframe.value, names);
+    }
+    for expr in [&animate.duration, &animate.ease].into_iter().flatten() {
+        expression(expr, names);
+    }
+}
+
+fn expressions(exprs: &[Expression], names: &mut BTreeSet<String>) {
+    for expr in exprs {
+        expression(expr, names);
+    }
+}
+
+fn expression(expr: &Expression, names: &mut BTreeSet<String>) {
+    match expr {
+        Expression::Literal(_) => {}
+        Expression::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        Expression::Binary { left, right, .. } => {
+            expression(left, names);
+            expression(right, names);
+        }
+        Expression::Unary { expr, .. } | Expression::Await(expr) => expression(expr, names),
+        Expression::Call { func, args } => {
+            expression(func, names);
+            expressions(args, names);
+        }
+        Expression::Member { object, .. } => expression(object, names),
+        Expression::Index { object, index } => {
+            expression(object, names);
+            expression(index, names);
+        }
+        Expression::Tuple(exprs) | Expression::Array(exprs) => expressions(exprs, names),
+        Expression::Block(body) | Expression::AsyncBlock(body) => statements(body, names),
+        Expression::If { condition, then, else_ } => {
+            expression(condition, names);
+            expression(then, names);
+            expression(else_, names);
+        }
+        Expression::Lambda { params, body } => {
+            for param in params {
+                if let Some(type_) = &param.type_ {
+                    type_names(type_, names);
+                }
+            }
+            expression(body, names);
+        }
+        Expression::MacroCall { name, args, .. } => {
+            names.insert(name.clone());
+            expressions(args, names);
+        }
+    }
+}
+
+fn pattern(pattern: &Pattern, names: &mut BTreeSet<String>) {
+    match pattern {
+        Pattern::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        Pattern::Tuple(patterns) => {
+            for p in patterns {
+                self::pattern(p, names);
+            }
+        }
+        Pattern::Struct { name, fields } => {
+            names.insert(name.clone());
+            for (_, p) in fields {
+                self::pattern(p, names);
+            }
+        }
+        Pattern::Literal(_) | Pattern::Wildcard => {}
+    }
+}
+
+fn type_names(type_: &Type, names: &mut BTreeSet<String>) {
+    match type_ {
+        Type::Named(name) => {
+            names.insert(name.clone());
+        }
+        Type::Optional(inner) | Type::Array(inner) => type_names(inner, names),
+        Type::Result { ok, err } => {
+            type_names(ok, names);
+            type_names(err, names);
+        }
+        Type::Tuple(types) => {
+            for t in types {
+                type_names(t, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn behavior_node(node: &BehaviorNode, names: &mut BTreeSet<String>) {
+    match node {
+        BehaviorNode::Selector { children } | BehaviorNode::Sequence { children } => {
+            for child in children {
+                behavior_node(child, names);
+            }
+        }
+        BehaviorNode::Condition { expr } => expression(expr, names),
+        BehaviorNode::Action { name, params } => {
+            names.insert(name.clone());
+            expressions(params, names);
+        }
+        BehaviorNode::Decorator { decorator_type, child } => {
+            match decorator_type {
+                DecoratorType::Limit { max } => expression(max, names),
+                DecoratorType::Cooldown { duration } => expression(duration, names),
+                DecoratorType::UntilSuccess | DecoratorType::UntilFailure => {}
+            }
+            behavior_node(child, names);
+        }
+        BehaviorNode::Inverter { child } => behavior_node(child, names),
+        BehaviorNode::Repeater { count, child } => {
+            if let Some(count) = count {
+                expression(count, names);
+            }
+            behavior_node(child, names);
+        }
+        BehaviorNode::Wait { duration } => expression(duration, names),
+    }
+}
@@ -0,0 +1,174 @@
+//! Per-item parsing
+//!
+//! A file's tokens are split into top-level items, each running until the
+//! brace that closes its body, and every item is parsed on its own. Parses
+//! are memoized on the item's text with lines counted from the item's first
+//! line, so an item that only moved (because lines were added above it) is
+//! reused with its spans shifted.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::expander::respan;
+use crate::lexer::Token;
+use crate::parser::extensions::MacroBody;
+use crate::parser::{Item, Parser, Span};
+use super::tokens::Lexeme;
+use super::Problem;
+
+/// Memo key: the item's first column and its source text
+type SegmentKey = (usize, String);
+
+/// Parses of the items in one file
+#[derive(Default)]
+pub struct ItemParses {
+    /// Items with spans relative to their segment's first line
+    memo: HashMap<SegmentKey, Result<Vec<Item>, Problem>>,
+}
+
+impl ItemParses {
+    /// Parse `lexemes` item by item, reusing memoized parses. Returns the
+    /// items (or the first error, like `Parser::parse`) and how many items
+    /// had to be parsed.
+    pub fn parse(&mut self, text: &str, lexemes: &[Lexeme]) -> (Result<Vec<Item>, Problem>, usize) {
+        let mut memo = HashMap::new();
+        let mut items = Vec::new();
+        let mut parsed = 0;
+        let ranges = segments(lexemes);
+        for (index, range) in ranges.iter().enumerate() {
+            let segment = &lexemes[range.clone()];
+            let (first, last) = (&segment[0], &segment[segment.len() - 1]);
+            let key = (first.column, text[first.start..last.end].to_string());
+            let result = match self.memo.remove(&key).or_else(|| memo.get(&key).cloned()) {
+                Some(result) => result,
+                None => {
+                    parsed += 1;
+                    parse_segment(segment)
+                }
+            };
+            memo.insert(key.clone(), result);
+
+            let offset = first.line - 1;
+            match &memo[&key] {
+                Ok(segment_items) => {
+                    let mut segment_items = segment_items.clone();
+                    relocate(&mut segment_items, &|span| Span { line: span.line + offset, ..span });
+                    items.extend(segment_items);
+                }
+                Err(problem) => {
+                    let mut problem = problem.clone();
+                    if problem.line == 0 {
+                        // Ran out of tokens: the full parser would have hit the next item
+                        if let Some(next) = ranges.get(index + 1) {
+                            problem.line = lexemes[next.start].line;
+                            problem.column = lexemes[next.start].column;
+                        }
+                    } else {
+                        problem.line += offset;
+                    }
+                    self.memo = memo;
+                    return (Err(problem), parsed);
+                }
+            }
+        }
+        self.memo = memo;
+        (Ok(items), parsed)
+    }
+}
+
+/// Token ranges of the top-level items
+fn segments(lexemes: &[Lexeme]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let (mut start, mut depth) = (0, 0usize);
+    for (index, lexeme) in lexemes.iter().enumerate() {
+        match lexeme.token {
+            Token::LeftBrace => depth += 1,
+            Token::RightBrace => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    segments.push(start..index + 1);
+                    start = index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if start < lexemes.len() {
+        segments.push(start..lexemes.len());
+    }
+    segments
+}
+
+/// Parse one item's tokens with lines counted from its first line
+fn parse_segment(segment: &[Lexeme]) -> Result<Vec<Item>, Problem> {
+    let offset = segment[0].line - 1;
+    let tokens = segment.iter()
+        .map(|l| (l.token.clone(), l.line - offset, l.column))
+        .collect();
+    Parser::from_tokens(tokens).parse()
+        .map(|program| program.items)
+        .map_err(|e| Problem::from_error(&e, Span::default()))
+}
+
+/// Rewrite the span of every item and statement, including nested ones
+pub fn relocate(items: &mut [Item], f: &impl Fn(Span) -> Span) {
+    for item in items {
+        match item {
+            Item::App(d) => {
+                d.span = f(d.span);
+                relocate(&mut d.body, f);
+            }
+            Item::Module(d) => {
+                d.span = f(d.span);
+                relocate(&mut d.items, f);
+            }
+            Item::Scene(d) => {
+                d.span = f(d.span);
+                respan(&mut d.body, f);
+            }
+            Item::Entity(d) => {
+                d.span = f(d.span);
+                respan(&mut d.body, f);
+            }
+            Item::Component(d) => d.span = f(d.span),
+            Item::Enum(d) => d.span = f(d.span),
+            Item::System(d) => {
+                d.span = f(d.span);
+                respan(&mut d.body, f);
+            }
+            Item::Function(d) => {
+                d.span = f(d.span);
+                respan(&mut d.body, f);
+            }
+            Item::Animation(d) => d.span = f(d.span),
+            Item::Shader(d) => d.span = f(d.span),
+            Item::BehaviorTree(d) => d.span = f(d.span),
+            Item::Network(d) => d.span = f(d.span),
+            Item::Macro(d) => {
+                d.span = f(d.span);
+                if let MacroBody::Code(body) = &mut d.body {
+                    respan(body, f);
+                }
+            }
+            Item::Plugin(d) => d.span = f(d.span),
+            Item::Package(d) => d.span = f(d.span),
+            Item::Test(d) => {
+                d.span = f(d.span);
+                respan(&mut d.body, f);
+            }
+            Item::Use(d) => d.span = f(d.span),
+        }
+    }
+}
+
+/// Hash of an item's contents, ignoring where it is
+pub fn fingerprint(item: &Item) -> u64 {
+    let mut item = [item.clone()];
+    relocate(&mut item, &|_| Span::default());
+    fnv1a(format!("{:?}", item[0]).as_bytes())
+}
+
+/// FNV-1a: stable across runs and compiler versions, unlike `DefaultHasher`
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,400 @@
+//! Incremental front end
+//!
+//! A small query database in the spirit of salsa: source text is the input,
+//! and tokens, items, per-item analyses and diagnostics are derived from it
+//! on demand and memoized against the revision they were computed at. Each
+//! layer does as little as an edit requires:
+//!
+//! - lexing restarts at the edited line and resyncs with the old tokens
+//!   past the edit (`tokens`)
+//! - items are parsed one at a time and reused when their text is
+//!   unchanged, even if they moved (`items`)
+//! - analyses are cached per item and invalidated through the names each
+//!   item uses (`deps`)
+//!
+//! Item analyses and diagnostics can be saved to disk, so repeated
+//! `grump check` runs only re-check what changed since the last one.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer::types::Type;
+use crate::analyzer::Analyzer;
+use crate::error::{GrumpError, GrumpResult};
+use crate::expander::Expander;
+use crate::parser::{Program, Span};
+
+pub mod deps;
+pub mod items;
+pub mod tokens;
+
+pub use tokens::{Lexeme, Tokens};
+
+/// An error found by a query, detached from the `GrumpError` that raised
+/// it so it can be cached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// 1-based; the offending token for lexer and parser errors, otherwise
+    /// the name of the item it was found in. Line 0 is the end of the file.
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProblemKind {
+    Lexer,
+    Parser,
+    Type,
+    Ownership,
+    Animation,
+    Runtime,
+    Other,
+}
+
+impl Problem {
+    pub fn lexer(line: usize, column: usize, message: String) -> Self {
+        Problem { kind: ProblemKind::Lexer, line, column, message }
+    }
+
+    /// `error`, placed at `span` unless it carries its own position
+    pub fn from_error(error: &GrumpError, span: Span) -> Self {
+        let (kind, line, column, message) = match error {
+            GrumpError::Lexer { line, column, message } => (ProblemKind::Lexer, *line, *column, message.clone()),
+            GrumpError::Parser { line, column, message } => (ProblemKind::Parser, *line, *column, message.clone()),
+            GrumpError::Type { message } => (ProblemKind::Type, span.line, span.column, message.clone()),
+            GrumpError::Ownership { message } => (ProblemKind::Ownership, span.line, span.column, message.clone()),
+            GrumpError::Animation { message } => (ProblemKind::Animation, span.line, span.column, message.clone()),
+            GrumpError::Runtime { message } => (ProblemKind::Runtime, span.line, span.column, message.clone()),
+            GrumpError::Module { file, line, column, message } => {
+                (ProblemKind::Other, *line, *column, format!("{}: {}", file, message))
+            }
+            GrumpError::Macro { line, column, message } => (ProblemKind::Other, *line, *column, message.clone()),
+            GrumpError::Package { message } => (ProblemKind::Other, span.line, span.column, message.clone()),
+            GrumpError::Plugin { plugin, message } => {
+                (ProblemKind::Other, span.line, span.column, format!("{}: {}", plugin, message))
+            }
+            GrumpError::Io(e) => (ProblemKind::Other, span.line, span.column, e.to_string()),
+            GrumpError::Other(e) => (ProblemKind::Other, span.line, span.column, e.to_string()),
+        };
+        Problem { kind, line, column, message }
+    }
+
+    pub fn to_error(&self) -> GrumpError {
+        let message = self.message.clone();
+        match self.kind {
+            ProblemKind::Lexer => GrumpError::Lexer { line: self.line, column: self.column, message },
+            ProblemKind::Parser => GrumpError::Parser { line: self.line, column: self.column, message },
+            ProblemKind::Type => GrumpError::Type { message },
+            ProblemKind::Ownership => GrumpError::Ownership { message },
+            ProblemKind::Animation => GrumpError::Animation { message },
+            ProblemKind::Runtime => GrumpError::Runtime { message },
+            ProblemKind::Other => GrumpError::Other(anyhow::anyhow!(message)),
+        }
+    }
+}
+
+/// What analysis found in one item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemResult {
+    pub span: Span,
+    pub problems: Vec<Problem>,
+    /// Types of the item's `let` bindings, in order
+    pub bindings: Vec<(String, Type)>,
+}
+
+/// A file as of its last analysis
+pub struct Analysis<'a> {
+    pub text: &'a str,
+    /// The last version of the file that parsed: the current one unless
+    /// `parse_error` is set
+    pub program: Option<&'a Program>,
+    pub parse_error: Option<&'a Problem>,
+    /// One per `program.all_items()`
+    pub items: &'a [ItemResult],
+}
+
+/// Work done by queries, for checking that edits stay cheap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub tokens_lexed: usize,
+    pub items_parsed: usize,
+    pub items_analyzed: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedItem {
+    fingerprint: u64,
+    /// Names the item uses and the fingerprint of their declaration then
+    deps: Vec<(String, Option<u64>)>,
+    result: ItemResult,
+}
+
+#[derive(Default)]
+struct SourceFile {
+    text: String,
+    changed_at: u64,
+    tokens: Tokens,
+    lexed_at: Option<u64>,
+    parses: items::ItemParses,
+    parsed_at: Option<u64>,
+    program: Option<Program>,
+    program_at: u64,
+    parse_error: Option<Problem>,
+    analyzed_at: Option<u64>,
+    items: Vec<ItemResult>,
+    /// Item analyses by item key (`fn update`)
+    cache: HashMap<String, CachedItem>,
+    /// Diagnostics for the text with this hash
+    checked: Option<(u64, Vec<Problem>)>,
+}
+
+/// On-disk form of the database
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    version: String,
+    files: HashMap<String, StoredFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredFile {
+    checked: Option<(u64, Vec<Problem>)>,
+    items: HashMap<String, CachedItem>,
+}
+
+/// Source files and everything derived from them
+#[derive(Default)]
+pub struct Database {
+    revision: u64,
+    files: HashMap<String, SourceFile>,
+    stats: Stats,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a database saved by `save`; a missing, unreadable or outdated
+    /// file gives an empty database
+    pub fn load(path: &Path) -> Self {
+        let stored: Option<Stored> = std::fs::read_to_string(path).ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .filter(|stored: &Stored| stored.version == env!("CARGO_PKG_VERSION"));
+        let mut db = Database::new();
+        for (name, file) in stored.map(|s| s.files).unwrap_or_default() {
+            db.files.insert(name, SourceFile { checked: file.checked, cache: file.items, ..Default::default() });
+        }
+        db
+    }
+
+    /// Save item analyses and diagnostics for `load`
+    pub fn save(&self, path: &Path) -> GrumpResult<()> {
+        let stored = Stored {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            files: self.files.iter().map(|(name, file)| {
+                (name.clone(), StoredFile { checked: file.checked.clone(), items: file.cache.clone() })
+            }).collect(),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(&stored).map_err(|e| GrumpError::Other(e.into()))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Set a file's text. Nothing is recomputed until a query asks.
+    pub fn set_source(&mut self, file: &str, text: &str) {
+        let revision = self.revision + 1;
+        let source = self.files.entry(file.to_string()).or_default();
+        if source.text != text || source.lexed_at.is_none() {
+            source.text = text.to_string();
+            source.changed_at = revision;
+            self.revision = revision;
+        }
+    }
+
+    pub fn remove(&mut self, file: &str) {
+        self.files.remove(file);
+    }
+
+    pub fn source(&self, file: &str) -> Option<&str> {
+        self.files.get(file).map(|f| f.text.as_str())
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Tokens of the file's current text
+    pub fn tokens(&mut self, file: &str) -> &[Lexeme] {
+        self.lex(file);
+        match self.files.get(file) {
+            Some(source) => source.tokens.lexemes(),
+            None => &[],
+        }
+    }
+
+    /// Items of the file's current text, or why it doesn't parse.
+    /// Panics if the file was never set.
+    pub fn parse(&mut self, file: &str) -> Result<&Program, &Problem> {
+        self.parse_items(file);
+        let source = &self.files[file];
+        match (&source.parse_error, &source.program) {
+            (None, Some(program)) => Ok(program),
+            (Some(problem), _) => Err(problem),
+            (None, None) => unreachable!("a file that parsed has a program"),
+        }
+    }
+
+    /// Analysis of the last version of the file that parsed
+    pub fn analyze(&mut self, file: &str) -> Option<Analysis<'_>> {
+        self.analyze_items(file);
+        let source = self.files.get(file)?;
+        Some(Analysis {
+            text: &source.text,
+            program: source.program.as_ref(),
+            parse_error: source.parse_error.as_ref(),
+            items: &source.items,
+        })
+    }
+
+    /// Every problem in the file's current text: the parse error if it
+    /// doesn't parse, otherwise what analysis found in each item
+    pub fn diagnostics(&mut self, file: &str) -> Vec<Problem> {
+        let hash = match self.files.get(file) {
+            Some(source) => items::fnv1a(source.text.as_bytes()),
+            None => return Vec::new(),
+        };
+        if let Some((checked, problems)) = &self.files[file].checked {
+            if *checked == hash {
+                return problems.clone();
+            }
+        }
+        self.analyze_items(file);
+        let source = self.files.get_mut(file).expect("file exists");
+        let problems = match &source.parse_error {
+            Some(problem) => vec![problem.clone()],
+            None => source.items.iter().flat_map(|item| item.problems.iter().cloned()).collect(),
+        };
+        source.checked = Some((hash, problems.clone()));
+        problems
+    }
+
+    /// The first problem in the file, as `grump check` reports it
+    pub fn check(&mut self, file: &str) -> GrumpResult<()> {
+        match self.diagnostics(file).first() {
+            Some(problem) => Err(problem.to_error()),
+            None => Ok(()),
+        }
+    }
+
+    fn lex(&mut self, file: &str) {
+        if let Some(source) = self.files.get_mut(file) {
+            if source.lexed_at != Some(source.changed_at) {
+                self.stats.tokens_lexed += source.tokens.update(&source.text);
+                source.lexed_at = Some(source.changed_at);
+            }
+        }
+    }
+
+    fn parse_items(&mut self, file: &str) {
+        self.lex(file);
+        let source = match self.files.get_mut(file) {
+            Some(source) if source.parsed_at != Some(source.changed_at) => source,
+            _ => return,
+        };
+        let (result, parsed) = source.parses.parse(&source.text, source.tokens.lexemes());
+        self.stats.items_parsed += parsed;
+        // Tokens stop at a lexer error; unless the parser failed before
+        // reaching it, the lexer error is the real problem
+        let result = match (result, source.tokens.error()) {
+            (Err(problem), _) if problem.line > 0 => Err(problem),
+            (_, Some(problem)) => Err(problem),
+            (result, None) => result,
+        };
+        // Items are parsed on their own, so calls to macros declared in
+        // other items of the file are expanded here
+        let result = result.and_then(|items| {
+            let mut program = Program { items };
+            Expander::new(&program).lenient().expand(&mut program)
+                .map_err(|e| Problem::from_error(&e, Span::default()))?;
+            Ok(program)
+        });
+        match result {
+            Ok(program) => {
+                source.program = Some(program);
+                source.program_at = source.changed_at;
+                source.parse_error = None;
+            }
+            Err(problem) => source.parse_error = Some(problem),
+        }
+        source.parsed_at = Some(source.changed_at);
+    }
+
+    fn analyze_items(&mut self, file: &str) {
+        self.parse_items(file);
+        let source = match self.files.get_mut(file) {
+            Some(source) if source.program.is_some() && source.analyzed_at != Some(source.program_at) => source,
+            _ => return,
+        };
+        let program = source.program.as_ref().expect("checked above");
+        let items = program.all_items();
+        let mut analyzer = Analyzer::new();
+        analyzer.declare(&items);
+        let interfaces = deps::interfaces(&items);
+
+        let mut old = std::mem::take(&mut source.cache);
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            // `fn update`, then `fn update#1` for a second one
+            let name = format!("{} {}", item.keyword(), item.name());
+            let seen = occurrences.entry(name.clone()).or_insert(0);
+            let key = if *seen == 0 { name } else { format!("{}#{}", name, seen) };
+            *seen += 1;
+
+            let fingerprint = items::fingerprint(item);
+            let span = item.span();
+            let entry = match old.remove(&key) {
+                Some(mut entry) if entry.fingerprint == fingerprint
+                    && entry.deps.iter().all(|(name, fp)| interfaces.get(name) == fp.as_ref()) =>
+                {
+                    // Still valid; the item may have moved
+                    entry.result.span = span;
+                    for problem in &mut entry.result.problems {
+                        problem.line = span.line;
+                        problem.column = span.column;
+                    }
+                    entry
+                }
+                _ => {
+                    self.stats.items_analyzed += 1;
+                    let analysis = analyzer.analyze_item(item);
+                    CachedItem {
+                        fingerprint,
+                        deps: deps::references(item).into_iter()
+                            .map(|name| {
+                                let declared = interfaces.get(&name).copied();
+                                (name, declared)
+                            })
+                            .collect(),
+                        result: ItemResult {
+                            span,
+                            problems: analysis.errors.iter().map(|e| Problem::from_error(e, span)).collect(),
+                            bindings: analysis.bindings,
+                        },
+                    }
+                }
+            };
+            results.push(entry.result.clone());
+            source.cache.insert(key, entry);
+        }
+        source.items = results;
+        source.analyzed_at = Some(source.program_at);
+    }
+}
@@ -0,0 +1,140 @@
+//! Re-lexing after an edit
+//!
+//! Tokens that end before the first edited line are kept. Lexing resumes
+//! right after the last of them (never inside a comment or string, so an
+//! edit in a block comment that began further up is still seen whole) and
+//! stops as soon as it lines up with the old tokens past the edit, which
+//! are shifted into place instead of being lexed again.
+
+use logos::Logos;
+
+use crate::lexer::Token;
+use super::Problem;
+
+/// A token with its byte range and 1-based position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    pub token: Token,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The token stream of one file
+#[derive(Default)]
+pub struct Tokens {
+    text: String,
+    line_starts: Vec<usize>,
+    lexemes: Vec<Lexeme>,
+    /// First unrecognized character; lexing stops there, like the parser does
+    error: Option<usize>,
+}
+
+impl Tokens {
+    pub fn new(text: &str) -> Self {
+        let mut tokens = Tokens { line_starts: vec![0], ..Default::default() };
+        tokens.update(text);
+        tokens
+    }
+
+    pub fn lexemes(&self) -> &[Lexeme] {
+        &self.lexemes
+    }
+
+    /// The lexer error that cut the stream short, if any
+    pub fn error(&self) -> Option<Problem> {
+        self.error.map(|offset| {
+            let (line, column) = self.position(offset);
+            let found = self.text[offset..].chars().next().unwrap_or('?');
+            Problem::lexer(line, column, format!("Unexpected character: '{}'", found))
+        })
+    }
+
+    /// Bring the tokens up to date with `text`; returns how many were lexed
+    pub fn update(&mut self, text: &str) -> usize {
+        if text == self.text && !self.line_starts.is_empty() {
+            return 0;
+        }
+        let old_text = std::mem::replace(&mut self.text, text.to_string());
+        self.line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        // Bytes shared at either end, kept on char boundaries
+        let mut prefix = old_text.bytes().zip(text.bytes()).take_while(|(a, b)| a == b).count();
+        while !text.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old_text.len().min(text.len()) - prefix;
+        let mut suffix = old_text.bytes().rev().zip(text.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !text.is_char_boundary(text.len() - suffix) || !old_text.is_char_boundary(old_text.len() - suffix) {
+            suffix -= 1;
+        }
+        let old_edit_end = old_text.len() - suffix;
+        let new_edit_end = text.len() - suffix;
+        let delta = text.len() as isize - old_text.len() as isize;
+
+        // Keep everything that ends before the edited line
+        let edit_line = text[..prefix].rfind('\n').map_or(0, |i| i + 1);
+        let keep = self.lexemes.partition_point(|l| l.end <= edit_line);
+        let restart = if keep == 0 { 0 } else { self.lexemes[keep - 1].end };
+        let old_tail = self.lexemes.split_off(keep);
+        let old_error = self.error.take();
+        // Old tokens past the edit are candidates for reuse
+        let reusable = old_tail.partition_point(|l| l.start < old_edit_end);
+
+        let mut lexer = Token::lexer(&text[restart..]);
+        let mut lexed = 0;
+        while let Some(result) = lexer.next() {
+            let span = lexer.span();
+            let (start, end) = (restart + span.start, restart + span.end);
+            let token = match result {
+                Ok(token) => token,
+                Err(()) => {
+                    self.error = Some(start);
+                    break;
+                }
+            };
+
+            if start >= new_edit_end {
+                let old_start = (start as isize - delta) as usize;
+                let found = old_tail[reusable..].binary_search_by_key(&old_start, |l| l.start);
+                if let Ok(index) = found {
+                    let old = &old_tail[reusable + index];
+                    if old.token == token && old.end - old.start == end - start {
+                        // Back in step with the old stream: shift the rest
+                        for old in &old_tail[reusable + index..] {
+                            let start = (old.start as isize + delta) as usize;
+                            let (line, column) = self.position(start);
+                            self.lexemes.push(Lexeme {
+                                token: old.token.clone(),
+                                start,
+                                end: (old.end as isize + delta) as usize,
+                                line,
+                                column,
+                            });
+                        }
+                        self.error = old_error.map(|offset| (offset as isize + delta) as usize);
+                        return lexed;
+                    }
+                }
+            }
+
+            let (line, column) = self.position(start);
+            self.lexemes.push(Lexeme { token, start, end, line, column });
+            lexed += 1;
+        }
+        lexed
+    }
+
+    /// 1-based line and column (in chars, like `Lexer`) of a byte offset
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        (line, self.text[line_start..offset].chars().count() + 1)
+    }
+}
@@ -0,0 +1,53 @@
+//! Line editing for the REPL
+//!
+//! `rustyline` does the editing: cursor movement, the usual Ctrl shortcuts,
+//! history on the arrow keys, and putting the terminal back if we panic.
+//! Piped input is read a plain line at a time.
+
+use std::io;
+
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Config, Editor};
+
+pub struct LineEditor {
+    editor: Editor<(), DefaultHistory>,
+}
+
+impl LineEditor {
+    pub fn new() -> io::Result<Self> {
+        let config = Config::builder()
+            .auto_add_history(false)
+            .history_ignore_dups(true)
+            .map_err(io_error)?
+            .build();
+        let editor = Editor::with_config(config).map_err(io_error)?;
+        Ok(Self { editor })
+    }
+
+    /// Read one line, or `None` at the end of input (Ctrl-D on an empty line)
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        match self.editor.readline(prompt) {
+            Ok(line) => Ok(Some(line)),
+            // Ctrl-C abandons the line
+            Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+            Err(ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    /// Remember a line for the arrow keys (repeats are kept once)
+    pub fn add_history(&mut self, line: &str) {
+        if !line.trim().is_empty() {
+            // Only fails when history is persisted to a file, which it isn't
+            let _ = self.editor.add_history_entry(line);
+        }
+    }
+}
+
+fn io_error(e: ReadlineError) -> io::Error {
+    match e {
+        ReadlineError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
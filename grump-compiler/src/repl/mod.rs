@@ -0,0 +1,288 @@
+//! Interactive REPL
+//!
+//! Each input is a declaration (`fn`, `component`, `entity`, ...), one or
+//! more statements, or a `:command`. Declarations and statements are checked
+//! against everything entered before them, then run in the interpreter, so
+//! a `let` stays visible (with its type) for the rest of the session.
+
+use crate::analyzer::types::Type;
+use crate::analyzer::Analyzer;
+use crate::error::{GrumpError, GrumpResult};
+use crate::export::clip::{Clip, ClipLoop};
+use crate::interpreter::{Interpreter, Value};
+use crate::lexer::{Lexer, Token};
+use crate::parser::{Expression, Parser, Statement};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod editor;
+
+/// Samples shown by `:animate` unless another count is given
+const PREVIEW_INTERVALS: usize = 10;
+/// Width of the bars drawn next to single-valued previews
+const BAR_WIDTH: usize = 30;
+
+const HELP: &str = "\
+Enter declarations (fn, component, entity, enum, animation, ...), statements or expressions.
+
+  :type <expr>              show the type of an expression
+  :ast <input>              show the syntax tree of an expression or statements
+  :spawn <Entity>           create an entity and bind it to its lowercase name
+  :animate <animation> [n]  sample a declared animation n times (default 10)
+  :animate <target> { ... } sample an inline animate block
+  :help                     show this help
+  :quit                     leave";
+
+pub struct Repl {
+    analyzer: Analyzer,
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            analyzer: Analyzer::new(),
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    /// Declare everything in a source file, as if entered at once
+    pub fn load(&mut self, source: &str) -> GrumpResult<()> {
+        let program = Parser::new(source).parse()?;
+        let analyses = self.analyzer.analyze_items(&program);
+        if let Some(e) = analyses.into_iter().flat_map(|a| a.errors).next() {
+            return Err(e);
+        }
+        self.interpreter.load(&program);
+        Ok(())
+    }
+
+    /// Whether `input` can be run, or needs more lines to close a brace,
+    /// bracket or parenthesis
+    pub fn is_complete(input: &str) -> bool {
+        let mut depth = 0i32;
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                '"' => {
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => {
+                                chars.next();
+                            }
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+                }
+                _ => {}
+            }
+        }
+        depth <= 0
+    }
+
+    /// Run one input and return what to show for it
+    pub fn eval(&mut self, input: &str) -> GrumpResult<String> {
+        let input = input.trim();
+        if let Some(command) = input.strip_prefix(':') {
+            let (name, arg) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+            let arg = arg.trim();
+            return match name {
+                "help" | "h" => Ok(HELP.to_string()),
+                "type" | "t" => self.type_of(arg),
+                "ast" => self.ast(arg),
+                "spawn" => self.spawn(arg),
+                "animate" => self.animate(arg),
+                _ => Err(usage(format!("Unknown command :{} (try :help)", name))),
+            };
+        }
+        if input.is_empty() {
+            Ok(String::new())
+        } else if starts_declaration(input) {
+            self.declare(input)
+        } else {
+            self.run(input)
+        }
+    }
+
+    fn declare(&mut self, input: &str) -> GrumpResult<String> {
+        let program = Parser::new(input).parse()?;
+        let mut lines = Vec::new();
+        for item in program.all_items() {
+            self.analyzer.check_declaration(item)?;
+            self.interpreter.declare(item);
+            lines.push(format!("{} {}", item.keyword(), item.name()));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn run(&mut self, input: &str) -> GrumpResult<String> {
+        let statements = Parser::new(input).parse_statements()?;
+        let mut lines = Vec::new();
+        for stmt in &statements {
+            let type_ = match stmt {
+                Statement::Expression(expr, _) => Some(self.analyzer.type_of(expr)?),
+                other => {
+                    self.analyzer.check_statement(other)?;
+                    None
+                }
+            };
+            let result = self.interpreter.execute(stmt);
+            lines.extend(self.interpreter.take_output());
+            let value = result?;
+            match (stmt, type_) {
+                (Statement::Let { name, .. }, _) => {
+                    // `execute` gives `()` for a `let`; show what it bound
+                    let value = self.interpreter.variable(name).cloned().unwrap_or(value);
+                    let type_ = self.analyzer.context().get_variable(name).cloned().unwrap_or(Type::Unknown);
+                    lines.push(format!("{}: {} = {}", name, type_name(&type_, &value), value));
+                }
+                (_, Some(type_)) if value != Value::Unit => {
+                    lines.push(format!("{} : {}", value, type_name(&type_, &value)));
+                }
+                _ => {}
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn type_of(&mut self, arg: &str) -> GrumpResult<String> {
+        let expr = expression(arg)?;
+        Ok(self.analyzer.type_of(&expr)?.to_string())
+    }
+
+    fn ast(&self, arg: &str) -> GrumpResult<String> {
+        let statements = Parser::new(arg).parse_statements()?;
+        match statements.as_slice() {
+            [Statement::Expression(expr, _)] => Ok(format!("{:#?}", expr)),
+            _ => Ok(format!("{:#?}", statements)),
+        }
+    }
+
+    fn spawn(&mut self, name: &str) -> GrumpResult<String> {
+        if !self.interpreter.has_entity(name) {
+            return Err(usage(format!("No entity named '{}' (declare it first)", name)));
+        }
+        let result = self.interpreter.spawn(name);
+        let mut lines = self.interpreter.take_output();
+        let entity = result?;
+        let binding = name.to_lowercase();
+        self.analyzer.bind(binding.clone(), Type::Named(format!("Entity_{}", name)));
+        lines.push(format!("{} = {}", binding, entity));
+        self.interpreter.set_variable(&binding, entity);
+        Ok(lines.join("\n"))
+    }
+
+    fn animate(&mut self, arg: &str) -> GrumpResult<String> {
+        let words: Vec<&str> = arg.split_whitespace().collect();
+        if let Some(animation) = words.first().and_then(|name| self.interpreter.animation(name)) {
+            let intervals = match words.get(1) {
+                Some(count) if words.len() == 2 => count.parse::<usize>().ok().filter(|n| *n > 0)
+                    .ok_or_else(|| usage(format!("Expected a sample count, got '{}'", count)))?,
+                None => PREVIEW_INTERVALS,
+                Some(_) => return Err(usage("Usage: :animate <animation> [samples]")),
+            };
+            return Ok(preview(&Clip::from_declaration(animation)?, intervals));
+        }
+        let statements = Parser::new(&format!("animate {}", arg)).parse_statements()?;
+        match statements.as_slice() {
+            [Statement::Animate(animate)] => Ok(preview(&Clip::from_animate(animate)?, PREVIEW_INTERVALS)),
+            _ => Err(usage("Usage: :animate <animation> [samples] or :animate <target> { keyframes { ... } }")),
+        }
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn usage(message: impl Into<String>) -> GrumpError {
+    GrumpError::Other(anyhow::anyhow!(message.into()))
+}
+
+/// Whether `input` starts with an item keyword
+fn starts_declaration(input: &str) -> bool {
+    matches!(
+        Lexer::new(input).next_token(),
+        Ok(Some((
+            Token::App | Token::Scene | Token::Entity | Token::Component | Token::Enum | Token::System | Token::Fn
+                | Token::Animation | Token::Shader | Token::BehaviorTree | Token::Macro | Token::Plugin | Token::Package
+                | Token::Test,
+            _,
+            _,
+        )))
+    )
+}
+
+/// A single expression
+fn expression(input: &str) -> GrumpResult<Expression> {
+    let mut statements = Parser::new(input).parse_statements()?;
+    match (statements.len(), statements.pop()) {
+        (1, Some(Statement::Expression(expr, _))) => Ok(expr),
+        _ => Err(usage("Expected an expression")),
+    }
+}
+
+/// The checked type, or the value's own when checking couldn't tell
+fn type_name(type_: &Type, value: &Value) -> String {
+    match type_ {
+        Type::Unknown => value.type_name(),
+        known => known.to_string(),
+    }
+}
+
+/// The clip's values at evenly spaced times, with a bar chart when there is
+/// only one value to show
+fn preview(clip: &Clip, intervals: usize) -> String {
+    let mode = match clip.loop_mode {
+        ClipLoop::Once => "once",
+        ClipLoop::Loop => "loop",
+        ClipLoop::PingPong => "ping_pong",
+        ClipLoop::Reverse => "reverse",
+        ClipLoop::Section { .. } => "section",
+    };
+    let clip = clip.unrolled();
+    let mut columns = Vec::new();
+    for track in &clip.tracks {
+        let path = match track.property.as_str() {
+            "value" => track.target.join("."),
+            property => format!("{}.{}", track.target.join("."), property),
+        };
+        let channels = track.keyframes.first().map_or(1, |k| k.value.channels.len());
+        for channel in 0..channels {
+            columns.push(if channels > 1 { format!("{}[{}]", path, channel) } else { path.clone() });
+        }
+    }
+
+    let rows: Vec<(f64, Vec<f64>)> = (0..=intervals)
+        .map(|i| {
+            let time = clip.duration * i as f64 / intervals as f64;
+            (time, clip.tracks.iter().flat_map(|t| t.sample(time)).collect())
+        })
+        .collect();
+
+    let mut lines = vec![format!("{}: {}s, {}", clip.name, clip.duration, mode)];
+    let header: Vec<String> = columns.iter().map(|c| format!("{:>10}", c)).collect();
+    lines.push(format!("  {:>7}  {}", "time", header.join("  ")));
+    let values = rows.iter().filter_map(|(_, row)| match row.as_slice() {
+        [value] if columns.len() == 1 => Some(*value),
+        _ => None,
+    });
+    let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| (low.min(v), high.max(v)));
+    for (time, row) in &rows {
+        let cells: Vec<String> = row.iter().map(|v| format!("{:>10.3}", v)).collect();
+        let mut line = format!("  {:>6.2}s  {}", time, cells.join("  "));
+        if let [value] = row.as_slice() {
+            let filled = if high > low { ((value - low) / (high - low) * BAR_WIDTH as f64).round() as usize } else { 0 };
+            line.push_str(&format!("  |{}", "#".repeat(filled)));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
@@ -11,6 +11,19 @@ pub enum AnimationState {
     Finished,
 }
 
+/// Keyframe value at a time (seconds); one channel per component (`x`, `y`, ...)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub values: Vec<f64>,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, values: Vec<f64>) -> Self {
+        Self { time, values }
+    }
+}
+
 /// Animation instance
 pub struct Animation {
     name: String,
@@ -18,6 +31,7 @@ pub struct Animation {
     current_time: f64,
     state: AnimationState,
     loop_mode: LoopMode,
+    keyframes: Vec<Keyframe>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,9 +50,29 @@ impl Animation {
             current_time: 0.0,
             state: AnimationState::Stopped,
             loop_mode,
+            keyframes: Vec::new(),
         }
     }
     
+    /// Keyframes sorted by time
+    pub fn with_keyframes(mut self, mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.keyframes = keyframes;
+        self
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    pub fn state(&self) -> &AnimationState {
+        &self.state
+    }
+    
+    pub fn current_time(&self) -> f64 {
+        self.current_time
+    }
+    
     pub fn play(&mut self) {
         self.state = AnimationState::Playing;
     }
@@ -87,6 +121,25 @@ impl Animation {
         }
     }
     
+    /// Keyframe values interpolated at the current time
+    pub fn sample(&self) -> Option<Vec<f64>> {
+        let time = match self.loop_mode {
+            // The second half of a ping-pong cycle plays backwards
+            LoopMode::PingPong if self.current_time > self.duration => 2.0 * self.duration - self.current_time,
+            _ => self.current_time,
+        };
+        let next = self.keyframes.iter().position(|k| k.time >= time);
+        match next {
+            Some(0) => self.keyframes.first().map(|k| k.values.clone()),
+            Some(i) => {
+                let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                let t = if b.time > a.time { (time - a.time) / (b.time - a.time) } else { 1.0 };
+                Some(a.values.iter().zip(&b.values).map(|(x, y)| x + (y - x) * t).collect())
+            }
+            None => self.keyframes.last().map(|k| k.values.clone()),
+        }
+    }
+    
     pub fn progress(&self) -> f64 {
         if self.duration > 0.0 {
             (self.current_time / self.duration).min(1.0)
@@ -150,6 +203,13 @@ impl AnimationManager {
         }
     }
     
+    /// Names of the animations that are playing
+    pub fn playing(&self) -> impl Iterator<Item = &str> {
+        self.animations.iter()
+            .filter(|anim| matches!(anim.state, AnimationState::Playing))
+            .map(|anim| anim.name.as_str())
+    }
+    
     pub fn active_count(&self) -> usize {
         self.active_count
     }
@@ -12,6 +12,7 @@ pub type EntityId = u64;
 pub struct ComponentStorage {
     components: HashMap<TypeId, Vec<Option<Box<dyn Any>>>>,
     entity_to_index: HashMap<EntityId, usize>,
+    entities: Vec<EntityId>,
     next_entity_id: EntityId,
 }
 
@@ -20,6 +21,7 @@ impl ComponentStorage {
         Self {
             components: HashMap::new(),
             entity_to_index: HashMap::new(),
+            entities: Vec::new(),
             next_entity_id: 0,
         }
     }
@@ -27,25 +29,22 @@ impl ComponentStorage {
     pub fn create_entity(&mut self) -> EntityId {
         let id = self.next_entity_id;
         self.next_entity_id += 1;
-        self.entity_to_index.insert(id, 0);
+        // Slots are never reused, so an entity's index is its creation order
+        self.entity_to_index.insert(id, id as usize);
+        self.entities.push(id);
         id
     }
     
+    /// Add a component, replacing any existing component of the same type
     pub fn add_component<T: 'static>(&mut self, entity: EntityId, component: T) {
-        let type_id = TypeId::of::<T>();
-        let index = *self.entity_to_index.get(&entity).unwrap_or(&0);
-        
-        if let Some(components) = self.components.get_mut(&type_id) {
-            if index < components.len() {
-                components[index] = Some(Box::new(component));
-            } else {
-                components.push(Some(Box::new(component)));
-            }
-        } else {
-            let mut components = Vec::new();
-            components.push(Some(Box::new(component)));
-            self.components.insert(type_id, components);
+        let Some(&index) = self.entity_to_index.get(&entity) else {
+            return;
+        };
+        let components = self.components.entry(TypeId::of::<T>()).or_default();
+        if components.len() <= index {
+            components.resize_with(index + 1, || None);
         }
+        components[index] = Some(Box::new(component));
     }
     
     pub fn get_component<T: 'static>(&self, entity: EntityId) -> Option<&T> {
@@ -57,6 +56,51 @@ impl ComponentStorage {
             .as_ref()?
             .downcast_ref::<T>()
     }
+    
+    pub fn get_component_mut<T: 'static>(&mut self, entity: EntityId) -> Option<&mut T> {
+        let type_id = TypeId::of::<T>();
+        let index = *self.entity_to_index.get(&entity)?;
+        
+        self.components.get_mut(&type_id)?
+            .get_mut(index)?
+            .as_mut()?
+            .downcast_mut::<T>()
+    }
+    
+    pub fn remove_component<T: 'static>(&mut self, entity: EntityId) {
+        let Some(&index) = self.entity_to_index.get(&entity) else {
+            return;
+        };
+        if let Some(slot) = self.components.get_mut(&TypeId::of::<T>()).and_then(|c| c.get_mut(index)) {
+            *slot = None;
+        }
+    }
+    
+    fn has_type(&self, type_id: TypeId, entity: EntityId) -> bool {
+        let Some(&index) = self.entity_to_index.get(&entity) else {
+            return false;
+        };
+        self.components.get(&type_id)
+            .and_then(|components| components.get(index))
+            .is_some_and(|slot| slot.is_some())
+    }
+    
+    /// Live entities, in creation order
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+    
+    pub fn remove_entity(&mut self, entity: EntityId) {
+        let Some(index) = self.entity_to_index.remove(&entity) else {
+            return;
+        };
+        for components in self.components.values_mut() {
+            if let Some(slot) = components.get_mut(index) {
+                *slot = None;
+            }
+        }
+        self.entities.retain(|&e| e != entity);
+    }
 }
 
 /// Query for selecting entities with specific components
@@ -76,10 +120,12 @@ impl Query {
         self
     }
     
+    /// Entities that have all required components
     pub fn execute(&self, storage: &ComponentStorage) -> Vec<EntityId> {
-        // Find entities that have all required components
-        // For now, simplified implementation
-        Vec::new()  // TODO: Implement actual query execution
+        storage.entities().iter()
+            .copied()
+            .filter(|&entity| self.component_types.iter().all(|&t| storage.has_type(t, entity)))
+            .collect()
     }
 }
 
@@ -124,11 +170,6 @@ impl World {
     
     pub fn update(&mut self, delta: f64) {
         for system in &mut self.systems {
-            // Execute query if system specifies one
-            if let Some(query) = system.query() {
-                let entities = query.execute(&self.storage);
-                // System can use these entities
-            }
             system.update(&mut self.storage, delta);
         }
     }
@@ -137,8 +178,20 @@ impl World {
         Query::new()
     }
     
+    pub fn get_component_mut<T: 'static>(&mut self, entity: EntityId) -> Option<&mut T> {
+        self.storage.get_component_mut(entity)
+    }
+    
+    pub fn entities(&self) -> &[EntityId] {
+        self.storage.entities()
+    }
+    
+    pub fn storage(&self) -> &ComponentStorage {
+        &self.storage
+    }
+    
     pub fn remove_entity(&mut self, entity: EntityId) {
-        // TODO: Remove entity and all its components
+        self.storage.remove_entity(entity);
     }
     
     pub fn has_component<T: 'static>(&self, entity: EntityId) -> bool {
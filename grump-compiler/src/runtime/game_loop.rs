@@ -48,15 +48,23 @@ impl GameLoop {
         self.running
     }
     
-    pub fn update(&mut self, delta: f64) {
+    /// Length of a fixed step
+    pub fn fixed_delta(&self) -> f64 {
+        1.0 / self.config.target_fps
+    }
+    
+    /// Accumulate `delta` and return how many fixed steps are due
+    pub fn update(&mut self, delta: f64) -> u32 {
         // Fixed timestep update
-        let fixed_delta = 1.0 / self.config.target_fps;
+        let fixed_delta = self.fixed_delta();
         self.accumulated_time += delta;
         
+        let mut steps = 0;
         while self.accumulated_time >= fixed_delta {
-            // Update game logic here
+            steps += 1;
             self.accumulated_time -= fixed_delta;
         }
+        steps
     }
     
     fn current_time() -> f64 {
@@ -1,10 +1,13 @@
 //! Runtime Library for G-Rump
 //! 
-//! Core runtime components (game loop, ECS, animation engine, etc.)
+//! Core runtime components (game loop, ECS, animation engine, script
+//! scheduler, network replication, etc.)
 
 pub mod ecs;
 pub mod animation;
 pub mod game_loop;
+pub mod scheduler;
+pub mod network;
 
 /// Runtime configuration
 pub struct RuntimeConfig {
@@ -30,6 +33,7 @@ pub struct Runtime {
     pub game_loop: game_loop::GameLoop,
     pub world: ecs::World,
     pub animation_manager: animation::AnimationManager,
+    pub scheduler: scheduler::Scheduler,
     pub config: RuntimeConfig,
 }
 
@@ -44,14 +48,19 @@ impl Runtime {
             game_loop: game_loop::GameLoop::new(game_loop_config),
             world: ecs::World::new(),
             animation_manager: animation::AnimationManager::new(),
+            scheduler: scheduler::Scheduler::new(),
             config,
         }
     }
     
     pub fn update(&mut self, delta: f64) {
-        self.game_loop.update(delta);
+        let steps = self.game_loop.update(delta);
         self.world.update(delta);
         self.animation_manager.update(delta);
+        // Scripts resume on the game loop's fixed steps
+        for _ in 0..steps {
+            self.scheduler.tick(self.game_loop.fixed_delta(), &self.world, &mut self.animation_manager);
+        }
     }
     
     pub fn start(&mut self) {
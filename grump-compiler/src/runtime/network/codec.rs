@@ -0,0 +1,217 @@
+//! Binary packet format
+//!
+//! Integers are LEB128 varints (signed ones zigzagged first), floats are
+//! little-endian `f64`s and strings are length-prefixed UTF-8. A state packet
+//! is its tick, its baseline tick (0 for none) and one entry per object that
+//! differs from the baseline: the object's ID, whether it was created or
+//! removed, a mask of the fields that follow and their values.
+
+use super::{FieldKind, NetError, NetId, NetValue, Schema};
+
+const STATE: u8 = 0;
+const ACK: u8 = 1;
+
+const CHANGED: u8 = 0;
+const CREATED: u8 = 1;
+const REMOVED: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    /// Server to client: objects as of `tick`, relative to `baseline`
+    State {
+        tick: u32,
+        baseline: Option<u32>,
+        objects: Vec<ObjectDelta>,
+    },
+    /// Client to server: the newest snapshot the client has
+    Ack { tick: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectDelta {
+    /// Fields of `id` that changed, by index; a created object lists all of them
+    Changed {
+        id: NetId,
+        created: bool,
+        fields: Vec<(usize, NetValue)>,
+    },
+    Removed(NetId),
+}
+
+pub fn encode(packet: &Packet) -> Vec<u8> {
+    let mut out = Vec::new();
+    match packet {
+        Packet::State { tick, baseline, objects } => {
+            out.push(STATE);
+            write_varint(&mut out, *tick as u64);
+            write_varint(&mut out, baseline.unwrap_or(0) as u64);
+            write_varint(&mut out, objects.len() as u64);
+            for object in objects {
+                match object {
+                    ObjectDelta::Changed { id, created, fields } => {
+                        write_varint(&mut out, *id);
+                        out.push(if *created { CREATED } else { CHANGED });
+                        let mut fields: Vec<_> = fields.iter().collect();
+                        fields.sort_by_key(|(index, _)| *index);
+                        let mask = fields.iter().fold(0u64, |mask, (index, _)| mask | 1 << *index);
+                        write_varint(&mut out, mask);
+                        for (_, value) in fields {
+                            write_value(&mut out, value);
+                        }
+                    }
+                    ObjectDelta::Removed(id) => {
+                        write_varint(&mut out, *id);
+                        out.push(REMOVED);
+                    }
+                }
+            }
+        }
+        Packet::Ack { tick } => {
+            out.push(ACK);
+            write_varint(&mut out, *tick as u64);
+        }
+    }
+    out
+}
+
+/// Decode a packet whose field values are laid out by `schema`
+pub fn decode(bytes: &[u8], schema: &Schema) -> Result<Packet, NetError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let packet = match reader.byte()? {
+        STATE => {
+            let tick = reader.tick()?;
+            let baseline = Some(reader.tick()?).filter(|&tick| tick != 0);
+            let count = reader.varint()?;
+            let mut objects = Vec::new();
+            for _ in 0..count {
+                let id = reader.varint()?;
+                let flag = reader.byte()?;
+                if flag == REMOVED {
+                    objects.push(ObjectDelta::Removed(id));
+                    continue;
+                }
+                if flag != CHANGED && flag != CREATED {
+                    return Err(NetError::Malformed(format!("object flag {}", flag)));
+                }
+                let mask = reader.varint()?;
+                let len = schema.fields().len();
+                if len < 64 && mask >> len != 0 {
+                    return Err(NetError::Malformed(format!("field mask {:#x} for {} fields", mask, len)));
+                }
+                let mut fields = Vec::new();
+                for (index, field) in schema.fields().iter().enumerate() {
+                    if mask & 1 << index != 0 {
+                        fields.push((index, reader.value(field.kind)?));
+                    }
+                }
+                objects.push(ObjectDelta::Changed { id, created: flag == CREATED, fields });
+            }
+            Packet::State { tick, baseline, objects }
+        }
+        ACK => Packet::Ack { tick: reader.tick()? },
+        kind => return Err(NetError::Malformed(format!("packet kind {}", kind))),
+    };
+    if reader.pos != bytes.len() {
+        return Err(NetError::Malformed(format!("{} trailing bytes", bytes.len() - reader.pos)));
+    }
+    Ok(packet)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_f64(out: &mut Vec<u8>, x: f64) {
+    out.extend_from_slice(&x.to_le_bytes());
+}
+
+fn write_value(out: &mut Vec<u8>, value: &NetValue) {
+    match value {
+        NetValue::Int(n) => write_varint(out, ((n << 1) ^ (n >> 63)) as u64),
+        NetValue::Float(x) => write_f64(out, *x),
+        NetValue::Bool(b) => out.push(*b as u8),
+        NetValue::Text(s) => {
+            write_varint(out, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        }
+        NetValue::Vec2(x, y) => {
+            write_f64(out, *x);
+            write_f64(out, *y);
+        }
+        NetValue::Vec3(x, y, z) => {
+            write_f64(out, *x);
+            write_f64(out, *y);
+            write_f64(out, *z);
+        }
+        NetValue::Color(rgba) => out.extend_from_slice(rgba),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], NetError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len()).ok_or(NetError::Truncated)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, NetError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, NetError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(NetError::Malformed("varint longer than 64 bits".to_string()))
+    }
+
+    fn tick(&mut self) -> Result<u32, NetError> {
+        let n = self.varint()?;
+        u32::try_from(n).map_err(|_| NetError::Malformed(format!("tick {}", n)))
+    }
+
+    fn f64(&mut self) -> Result<f64, NetError> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn value(&mut self, kind: FieldKind) -> Result<NetValue, NetError> {
+        Ok(match kind {
+            FieldKind::Int => {
+                let n = self.varint()?;
+                NetValue::Int((n >> 1) as i64 ^ -((n & 1) as i64))
+            }
+            FieldKind::Float => NetValue::Float(self.f64()?),
+            FieldKind::Bool => match self.byte()? {
+                0 => NetValue::Bool(false),
+                1 => NetValue::Bool(true),
+                b => return Err(NetError::Malformed(format!("bool {}", b))),
+            },
+            FieldKind::Text => {
+                let len = self.varint()?;
+                let len = usize::try_from(len).map_err(|_| NetError::Truncated)?;
+                let bytes = self.take(len)?;
+                let text = std::str::from_utf8(bytes).map_err(|_| NetError::Malformed("string isn't UTF-8".to_string()))?;
+                NetValue::Text(text.to_string())
+            }
+            FieldKind::Vec2 => NetValue::Vec2(self.f64()?, self.f64()?),
+            FieldKind::Vec3 => NetValue::Vec3(self.f64()?, self.f64()?, self.f64()?),
+            FieldKind::Color => NetValue::Color(self.take(4)?.try_into().unwrap()),
+        })
+    }
+}
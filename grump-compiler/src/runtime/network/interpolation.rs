@@ -0,0 +1,61 @@
+//! Interpolation buffers for `Interpolated` fields
+
+use std::collections::VecDeque;
+
+use super::NetValue;
+
+/// A field's values at the ticks they were received, oldest first
+#[derive(Debug, Clone)]
+pub struct InterpolationBuffer {
+    samples: VecDeque<(u32, NetValue)>,
+    capacity: usize,
+}
+
+impl InterpolationBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::new(), capacity: capacity.max(2) }
+    }
+
+    /// Add the value at `tick`; values at or before the newest tick are
+    /// ignored, and the oldest is dropped once the buffer is full
+    pub fn push(&mut self, tick: u32, value: NetValue) {
+        if self.samples.back().is_some_and(|(newest, _)| *newest >= tick) {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((tick, value));
+    }
+
+    /// The value at `tick`, interpolated between the samples either side
+    /// of it. Outside the buffered ticks it's the nearest sample; there's
+    /// no extrapolation.
+    pub fn sample(&self, tick: f64) -> Option<NetValue> {
+        let (first_tick, first) = self.samples.front()?;
+        if tick <= *first_tick as f64 {
+            return Some(first.clone());
+        }
+        let after = self.samples.iter().position(|(t, _)| *t as f64 >= tick);
+        let Some(after) = after else {
+            return self.samples.back().map(|(_, value)| value.clone());
+        };
+        let (from_tick, from) = &self.samples[after - 1];
+        let (to_tick, to) = &self.samples[after];
+        let t = (tick - *from_tick as f64) / (*to_tick - *from_tick) as f64;
+        Some(from.lerp(to, t))
+    }
+
+    /// Newest tick buffered
+    pub fn newest(&self) -> Option<u32> {
+        self.samples.back().map(|(tick, _)| *tick)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
@@ -0,0 +1,193 @@
+//! Network replication runtime
+//!
+//! Replicates the sync fields of `network` declarations from a server to
+//! its clients. Each tick the server sends every client a delta of its
+//! objects against the last snapshot that client acked, so a lost packet is
+//! made up for by the next one instead of being resent. How a field
+//! replicates depends on its sync mode:
+//!
+//! - `Reliable` fields are sent until a snapshot holding their value is acked
+//! - `Unreliable` fields are sent once, on the tick they change
+//! - `Snapshot` fields are sent every `snapshot_interval` ticks
+//! - `Interpolated` fields are sent like `Reliable` ones; clients buffer them
+//!   and read values interpolated between snapshots
+//!
+//! Packets go over a pluggable `Transport`; `Loopback` connects peers in
+//! the same process.
+
+pub mod codec;
+pub mod interpolation;
+pub mod replication;
+pub mod transport;
+
+pub use replication::{Client, Server};
+pub use transport::{Loopback, LoopbackTransport, PeerId, Transport};
+
+use thiserror::Error;
+
+/// Replicated object ID
+pub type NetId = u64;
+
+/// Most fields a schema can have; deltas mark changed fields in a 64-bit mask
+pub const MAX_FIELDS: usize = 64;
+
+/// How a field is replicated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    Reliable,
+    Unreliable,
+    Interpolated,
+    Snapshot,
+}
+
+/// What a field holds, which decides its wire format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Int,
+    Float,
+    Bool,
+    Text,
+    Vec2,
+    Vec3,
+    Color,
+}
+
+impl FieldKind {
+    pub fn default_value(&self) -> NetValue {
+        match self {
+            FieldKind::Int => NetValue::Int(0),
+            FieldKind::Float => NetValue::Float(0.0),
+            FieldKind::Bool => NetValue::Bool(false),
+            FieldKind::Text => NetValue::Text(String::new()),
+            FieldKind::Vec2 => NetValue::Vec2(0.0, 0.0),
+            FieldKind::Vec3 => NetValue::Vec3(0.0, 0.0, 0.0),
+            FieldKind::Color => NetValue::Color([0, 0, 0, 255]),
+        }
+    }
+}
+
+/// A sync field's value
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+    Vec2(f64, f64),
+    Vec3(f64, f64, f64),
+    Color([u8; 4]),
+}
+
+impl NetValue {
+    pub fn kind(&self) -> FieldKind {
+        match self {
+            NetValue::Int(_) => FieldKind::Int,
+            NetValue::Float(_) => FieldKind::Float,
+            NetValue::Bool(_) => FieldKind::Bool,
+            NetValue::Text(_) => FieldKind::Text,
+            NetValue::Vec2(..) => FieldKind::Vec2,
+            NetValue::Vec3(..) => FieldKind::Vec3,
+            NetValue::Color(_) => FieldKind::Color,
+        }
+    }
+
+    /// The value `t` of the way from `self` to `to`; values that don't
+    /// interpolate hold until `t` reaches 1
+    pub fn lerp(&self, to: &NetValue, t: f64) -> NetValue {
+        let mix = |a: f64, b: f64| a + (b - a) * t;
+        match (self, to) {
+            (NetValue::Float(a), NetValue::Float(b)) => NetValue::Float(mix(*a, *b)),
+            (NetValue::Vec2(ax, ay), NetValue::Vec2(bx, by)) => NetValue::Vec2(mix(*ax, *bx), mix(*ay, *by)),
+            (NetValue::Vec3(ax, ay, az), NetValue::Vec3(bx, by, bz)) => {
+                NetValue::Vec3(mix(*ax, *bx), mix(*ay, *by), mix(*az, *bz))
+            }
+            _ if t >= 1.0 => to.clone(),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// One field of a schema
+#[derive(Debug, Clone)]
+pub struct FieldSchema {
+    pub name: String,
+    pub kind: FieldKind,
+    pub mode: SyncMode,
+}
+
+/// The sync fields of a `network` declaration, in declaration order
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub name: String,
+    fields: Vec<FieldSchema>,
+}
+
+impl Schema {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), fields: Vec::new() }
+    }
+
+    /// Add a field
+    pub fn field(mut self, name: &str, kind: FieldKind, mode: SyncMode) -> Self {
+        assert!(self.fields.len() < MAX_FIELDS, "schema '{}' has more than {} fields", self.name, MAX_FIELDS);
+        self.fields.push(FieldSchema { name: name.to_string(), kind, mode });
+        self
+    }
+
+    pub fn fields(&self) -> &[FieldSchema] {
+        &self.fields
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    /// Values of a newly spawned object
+    pub fn defaults(&self) -> Vec<NetValue> {
+        self.fields.iter().map(|field| field.kind.default_value()).collect()
+    }
+}
+
+/// Replication timing
+#[derive(Debug, Clone)]
+pub struct ReplicationConfig {
+    /// Server ticks per second
+    pub tick_rate: f64,
+    /// How many ticks behind the newest snapshot clients render
+    /// interpolated fields
+    pub interpolation_delay: f64,
+    /// Ticks between resends of `Snapshot` fields
+    pub snapshot_interval: u32,
+    /// Snapshots kept as delta baselines, and values kept per
+    /// interpolation buffer
+    pub history: usize,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: 20.0,
+            interpolation_delay: 2.0,
+            snapshot_interval: 10,
+            history: 32,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum NetError {
+    #[error("Packet ended early")]
+    Truncated,
+
+    #[error("Malformed packet: {0}")]
+    Malformed(String),
+
+    #[error("No field '{field}' in network '{schema}'")]
+    UnknownField { schema: String, field: String },
+
+    #[error("Field '{field}' holds {expected:?}, got {got:?}")]
+    WrongKind { field: String, expected: FieldKind, got: FieldKind },
+
+    #[error("No replicated object {0}")]
+    UnknownObject(NetId),
+}
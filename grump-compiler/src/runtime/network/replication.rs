@@ -0,0 +1,360 @@
+//! Server and client replication
+//!
+//! The server snapshots its objects every tick and keeps the recent ones as
+//! baselines. A client acks each snapshot it applies; the server diffs the
+//! next snapshot it sends that client against the newest one acked.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::codec::{self, ObjectDelta, Packet};
+use super::interpolation::InterpolationBuffer;
+use super::transport::{PeerId, Transport};
+use super::{NetError, NetId, NetValue, ReplicationConfig, Schema, SyncMode};
+
+type Objects = BTreeMap<NetId, Vec<NetValue>>;
+
+/// Objects as of a tick
+struct Snapshot {
+    tick: u32,
+    objects: Objects,
+}
+
+/// Owns the replicated objects and sends them to its clients
+pub struct Server<T: Transport> {
+    schema: Schema,
+    transport: T,
+    config: ReplicationConfig,
+    tick: u32,
+    objects: Objects,
+    /// Snapshots sent, oldest first
+    history: VecDeque<Snapshot>,
+    /// Clients and the newest tick each acked
+    clients: BTreeMap<PeerId, Option<u32>>,
+    skipped: VecDeque<(PeerId, NetError)>,
+}
+
+impl<T: Transport> Server<T> {
+    pub fn new(schema: Schema, transport: T, config: ReplicationConfig) -> Self {
+        Self {
+            schema,
+            transport,
+            config,
+            tick: 0,
+            objects: Objects::new(),
+            history: VecDeque::new(),
+            clients: BTreeMap::new(),
+            skipped: VecDeque::new(),
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Start replicating to `peer`; its first snapshot holds every object
+    pub fn add_client(&mut self, peer: PeerId) {
+        self.clients.insert(peer, None);
+    }
+
+    pub fn remove_client(&mut self, peer: PeerId) {
+        self.clients.remove(&peer);
+    }
+
+    /// Ticks sent so far
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Start replicating object `id`, with every field at its default
+    pub fn spawn(&mut self, id: NetId) {
+        self.objects.insert(id, self.schema.defaults());
+    }
+
+    /// Stop replicating object `id`; clients remove it too
+    pub fn despawn(&mut self, id: NetId) {
+        self.objects.remove(&id);
+    }
+
+    pub fn set(&mut self, id: NetId, field: &str, value: NetValue) -> Result<(), NetError> {
+        let index = field_index(&self.schema, field)?;
+        let expected = self.schema.fields()[index].kind;
+        if value.kind() != expected {
+            return Err(NetError::WrongKind { field: field.to_string(), expected, got: value.kind() });
+        }
+        let values = self.objects.get_mut(&id).ok_or(NetError::UnknownObject(id))?;
+        values[index] = value;
+        Ok(())
+    }
+
+    pub fn get(&self, id: NetId, field: &str) -> Option<&NetValue> {
+        let index = self.schema.index_of(field)?;
+        self.objects.get(&id).map(|values| &values[index])
+    }
+
+    /// Packets skipped since the last call, who sent them and why
+    pub fn take_skipped(&mut self) -> Vec<(PeerId, NetError)> {
+        self.skipped.drain(..).collect()
+    }
+
+    /// Read acks, snapshot the objects and send each client its delta.
+    /// Packets that aren't acks are skipped.
+    pub fn update(&mut self) {
+        while let Some((peer, bytes)) = self.transport.receive() {
+            let tick = match codec::decode(&bytes, &self.schema) {
+                Ok(Packet::Ack { tick }) => tick,
+                Ok(Packet::State { .. }) => {
+                    skip(&mut self.skipped, &self.config, peer, NetError::Malformed("server received a state packet".to_string()));
+                    continue;
+                }
+                Err(error) => {
+                    skip(&mut self.skipped, &self.config, peer, error);
+                    continue;
+                }
+            };
+            if let Some(acked) = self.clients.get_mut(&peer) {
+                if tick <= self.tick && acked.is_none_or(|acked| tick > acked) {
+                    *acked = Some(tick);
+                }
+            }
+        }
+
+        self.tick += 1;
+        let previous = self.history.back().map(|snapshot| &snapshot.objects);
+        for (&peer, acked) in &self.clients {
+            let baseline = acked.and_then(|acked| self.history.iter().find(|snapshot| snapshot.tick == acked));
+            let packet = Packet::State {
+                tick: self.tick,
+                baseline: baseline.map(|snapshot| snapshot.tick),
+                objects: self.delta(baseline.map(|snapshot| &snapshot.objects), previous),
+            };
+            self.transport.send(peer, codec::encode(&packet));
+        }
+
+        self.history.push_back(Snapshot { tick: self.tick, objects: self.objects.clone() });
+        while self.history.len() > self.config.history {
+            self.history.pop_front();
+        }
+    }
+
+    /// What changed since `baseline`, which a client has, and `previous`,
+    /// the last snapshot sent
+    fn delta(&self, baseline: Option<&Objects>, previous: Option<&Objects>) -> Vec<ObjectDelta> {
+        let resend_snapshots = self.tick.is_multiple_of(self.config.snapshot_interval.max(1));
+        let mut deltas = Vec::new();
+        for (&id, values) in &self.objects {
+            let Some(base) = baseline.and_then(|baseline| baseline.get(&id)) else {
+                deltas.push(ObjectDelta::Changed { id, created: true, fields: values.iter().cloned().enumerate().collect() });
+                continue;
+            };
+            let last = previous.and_then(|previous| previous.get(&id));
+            let fields: Vec<_> = self.schema.fields().iter().enumerate()
+                .filter(|(index, field)| match field.mode {
+                    SyncMode::Reliable | SyncMode::Interpolated => values[*index] != base[*index],
+                    SyncMode::Unreliable => last.is_none_or(|last| values[*index] != last[*index]),
+                    SyncMode::Snapshot => resend_snapshots,
+                })
+                .map(|(index, _)| (index, values[index].clone()))
+                .collect();
+            if !fields.is_empty() {
+                deltas.push(ObjectDelta::Changed { id, created: false, fields });
+            }
+        }
+        if let Some(baseline) = baseline {
+            for &id in baseline.keys() {
+                if !self.objects.contains_key(&id) {
+                    deltas.push(ObjectDelta::Removed(id));
+                }
+            }
+        }
+        deltas
+    }
+}
+
+/// Applies a server's snapshots and acks them
+pub struct Client<T: Transport> {
+    schema: Schema,
+    transport: T,
+    server: PeerId,
+    config: ReplicationConfig,
+    /// Snapshots applied, oldest first; the newest is the current state
+    snapshots: VecDeque<Snapshot>,
+    buffers: HashMap<(NetId, usize), InterpolationBuffer>,
+    /// Tick `Interpolated` fields are read at
+    render_tick: f64,
+    skipped: VecDeque<(PeerId, NetError)>,
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(schema: Schema, transport: T, server: PeerId, config: ReplicationConfig) -> Self {
+        Self {
+            schema,
+            transport,
+            server,
+            config,
+            snapshots: VecDeque::new(),
+            buffers: HashMap::new(),
+            render_tick: 0.0,
+            skipped: VecDeque::new(),
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Newest tick applied
+    pub fn tick(&self) -> Option<u32> {
+        self.snapshots.back().map(|snapshot| snapshot.tick)
+    }
+
+    pub fn render_tick(&self) -> f64 {
+        self.render_tick
+    }
+
+    /// Packets skipped since the last call, who sent them and why
+    pub fn take_skipped(&mut self) -> Vec<(PeerId, NetError)> {
+        self.skipped.drain(..).collect()
+    }
+
+    /// Apply the snapshots that arrived, acking each. Stale snapshots, those
+    /// diffed against a baseline no longer kept, and packets that aren't
+    /// snapshots are skipped. Returns how many were applied.
+    pub fn receive(&mut self) -> usize {
+        let mut applied = 0;
+        while let Some((peer, bytes)) = self.transport.receive() {
+            if peer != self.server {
+                continue;
+            }
+            let (tick, baseline, objects) = match codec::decode(&bytes, &self.schema) {
+                Ok(Packet::State { tick, baseline, objects }) => (tick, baseline, objects),
+                Ok(Packet::Ack { .. }) => {
+                    skip(&mut self.skipped, &self.config, peer, NetError::Malformed("client received an ack".to_string()));
+                    continue;
+                }
+                Err(error) => {
+                    skip(&mut self.skipped, &self.config, peer, error);
+                    continue;
+                }
+            };
+            if self.tick().is_some_and(|newest| tick <= newest) {
+                continue;
+            }
+            if self.apply(tick, baseline, objects) {
+                self.transport.send(self.server, codec::encode(&Packet::Ack { tick }));
+                applied += 1;
+            }
+        }
+        applied
+    }
+
+    /// Advance the render clock by `delta` seconds. It trails the newest
+    /// snapshot by `interpolation_delay` ticks, and jumps there if it drifts
+    /// more than that delay away.
+    pub fn update(&mut self, delta: f64) {
+        let Some(newest) = self.tick() else {
+            return;
+        };
+        let target = newest as f64 - self.config.interpolation_delay;
+        self.render_tick += delta * self.config.tick_rate;
+        if (self.render_tick - target).abs() > self.config.interpolation_delay.max(1.0) {
+            self.render_tick = target;
+        }
+        self.render_tick = self.render_tick.min(newest as f64);
+    }
+
+    /// Objects in the current state
+    pub fn objects(&self) -> Vec<NetId> {
+        self.snapshots.back().map(|snapshot| snapshot.objects.keys().copied().collect()).unwrap_or_default()
+    }
+
+    /// A field of object `id`; `Interpolated` fields are read at the
+    /// render clock, the rest are their newest value
+    pub fn get(&self, id: NetId, field: &str) -> Option<NetValue> {
+        let index = self.schema.index_of(field)?;
+        let newest = self.snapshots.back()?.objects.get(&id)?[index].clone();
+        if self.schema.fields()[index].mode != SyncMode::Interpolated {
+            return Some(newest);
+        }
+        self.buffers.get(&(id, index)).and_then(|buffer| buffer.sample(self.render_tick)).or(Some(newest))
+    }
+
+    /// Build the snapshot at `tick` from `baseline` and `deltas`; false if
+    /// the baseline isn't kept anymore
+    fn apply(&mut self, tick: u32, baseline: Option<u32>, deltas: Vec<ObjectDelta>) -> bool {
+        let mut objects = match baseline {
+            Some(baseline) => match self.snapshots.iter().find(|snapshot| snapshot.tick == baseline) {
+                Some(snapshot) => snapshot.objects.clone(),
+                None => return false,
+            },
+            None => Objects::new(),
+        };
+        let mut sent: HashMap<NetId, u64> = HashMap::new();
+        for delta in deltas {
+            match delta {
+                ObjectDelta::Removed(id) => {
+                    objects.remove(&id);
+                }
+                ObjectDelta::Changed { id, created, fields } => {
+                    if created {
+                        objects.insert(id, self.schema.defaults());
+                    }
+                    let values = objects.entry(id).or_insert_with(|| self.schema.defaults());
+                    let mask = sent.entry(id).or_default();
+                    for (index, value) in fields {
+                        *mask |= 1 << index;
+                        values[index] = value;
+                    }
+                }
+            }
+        }
+
+        // Fields only sent when they change, or every few ticks, aren't
+        // resent against an older baseline; they keep their newest value
+        if let Some(newest) = self.snapshots.back() {
+            for (id, values) in objects.iter_mut() {
+                let (Some(current), mask) = (newest.objects.get(id), sent.get(id).copied().unwrap_or(0)) else {
+                    continue;
+                };
+                for (index, field) in self.schema.fields().iter().enumerate() {
+                    let unsent = mask & 1 << index == 0;
+                    if unsent && matches!(field.mode, SyncMode::Unreliable | SyncMode::Snapshot) {
+                        values[index] = current[index].clone();
+                    }
+                }
+            }
+        }
+
+        for (&id, values) in &objects {
+            for (index, field) in self.schema.fields().iter().enumerate() {
+                if field.mode == SyncMode::Interpolated {
+                    self.buffers
+                        .entry((id, index))
+                        .or_insert_with(|| InterpolationBuffer::new(self.config.history))
+                        .push(tick, values[index].clone());
+                }
+            }
+        }
+        self.buffers.retain(|(id, _), _| objects.contains_key(id));
+
+        if self.snapshots.is_empty() {
+            self.render_tick = tick as f64 - self.config.interpolation_delay;
+        }
+        self.snapshots.push_back(Snapshot { tick, objects });
+        while self.snapshots.len() > self.config.history {
+            self.snapshots.pop_front();
+        }
+        true
+    }
+}
+
+/// Record a skipped packet, keeping only the most recent `history` of them
+fn skip(skipped: &mut VecDeque<(PeerId, NetError)>, config: &ReplicationConfig, peer: PeerId, error: NetError) {
+    if skipped.len() >= config.history.max(1) {
+        skipped.pop_front();
+    }
+    skipped.push_back((peer, error));
+}
+
+fn field_index(schema: &Schema, field: &str) -> Result<usize, NetError> {
+    schema.index_of(field).ok_or_else(|| NetError::UnknownField { schema: schema.name.clone(), field: field.to_string() })
+}
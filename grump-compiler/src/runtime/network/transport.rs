@@ -0,0 +1,90 @@
+//! Packet transports
+//!
+//! Replication only needs unreliable, unordered datagrams; lost packets are
+//! made up for by the deltas that follow them.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+/// Peer ID on a transport
+pub type PeerId = u32;
+
+/// Sends and receives packets between peers
+pub trait Transport {
+    /// Send `packet` to `to`; it may be dropped
+    fn send(&mut self, to: PeerId, packet: Vec<u8>);
+
+    /// The next packet that arrived, and who sent it
+    fn receive(&mut self) -> Option<(PeerId, Vec<u8>)>;
+}
+
+#[derive(Default)]
+struct Network {
+    inboxes: HashMap<PeerId, VecDeque<(PeerId, Vec<u8>)>>,
+    /// Links whose packets are dropped, as (from, to)
+    cut: HashSet<(PeerId, PeerId)>,
+    bytes_sent: usize,
+}
+
+/// In-process network: packets are delivered to the receiver's inbox as
+/// they're sent. Cloning it gives another handle to the same network.
+#[derive(Clone, Default)]
+pub struct Loopback {
+    network: Rc<RefCell<Network>>,
+}
+
+impl Loopback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A transport for peer `peer`
+    pub fn connect(&self, peer: PeerId) -> LoopbackTransport {
+        self.network.borrow_mut().inboxes.entry(peer).or_default();
+        LoopbackTransport { network: self.clone(), peer }
+    }
+
+    /// Drop packets sent from `from` to `to` until `restore` is called
+    pub fn cut(&self, from: PeerId, to: PeerId) {
+        self.network.borrow_mut().cut.insert((from, to));
+    }
+
+    pub fn restore(&self, from: PeerId, to: PeerId) {
+        self.network.borrow_mut().cut.remove(&(from, to));
+    }
+
+    /// Bytes sent so far, dropped packets included
+    pub fn bytes_sent(&self) -> usize {
+        self.network.borrow().bytes_sent
+    }
+}
+
+/// One peer's end of a `Loopback`
+pub struct LoopbackTransport {
+    network: Loopback,
+    peer: PeerId,
+}
+
+impl LoopbackTransport {
+    pub fn peer(&self) -> PeerId {
+        self.peer
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, to: PeerId, packet: Vec<u8>) {
+        let mut network = self.network.network.borrow_mut();
+        network.bytes_sent += packet.len();
+        if network.cut.contains(&(self.peer, to)) {
+            return;
+        }
+        if let Some(inbox) = network.inboxes.get_mut(&to) {
+            inbox.push_back((self.peer, packet));
+        }
+    }
+
+    fn receive(&mut self) -> Option<(PeerId, Vec<u8>)> {
+        self.network.network.borrow_mut().inboxes.get_mut(&self.peer)?.pop_front()
+    }
+}
@@ -0,0 +1,221 @@
+//! Script scheduler runtime
+//!
+//! Cooperative scheduling for long-running scripts such as cutscenes.
+//! A script is a future polled once per game loop tick; it suspends on
+//! `wait`, `animation_finished` and `event`, which resume it on the first
+//! tick their condition holds. Scripts owned by an entity are dropped once
+//! the entity is removed from the world.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use super::animation::AnimationManager;
+use super::ecs::{EntityId, World};
+
+/// Script ID
+pub type TaskId = u64;
+
+/// How far a clock can drift below a wait's end and still count, since
+/// summing frame times rounds
+const EPSILON: f64 = 1e-9;
+
+/// What suspended scripts wait on
+#[derive(Default)]
+struct Clock {
+    time: f64,
+    ticks: u64,
+    /// Animations playing as of the last tick, plus those scripts started since
+    playing: HashSet<String>,
+    /// Events delivered this tick, and those emitted for the next
+    fired: Vec<String>,
+    emitted: Vec<String>,
+    /// Animations scripts asked to play, started on the next tick
+    plays: Vec<String>,
+}
+
+/// A running script's view of the game; cloning it is cheap
+#[derive(Clone)]
+pub struct Script {
+    clock: Rc<RefCell<Clock>>,
+}
+
+impl Script {
+    /// Seconds of game time since the scheduler started
+    pub fn time(&self) -> f64 {
+        self.clock.borrow().time
+    }
+
+    /// Resumes on the first tick `seconds` of game time from now
+    pub fn wait(&self, seconds: f64) -> Until {
+        let end = self.time() + seconds;
+        self.until(move |clock| clock.time + EPSILON >= end)
+    }
+
+    /// Resumes once the animation named `name` isn't playing
+    pub fn animation_finished(&self, name: &str) -> Until {
+        let name = name.to_string();
+        self.until(move |clock| !clock.playing.contains(&name))
+    }
+
+    /// Resumes on the first tick an event named `name` is delivered,
+    /// after this one
+    pub fn event(&self, name: &str) -> Until {
+        let name = name.to_string();
+        let since = self.clock.borrow().ticks;
+        self.until(move |clock| clock.ticks > since && clock.fired.contains(&name))
+    }
+
+    /// Deliver an event to the scripts waiting for it, on the next tick
+    pub fn emit(&self, name: &str) {
+        self.clock.borrow_mut().emitted.push(name.to_string());
+    }
+
+    /// Play the animation named `name`; it counts as playing right away
+    pub fn play(&self, name: &str) {
+        let mut clock = self.clock.borrow_mut();
+        clock.playing.insert(name.to_string());
+        clock.plays.push(name.to_string());
+    }
+
+    fn until(&self, ready: impl Fn(&Clock) -> bool + 'static) -> Until {
+        Until { clock: self.clock.clone(), ready: Box::new(ready) }
+    }
+}
+
+/// Future that's ready once a condition on the scheduler's clock holds
+pub struct Until {
+    clock: Rc<RefCell<Clock>>,
+    ready: Box<dyn Fn(&Clock) -> bool>,
+}
+
+impl Future for Until {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if (self.ready)(&self.clock.borrow()) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+struct Task {
+    id: TaskId,
+    owner: Option<EntityId>,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+/// Runs scripts a step at a time, once per tick
+pub struct Scheduler {
+    clock: Rc<RefCell<Clock>>,
+    tasks: Vec<Task>,
+    next_id: TaskId,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            clock: Rc::new(RefCell::new(Clock::default())),
+            tasks: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Start a script; it runs up to its first suspension right away
+    pub fn spawn<F, Fut>(&mut self, script: F) -> TaskId
+    where
+        F: FnOnce(Script) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.start(None, script)
+    }
+
+    /// Start a script that's cancelled when `entity` is removed from the world
+    pub fn spawn_for<F, Fut>(&mut self, entity: EntityId, script: F) -> TaskId
+    where
+        F: FnOnce(Script) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.start(Some(entity), script)
+    }
+
+    fn start<F, Fut>(&mut self, owner: Option<EntityId>, script: F) -> TaskId
+    where
+        F: FnOnce(Script) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut task = Task { id, owner, future: Box::pin(script(Script { clock: self.clock.clone() })) };
+        if poll(&mut task).is_pending() {
+            self.tasks.push(task);
+        }
+        id
+    }
+
+    /// Deliver an event to the scripts waiting for it, on the next tick
+    pub fn emit(&mut self, name: &str) {
+        self.clock.borrow_mut().emitted.push(name.to_string());
+    }
+
+    /// Stop a script; it won't be polled again
+    pub fn cancel(&mut self, id: TaskId) {
+        self.tasks.retain(|task| task.id != id);
+    }
+
+    pub fn is_running(&self, id: TaskId) -> bool {
+        self.tasks.iter().any(|task| task.id == id)
+    }
+
+    /// Scripts still running
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Seconds of game time ticked so far
+    pub fn time(&self) -> f64 {
+        self.clock.borrow().time
+    }
+
+    /// Advance game time by `delta` and resume every script whose wait is
+    /// over. Animations scripts asked to play start first; scripts of
+    /// entities no longer in `world` are dropped without resuming.
+    pub fn tick(&mut self, delta: f64, world: &World, animations: &mut AnimationManager) {
+        {
+            let mut clock = self.clock.borrow_mut();
+            for name in std::mem::take(&mut clock.plays) {
+                animations.play_animation(&name);
+            }
+            clock.time += delta;
+            clock.ticks += 1;
+            clock.playing = animations.playing().map(str::to_string).collect();
+            clock.fired = std::mem::take(&mut clock.emitted);
+        }
+
+        let live = world.entities();
+        self.tasks.retain(|task| task.owner.is_none_or(|owner| live.contains(&owner)));
+        self.tasks.retain_mut(|task| poll(task).is_pending());
+        self.clock.borrow_mut().fired.clear();
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scripts only wait on the clock, which the scheduler checks every tick,
+/// so nothing needs waking
+fn poll(task: &mut Task) -> Poll<()> {
+    task.future.as_mut().poll(&mut Context::from_waker(Waker::noop()))
+}
@@ -0,0 +1,137 @@
+//! `grump test`
+//!
+//! Finds the `test "name" { ... }` items in a project's .grump files and runs
+//! each one in a fresh interpreter with the rest of its file loaded. A file
+//! with tests that doesn't parse or type check fails as a whole; files
+//! without tests are never checked.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::analyzer::Analyzer;
+use crate::error::GrumpResult;
+use crate::interpreter::Interpreter;
+use crate::lexer::{Lexer, Token};
+use crate::loader;
+use crate::parser::{Item, Parser, Program};
+
+pub mod report;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// An assertion failed or the test hit a runtime error
+    Failed(String),
+    /// The file the test is in doesn't compile
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    /// File the test is in, relative to the project
+    pub file: String,
+    pub name: String,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// Every test run, in file order
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub results: Vec<TestResult>,
+    pub duration: Duration,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.outcome == Outcome::Passed).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    pub fn success(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// The .grump files under `root` (or `root` itself if it's a file), sorted
+pub fn discover(root: &Path) -> Vec<PathBuf> {
+    loader::source_files(root)
+}
+
+/// Run the tests in every .grump file under `root` whose name contains
+/// `filter`
+pub fn run_project(root: &Path, filter: Option<&str>) -> GrumpResult<TestReport> {
+    let start = Instant::now();
+    let mut report = TestReport::default();
+    for path in discover(root) {
+        let source = std::fs::read_to_string(&path)?;
+        let relative = path.strip_prefix(root).ok().filter(|p| !p.as_os_str().is_empty()).unwrap_or(&path);
+        let file = relative.display().to_string().replace('\\', "/");
+        report.results.extend(run_source(&file, &source, filter));
+    }
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// Run the tests in one file's `source`
+pub fn run_source(file: &str, source: &str, filter: Option<&str>) -> Vec<TestResult> {
+    let program = match Parser::new(source).parse() {
+        Ok(program) => program,
+        Err(e) if has_tests(source) => return compile_error(file, e.to_string()),
+        Err(_) => return Vec::new(),
+    };
+    let tests: Vec<_> = program.all_items().into_iter()
+        .filter_map(|item| match item {
+            Item::Test(test) => Some(test),
+            _ => None,
+        })
+        .filter(|test| filter.is_none_or(|f| test.name.contains(f)))
+        .collect();
+    if tests.is_empty() {
+        return Vec::new();
+    }
+    if let Err(e) = Analyzer::new().analyze(&program) {
+        return compile_error(file, e.to_string());
+    }
+
+    tests.into_iter()
+        .map(|test| {
+            let start = Instant::now();
+            let outcome = match fresh(&program).run_test(test) {
+                Ok(()) => Outcome::Passed,
+                Err(e) => Outcome::Failed(e.to_string()),
+            };
+            TestResult { file: file.to_string(), name: test.name.clone(), outcome, duration: start.elapsed() }
+        })
+        .collect()
+}
+
+/// Whether `source` has a `test` item, going by its tokens
+fn has_tests(source: &str) -> bool {
+    let mut lexer = Lexer::new(source);
+    while let Ok(Some((token, _, _))) = lexer.next_token() {
+        if token == Token::Test {
+            return true;
+        }
+    }
+    false
+}
+
+/// An interpreter with `program` loaded, so no test sees another's changes
+fn fresh(program: &Program) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.load(program);
+    interpreter
+}
+
+fn compile_error(file: &str, message: String) -> Vec<TestResult> {
+    vec![TestResult {
+        file: file.to_string(),
+        name: "(compile)".to_string(),
+        outcome: Outcome::Error(message),
+        duration: Duration::ZERO,
+    }]
+}
@@ -0,0 +1,126 @@
+//! Test reports: for people, and JUnit XML for CI
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{Outcome, TestReport, TestResult};
+
+impl TestReport {
+    /// One line per test, then the failures in full and a summary
+    pub fn human(&self) -> String {
+        let mut out = format!("running {} test{}\n", self.results.len(), plural(self.results.len()));
+        for result in &self.results {
+            let status = match result.outcome {
+                Outcome::Passed => "ok",
+                Outcome::Failed(_) => "FAILED",
+                Outcome::Error(_) => "ERROR",
+            };
+            out.push_str(&format!("test {}: {} ... {}\n", result.file, result.name, status));
+        }
+
+        let failures: Vec<(&TestResult, &str)> = self.results.iter()
+            .filter_map(|r| match &r.outcome {
+                Outcome::Passed => None,
+                Outcome::Failed(message) | Outcome::Error(message) => Some((r, message.as_str())),
+            })
+            .collect();
+        if !failures.is_empty() {
+            out.push_str("\nfailures:\n");
+            for (result, message) in &failures {
+                out.push_str(&format!("\n---- {}: {} ----\n{}\n", result.file, result.name, message));
+            }
+        }
+
+        out.push_str(&format!(
+            "\ntest result: {}. {} passed; {} failed; finished in {:.2}s\n",
+            if self.success() { "ok" } else { "FAILED" },
+            self.passed(),
+            self.failed(),
+            self.duration.as_secs_f64()
+        ));
+        out
+    }
+
+    /// JUnit XML, one `<testsuite>` per file
+    pub fn junit(&self) -> String {
+        let mut files: BTreeMap<&str, Vec<&TestResult>> = BTreeMap::new();
+        for result in &self.results {
+            files.entry(&result.file).or_default().push(result);
+        }
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites name=\"grump\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+            self.results.len(),
+            count(self.results.iter(), |o| matches!(o, Outcome::Failed(_))),
+            count(self.results.iter(), |o| matches!(o, Outcome::Error(_))),
+            seconds(self.duration)
+        ));
+        for (file, results) in files {
+            let time: Duration = results.iter().map(|r| r.duration).sum();
+            out.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+                escape(file),
+                results.len(),
+                count(results.iter().copied(), |o| matches!(o, Outcome::Failed(_))),
+                count(results.iter().copied(), |o| matches!(o, Outcome::Error(_))),
+                seconds(time)
+            ));
+            let classname = file.strip_suffix(".grump").unwrap_or(file).replace('/', ".");
+            for result in results {
+                let open = format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                    escape(&result.name),
+                    escape(&classname),
+                    seconds(result.duration)
+                );
+                match &result.outcome {
+                    Outcome::Passed => out.push_str(&format!("{}/>\n", open)),
+                    Outcome::Failed(message) | Outcome::Error(message) => {
+                        let tag = if matches!(result.outcome, Outcome::Failed(_)) { "failure" } else { "error" };
+                        out.push_str(&format!("{}>\n", open));
+                        out.push_str(&format!(
+                            "      <{} message=\"{}\">{}</{}>\n",
+                            tag, escape(first_line(message)), escape(message), tag
+                        ));
+                        out.push_str("    </testcase>\n");
+                    }
+                }
+            }
+            out.push_str("  </testsuite>\n");
+        }
+        out.push_str("</testsuites>\n");
+        out
+    }
+}
+
+fn count<'a>(results: impl Iterator<Item = &'a TestResult>, f: impl Fn(&Outcome) -> bool) -> usize {
+    results.filter(|r| f(&r.outcome)).count()
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or("")
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
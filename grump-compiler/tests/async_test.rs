@@ -0,0 +1,101 @@
+//! Tests for async functions, `await` and the frame-aware `wait` and
+//! `animation_finished` helpers
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::{CodeGenerator, Target};
+use grump_compiler::interpreter::{Interpreter, Value};
+use grump_compiler::parser::{Parser, Program};
+
+const INTRO: &str = r#"
+async fn step(n: int) -> int {
+    await wait(500ms);
+    return n + 1;
+}
+
+async fn intro(x: float) -> int {
+    let first = await step(1);
+    let later = async { 2 };
+    let second = await later;
+    animate x {
+        keyframes { 0.0: 0.0  2.0: 1.0 }
+    }
+    await animation_finished(x);
+    return first + second;
+}
+"#;
+
+fn parse(source: &str) -> Program {
+    Parser::new(source).parse().expect("parse")
+}
+
+fn generate(target: Target, path: &str) -> String {
+    let files = CodeGenerator::new(target).generate(&parse(INTRO)).expect("codegen");
+    files.get_text(path).unwrap().to_string()
+}
+
+#[test]
+fn test_analyzer_checks_await() {
+    Analyzer::new().analyze(&parse(INTRO)).unwrap();
+
+    let error = Analyzer::new().analyze(&parse("fn tick() {\n    await wait(1s);\n}\n")).unwrap_err().to_string();
+    assert!(error.contains("`await` outside an async function or block"), "{}", error);
+
+    let error = Analyzer::new().analyze(&parse("async fn tick() {\n    let n = await 3;\n}\n")).unwrap_err().to_string();
+    assert!(error.contains("Cannot await non-async expression"), "{}", error);
+
+    // Calling an async function gives a value to await, not its result
+    let error = Analyzer::new()
+        .analyze(&parse("async fn one() -> int {\n    return 1;\n}\nfn two() -> int {\n    return one() + 1;\n}\n"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Async"), "{}", error);
+}
+
+#[test]
+fn test_interpreter_waits_in_game_time() {
+    let program = parse("async fn later() -> float {\n    await wait(1s);\n    return now();\n}\n");
+    Analyzer::new().analyze(&program).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+    let Value::Float(time) = interpreter.call("later", vec![]).unwrap() else {
+        panic!("not a float");
+    };
+    assert!((0.99..1.1).contains(&time), "{}", time);
+}
+
+#[test]
+fn test_swift_and_kotlin_lowering() {
+    let swift = generate(Target::Ios, "Sources/GrumpGame/Game.swift");
+    assert!(swift.contains("try await step(1)"), "{}", swift);
+    assert!(swift.contains("Task {"), "{}", swift);
+    assert!(swift.contains("try await later.value"), "{}", swift);
+    assert!(swift.contains("GrumpFrames.shared.animating(\"x\", for: 2.0)"), "{}", swift);
+    assert!(swift.contains("animation_finished(\"x\")"), "{}", swift);
+    assert!(generate(Target::Ios, "Sources/GrumpGame/GrumpRuntime.swift").contains("func wait(_ seconds: Double) async"));
+
+    let kotlin = generate(Target::Android, "app/src/main/java/com/grump/generated/Game.kt");
+    assert!(kotlin.contains("suspend fun step("), "{}", kotlin);
+    assert!(kotlin.contains("grumpScope.async {"), "{}", kotlin);
+    assert!(kotlin.contains("later.await()"), "{}", kotlin);
+    assert!(!kotlin.contains("step(1).await()"), "{}", kotlin);
+    let runtime = generate(Target::Android, "app/src/main/java/com/grump/generated/GrumpRuntime.kt");
+    assert!(runtime.contains("suspend fun animation_finished(key: String)"), "{}", runtime);
+}
+
+#[test]
+fn test_dart_and_typescript_lowering() {
+    let game = generate(Target::Flutter, "lib/game.dart");
+    assert!(game.contains("Future<int> step("), "{}", game);
+    assert!(game.contains("(await step(1))"), "{}", game);
+    assert!(game.contains("GrumpClock.animating(\"x\", 2.0);"), "{}", game);
+    assert!(game.contains("await add(GrumpClock());"), "{}", game);
+    assert!(generate(Target::Flutter, "lib/grump/runtime.dart").contains("Future<void> wait(double seconds)"));
+
+    let ts = generate(Target::TypeScript, "src/game.ts");
+    assert!(ts.contains("export async function step(n: number): Promise<number>"), "{}", ts);
+    assert!(ts.contains("await wait(0.5)"), "{}", ts);
+    assert!(ts.contains("loop: \"none\" }, \"x\")"), "{}", ts);
+    assert!(ts.contains("animation_finished(\"x\")"), "{}", ts);
+    let runtime = generate(Target::TypeScript, "src/runtime.ts");
+    assert!(runtime.contains("export function wait(seconds: number): Promise<void>"), "{}", runtime);
+}
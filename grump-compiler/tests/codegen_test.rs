@@ -0,0 +1,212 @@
+//! Tests for the G-Rump code generators
+
+use grump_compiler::parser::Parser;
+use grump_compiler::codegen::{CodeGenerator, OutputTree, SourceMap, Target, BUILD_MARKER};
+
+const GAME: &str = r#"
+    component Position {
+        x: float = 0.0;
+        y: float = 0.0;
+    }
+
+    entity Player {
+        position: (10, 20)
+    }
+
+    system movement {
+        query: [Position]
+        position.x = position.x + 1.0;
+    }
+"#;
+
+#[test]
+fn test_flutter_generates_flame_project() {
+    let program = Parser::new(GAME).parse().expect("parse");
+    let mut codegen = CodeGenerator::new(Target::Flutter);
+    let files = codegen.generate(&program).expect("codegen");
+
+    assert!(files.contains("pubspec.yaml"));
+    assert!(files.get_text("pubspec.yaml").unwrap().contains("flame:"));
+    assert!(files.get_text("lib/main.dart").unwrap().contains("GameWidget"));
+    assert!(files.get_text("lib/game.dart").unwrap().contains("extends FlameGame"));
+    assert!(files.get_text("lib/entities/player.dart").unwrap().contains("class Player extends PositionComponent"));
+    assert!(files.get_text("lib/systems/movement.dart").unwrap().contains("void update(double dt)"));
+}
+
+#[test]
+fn test_typescript_generates_typed_project() {
+    let source = format!("{}\n    enum Mood {{ Happy, Grumpy }}\n", GAME);
+    let program = Parser::new(&source).parse().expect("parse");
+    let files = CodeGenerator::new(Target::TypeScript).generate(&program).expect("codegen");
+
+    assert!(files.get_text("tsconfig.json").unwrap().contains("\"strict\": true"));
+    assert_eq!(files.entry(), Some("src/game.ts"));
+    let game = files.get_text("src/game.ts").unwrap();
+    assert!(game.contains("export interface Position {\n  x: number;\n  y: number;\n}"));
+    assert!(game.contains("export type Mood = \"Happy\" | \"Grumpy\";"));
+    assert!(game.contains("export function movementSystem(world: GameWorld, dt: number): void"));
+    assert!(game.contains("createPosition({ x: 10, y: 20 })"));
+}
+
+#[test]
+fn test_reassigned_lets_are_mutable_on_every_target() {
+    let source = "fn count() -> int {\n    let total = 0;\n    let step = 2;\n    while (total < 10) {\n        total = total + step;\n    }\n    return total;\n}\n";
+    let program = Parser::new(source).parse().expect("parse");
+    grump_compiler::analyzer::Analyzer::new().analyze(&program).expect("a plain `let` may be reassigned");
+
+    let ts = CodeGenerator::new(Target::TypeScript).generate(&program).expect("codegen");
+    let game = ts.entry_source().unwrap();
+    assert!(game.contains("let total = 0;"), "{}", game);
+    assert!(game.contains("const step = 2;"), "{}", game);
+    let kotlin = CodeGenerator::new(Target::Android).generate(&program).expect("codegen");
+    let game = kotlin.entry_source().unwrap();
+    assert!(game.contains("var total") && game.contains("val step"), "{}", game);
+}
+
+#[test]
+fn test_godot_generates_scenes_scripts_and_animations() {
+    let source = format!(
+        "{}\n    scene Level {{ let score = 0; }}\n    animation bounce {{\n        keyframes {{ 0.0: 0.0  0.5: 10.0  1.0: 0.0 }}\n        loop ping_pong\n    }}\n",
+        GAME
+    );
+    let program = Parser::new(&source).parse().expect("parse");
+    let files = CodeGenerator::new(Target::Godot).generate(&program).expect("codegen");
+
+    assert!(files.get_text("project.godot").unwrap().contains("run/main_scene=\"res://scenes/level.tscn\""));
+    let scene = files.get_text("scenes/level.tscn").unwrap();
+    assert!(scene.contains("[node name=\"Player\" type=\"Node2D\" parent=\".\"]"));
+    assert!(scene.contains("[node name=\"AnimationPlayer\" type=\"AnimationPlayer\" parent=\".\"]"));
+    assert!(files.get_text("scripts/systems/movement.gd").unwrap().contains("get_nodes_in_group(\"Position\")"));
+    assert!(files.get_text("scripts/components/position.gd").unwrap().contains("@export var x: float = 0.0"));
+    let animation = files.get_text("animations/bounce.tres").unwrap();
+    assert!(animation.contains("loop_mode = 2"));
+    assert!(animation.contains("PackedFloat32Array(0.0, 0.5, 1.0)"));
+}
+
+#[test]
+fn test_rust_generates_crate_on_the_runtime() {
+    let program = Parser::new(GAME).parse().expect("parse");
+    let files = CodeGenerator::new(Target::Rust).generate(&program).expect("codegen");
+
+    assert!(files.get_text("Cargo.toml").unwrap().contains("grump-runtime = { path = \"grump-runtime\" }"));
+    assert!(files.get_text("grump-runtime/Cargo.toml").unwrap().contains("name = \"grump-runtime\""));
+    assert!(files.contains("grump-runtime/src/network/replication.rs"));
+    assert!(files.contains("tests/game.rs"));
+    let lib = files.get_text("src/lib.rs").unwrap();
+    assert!(lib.contains("pub struct Position {\n    pub x: f64,\n    pub y: f64,\n}"));
+    assert!(lib.contains("impl System for MovementSystem"));
+    assert!(lib.contains("position.x = (position.x + 1.0);"));
+    assert!(lib.contains("world.add_component(entity, Position { x: 10.0, y: 20.0 });"));
+
+    // Or an existing copy of the runtime, instead of writing one
+    let files = CodeGenerator::new(Target::Rust).with_rust_runtime("/opt/grump-runtime").generate(&program).expect("codegen");
+    assert!(files.get_text("Cargo.toml").unwrap().contains("grump-runtime = { path = \"/opt/grump-runtime\" }"));
+    assert!(!files.paths().any(|path| path.starts_with("grump-runtime/")));
+}
+
+#[test]
+fn test_build_output_is_an_openable_project() {
+    let program = Parser::new(GAME).parse().expect("parse");
+
+    let ios = CodeGenerator::new(Target::Ios).generate(&program).expect("codegen");
+    assert!(ios.contains("Package.swift"));
+    assert_eq!(ios.entry(), Some("Sources/GrumpGame/Game.swift"));
+
+    let android = CodeGenerator::new(Target::Android).generate(&program).expect("codegen");
+    assert!(android.contains("settings.gradle.kts"));
+    assert!(android.contains("app/src/main/AndroidManifest.xml"));
+
+    let web = CodeGenerator::new(Target::Web).generate(&program).expect("codegen");
+    assert!(web.get_text("index.html").unwrap().contains("game.js"));
+    assert_eq!(web.entry(), Some("game.js"));
+}
+
+#[test]
+fn test_write_atomic_replaces_previous_build() {
+    let dir = std::env::temp_dir().join(format!("grump-build-{}", std::process::id()));
+    let dest = dir.join("web");
+    let mut tree = OutputTree::new();
+    tree.add_text("index.html", "<html></html>");
+    tree.add_asset("sprites/player.png", vec![0x89, b'P', b'N', b'G']);
+    tree.write_atomic(&dest).expect("write");
+    assert!(dest.join(BUILD_MARKER).exists());
+
+    std::fs::write(dest.join("stale.js"), "old").unwrap();
+    tree.write_atomic(&dest).expect("rewrite");
+    assert!(dest.join("index.html").exists());
+    assert!(dest.join("sprites/player.png").exists());
+    assert!(!dest.join("stale.js").exists());
+
+    let mut bad = OutputTree::new();
+    bad.add_text("../escape.txt", "");
+    assert!(bad.write_atomic(&dest).is_err());
+    assert!(dest.join("index.html").exists());
+
+    // A directory grump didn't build is left alone, and nothing is staged
+    let mine = dir.join("mine");
+    std::fs::create_dir_all(&mine).unwrap();
+    std::fs::write(mine.join("notes.txt"), "keep").unwrap();
+    let error = tree.write_atomic(&mine).unwrap_err().to_string();
+    assert!(error.contains("Refusing to replace"), "{}", error);
+    assert_eq!(std::fs::read_to_string(mine.join("notes.txt")).unwrap(), "keep");
+    let leftovers: Vec<_> = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with('.'))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_source_maps_point_back_to_grump_lines() {
+    let program = Parser::new(GAME).parse().expect("parse");
+
+    let mut ios = CodeGenerator::new(Target::Ios).with_source("game.grump", None);
+    let tree = ios.generate(&program).expect("codegen");
+    let swift = tree.get_text("Sources/GrumpGame/Game.swift").unwrap();
+    assert!(swift.contains("#sourceLocation(file: \"game.grump\", line: 7)"));
+    assert!(tree.contains("Sources/GrumpGame/Game.swift.map"));
+
+    // The entity declaration starts on line 7 of GAME
+    let player = ios.source_map().mappings().iter()
+        .find(|m| m.name.as_deref() == Some("Player"))
+        .expect("Player mapping");
+    assert_eq!(player.span.line, 7);
+    let trace = format!("Fatal error at Game.swift:{}", player.generated_line + 2);
+    assert_eq!(ios.source_map().symbolicate(&trace), "Fatal error at game.grump:7");
+
+    let map = SourceMap::from_json(tree.get_text("Sources/GrumpGame/Game.swift.map").unwrap()).expect("map");
+    assert_eq!(map.mappings(), ios.source_map().mappings());
+}
+
+#[test]
+fn test_errors_inside_function_bodies_map_to_their_statement() {
+    let source = "fn damage(hp: int, amount: int) -> int {\n    let left = hp - amount;\n    if (left < 0) {\n        fail(\"negative health\");\n    }\n    return left;\n}\n";
+    let program = Parser::new(source).parse().expect("parse");
+
+    let targets = [
+        (Target::TypeScript, "src/game.ts"),
+        (Target::Ios, "Sources/GrumpGame/Game.swift"),
+        (Target::Android, "app/src/main/java/com/grump/generated/Game.kt"),
+        (Target::Rust, "src/lib.rs"),
+        (Target::Godot, "scripts/functions/damage.gd"),
+    ];
+    for (target, path) in targets {
+        let tree = CodeGenerator::new(target).with_source("main.grump", None).generate(&program).expect("codegen");
+        let text = tree.get_text(path).unwrap();
+        assert!(!text.contains('\u{1}'), "{:?}: {}", target, text);
+
+        // A runtime error thrown by the `fail` call, as the target reports it
+        let line = text.lines().position(|line| line.contains("\"negative health\"")).expect("fail call") + 1;
+        let file = path.rsplit('/').next().unwrap();
+        let map = SourceMap::from_json(tree.get_text(&format!("{}.map", path)).expect("map")).expect("map");
+        let trace = format!("Error: negative health\n    at damage ({}:{})", file, line);
+        assert_eq!(map.symbolicate(&trace), "Error: negative health\n    at damage (main.grump:4)", "{:?}: {}", target, text);
+    }
+
+    // Swift reports `#sourceLocation`s itself
+    let tree = CodeGenerator::new(Target::Ios).with_source("main.grump", None).generate(&program).expect("codegen");
+    let swift = tree.get_text("Sources/GrumpGame/Game.swift").unwrap();
+    assert!(swift.contains("#sourceLocation(file: \"main.grump\", line: 4)\n        fail(\"negative health\")"), "{}", swift);
+}
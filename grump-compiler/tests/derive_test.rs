@@ -0,0 +1,81 @@
+//! Tests for `@derive` on components
+
+use grump_compiler::codegen::{CodeGenerator, Target};
+use grump_compiler::parser::{Item, Parser, Program};
+
+const SPRITE: &str = r#"
+enum Mood {
+    Happy,
+    Grumpy,
+}
+
+@derive(Serialize, Inspect, Lerp)
+component Sprite {
+    position: vec2 = (0.0, 0.0);
+    tint: color;
+    frame: int = 0;
+    mood: Mood;
+    label: string = "";
+}
+"#;
+
+fn parse(source: &str) -> Program {
+    Parser::new(source).parse().expect("parse")
+}
+
+fn generate(target: Target, path: &str) -> String {
+    let files = CodeGenerator::new(target).generate(&parse(SPRITE)).expect("codegen");
+    files.get_text(path).unwrap().to_string()
+}
+
+#[test]
+fn test_derives_are_parsed_and_checked() {
+    let program = parse(SPRITE);
+    let Some(Item::Component(sprite)) = program.items.iter().find(|item| item.name() == "Sprite") else {
+        panic!("no Sprite");
+    };
+    assert_eq!(sprite.derives, vec!["Serialize", "Inspect", "Lerp"]);
+
+    let error = Parser::new("@derive(Debug)\ncomponent A {\n    x: float;\n}\n").parse().unwrap_err().to_string();
+    assert!(error.contains("Macro error at 2:"), "{}", error);
+    assert!(error.contains("isn't one of Serialize, Inspect, Lerp"), "{}", error);
+
+    let error = Parser::new("@derive(Lerp, Lerp)\ncomponent A {\n    x: float;\n}\n").parse().unwrap_err().to_string();
+    assert!(error.contains("derives `Lerp` twice"), "{}", error);
+
+    let error = Parser::new("@derive(Lerp)\ncomponent Name {\n    text: string;\n}\n").parse().unwrap_err().to_string();
+    assert!(error.contains("has no animatable fields"), "{}", error);
+}
+
+#[test]
+fn test_typescript_derives() {
+    let game = generate(Target::TypeScript, "src/game.ts");
+    assert!(game.contains("export function serializeSprite("), "{}", game);
+    assert!(game.contains("export function deserializeSprite("), "{}", game);
+    assert!(game.contains("export const SpriteInspector"), "{}", game);
+    assert!(game.contains("\"Happy\""), "{}", game);
+    assert!(game.contains("export const SpriteLerp"), "{}", game);
+    assert!(game.contains("export function lerpSprite("), "{}", game);
+    assert!(!game.contains("label: (a: Sprite"), "{}", game);
+}
+
+#[test]
+fn test_godot_derives() {
+    let script = generate(Target::Godot, "scripts/components/sprite.gd");
+    assert!(script.contains("func serialize() -> String:"), "{}", script);
+    assert!(script.contains("static func inspector()"), "{}", script);
+    assert!(script.contains("func lerp_position("), "{}", script);
+    assert!(script.contains("func interpolate("), "{}", script);
+    assert!(!script.contains("func lerp_label("), "{}", script);
+}
+
+#[test]
+fn test_rust_derives() {
+    let lib = generate(Target::Rust, "src/lib.rs");
+    assert!(lib.contains("serde::Serialize"), "{}", lib);
+    assert!(lib.contains("pub fn to_json("), "{}", lib);
+    assert!(lib.contains("pub const INSPECTOR"), "{}", lib);
+    assert!(lib.contains("pub fn lerp_frame("), "{}", lib);
+    assert!(lib.contains("pub fn interpolate("), "{}", lib);
+    assert!(generate(Target::Rust, "Cargo.toml").contains("serde_json"));
+}
@@ -0,0 +1,245 @@
+//! Tests for animation export
+
+use grump_compiler::parser::Parser;
+use grump_compiler::export::{self, lottie, Clip, ClipKeyframe, ClipLoop, Easing, ExportFormat, ExportOptions, Track, Value};
+
+fn keyframe(time: f64, channels: Vec<f64>, easing: Easing) -> ClipKeyframe {
+    ClipKeyframe { time, value: Value { channels, unit: None }, easing }
+}
+
+#[test]
+fn test_lottie_bakes_ping_pong_and_marks_loop() {
+    let source = r#"
+        app "Demo" @fps 30 {
+            animation bounce {
+                keyframes { 0.0: 0.0  0.5: 10.0  1.0: 0.0 }
+                loop ping_pong
+            }
+        }
+    "#;
+    let program = Parser::new(source).parse().expect("parse");
+    let files = export::export(&program, &ExportFormat::Lottie, &ExportOptions::default()).expect("export");
+    let json: serde_json::Value = serde_json::from_str(files.get_text("lottie/bounce.json").unwrap()).unwrap();
+
+    assert_eq!(json["fr"], 30.0);
+    // One second, played forward then back
+    assert_eq!(json["op"], 60.0);
+    assert_eq!(json["markers"][0]["cm"], "loop");
+    let layer = &json["layers"][0];
+    assert_eq!(layer["ty"], 3);
+    let slider = &layer["ef"][0];
+    assert_eq!(slider["nm"], "value");
+    assert_eq!(slider["ef"][0]["v"]["k"].as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn test_lottie_maps_transforms_easing_and_parents() {
+    let ease = Easing::named("ease_in_out").unwrap();
+    let clip = Clip {
+        name: "intro".to_string(),
+        duration: 1.0,
+        loop_mode: ClipLoop::Once,
+        tracks: vec![
+            Track {
+                target: vec!["hero".to_string()],
+                property: "position".to_string(),
+                keyframes: vec![keyframe(0.0, vec![0.0, 0.0], ease), keyframe(1.0, vec![100.0, 50.0], Easing::LINEAR)],
+            },
+            Track {
+                target: vec!["hero".to_string(), "eyes".to_string()],
+                property: "opacity".to_string(),
+                keyframes: vec![keyframe(0.0, vec![0.5], Easing::LINEAR)],
+            },
+        ],
+    };
+    let options = lottie::LottieOptions { fps: 24.0, ..Default::default() };
+    let json: serde_json::Value = serde_json::from_str(&lottie::export(&clip, &options).unwrap()).unwrap();
+
+    let hero = &json["layers"][0];
+    let position = &hero["ks"]["p"];
+    assert_eq!(position["a"], 1);
+    assert_eq!(position["k"][0]["o"]["x"][0], ease.x1);
+    assert_eq!(position["k"][0]["i"]["y"][0], ease.y2);
+    assert_eq!(position["k"][1]["t"], 24.0);
+    assert_eq!(position["k"][1]["s"], serde_json::json!([100.0, 50.0, 0.0]));
+
+    let eyes = &json["layers"][1];
+    assert_eq!(eyes["parent"], hero["ind"]);
+    assert_eq!(eyes["ks"]["o"]["k"], 50.0);
+    assert!(json["markers"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_css_keyframes_use_cubic_bezier_and_units() {
+    let clip = Clip {
+        name: "wave".to_string(),
+        duration: 2.0,
+        loop_mode: ClipLoop::PingPong,
+        tracks: vec![
+            Track {
+                target: vec!["hero".to_string(), "arm".to_string()],
+                property: "rotation".to_string(),
+                keyframes: vec![
+                    keyframe(0.0, vec![0.0], Easing::named("smooth").unwrap()),
+                    keyframe(2.0, vec![1.5], Easing::LINEAR),
+                ],
+            },
+            Track {
+                target: vec!["hero".to_string()],
+                property: "position".to_string(),
+                keyframes: vec![keyframe(0.0, vec![0.0, 0.0], Easing::LINEAR), keyframe(1.0, vec![40.0, -8.0], Easing::LINEAR)],
+            },
+        ],
+    };
+    let css = export::css::export(&clip).unwrap();
+
+    assert!(css.contains("@keyframes wave-hero-arm-rotation"));
+    assert!(css.contains("0% { rotate: 0deg; animation-timing-function: cubic-bezier(0.42, 0, 0.58, 1); }"));
+    assert!(css.contains("100% { rotate: 1.5deg; }"));
+    assert!(css.contains("50% { translate: 40px -8px; }"));
+    assert!(css.contains(".wave__hero-arm {"));
+    assert!(css.contains("wave-hero-arm-rotation 2s linear 0s infinite alternate both"));
+}
+
+#[test]
+fn test_web_animations_register_custom_properties_and_loop_sections() {
+    let source = r#"
+        animation bounce {
+            keyframes { 0.0: 0.0  0.5: 10.0  1.0: 0.0 }
+            loop ping_pong
+        }
+    "#;
+    let program = Parser::new(source).parse().expect("parse");
+    let files = export::export(&program, &ExportFormat::WebAnimations, &ExportOptions::default()).expect("export");
+    let js = files.get_text("js/bounce.js").unwrap();
+
+    assert!(js.contains("register(\"--bounce\", \"<number>\", \"0\");"));
+    assert!(js.contains("export function bounce(targets)"));
+    assert!(js.contains("{ offset: 0.5, \"--bounce\": \"10\" }"));
+    assert!(js.contains("iterations: Infinity, direction: \"alternate\""));
+
+    let mut clip = export::Clip::collect(&program).unwrap().remove(0);
+    clip.loop_mode = ClipLoop::Section { start: 0.5, end: 1.0 };
+    let js = export::web_animations::export(&clip).unwrap();
+    assert!(js.contains("{ duration: 1000, iterations: 1, direction: \"normal\", fill: \"both\" }"));
+    assert!(js.contains("{ duration: 500, iterations: Infinity, direction: \"normal\", fill: \"both\", delay: 1000 }"));
+}
+
+#[test]
+fn test_sprite_sheet_renders_frames_without_a_gpu() {
+    let source = r#"
+        app "Demo" @fps 10 {
+            animation bounce {
+                keyframes { 0.0: 0.0  0.5: 10.0  1.0: 0.0 }
+                loop ping_pong
+            }
+        }
+    "#;
+    let program = Parser::new(source).parse().expect("parse");
+    let options = ExportOptions { size: Some((32, 32)), ..Default::default() };
+    let files = export::export(&program, &ExportFormat::SpriteSheet, &options).expect("export");
+
+    // Two seconds once ping-pong is unrolled; a loop stops one frame short of wrapping
+    assert!(files.contains("frames/bounce/bounce_0019.png"));
+    assert!(!files.contains("frames/bounce/bounce_0020.png"));
+
+    let frame = export::png::decode(files.get("frames/bounce/bounce_0000.png").unwrap().as_bytes()).unwrap();
+    assert_eq!(frame.get(16, 16), [255, 140, 0, 255]);
+    assert_eq!(frame.get(0, 0)[3], 0);
+
+    let sheet = export::png::decode(files.get("spritesheet/bounce.png").unwrap().as_bytes()).unwrap();
+    assert_eq!((sheet.width, sheet.height), (32 * 5, 32 * 4));
+    let json: serde_json::Value = serde_json::from_str(files.get_text("spritesheet/bounce.json").unwrap()).unwrap();
+    assert_eq!(json["frames"]["bounce_0006.png"]["frame"], serde_json::json!({ "x": 32, "y": 32, "w": 32, "h": 32 }));
+    assert_eq!(json["frames"]["bounce_0006.png"]["duration"], 100);
+    assert_eq!(json["animations"]["bounce"].as_array().unwrap().len(), 20);
+    assert_eq!(json["meta"]["image"], "bounce.png");
+}
+
+#[test]
+fn test_png_headers_claiming_huge_images_are_rejected() {
+    let program = Parser::new("app \"Demo\" @fps 10 {\n    animation blink {\n        keyframes { 0.0: 0.0  0.1: 1.0 }\n    }\n}\n").parse().expect("parse");
+    let options = ExportOptions { size: Some((8, 8)), ..Default::default() };
+    let files = export::export(&program, &ExportFormat::SpriteSheet, &options).expect("export");
+    let mut png = files.get("spritesheet/blink.png").unwrap().as_bytes().to_vec();
+
+    // 100000 x 100000 RGBA, with the IHDR checksum fixed up to match
+    png[16..20].copy_from_slice(&100_000u32.to_be_bytes());
+    png[20..24].copy_from_slice(&100_000u32.to_be_bytes());
+    let crc = crc32(&png[12..29]);
+    png[29..33].copy_from_slice(&crc.to_be_bytes());
+    assert!(export::png::decode(&png).is_err());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 })
+    })
+}
+
+fn graphic_controls(gif: &[u8]) -> Vec<u16> {
+    gif.windows(8)
+        .filter(|w| w[..4] == [0x21, 0xf9, 0x04, 0x09])
+        .map(|w| u16::from_le_bytes([w[4], w[5]]))
+        .collect()
+}
+
+#[test]
+fn test_gif_normalizes_delays_and_honors_loop_modes() {
+    let source = r#"
+        app "Demo" @fps 60 {
+            animation bounce {
+                keyframes { 0.0: 0.0  0.5: 10.0  1.0: 0.0 }
+                loop ping_pong
+            }
+            animation drop {
+                keyframes { 0.0: 0.0  0.2: 10.0 }
+            }
+        }
+    "#;
+    let program = Parser::new(source).parse().expect("parse");
+    let options = ExportOptions { size: Some((32, 32)), ..Default::default() };
+    let files = export::export(&program, &ExportFormat::Gif, &options).expect("export");
+
+    let bounce = files.get("gif/bounce.gif").unwrap().as_bytes();
+    assert_eq!(&bounce[..6], b"GIF89a");
+    assert_eq!(u16::from_le_bytes([bounce[6], bounce[7]]), 32);
+    // 60fps is faster than GIF viewers honor: 2cs (50fps) is the floor
+    assert!(graphic_controls(bounce).iter().all(|delay| delay % 2 == 0));
+    assert!(bounce.windows(11).any(|w| w == b"NETSCAPE2.0"));
+
+    // Plays once: no looping extension
+    let drop = files.get("gif/drop.gif").unwrap().as_bytes();
+    assert!(!drop.windows(11).any(|w| w == b"NETSCAPE2.0"));
+}
+
+#[test]
+fn test_gif_records_frames_of_a_running_scene() {
+    let source = r##"
+        app "Demo" @fps 25 {
+            entity ball {
+                position: (8, 16)
+                size: (8, 8)
+                tint: "#00ff00"
+            }
+            scene Level {
+                animate ball.x {
+                    keyframes { 0.0: 8.0  1.0: 56.0 }
+                }
+            }
+        }
+    "##;
+    let program = Parser::new(source).parse().expect("parse");
+    let options = ExportOptions {
+        size: Some((64, 32)),
+        scene: Some("Level".to_string()),
+        frames: Some(5),
+        ..Default::default()
+    };
+    let files = export::export(&program, &ExportFormat::Gif, &options).expect("export");
+    let gif = files.get("gif/Level.gif").unwrap().as_bytes();
+
+    // The ball moves every frame, so nothing is merged; 25fps is exactly 4cs
+    assert_eq!(graphic_controls(gif), vec![4; 5]);
+    assert_eq!(*gif.last().unwrap(), 0x3b);
+}
@@ -0,0 +1,184 @@
+//! Tests for multi-file projects and `use` resolution
+
+use std::path::{Path, PathBuf};
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::{CodeGenerator, SourceMap, Target};
+use grump_compiler::loader::Project;
+use grump_compiler::parser::Item;
+
+const MAIN: &str = "use physics::forces;
+use util::clamp;
+
+component Position {
+    x: float = 0.0;
+    y: float = 0.0;
+}
+
+fn fall(y: float) -> float {
+    return clamp(y - gravity(), 0.0, 100.0);
+}
+";
+
+const FORCES: &str = "fn gravity() -> float {
+    return 9.8;
+}
+";
+
+const UTIL: &str = "fn clamp(value: float, low: float, high: float) -> float {
+    if (value < low) {
+        return low;
+    }
+    if (value > high) {
+        return high;
+    }
+    return value;
+}
+";
+
+const GRAVITY: &str = "fn gravity() -> float {\n    return 9.8;\n}\n";
+
+fn link(sources: &[(&str, &str)]) -> Result<Project, String> {
+    let sources = sources.iter().map(|(path, text)| (PathBuf::from(path), text.to_string())).collect();
+    Project::from_sources(Path::new("src"), sources).map_err(|e| e.to_string())
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grump-loader-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+#[test]
+fn test_project_directory_is_linked_into_one_program() {
+    let dir = temp_dir("project");
+    std::fs::create_dir_all(dir.join("src/physics")).unwrap();
+    std::fs::create_dir_all(dir.join("build")).unwrap();
+    std::fs::write(dir.join("src/main.grump"), MAIN).unwrap();
+    std::fs::write(dir.join("src/physics/forces.grump"), FORCES).unwrap();
+    std::fs::write(dir.join("src/util.grump"), UTIL).unwrap();
+    std::fs::write(dir.join("build/stale.grump"), "fn clamp() {}").unwrap();
+
+    let project = Project::load(&dir).unwrap();
+    let modules: Vec<&str> = project.files.iter().map(|f| f.module.as_str()).collect();
+    assert_eq!(modules, ["physics::forces", "util", "main"]);
+    assert_eq!(project.entry().unwrap().module, "main");
+    assert_eq!(project.symbols["gravity"].module, "physics::forces");
+    assert_eq!(project.symbols["Position"].keyword, "component");
+
+    // Other files become modules ahead of the entry file's items
+    let program = project.program();
+    assert!(matches!(&program.items[0], Item::Module(m) if m.name == "physics::forces"));
+    assert!(matches!(&program.items[1], Item::Module(m) if m.name == "util"));
+    assert!(program.items.iter().any(|item| matches!(item, Item::Component(c) if c.name == "Position")));
+    Analyzer::new().analyze(&program).unwrap();
+
+    let tree = CodeGenerator::new(Target::TypeScript).generate(&program).unwrap();
+    let source = tree.entry_source().unwrap();
+    assert!(source.contains("export function gravity("));
+    assert!(source.contains("export function clamp("));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_single_file_loads_what_it_uses() {
+    let dir = temp_dir("file");
+    std::fs::create_dir_all(dir.join("physics")).unwrap();
+    std::fs::write(dir.join("game.grump"), "use physics::forces::gravity;\n\nfn fall(y: float) -> float {\n    return y - gravity();\n}\n").unwrap();
+    std::fs::write(dir.join("physics/forces.grump"), FORCES).unwrap();
+    std::fs::write(dir.join("unrelated.grump"), "this is not grump {").unwrap();
+
+    let project = Project::load(&dir.join("game.grump")).unwrap();
+    let modules: Vec<&str> = project.files.iter().map(|f| f.module.as_str()).collect();
+    assert_eq!(modules, ["physics::forces", "game"]);
+    assert_eq!(project.entry().unwrap().module, "game");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_names_from_other_files_must_be_imported() {
+    let error = link(&[
+        ("src/a.grump", "fn fall() -> float {\n    return gravity();\n}\n"),
+        ("src/physics.grump", GRAVITY),
+    ])
+    .unwrap_err();
+    assert!(error.contains("src/a.grump at 1:"), "{}", error);
+    assert!(error.contains("`gravity` is declared in module `physics`; add `use physics::gravity;`"));
+
+    // Parameters and `let`s may shadow them
+    link(&[
+        ("src/a.grump", "fn fall(gravity: float) -> float {\n    let rest = gravity;\n    return rest;\n}\n"),
+        ("src/physics.grump", GRAVITY),
+    ])
+    .unwrap();
+}
+
+#[test]
+fn test_use_errors() {
+    let cycle = link(&[
+        ("src/a.grump", "use b;\n\nfn first() -> int {\n    return second();\n}\n"),
+        ("src/b.grump", "use a;\n\nfn second() -> int {\n    return 1;\n}\n"),
+    ])
+    .unwrap_err();
+    assert!(cycle.contains("Cyclic use: a -> b -> a"), "{}", cycle);
+    assert!(cycle.contains("src/b.grump at 1:5"));
+
+    let missing = link(&[("src/a.grump", "use nothing::here;\n")]).unwrap_err();
+    assert!(missing.contains("Unresolved use `nothing::here`: there is no module `nothing`"), "{}", missing);
+
+    let no_item = link(&[("src/a.grump", "use physics::speed;\n"), ("src/physics.grump", GRAVITY)]).unwrap_err();
+    assert!(no_item.contains("Module `physics` has no item `speed`"), "{}", no_item);
+
+    let twice = link(&[("src/a.grump", GRAVITY), ("src/physics.grump", GRAVITY)]).unwrap_err();
+    assert!(twice.contains("src/physics.grump") && twice.contains("`gravity` is already declared in module `a`"), "{}", twice);
+
+    let parse = link(&[("src/a.grump", "fn broken( {\n")]).unwrap_err();
+    assert!(parse.contains("(in src/a.grump)"), "{}", parse);
+}
+
+#[test]
+fn test_every_target_generates_other_files_at_their_own_lines() {
+    let world = "fn gravity() -> float {\n    return 9.8;\n}\n\nscene Level { let score = 0; }\n";
+    let main = "use world::gravity;\n\nfn fall(y: float) -> float {\n    return y - gravity();\n}\n";
+    let project = link(&[("src/main.grump", main), ("src/world.grump", world)]).unwrap();
+    let program = project.program();
+    let sources: Vec<(String, Option<String>)> = project.sources().iter()
+        .map(|f| (f.path.display().to_string(), Some(f.source.clone())))
+        .collect();
+    assert_eq!(sources.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>(), ["src/main.grump", "src/world.grump"]);
+
+    let targets = [Target::Ios, Target::Android, Target::Web, Target::Flutter, Target::TypeScript, Target::Godot, Target::Rust];
+    for target in targets {
+        let mut codegen = CodeGenerator::new(target).with_sources(sources.clone());
+        let tree = codegen.generate(&program).unwrap_or_else(|e| panic!("{:?}: {}", target, e));
+        let text: String = tree.iter().filter_map(|(_, contents)| contents.as_text()).collect();
+        let map = codegen.source_map();
+        match target {
+            // Phaser only places scenes and entities
+            Target::Web => {
+                let level = map.mappings().iter().find(|m| m.name.as_deref() == Some("Level")).expect("Level mapping");
+                assert_eq!((level.span.file, level.span.line), (1, 5));
+                assert_eq!(map.source_path(level.span.file), "src/world.grump");
+            }
+            Target::TypeScript => {
+                assert!(text.contains("gravity("), "{}", text);
+                let gravity = map.mappings().iter().find(|m| m.name.as_deref() == Some("gravity")).expect("gravity mapping");
+                assert_eq!((gravity.span.file, gravity.span.line), (1, 1));
+                let fall = map.mappings().iter().find(|m| m.name.as_deref() == Some("fall")).expect("fall mapping");
+                assert_eq!((fall.span.file, fall.span.line), (0, 3));
+            }
+            Target::Ios => {
+                assert!(text.contains("#sourceLocation(file: \"src/world.grump\", line: 1)"), "{}", text);
+                assert!(text.contains("#sourceLocation(file: \"src/main.grump\", line: 3)"), "{}", text);
+                let json = tree.get_text("Sources/GrumpGame/Game.swift.map").unwrap();
+                assert_eq!(SourceMap::from_json(json).unwrap().mappings(), map.mappings());
+            }
+            _ => {
+                assert!(text.contains("src/world.grump:1\n"), "{:?}: {}", target, text);
+                assert!(text.contains("src/main.grump:3\n"), "{:?}: {}", target, text);
+            }
+        }
+    }
+}
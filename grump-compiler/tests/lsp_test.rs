@@ -0,0 +1,172 @@
+//! Tests for the language server
+
+use grump_compiler::lsp::{self, Server};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///game/main.grump";
+
+const GAME: &str = "component Position {
+    x: float = 0.0;
+    y: float = 0.0;
+}
+
+fn flee(speed: float) -> float {
+    let boost = speed * 2.0;
+    return boost;
+}
+
+behavior_tree Guard {
+    selector {
+        action flee(1.0)
+    }
+}
+
+system movement {
+    query: [Position]
+    position.x = position.x + 1.0;
+}
+";
+
+fn open(server: &mut Server, text: &str) -> Vec<Value> {
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "grump", "version": 1, "text": text } },
+    }))
+}
+
+fn request(server: &mut Server, method: &str, line: u32, character: u32) -> Value {
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 7,
+        "method": method,
+        "params": { "textDocument": { "uri": URI }, "position": { "line": line, "character": character } },
+    }));
+    assert_eq!(replies[0]["id"], 7);
+    replies[0]["result"].clone()
+}
+
+#[test]
+fn test_lsp_publishes_parser_and_analyzer_diagnostics() {
+    let mut server = Server::new();
+    let init = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }));
+    assert_eq!(init[0]["result"]["capabilities"]["hoverProvider"], true);
+
+    let published = open(&mut server, GAME);
+    assert_eq!(published[0]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+
+    // Type errors land on the name of the item they were found in
+    let published = open(&mut server, "fn broken() {\n    let ready: bool = 1;\n}\n");
+    let diagnostic = &published[0]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["range"]["start"], json!({ "line": 0, "character": 3 }));
+    assert!(diagnostic["message"].as_str().unwrap().contains("Type mismatch"));
+
+    // Parse errors land on the offending token
+    let published = open(&mut server, "component Position {\n    x float\n}\n");
+    let diagnostic = &published[0]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["range"]["start"]["line"], 1);
+    assert_eq!(diagnostic["severity"], 1);
+
+    let closed = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didClose",
+        "params": { "textDocument": { "uri": URI } },
+    }));
+    assert_eq!(closed[0]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn test_lsp_hover_definition_and_completion() {
+    let mut server = Server::new();
+    open(&mut server, GAME);
+
+    // `boost` on `return boost;`
+    let hover = request(&mut server, "textDocument/hover", 7, 12);
+    assert_eq!(hover["contents"]["value"], "```grump\nlet boost: float\n```");
+    let hover = request(&mut server, "textDocument/hover", 17, 13);
+    assert!(hover["contents"]["value"].as_str().unwrap().contains("component Position {\n    x: float,"));
+
+    // The behavior-tree action jumps to its function
+    let definition = request(&mut server, "textDocument/definition", 12, 16);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(definition["range"]["start"], json!({ "line": 5, "character": 3 }));
+
+    // Component fields after `position.`, even while the edit doesn't parse
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": GAME.replace("position.x = position.x + 1.0;", "position.") }],
+        },
+    }));
+    let members = request(&mut server, "textDocument/completion", 18, 13);
+    let labels: Vec<&str> = members.as_array().unwrap().iter().map(|c| c["label"].as_str().unwrap()).collect();
+    assert_eq!(labels, vec!["x", "y"]);
+
+    let everything = request(&mut server, "textDocument/completion", 6, 4);
+    let lerp = everything.as_array().unwrap().iter().find(|c| c["label"] == "lerp").unwrap();
+    assert_eq!(lerp["detail"], "fn lerp(a: float, b: float, t: float) -> float");
+    assert!(everything.as_array().unwrap().iter().any(|c| c["label"] == "speed"));
+}
+
+#[test]
+fn test_lsp_document_symbols_and_framing() {
+    let mut server = Server::new();
+    open(&mut server, GAME);
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "textDocument/documentSymbol",
+        "params": { "textDocument": { "uri": URI } },
+    }));
+    let symbols = replies[0]["result"].as_array().unwrap();
+    let names: Vec<&str> = symbols.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["Position", "flee", "Guard", "movement"]);
+    assert_eq!(symbols[0]["children"][1]["name"], "y");
+    assert_eq!(symbols[0]["range"]["end"]["line"], 4);
+
+    // Content-Length framing round-trips
+    let mut wire = Vec::new();
+    lsp::write_message(&mut wire, &replies[0]).unwrap();
+    assert!(wire.starts_with(b"Content-Length: "));
+    let mut reader = std::io::BufReader::new(wire.as_slice());
+    assert_eq!(lsp::read_message(&mut reader).unwrap(), Some(replies[0].clone()));
+    assert_eq!(lsp::read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn test_lsp_positions_count_utf16_code_units() {
+    let mut server = Server::new();
+    let init = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }));
+    assert_eq!(init[0]["result"]["capabilities"]["positionEncoding"], "utf-16");
+    open(&mut server, "fn flee(speed: float) -> float {\n    let fast = speed * 2.0;\n    let slow = speed;\n    let crab = \"🦀\"; return fast+slow;\n}\n");
+
+    // The crab is two UTF-16 code units, so character 32 is the `+`, just after `fast`
+    let hover = request(&mut server, "textDocument/hover", 3, 32);
+    assert_eq!(hover["contents"]["value"], "```grump\nlet fast: float\n```");
+}
+
+#[test]
+fn test_lsp_answers_malformed_json_and_keeps_serving() {
+    let mut input = Vec::new();
+    input.extend_from_slice(b"Content-Length: 9\r\n\r\n{\"id\": 1,");
+    for message in [
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ] {
+        lsp::write_message(&mut input, &message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    lsp::serve(&mut std::io::BufReader::new(input.as_slice()), &mut output).unwrap();
+    let mut reader = std::io::BufReader::new(output.as_slice());
+    let error = lsp::read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(error["id"], Value::Null);
+    assert_eq!(error["error"]["code"], -32700);
+    let init = lsp::read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(init["id"], 2);
+    assert_eq!(init["result"]["capabilities"]["hoverProvider"], true);
+}
@@ -0,0 +1,200 @@
+//! Tests for macro expansion
+
+use std::path::{Path, PathBuf};
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::interpreter::{Interpreter, Value};
+use grump_compiler::loader::Project;
+use grump_compiler::parser::{Expression, Item, Parser, Statement};
+use grump_compiler::query::Database;
+use grump_compiler::testing::{self, Outcome};
+
+fn run(source: &str, function: &str, args: Vec<Value>) -> Value {
+    let program = Parser::new(source).parse().unwrap();
+    Analyzer::new().analyze(&program).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+    interpreter.call(function, args).unwrap()
+}
+
+fn parse_error(source: &str) -> String {
+    Parser::new(source).parse().unwrap_err().to_string()
+}
+
+#[test]
+fn test_macros_expand_with_block_and_variadic_params() {
+    let source = "macro repeat(count: int, body: block) {
+    let mut i = 0;
+    while (i < count) {
+        body(i);
+        i = i + 1;
+    }
+}
+
+macro sum(first: int, rest...) {
+    let mut total = first;
+    for (value in rest) {
+        total = total + value;
+    }
+    total
+}
+
+fn triangle(n: int) -> int {
+    let mut total = 0;
+    repeat!(n) { |k|
+        total = total + k;
+    }
+    return total;
+}
+
+fn tally() -> int {
+    return sum!(1, 2, 3) + sum!(10);
+}
+";
+    assert_eq!(run(source, "triangle", vec![Value::Int(5)]), Value::Int(10));
+    assert_eq!(run(source, "tally", Vec::new()), Value::Int(16));
+
+    // No calls are left for the analyzer or code generators
+    let program = Parser::new(source).parse().unwrap();
+    let Some(Item::Function(tally)) = program.items.iter().find(|item| item.name() == "tally") else {
+        panic!("no tally");
+    };
+    let Statement::Return(Some(Expression::Binary { left, .. }), _) = &tally.body[0] else {
+        panic!("unexpected body {:?}", tally.body);
+    };
+    assert!(matches!(left.as_ref(), Expression::Block(_)));
+}
+
+#[test]
+fn test_expansion_is_hygienic() {
+    let source = "macro swap_twice(x) {
+    let tmp = x;
+    let i = tmp * 2;
+    i
+}
+
+macro each(count: int, body: block) {
+    let mut i = 0;
+    while (i < count) {
+        body;
+        i = i + 1;
+    }
+}
+
+fn caller() -> int {
+    let tmp = 100;
+    let i = 7;
+    let doubled = swap_twice!(i);
+    let mut hits = 0;
+    each!(3) {
+        hits = hits + i;
+    }
+    return tmp + i + doubled + hits;
+}
+";
+    // The macros' `tmp` and `i` don't clobber the caller's, and the block
+    // sees the caller's `i`, not the loop counter
+    assert_eq!(run(source, "caller", Vec::new()), Value::Int(100 + 7 + 14 + 21));
+}
+
+#[test]
+fn test_blocks_run_in_their_own_scope_and_fresh_names_avoid_the_callers() {
+    let source = "macro pair(a: int, b: int, body: block) {
+    body(a);
+    body(b);
+}
+
+fn scoped() -> int {
+    let n = 10;
+    let mut sum = 0;
+    pair!(1, 2) { |n|
+        let doubled = n * 2;
+        sum = sum + doubled;
+    }
+    return sum + n;
+}
+";
+    // Each run's `|n|` and `let` end with it
+    assert_eq!(run(source, "scoped", Vec::new()), Value::Int(2 + 4 + 10));
+
+    // The first expansion's `a` would be `a__pair1`, which the caller has
+    let source = "macro pair(a: int, b: int, body: block) {
+    body(a);
+    body(b);
+}
+
+fn clash() -> int {
+    let a__pair1 = 100;
+    let mut sum = 0;
+    pair!(1, 2) { |n|
+        sum = sum + n;
+    }
+    return sum + a__pair1;
+}
+";
+    assert_eq!(run(source, "clash", Vec::new()), Value::Int(3 + 100));
+}
+
+#[test]
+fn test_errors_point_at_the_call() {
+    let error = parse_error("macro twice(x) {\n    x * 2\n}\n\nfn f() -> int {\n    return twice!(1, 2);\n}\n");
+    assert!(error.contains("Macro error at 6:"), "{}", error);
+    assert!(error.contains("`twice!` takes 1 arguments, got 2"), "{}", error);
+
+    let error = parse_error("macro each(items...) {\n    items\n}\n\nfn f() {\n    each!();\n    each!(1);\n    loop_forever!(1);\n}\n\nmacro loop_forever(x) {\n    loop_forever!(x);\n}\n");
+    assert!(error.contains("Macro error at 8:"), "{}", error);
+    assert!(error.contains("expands into itself more than 32 levels deep"), "{}", error);
+
+    let error = parse_error("macro run(body: block) {\n    let f = body;\n}\n\nfn f() {\n    run!() {\n        print(1);\n    }\n}\n");
+    assert!(error.contains("`body` is a block; run it as a statement"), "{}", error);
+
+    let program = Parser::new("macro bad(a, a) {\n    a\n}\n").parse().unwrap();
+    let error = Analyzer::new().analyze(&program).unwrap_err().to_string();
+    assert!(error.contains("two parameters named `a`"), "{}", error);
+
+    // Calls to macros no file declares are left to the analyzer
+    let program = Parser::new("fn f() {\n    mystery!(1);\n}\n").parse().unwrap();
+    let error = Analyzer::new().analyze(&program).unwrap_err().to_string();
+    assert!(error.contains("Undefined macro: mystery!"), "{}", error);
+}
+
+#[test]
+fn test_macros_are_imported_across_files() {
+    let sources = vec![
+        (PathBuf::from("src/util.grump"), "macro square(x: int) {\n    x * x\n}\n".to_string()),
+        (PathBuf::from("src/main.grump"), "use util::square;\n\nfn area(side: int) -> int {\n    return square!(side);\n}\n".to_string()),
+    ];
+    let project = Project::from_sources(Path::new("src"), sources).unwrap();
+    let program = project.program();
+    Analyzer::new().analyze(&program).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+    assert_eq!(interpreter.call("area", vec![Value::Int(4)]).unwrap(), Value::Int(16));
+
+    // Without the `use`, the call is an error in the calling file
+    let sources = vec![
+        (PathBuf::from("src/util.grump"), "macro square(x: int) {\n    x * x\n}\n".to_string()),
+        (PathBuf::from("src/main.grump"), "fn area(side: int) -> int {\n    return square!(side);\n}\n".to_string()),
+    ];
+    let error = Project::from_sources(Path::new("src"), sources).err().unwrap().to_string();
+    assert!(error.contains("src/main.grump"), "{}", error);
+    assert!(error.contains("add `use util::square;`"), "{}", error);
+}
+
+#[test]
+fn test_incremental_checks_expand_macros_from_other_items() {
+    let mut db = Database::new();
+    db.set_source("main.grump", "macro twice(x: int) {\n    x * 2\n}\n\nfn four() -> int {\n    return twice!(2);\n}\n");
+    assert!(db.diagnostics("main.grump").is_empty());
+    db.set_source("main.grump", "fn four() -> int {\n    return triple!(2);\n}\n");
+    let problems = db.diagnostics("main.grump");
+    assert!(problems[0].message.contains("Undefined macro: triple!"), "{:?}", problems);
+}
+
+#[test]
+fn test_macro_example_runs() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/macro-example.grump")).unwrap();
+    let results = testing::run_source("macro-example.grump", &source, None);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].outcome, Outcome::Passed, "{:?}", results[0]);
+}
@@ -0,0 +1,51 @@
+//! Tests for manifest-driven build planning
+
+use grump_compiler::codegen::Target;
+use grump_compiler::manifest::{BuildPlan, Manifest};
+use grump_compiler::parser::Parser;
+
+fn manifest(targets: &[&str]) -> Manifest {
+    Manifest {
+        name: "demo".to_string(),
+        version: "0.1.0".to_string(),
+        targets: targets.iter().map(|t| t.to_string()).collect(),
+        fps: Some(30.0),
+    }
+}
+
+#[test]
+fn test_all_builds_manifest_targets_allowed_by_source() {
+    let program = Parser::new(r#"app "Demo" @target [ios, web] { }"#).parse().expect("parse");
+    let plan = BuildPlan::resolve(Some("all"), Some(&manifest(&["ios", "android", "web"])), &program).expect("plan");
+
+    assert_eq!(plan.targets, vec![Target::Ios, Target::Web]);
+    assert_eq!(plan.fps, Some(30.0));
+    assert!(plan.warnings.iter().any(|w| w.contains("android")));
+}
+
+#[test]
+fn test_unknown_and_excluded_targets_are_rejected() {
+    let program = Parser::new(r#"app "Demo" @target [ios] { }"#).parse().expect("parse");
+
+    assert!(BuildPlan::resolve(Some("playstation"), None, &program).is_err());
+    assert!(BuildPlan::resolve(Some("web"), None, &program).is_err());
+    assert!(BuildPlan::resolve(None, Some(&manifest(&["ios", "gameboy"])), &program).is_err());
+}
+
+#[test]
+fn test_default_targets_follow_the_source() {
+    let program = Parser::new(r#"app "Demo" @target [web, android, web] { }"#).parse().expect("parse");
+    let plan = BuildPlan::resolve(None, None, &program).expect("plan");
+    assert_eq!(plan.targets, vec![Target::Web, Target::Android]);
+
+    // A fresh manifest lists nothing, so it doesn't override the source either
+    let plan = BuildPlan::resolve(None, Some(&Manifest::new("demo")), &program).expect("plan");
+    assert_eq!(plan.targets, vec![Target::Web, Target::Android]);
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+
+    let program = Parser::new(r#"app "Demo" { }"#).parse().expect("parse");
+    assert_eq!(BuildPlan::resolve(None, None, &program).expect("plan").targets, vec![Target::Ios]);
+
+    let plan = BuildPlan::resolve(Some("all"), Some(&manifest(&["web", "ios", "web"])), &program).expect("plan");
+    assert_eq!(plan.targets, vec![Target::Web, Target::Ios]);
+}
@@ -0,0 +1,210 @@
+//! Tests for network declarations and the replication runtime
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::{CodeGenerator, Target};
+use grump_compiler::parser::extensions::SyncMode as AstSyncMode;
+use grump_compiler::parser::{Item, Parser, Program};
+use grump_compiler::runtime::network::codec::{self, ObjectDelta, Packet};
+use grump_compiler::runtime::network::{
+    Client, FieldKind, Loopback, LoopbackTransport, NetError, NetValue, ReplicationConfig, Schema, Server, SyncMode,
+    Transport,
+};
+
+const PLAYER: &str = r#"
+network Player {
+    sync position: vec2 @interpolated
+    sync health: int
+    sync shot: bool @unreliable
+    sync score: int @snapshot
+
+    rpc take_damage(amount: int) -> void: server
+}
+"#;
+
+fn parse(source: &str) -> Program {
+    Parser::new(source).parse().expect("parse")
+}
+
+fn schema() -> Schema {
+    Schema::new("Player")
+        .field("position", FieldKind::Vec2, SyncMode::Interpolated)
+        .field("health", FieldKind::Int, SyncMode::Reliable)
+        .field("shot", FieldKind::Bool, SyncMode::Unreliable)
+        .field("score", FieldKind::Int, SyncMode::Snapshot)
+}
+
+/// A server with one object replicating to client 1
+fn connect() -> (Loopback, Server<LoopbackTransport>, Client<LoopbackTransport>) {
+    let network = Loopback::new();
+    let mut server = Server::new(schema(), network.connect(0), ReplicationConfig::default());
+    let client = Client::new(schema(), network.connect(1), 0, ReplicationConfig::default());
+    server.add_client(1);
+    server.spawn(7);
+    (network, server, client)
+}
+
+fn step(server: &mut Server<LoopbackTransport>, client: &mut Client<LoopbackTransport>) {
+    server.update();
+    client.receive();
+}
+
+#[test]
+fn test_sync_modes_are_parsed_and_checked() {
+    let program = parse(PLAYER);
+    let Item::Network(network) = &program.items[0] else {
+        panic!("not a network");
+    };
+    let modes: Vec<_> = network.sync_fields.iter().map(|field| field.sync_mode.clone()).collect();
+    assert!(matches!(
+        modes.as_slice(),
+        [AstSyncMode::Interpolated, AstSyncMode::Reliable, AstSyncMode::Unreliable, AstSyncMode::Snapshot]
+    ));
+    Analyzer::new().analyze(&program).unwrap();
+
+    let error = Parser::new("network N {\n    sync x: int @sometimes\n}\n").parse().unwrap_err().to_string();
+    assert!(error.contains("Unknown sync mode 'sometimes'"), "{}", error);
+
+    let error = Analyzer::new().analyze(&parse("network N {\n    sync name: string @interpolated\n}\n")).unwrap_err().to_string();
+    assert!(error.contains("only numbers and vectors interpolate"), "{}", error);
+
+    let error = Analyzer::new().analyze(&parse("network N {\n    sync x: int\n    sync x: float\n}\n")).unwrap_err().to_string();
+    assert!(error.contains("syncs 'x' twice"), "{}", error);
+
+    let error = Analyzer::new().analyze(&parse("network N {\n    sync target: Enemy\n}\n")).unwrap_err().to_string();
+    assert!(error.contains("can't be replicated"), "{}", error);
+}
+
+#[test]
+fn test_packets_round_trip() {
+    let packet = Packet::State {
+        tick: 300,
+        baseline: Some(298),
+        objects: vec![
+            ObjectDelta::Changed { id: 7, created: false, fields: vec![(1, NetValue::Int(-5)), (2, NetValue::Bool(true))] },
+            ObjectDelta::Changed { id: 8, created: true, fields: schema().defaults().into_iter().enumerate().collect() },
+            ObjectDelta::Removed(9),
+        ],
+    };
+    let bytes = codec::encode(&packet);
+    assert_eq!(codec::decode(&bytes, &schema()).unwrap(), packet);
+    assert_eq!(codec::decode(&bytes[..bytes.len() - 1], &schema()), Err(NetError::Truncated));
+    assert_eq!(codec::decode(&codec::encode(&Packet::Ack { tick: 4 }), &schema()).unwrap(), Packet::Ack { tick: 4 });
+}
+
+#[test]
+fn test_deltas_are_against_the_last_acked_snapshot() {
+    let (network, mut server, mut client) = connect();
+    server.set(7, "health", NetValue::Int(100)).unwrap();
+    step(&mut server, &mut client);
+    step(&mut server, &mut client);
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(100)));
+    assert!(server.set(7, "health", NetValue::Float(1.0)).is_err());
+
+    // Nothing changed since the acked snapshot, so the delta is just a header
+    let sent = network.bytes_sent();
+    step(&mut server, &mut client);
+    assert!(network.bytes_sent() - sent < 10, "{} bytes", network.bytes_sent() - sent);
+
+    // A change whose packets are lost is in every delta until one is acked
+    network.cut(0, 1);
+    server.set(7, "health", NetValue::Int(40)).unwrap();
+    step(&mut server, &mut client);
+    step(&mut server, &mut client);
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(100)));
+    network.restore(0, 1);
+    step(&mut server, &mut client);
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(40)));
+
+    server.despawn(7);
+    step(&mut server, &mut client);
+    assert!(client.objects().is_empty());
+}
+
+#[test]
+fn test_unreliable_and_snapshot_fields() {
+    let (network, mut server, mut client) = connect();
+    step(&mut server, &mut client);
+
+    // Sent once, and kept while acks are lost and deltas fall back to an older baseline
+    server.set(7, "shot", NetValue::Bool(true)).unwrap();
+    network.cut(1, 0);
+    for _ in 0..3 {
+        step(&mut server, &mut client);
+    }
+    assert_eq!(client.get(7, "shot"), Some(NetValue::Bool(true)));
+    network.restore(1, 0);
+
+    // Sent every `snapshot_interval` ticks, whether or not it changed
+    server.set(7, "score", NetValue::Int(9)).unwrap();
+    while server.tick() % ReplicationConfig::default().snapshot_interval != 0 {
+        assert_eq!(client.get(7, "score"), Some(NetValue::Int(0)));
+        step(&mut server, &mut client);
+    }
+    assert_eq!(client.get(7, "score"), Some(NetValue::Int(9)));
+}
+
+#[test]
+fn test_clients_interpolate_between_snapshots() {
+    let (_, mut server, mut client) = connect();
+    let mut previous = f64::NEG_INFINITY;
+    for i in 0..10 {
+        server.set(7, "position", NetValue::Vec2(i as f64 * 10.0, 0.0)).unwrap();
+        step(&mut server, &mut client);
+        // Three frames per tick at 60 fps and 20 ticks per second
+        for _ in 0..3 {
+            client.update(1.0 / 60.0);
+            let Some(NetValue::Vec2(x, _)) = client.get(7, "position") else {
+                panic!("no position");
+            };
+            assert!(x >= previous, "{} after {}", x, previous);
+            previous = x;
+        }
+    }
+    // Rendered `interpolation_delay` ticks behind each snapshot as it
+    // arrives, catching up a tick by the next
+    let behind = server.tick() as f64 - client.render_tick();
+    assert!((1.0 - 1e-9..=2.0).contains(&behind), "{}", behind);
+    assert!(previous > 60.0 && previous < 90.0, "{}", previous);
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(0)));
+}
+
+#[test]
+fn test_garbage_packets_are_skipped() {
+    let (network, mut server, mut client) = connect();
+    server.set(7, "health", NetValue::Int(100)).unwrap();
+    server.update();
+
+    // Garbage and a misdirected packet reach the server ahead of the client's ack
+    let mut rogue = network.connect(1);
+    rogue.send(0, vec![0xff, 0x00, 0x13]);
+    rogue.send(0, codec::encode(&Packet::State { tick: 1, baseline: None, objects: Vec::new() }));
+    assert_eq!(client.receive(), 1);
+
+    // The ack still counts: nothing changed since it, so the delta is just a header
+    let sent = network.bytes_sent();
+    server.update();
+    assert!(network.bytes_sent() - sent < 10, "{} bytes", network.bytes_sent() - sent);
+    let skipped = server.take_skipped();
+    assert_eq!(skipped.len(), 2, "{:?}", skipped);
+    assert!(skipped.iter().all(|(peer, error)| *peer == 1 && matches!(error, NetError::Malformed(_))));
+    client.receive();
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(100)));
+
+    // Clients skip garbage from the server the same way
+    let mut rogue = network.connect(0);
+    rogue.send(1, vec![0x00, 0xff]);
+    assert_eq!(client.receive(), 0);
+    assert_eq!(client.take_skipped(), vec![(0, NetError::Truncated)]);
+    server.set(7, "health", NetValue::Int(5)).unwrap();
+    step(&mut server, &mut client);
+    assert_eq!(client.get(7, "health"), Some(NetValue::Int(5)));
+}
+
+#[test]
+fn test_rust_target_emits_schemas() {
+    let files = CodeGenerator::new(Target::Rust).generate(&parse(PLAYER)).expect("codegen");
+    let lib = files.get_text("src/lib.rs").unwrap();
+    assert!(lib.contains("pub fn player_schema() -> Schema {"), "{}", lib);
+    assert!(lib.contains(".field(\"position\", FieldKind::Vec2, SyncMode::Interpolated)"), "{}", lib);
+    assert!(lib.contains(".field(\"score\", FieldKind::Int, SyncMode::Snapshot)"), "{}", lib);
+}
@@ -0,0 +1,230 @@
+//! Tests for package declarations, `grump fetch`/`grump add` and grump.lock
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::loader::Project;
+use grump_compiler::package::{self, LockedSource, Lockfile};
+use grump_compiler::parser::extensions::Dependency;
+use grump_compiler::parser::{Item, Parser};
+
+const GAME: &str = r#"package my_game {
+    version: "0.1.0"
+
+    dependencies: {
+        "grump-ui": {
+            path: "../grump-ui"
+        }
+    }
+}
+
+use grump_ui::button;
+
+fn play() -> string {
+    return button("Play");
+}
+"#;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grump-package-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, text: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+fn declaration(name: &str, version: &str, dependencies: &str) -> String {
+    format!("package {} {{\n    version: \"{}\"\n    dependencies: {{\n{}    }}\n}}\n", name, version, dependencies)
+}
+
+fn dependency(name: &str) -> Dependency {
+    Dependency { name: name.to_string(), version: None, path: None, git: None, branch: None, features: Vec::new() }
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=grump", "-c", "user.email=grump@example.com", "-c", "init.defaultBranch=main"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// Commit a `physics` package at `version` and tag it
+fn release(repo: &Path, version: &str) {
+    write(&repo.join("src/lib.grump"), &format!(
+        "{}\nfn gravity() -> float {{\n    return 9.8;\n}}\n",
+        declaration("physics", version, "")
+    ));
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "--quiet", "-m", version]);
+    git(repo, &["tag", &format!("v{}", version)]);
+}
+
+#[test]
+fn test_package_declarations_parse() {
+    let source = r#"package my_game {
+    version: "1.0.0"
+    dependencies: {
+        "grump-particles": {
+            version: "1.5"
+            features: ["gpu", "collision"]
+        }
+        "my-utils": { git: "file:///srv/utils.git", branch: "main" },
+        "local-plugin": { path: "../plugins/local" }
+    }
+    dev_dependencies: {
+        "grump-test": { version: "1.0.0" }
+    }
+}
+"#;
+    let program = Parser::new(source).parse().unwrap();
+    let Item::Package(package) = &program.items[0] else { panic!("expected a package") };
+    assert_eq!(package.dependencies.len(), 3);
+    assert_eq!(package.dependencies[0].features, ["gpu", "collision"]);
+    assert_eq!(package.dependencies[1].branch.as_deref(), Some("main"));
+    assert_eq!(package.dependencies[2].path.as_deref(), Some("../plugins/local"));
+    assert_eq!(package.dev_dependencies[0].name, "grump-test");
+    Analyzer::new().analyze(&program).unwrap();
+
+    let bad = Parser::new("package my_game {\n    version: \"one\"\n}\n").parse().unwrap();
+    let error = Analyzer::new().analyze(&bad).unwrap_err().to_string();
+    assert!(error.contains("`my_game` has version \"one\""), "{}", error);
+}
+
+#[test]
+fn test_add_edits_the_declaration() {
+    let mut physics = dependency("grump-physics");
+    physics.version = Some("1.2".to_string());
+    let added = package::add_dependency(GAME, &physics, false).unwrap();
+    assert!(added.contains("            path: \"../grump-ui\"\n        }\n        \"grump-physics\": {\n            version: \"1.2\"\n        }\n    }\n"), "{}", added);
+
+    // A package already listed has its entry replaced
+    let mut again = dependency("grump-ui");
+    again.path = Some("vendor/ui".to_string());
+    let replaced = package::add_dependency(&added, &again, false).unwrap();
+    assert_eq!(replaced.matches("\"grump-ui\"").count(), 1);
+    assert!(replaced.contains("        \"grump-ui\": {\n            path: \"vendor/ui\"\n        }\n"), "{}", replaced);
+
+    // A missing block is created
+    let dev = package::add_dependency(GAME, &dependency("grump-test"), true).unwrap();
+    assert!(dev.contains("    }\n    dev_dependencies: {\n        \"grump-test\": {}\n    }\n}\n"), "{}", dev);
+    Parser::new(&dev).parse().unwrap();
+}
+
+#[test]
+fn test_fetch_path_dependencies_and_load_them() {
+    let dir = temp_dir("path");
+    let game = dir.join("game");
+    write(&game.join("src/main.grump"), GAME);
+    write(&dir.join("grump-ui/src/lib.grump"), &format!(
+        "{}\nuse shared::label;\n\nfn button(text: string) -> string {{\n    return label(text);\n}}\n",
+        declaration("grump_ui", "1.3.0", "        \"shared\": { path: \"../shared\", version: \"0.2\" }\n")
+    ));
+    write(&dir.join("shared/src/lib.grump"), &format!(
+        "{}\nfn label(text: string) -> string {{\n    return text;\n}}\n",
+        declaration("shared", "0.2.5", "")
+    ));
+
+    let lock = package::fetch(&game, None).unwrap();
+    let names: Vec<&str> = lock.packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["grump-ui", "shared"]);
+    assert_eq!(lock.packages[0].version, "1.3.0");
+    assert_eq!(lock.packages[0].dependencies, ["shared"]);
+    assert_eq!(lock.packages[1].source, LockedSource::Path { path: "../shared".to_string() });
+
+    // The lock is deterministic
+    let written = std::fs::read_to_string(game.join(package::LOCK_FILE)).unwrap();
+    assert_eq!(written, lock.to_json());
+    package::fetch(&game, None).unwrap();
+    assert_eq!(std::fs::read_to_string(game.join(package::LOCK_FILE)).unwrap(), written);
+
+    let project = Project::load(&game).unwrap();
+    let modules: Vec<&str> = project.files.iter().map(|f| f.module.as_str()).collect();
+    assert_eq!(modules, ["shared", "grump_ui", "main"]);
+    Analyzer::new().analyze(&project.program()).unwrap();
+
+    // A requirement the directory's version doesn't meet
+    write(&dir.join("shared/src/lib.grump"), &declaration("shared", "0.3.0", ""));
+    let error = package::fetch(&game, None).unwrap_err().to_string();
+    assert!(error.contains("`shared` 0.3.0 (path+../shared) doesn't meet `^0.2`, required by `grump-ui`"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_fetch_git_dependencies_by_tag() {
+    let dir = temp_dir("git");
+    let repo = dir.join("physics");
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    release(&repo, "1.0.0");
+    release(&repo, "1.4.0");
+    release(&repo, "2.0.0");
+
+    let game = dir.join("game");
+    let url = format!("file://{}", repo.display());
+    write(&game.join("src/main.grump"), &declaration("my_game", "0.1.0", ""));
+    let mut physics = dependency("physics");
+    physics.git = Some(url.clone());
+    physics.version = Some("1".to_string());
+    let lock = package::add(&game, &physics, false, None).unwrap();
+
+    // The newest tag meeting `^1`, checked out under .grump/deps
+    let locked = lock.get("physics").unwrap();
+    assert_eq!(locked.version, "1.4.0");
+    let LockedSource::Git { url: locked_url, commit, .. } = &locked.source else { panic!("expected a git source") };
+    assert_eq!(locked_url, &url);
+    assert!(game.join(locked.dir()).join("src/lib.grump").is_file());
+    assert!(locked.dir().starts_with(package::DEPS_DIR));
+
+    // A newer matching tag doesn't move the lock
+    release(&repo, "1.5.0");
+    let again = package::fetch(&game, None).unwrap();
+    assert!(matches!(&again.get("physics").unwrap().source, LockedSource::Git { commit: c, .. } if c == commit));
+
+    // A requirement no tag meets leaves the declaration as it was
+    let before = std::fs::read_to_string(game.join("src/main.grump")).unwrap();
+    physics.version = Some("3".to_string());
+    let error = package::add(&game, &physics, false, None).unwrap_err().to_string();
+    assert!(error.contains("it has 1.0.0, 1.4.0, 1.5.0, 2.0.0"), "{}", error);
+    assert_eq!(std::fs::read_to_string(game.join("src/main.grump")).unwrap(), before);
+    assert_eq!(Lockfile::read(&game).unwrap().unwrap(), again);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_fetch_rejects_unsafe_remotes_and_names() {
+    let dir = temp_dir("unsafe");
+    let game = dir.join("game");
+    write(&game.join("src/main.grump"), &declaration("my_game", "0.1.0", ""));
+    let before = std::fs::read_to_string(game.join("src/main.grump")).unwrap();
+
+    // Options, and transports other than local ones, never reach git
+    for url in ["--upload-pack=touch pwned", "ext::sh -c touch% pwned", "ssh://example.com/physics.git", "example.com:physics.git"] {
+        let mut physics = dependency("physics");
+        physics.git = Some(url.to_string());
+        let error = package::add(&game, &physics, false, None).unwrap_err().to_string();
+        assert!(error.contains("must be a local path or a file:// URL"), "{}: {}", url, error);
+    }
+    assert!(!game.join("pwned").exists());
+
+    // Names become directories under .grump/deps
+    for name in ["../escape", "a/b", ".hidden", ""] {
+        let mut escape = dependency(name);
+        escape.path = Some("../physics".to_string());
+        let error = package::add(&game, &escape, false, None).unwrap_err().to_string();
+        assert!(error.contains("isn't a valid package name"), "{}: {}", name, error);
+    }
+    assert_eq!(std::fs::read_to_string(game.join("src/main.grump")).unwrap(), before);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
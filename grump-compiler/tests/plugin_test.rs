@@ -0,0 +1,189 @@
+//! Tests for WASM and source plugins
+
+use std::path::{Path, PathBuf};
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::OutputTree;
+use grump_compiler::interpreter::{Interpreter, Value};
+use grump_compiler::loader::Project;
+use grump_compiler::parser::{Parser, Program};
+use grump_compiler::plugin::PluginHost;
+
+const MATH: &str = r#"(module
+  (func (export "grump_abi_version") (result i32) i32.const 1)
+  (func (export "twice") (param f64) (result f64) local.get 0 local.get 0 f64.add)
+  (func (export "is_even") (param i64) (result i32) local.get 0 i64.const 2 i64.rem_s i64.eqz)
+  (func (export "spin") (result i64) (loop br 0) i64.const 0)
+  (func (export "half") (param f32) (result f32) local.get 0)
+)"#;
+
+/// Lints every item as "too cheerful" and replaces every generated file
+/// with "// patched"
+const HOOKS: &str = r#"(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "too cheerful")
+  (data (i32.const 16) "// patched")
+  (func (export "grump_abi_version") (result i32) i32.const 1)
+  (func (export "grump_alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "grump_lint") (param i32 i32) (result i64) i64.const 12)
+  (func (export "grump_codegen") (param i32 i32) (result i64) i64.const 68719476746)
+)"#;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grump-plugin-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, text: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+fn wasm(dir: &Path, name: &str, wat: &str) {
+    std::fs::write(dir.join(name), wat::parse_str(wat).unwrap()).unwrap();
+}
+
+fn plugin(name: &str, path: &str, exports: &[&str]) -> String {
+    let exports: Vec<String> = exports.iter().map(|e| format!("\"{}\"", e)).collect();
+    format!("plugin {} {{\n    version: \"1.0.0\"\n    path: \"{}\"\n    exports: [{}]\n}}\n", name, path, exports.join(", "))
+}
+
+fn load(source: &str, dir: &Path) -> (Program, String) {
+    let program = Parser::new(source).parse().unwrap();
+    let error = PluginHost::load(&program, dir).err().map(|e| e.to_string()).unwrap_or_default();
+    (program, error)
+}
+
+#[test]
+fn test_wasm_exports_become_builtins() {
+    let dir = temp_dir("builtins");
+    wasm(&dir, "math.wasm", MATH);
+    let source = format!(
+        "{}\nfn quadruple(x: float) -> float {{\n    return twice(twice(x));\n}}\n",
+        plugin("math", "math.wasm", &["twice", "is_even", "spin"])
+    );
+    let program = Parser::new(&source).parse().unwrap();
+    let plugins = PluginHost::load(&program, &dir).unwrap();
+
+    // Unknown to the analyzer until the plugin registers them
+    assert!(Analyzer::new().analyze(&program).is_err());
+    let mut analyzer = Analyzer::new();
+    analyzer.add_plugin_functions(&plugins);
+    analyzer.analyze(&program).unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.use_plugins(plugins);
+    interpreter.load(&program);
+    assert_eq!(interpreter.call("quadruple", vec![Value::Float(1.5)]).unwrap(), Value::Float(6.0));
+    assert_eq!(interpreter.call("is_even", vec![Value::Int(4)]).unwrap(), Value::Bool(true));
+
+    // A runaway loop runs out of fuel instead of hanging
+    let error = interpreter.call("spin", Vec::new()).unwrap_err().to_string();
+    assert!(error.contains("Plugin error in math: `spin` trapped"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_loading_fails_cleanly() {
+    let dir = temp_dir("failures");
+    wasm(&dir, "math.wasm", MATH);
+    wasm(&dir, "sneaky.wasm", r#"(module
+  (import "env" "open" (func (param i32) (result i32)))
+  (func (export "grump_abi_version") (result i32) i32.const 1)
+)"#);
+    wasm(&dir, "future.wasm", r#"(module (func (export "grump_abi_version") (result i32) i32.const 2))"#);
+
+    let (_, error) = load(&plugin("math", "math.wasm", &["twice", "perlin"]), &dir);
+    assert!(error.contains("doesn't export a function `perlin`"), "{}", error);
+    let (_, error) = load(&plugin("math", "math.wasm", &["half"]), &dir);
+    assert!(error.contains("exports take and return f64, i64 or i32"), "{}", error);
+    let (_, error) = load(&plugin("sneaky", "sneaky.wasm", &[]), &dir);
+    assert!(error.contains("imports `env::open`, but plugins are sandboxed"), "{}", error);
+    let (_, error) = load(&plugin("future", "future.wasm", &[]), &dir);
+    assert!(error.contains("is built for plugin ABI 2"), "{}", error);
+    let (_, error) = load(&plugin("gone", "gone.wasm", &[]), &dir);
+    assert!(error.contains("gone.wasm doesn't exist"), "{}", error);
+
+    let (_, error) = load("plugin remote {\n    url: \"https://example.com/remote.wasm\"\n}\n", &dir);
+    assert!(error.contains("only local plugins can be loaded"), "{}", error);
+    let (program, error) = load("plugin odd {\n    version: \"one\"\n    path: \"math.wasm\"\n}\n", &dir);
+    assert!(error.contains("isn't a semantic version"), "{}", error);
+    assert!(Analyzer::new().analyze(&program).is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_wasm_lint_and_codegen_hooks() {
+    let dir = temp_dir("hooks");
+    wasm(&dir, "style.wasm", HOOKS);
+    let source = format!("{}\nfn happy() {{\n}}\n\nfn jolly() {{\n}}\n", plugin("style", "style.wasm", &[]));
+    let program = Parser::new(&source).parse().unwrap();
+    let plugins = PluginHost::load(&program, &dir).unwrap();
+
+    let warnings = plugins.lint(&program).unwrap();
+    let items: Vec<&str> = warnings.iter().map(|w| w.item.as_str()).collect();
+    assert_eq!(items, ["happy", "jolly"]);
+    assert_eq!(warnings[0].message, "too cheerful");
+    assert_eq!(warnings[0].plugin, "style");
+
+    let mut tree = OutputTree::new();
+    tree.add_text("index.js", "console.log('hi');");
+    plugins.transform("web", &mut tree).unwrap();
+    assert_eq!(tree.get_text("index.js"), Some("// patched"));
+
+    // A result longer than the plugin's memory is refused, not allocated
+    wasm(&dir, "greedy.wasm", r#"(module
+  (memory (export "memory") 1)
+  (func (export "grump_abi_version") (result i32) i32.const 1)
+  (func (export "grump_alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "grump_lint") (param i32 i32) (result i64) i64.const 4294967295)
+)"#);
+    let program = Parser::new(&format!("{}
+fn happy() {{
+}}
+", plugin("greedy", "greedy.wasm", &[]))).parse().unwrap();
+    let error = PluginHost::load(&program, &dir).unwrap().lint(&program).unwrap_err().to_string();
+    assert!(error.contains("`grump_lint` returned a buffer outside its memory"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_source_plugins_load_as_modules() {
+    let dir = temp_dir("source");
+    write(&dir.join("plugins/shouting/lib.grump"), r#"fn shout(text: string) -> string {
+    return text;
+}
+
+fn grump_lint(name: string, keyword: string) -> string {
+    if (name == "whisper") {
+        return "speak up";
+    }
+    return "";
+}
+"#);
+    write(&dir.join("src/main.grump"), &format!(
+        "{}\nuse shouting::shout;\n\nfn whisper() -> string {{\n    return shout(\"hi\");\n}}\n",
+        plugin("shouting", "plugins/shouting", &["shout"])
+    ));
+
+    let project = Project::load(&dir).unwrap();
+    let modules: Vec<&str> = project.files.iter().map(|f| f.module.as_str()).collect();
+    assert_eq!(modules, ["shouting", "main"]);
+    let program = project.program();
+    Analyzer::new().analyze(&program).unwrap();
+
+    let plugins = PluginHost::load(&program, &dir).unwrap();
+    let warnings = plugins.lint(&program).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!((warnings[0].item.as_str(), warnings[0].message.as_str()), ("whisper", "speak up"));
+
+    let (_, error) = load(&plugin("shouting", "plugins/shouting", &["scream"]), &dir);
+    assert!(error.contains("exports `scream`, but"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,120 @@
+//! Tests for the incremental front end
+
+use grump_compiler::parser::Parser;
+use grump_compiler::query::{Database, Tokens};
+
+const GAME: &str = "component Position {
+    x: float = 0.0;
+    y: float = 0.0;
+}
+
+fn speed(scale: float) -> float {
+    return scale * 2.0;
+}
+
+fn flee(amount: float) -> float {
+    let boost = speed(amount);
+    return boost;
+}
+
+fn idle() {
+    let rest = 1.0;
+}
+";
+
+#[test]
+fn test_relexing_restarts_at_the_edited_line() {
+    let mut tokens = Tokens::new(GAME);
+    let total = tokens.lexemes().len();
+
+    // Only the edited line is lexed again; the rest is shifted into place
+    let edited = GAME.replace("scale * 2.0", "scale * 2.0 + 1.0");
+    let lexed = tokens.update(&edited);
+    assert!(lexed < 10, "re-lexed {} of {} tokens", lexed, total);
+    assert_eq!(tokens.lexemes(), Tokens::new(&edited).lexemes());
+
+    // Commenting out a function re-lexes from where the comment opens
+    let commented = edited.replace("fn flee", "/* fn flee").replace("fn idle", "*/ fn idle");
+    tokens.update(&commented);
+    assert_eq!(tokens.lexemes(), Tokens::new(&commented).lexemes());
+    assert!(tokens.error().is_none());
+}
+
+#[test]
+fn test_items_are_parsed_and_analyzed_one_at_a_time() {
+    let mut db = Database::new();
+    db.set_source("main.grump", GAME);
+    assert!(db.diagnostics("main.grump").is_empty());
+    let before = db.stats();
+    assert_eq!(before.items_parsed, 4);
+    assert_eq!(before.items_analyzed, 4);
+
+    // A body edit re-parses and re-checks just that function
+    let edited = GAME.replace("let rest = 1.0;", "let rest = 2.0;");
+    db.set_source("main.grump", &edited);
+    db.diagnostics("main.grump");
+    let after = db.stats();
+    assert_eq!(after.items_parsed - before.items_parsed, 1);
+    assert_eq!(after.items_analyzed - before.items_analyzed, 1);
+
+    // A signature edit also re-checks its callers
+    let changed = edited.replace("fn speed(scale: float) -> float", "fn speed(scale: float) -> bool");
+    db.set_source("main.grump", &changed);
+    db.diagnostics("main.grump");
+    let callers = db.stats();
+    assert_eq!(callers.items_analyzed - after.items_analyzed, 2);
+
+    // Lines added above only move items; results follow them
+    let moved = format!("\n\n{}", changed);
+    db.set_source("main.grump", &moved);
+    let program = db.parse("main.grump").expect("parses");
+    let expected = Parser::new(&moved).parse().expect("parses");
+    assert_eq!(format!("{:?}", program), format!("{:?}", expected));
+    let analysis = db.analyze("main.grump").unwrap();
+    assert_eq!(analysis.items[2].span.line, 12);
+    assert_eq!(analysis.items[2].bindings[0].0, "boost");
+    assert_eq!(db.stats().items_analyzed, callers.items_analyzed);
+    assert_eq!(db.stats().items_parsed, callers.items_parsed);
+}
+
+#[test]
+fn test_parse_errors_keep_the_last_analysis() {
+    let mut db = Database::new();
+    db.set_source("main.grump", GAME);
+    db.diagnostics("main.grump");
+
+    db.set_source("main.grump", &GAME.replace("x: float", "x float"));
+    let problems = db.diagnostics("main.grump");
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].line, 2);
+    let analysis = db.analyze("main.grump").unwrap();
+    assert!(analysis.parse_error.is_some());
+    assert_eq!(analysis.program.unwrap().items.len(), 4);
+    assert!(db.check("main.grump").is_err());
+}
+
+#[test]
+fn test_saved_checks_skip_unchanged_work() {
+    let dir = std::env::temp_dir().join(format!("grump-query-{}", std::process::id()));
+    let cache = dir.join("check.json");
+    let broken = GAME.replace("let rest = 1.0;", "let rest: bool = 1.0;");
+
+    let mut db = Database::new();
+    db.set_source("main.grump", &broken);
+    assert!(db.check("main.grump").is_err());
+    db.save(&cache).unwrap();
+
+    // Same text: the saved result is used without lexing anything
+    let mut db = Database::load(&cache);
+    db.set_source("main.grump", &broken);
+    let problems = db.diagnostics("main.grump");
+    assert!(problems[0].message.contains("Type mismatch"));
+    assert_eq!(db.stats().tokens_lexed, 0);
+
+    // An edit elsewhere re-checks only the edited item
+    db.set_source("main.grump", &broken.replace("scale * 2.0", "scale * 3.0"));
+    assert!(db.check("main.grump").is_err());
+    assert_eq!(db.stats().items_analyzed, 1);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,137 @@
+//! Tests for directory registries and `grump publish`
+
+use std::path::{Path, PathBuf};
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::loader::Project;
+use grump_compiler::package::{self, registry, LockedSource, Registry};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grump-registry-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, text: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+/// A project with `declaration` and a function in its `src/lib.grump`
+fn project(dir: &Path, declaration: &str, function: &str) {
+    write(&dir.join("src/lib.grump"), &format!("{}\n{}", declaration, function));
+}
+
+const LABEL: &str = "fn label(text: string) -> string {\n    return text;\n}\n";
+const BUTTON: &str = "use shared::label;\n\nfn button(text: string) -> string {\n    return label(text);\n}\n";
+
+fn ui_package(version: &str) -> String {
+    format!(
+        "package grump_ui {{\n    version: \"{}\"\n    dependencies: {{\n        \"shared\": {{ version: \"0.1\" }}\n    }}\n}}\n",
+        version
+    )
+}
+
+#[test]
+fn test_publish_writes_index_and_archive() {
+    let dir = temp_dir("publish");
+    let shared = dir.join("shared");
+    project(&shared, "package shared {\n    version: \"0.1.0\"\n}\n", LABEL);
+
+    let root = dir.join("registry");
+    let release = package::publish(&shared, &root).unwrap();
+    assert_eq!(release.version, "0.1.0");
+    assert_eq!(release.checksum.len(), 64);
+    let registry = Registry::open(&root).unwrap();
+    assert_eq!(registry.releases("shared").unwrap(), [release.clone()]);
+    let archive = std::fs::read(registry.archive_path("shared", "0.1.0").unwrap()).unwrap();
+    assert_eq!(registry::checksum(&archive), release.checksum);
+
+    // Archives don't depend on when or where they were made
+    let copy = dir.join("copy");
+    project(&copy, "package shared {\n    version: \"0.1.0\"\n}\n", LABEL);
+    assert_eq!(registry::archive(&copy).unwrap(), archive);
+
+    let again = package::publish(&shared, &root).unwrap_err().to_string();
+    assert!(again.contains("`shared` 0.1.0 is already in the registry"), "{}", again);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_publish_validates_the_package() {
+    let dir = temp_dir("validate");
+    let root = dir.join("registry");
+
+    let local = dir.join("local");
+    project(&local, "package local {\n    version: \"1.0.0\"\n    dependencies: {\n        \"shared\": { path: \"../shared\" }\n    }\n}\n", LABEL);
+    let error = package::publish(&local, &root).unwrap_err().to_string();
+    assert!(error.contains("its dependency `shared` isn't from a registry"), "{}", error);
+
+    let unversioned = dir.join("unversioned");
+    project(&unversioned, "package unversioned {\n    version: \"first\"\n}\n", LABEL);
+    let error = package::publish(&unversioned, &root).unwrap_err().to_string();
+    assert!(error.contains("`unversioned` has version \"first\""), "{}", error);
+
+    assert!(Registry::open(&root).unwrap().releases("local").unwrap().is_empty());
+
+    // `grump-ui` and `grump_ui` are both module `grump_ui`
+    write(&root.join("index/grump_ui.json"), "{ \"name\": \"grump-ui\", \"releases\": [{ \"version\": \"1.0.0\", \"checksum\": \"00\" }] }");
+    let ui = dir.join("ui");
+    project(&ui, "package grump_ui {\n    version: \"1.1.0\"\n}\n", LABEL);
+    let error = package::publish(&ui, &root).unwrap_err().to_string();
+    assert!(error.contains("`grump-ui` is already in the registry, and both are module `grump_ui`"), "{}", error);
+    assert!(!root.join("index/grump_ui.json.lock").exists());
+
+    // Names never reach the filesystem unchecked
+    let registry = Registry::open(&root).unwrap();
+    assert!(registry.releases("../../etc/passwd").is_err());
+    assert!(registry.archive_path("grump_ui", "1.0.0/../../x").is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_fetch_resolves_versions_against_the_registry() {
+    let dir = temp_dir("fetch");
+    let root = dir.join("registry");
+    let shared = dir.join("shared");
+    project(&shared, "package shared {\n    version: \"0.1.3\"\n}\n", LABEL);
+    package::publish(&shared, &root).unwrap();
+    let ui = dir.join("ui");
+    for version in ["1.0.0", "1.2.0", "2.0.0"] {
+        project(&ui, &ui_package(version), BUTTON);
+        package::publish(&ui, &root).unwrap();
+    }
+
+    let game = dir.join("game");
+    write(
+        &game.join("src/main.grump"),
+        "package game {\n    version: \"0.1.0\"\n    dependencies: {\n        \"grump-ui\": { version: \"1\" }\n    }\n}\n\nuse grump_ui::button;\n\nfn play() -> string {\n    return button(\"Play\");\n}\n",
+    );
+    let lock = package::fetch(&game, Some(&root)).unwrap();
+    let picked: Vec<(&str, &str)> = lock.packages.iter().map(|p| (p.name.as_str(), p.version.as_str())).collect();
+    assert_eq!(picked, [("grump-ui", "1.2.0"), ("shared", "0.1.3")]);
+    assert!(matches!(&lock.packages[0].source, LockedSource::Registry { .. }));
+    assert!(game.join(".grump/deps/grump-ui-1.2.0/src/lib.grump").is_file());
+
+    let project = Project::load(&game).unwrap();
+    let modules: Vec<&str> = project.files.iter().map(|f| f.module.as_str()).collect();
+    assert_eq!(modules, ["shared", "grump_ui", "main"]);
+    Analyzer::new().analyze(&project.program()).unwrap();
+
+    // Later fetches find the registry through the lock
+    assert_eq!(package::fetch(&game, None).unwrap(), lock);
+
+    // A changed archive is refused
+    std::fs::remove_dir_all(game.join(".grump")).unwrap();
+    let archive = Registry::open(&root).unwrap().archive_path("grump-ui", "1.2.0").unwrap();
+    let mut bytes = std::fs::read(&archive).unwrap();
+    bytes[600] ^= 1;
+    std::fs::write(&archive, bytes).unwrap();
+    let error = package::fetch(&game, None).unwrap_err().to_string();
+    assert!(error.contains("doesn't match its checksum"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,84 @@
+//! Tests for the REPL and the interpreter behind it
+
+use grump_compiler::interpreter::{Interpreter, Value};
+use grump_compiler::repl::Repl;
+
+#[test]
+fn test_expressions_and_bindings() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.eval("1 + 2 * 3").unwrap(), "7 : int");
+    assert_eq!(repl.eval("let speed = 2.5;").unwrap(), "speed: float = 2.5");
+    assert_eq!(repl.eval("speed * 2.0").unwrap(), "5.0 : float");
+    assert_eq!(repl.eval(":type lerp(0.0, speed, 0.5)").unwrap(), "float");
+    assert_eq!(repl.eval("ease_in_out(0.25)").unwrap(), "0.125 : float");
+
+    // Checked before running: nothing is bound or printed on a type error
+    assert!(repl.eval("speed = true;").unwrap_err().to_string().contains("Cannot assign Bool to Float"));
+    assert_eq!(repl.eval("speed").unwrap(), "2.5 : float");
+    assert!(repl.eval("let flag: bool = 1;").is_err());
+    assert!(repl.eval("flag").unwrap_err().to_string().contains("Undefined variable"));
+
+    assert!(repl.eval(":ast 1 + 2").unwrap().contains("Binary"));
+    assert!(repl.eval(":nonsense").is_err());
+}
+
+#[test]
+fn test_declarations_loops_and_print() {
+    let mut repl = Repl::new();
+    let declared = repl.eval("fn twice(x: float) -> float {\n    return x * 2.0;\n}").unwrap();
+    assert_eq!(declared, "fn twice");
+    assert_eq!(repl.eval("twice(4.0)").unwrap(), "8.0 : float");
+    assert_eq!(repl.eval("print(\"hello\")").unwrap(), "hello");
+
+    let output = repl.eval("let mut total = 0;\nfor (n in [1, 2, 3]) { total = total + n; }\ntotal").unwrap();
+    assert_eq!(output, "total: int = 0\n6 : int");
+
+    assert!(!Repl::is_complete("fn half(x: float) -> float {"));
+    assert!(Repl::is_complete("print(\"{\")"));
+    assert!(repl.eval("1 / 0").unwrap_err().to_string().contains("Division by zero"));
+}
+
+#[test]
+fn test_spawn_binds_the_entity() {
+    let mut repl = Repl::new();
+    repl.load("component Position {\n    x: float = 0.0;\n    y: float = 5.0;\n}\n\nentity Hero {\n    Position: (10.0);\n    sprite: \"hero.png\";\n}\n").unwrap();
+
+    let spawned = repl.eval(":spawn Hero").unwrap();
+    assert_eq!(spawned, "hero = Hero#1 { Position { x: 10.0, y: 5.0 }, sprite: \"hero.png\" }");
+    repl.eval("hero.position.x = 3.0;").unwrap();
+    assert_eq!(repl.eval("hero.position.x + hero.position.y").unwrap(), "8.0 : float");
+    assert!(repl.eval(":spawn Villain").is_err());
+}
+
+#[test]
+fn test_animate_previews_sampled_values() {
+    let mut repl = Repl::new();
+    repl.eval("animation pulse {\n    keyframes { 0.0: 0.0  1.0: 10.0 }\n}").unwrap();
+
+    let preview = repl.eval(":animate pulse").unwrap();
+    let lines: Vec<&str> = preview.lines().collect();
+    assert_eq!(lines[0], "pulse: 1s, once");
+    assert_eq!(lines.len(), 2 + 11);
+    assert!(lines[7].contains("0.50s") && lines[7].contains("5.000"));
+    assert!(lines[2].ends_with('|'));
+    assert!(lines[12].ends_with(&"#".repeat(30)));
+
+    let inline = repl.eval(":animate hero.x { keyframes { 0.0: 0.0  2.0: 4.0 } }").unwrap();
+    assert!(inline.starts_with("hero_x: 2s, once"));
+    assert!(inline.contains("hero.x"));
+    assert_eq!(repl.eval(":animate pulse 4").unwrap().lines().count(), 2 + 5);
+}
+
+#[test]
+fn test_interpreter_calls_and_recursion() {
+    let program = grump_compiler::parser::Parser::new(
+        "fn fib(n: int) -> int {\n    if (n < 2) {\n        return n;\n    }\n    return fib(n - 1) + fib(n - 2);\n}\n\nfn forever(n: int) -> int {\n    return forever(n + 1);\n}\n",
+    )
+    .parse()
+    .unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+    assert_eq!(interpreter.call("fib", vec![Value::Int(10)]).unwrap(), Value::Int(55));
+    assert!(interpreter.call("forever", vec![Value::Int(0)]).unwrap_err().to_string().contains("recursion"));
+    assert_eq!(interpreter.call("sqrt", vec![Value::Float(9.0)]).unwrap(), Value::Float(3.0));
+}
@@ -0,0 +1,44 @@
+//! Tests for the G-Rump runtime
+
+use grump_compiler::runtime::animation::{Animation, AnimationManager, Keyframe, LoopMode};
+use grump_compiler::runtime::ecs::{Query, World};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Position(f64);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Velocity(f64);
+
+#[test]
+fn test_query_returns_entities_with_all_components() {
+    let mut world = World::new();
+    let moving = world.create_entity();
+    world.add_component(moving, Position(0.0));
+    world.add_component(moving, Velocity(1.0));
+    let still = world.create_entity();
+    world.add_component(still, Position(5.0));
+
+    let query = Query::new().with::<Position>().with::<Velocity>();
+    assert_eq!(query.execute(world.storage()), vec![moving]);
+    assert_eq!(world.entities(), &[moving, still]);
+    assert_eq!(world.get_component::<Position>(still), Some(&Position(5.0)));
+
+    world.get_component_mut::<Position>(moving).unwrap().0 = 3.0;
+    assert_eq!(world.get_component::<Position>(moving), Some(&Position(3.0)));
+
+    world.remove_entity(moving);
+    assert!(!world.has_component::<Position>(moving));
+    assert_eq!(world.entities(), &[still]);
+}
+
+#[test]
+fn test_animation_samples_keyframes() {
+    let mut manager = AnimationManager::new();
+    manager.add_animation(
+        Animation::new("slide".to_string(), 1.0, LoopMode::None)
+            .with_keyframes(vec![Keyframe::new(1.0, vec![10.0, 0.0]), Keyframe::new(0.0, vec![0.0, 0.0])]),
+    );
+    manager.play_animation("slide");
+    manager.update(0.25);
+    assert_eq!(manager.get_animation("slide").unwrap().sample(), Some(vec![2.5, 0.0]));
+}
@@ -0,0 +1,172 @@
+//! Tests for scripts: the runtime's scheduler, and scripts in the interpreter
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::{CodeGenerator, Target};
+use grump_compiler::interpreter::Interpreter;
+use grump_compiler::parser::Parser;
+use grump_compiler::runtime::animation::{Animation, Keyframe, LoopMode};
+use grump_compiler::runtime::{Runtime, RuntimeConfig};
+
+const CUTSCENE: &str = r#"
+async fn step(n: int) -> int {
+    await wait(500ms);
+    return n + 1;
+}
+
+async fn cutscene(x: float) {
+    print("start");
+    let mut i = 0;
+    while (i < 2) {
+        i = await step(i);
+    }
+    print("stepped");
+    animate x {
+        keyframes { 0.0: 0.0  1.0: 1.0 }
+    }
+    await animation_finished(x);
+    print("animated");
+    await event("go");
+    print("done");
+}
+"#;
+
+#[test]
+fn test_runtime_scripts_resume_on_ticks() {
+    let mut runtime = Runtime::new(RuntimeConfig::default());
+    runtime.animation_manager.add_animation(
+        Animation::new("slide".to_string(), 0.5, LoopMode::None).with_keyframes(vec![Keyframe::new(0.0, vec![0.0])]),
+    );
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let seen = log.clone();
+    let script = runtime.scheduler.spawn(|script| async move {
+        script.wait(1.0).await;
+        seen.borrow_mut().push("waited");
+        script.play("slide");
+        script.animation_finished("slide").await;
+        seen.borrow_mut().push("slid");
+        script.event("go").await;
+        seen.borrow_mut().push("went");
+    });
+
+    for _ in 0..59 {
+        runtime.update(1.0 / 60.0);
+    }
+    assert!(log.borrow().is_empty());
+    runtime.update(1.0 / 60.0);
+    assert_eq!(*log.borrow(), vec!["waited"]);
+    for _ in 0..60 {
+        runtime.update(1.0 / 60.0);
+    }
+    assert_eq!(*log.borrow(), vec!["waited", "slid"]);
+
+    runtime.scheduler.emit("go");
+    runtime.update(1.0 / 60.0);
+    assert_eq!(*log.borrow(), vec!["waited", "slid", "went"]);
+    assert!(!runtime.scheduler.is_running(script));
+}
+
+#[test]
+fn test_runtime_scripts_end_with_their_entity() {
+    let mut runtime = Runtime::new(RuntimeConfig::default());
+    let hero = runtime.world.create_entity();
+    let owned = runtime.scheduler.spawn_for(hero, |script| async move { script.wait(10.0).await });
+    let other = runtime.scheduler.spawn(|script| async move { script.wait(10.0).await });
+    runtime.update(1.0 / 60.0);
+    assert!(runtime.scheduler.is_running(owned));
+
+    runtime.world.remove_entity(hero);
+    runtime.update(1.0 / 60.0);
+    assert!(!runtime.scheduler.is_running(owned));
+    assert!(runtime.scheduler.is_running(other));
+    runtime.scheduler.cancel(other);
+    assert!(runtime.scheduler.is_empty());
+}
+
+#[test]
+fn test_interpreter_scripts_interleave_with_frames() {
+    let program = Parser::new(CUTSCENE).parse().unwrap();
+    Analyzer::new().analyze(&program).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+
+    let script = interpreter.start("cutscene", vec![grump_compiler::interpreter::Value::Float(0.0)]).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["start"]);
+    interpreter.simulate(59).unwrap();
+    assert!(interpreter.take_output().is_empty());
+    interpreter.simulate(1).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["stepped"]);
+    interpreter.simulate(60).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["animated"]);
+
+    // An event only reaches scripts already waiting for it
+    interpreter.simulate(10).unwrap();
+    assert!(interpreter.is_running(script));
+    interpreter.emit("go");
+    interpreter.simulate(1).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["done"]);
+    assert!(!interpreter.is_running(script));
+}
+
+#[test]
+fn test_rust_target_runs_async_functions_as_scripts() {
+    // The Rust target has no inline `animate`
+    let program = Parser::new(CUTSCENE).parse().unwrap();
+    let err = CodeGenerator::new(Target::Rust).generate(&program).unwrap_err();
+    assert!(err.to_string().contains("not supported by the Rust target"), "{}", err);
+
+    let source = CUTSCENE.replace("animate x {\n        keyframes { 0.0: 0.0  1.0: 1.0 }\n    }\n", "");
+    let program = Parser::new(&source).parse().unwrap();
+    let files = CodeGenerator::new(Target::Rust).generate(&program).expect("codegen");
+    let lib = files.get_text("src/lib.rs").unwrap();
+    assert!(lib.contains("pub async fn step(script: &Script, n: i64) -> i64"), "{}", lib);
+    assert!(lib.contains("script.wait(0.5).await"), "{}", lib);
+    assert!(lib.contains("step(script, i).await"), "{}", lib);
+    assert!(lib.contains("script.event(&\"go\").await"), "{}", lib);
+    assert!(lib.contains("script.animation_finished(\"x\").await"), "{}", lib);
+}
+
+#[test]
+fn test_scenes_run_as_scripts_and_despawning_cancels_scripts() {
+    let source = r#"
+entity Guard {
+    sprite: "guard.png";
+}
+
+async fn patrol() {
+    while (true) {
+        await wait(1s);
+        print("patrol");
+    }
+}
+
+scene Intro {
+    print("fade in");
+    await wait(1s);
+    print("title");
+}
+"#;
+    let program = Parser::new(source).parse().unwrap();
+    Analyzer::new().analyze(&program).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.load(&program);
+
+    let intro = interpreter.enter("Intro").unwrap();
+    assert_eq!(interpreter.take_output(), vec!["fade in"]);
+    interpreter.simulate(60).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["title"]);
+    assert!(!interpreter.is_running(intro));
+
+    // Still bound, but despawned
+    let guard = interpreter.spawn("Guard").unwrap();
+    interpreter.set_variable("guard", guard.clone());
+    let patrol = interpreter.start_for(&guard, "patrol", vec![]).unwrap();
+    interpreter.simulate(60).unwrap();
+    assert_eq!(interpreter.take_output(), vec!["patrol"]);
+    interpreter.despawn(&guard).unwrap();
+    assert!(!interpreter.is_running(patrol));
+    interpreter.simulate(60).unwrap();
+    assert!(interpreter.take_output().is_empty());
+}
@@ -0,0 +1,86 @@
+//! Tests for template macros, whose bodies are target code
+
+use grump_compiler::analyzer::Analyzer;
+use grump_compiler::codegen::{CodeGenerator, Target};
+use grump_compiler::parser::extensions::MacroBody;
+use grump_compiler::parser::{Item, Parser};
+
+const VIBRATE: &str = r#"
+macro vibrate(ms: int) {
+    @ios "AudioServicesPlaySystemSound(kSystemSoundID_Vibrate)"
+    @android "vibrator.vibrate(($ms).toLong())"
+    @web "navigator.vibrate($ms)"
+}
+
+macro log(parts...) {
+    @ios "print(\"grump:\", $parts)"
+    @web "console.log(\"grump:\", $parts)"
+}
+
+fn buzz(strength: int) {
+    vibrate!(strength * 100);
+}
+
+fn report() {
+    log!(1, "two");
+}
+"#;
+
+fn analyze(source: &str) -> Result<(), String> {
+    let program = Parser::new(source).parse().map_err(|e| e.to_string())?;
+    Analyzer::new().analyze(&program).map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_templates_are_parsed_per_target() {
+    let program = Parser::new(VIBRATE).parse().unwrap();
+    let Some(Item::Macro(vibrate)) = program.items.iter().find(|item| item.name() == "vibrate") else {
+        panic!("no vibrate");
+    };
+    let MacroBody::Template(templates) = &vibrate.body else {
+        panic!("not a template: {:?}", vibrate.body);
+    };
+    let targets: Vec<&str> = templates.iter().map(|(target, _)| target.as_str()).collect();
+    assert_eq!(targets, vec!["ios", "android", "web"]);
+    analyze(VIBRATE).unwrap();
+}
+
+#[test]
+fn test_analyzer_checks_template_calls() {
+    let error = analyze(&format!("{}\nfn twice() {{\n    vibrate!(1, 2);\n}}\n", VIBRATE)).unwrap_err();
+    assert!(error.contains("`vibrate!` takes 1 arguments, got 2"), "{}", error);
+
+    let error = analyze(&format!("{}\nfn quiet() {{\n    vibrate!(\"long\");\n}}\n", VIBRATE)).unwrap_err();
+    assert!(error.contains("Argument 0 to 'vibrate!' has wrong type"), "{}", error);
+
+    let error = analyze("macro beep(hz: int) {\n    @web \"beep($frequency)\"\n}\n").unwrap_err();
+    assert!(error.contains("`@web` template uses `$frequency`, which isn't a parameter"), "{}", error);
+
+    let error = analyze("macro beep(hz: int) {\n    @windows \"Beep($hz)\"\n}\n").unwrap_err();
+    assert!(error.contains("`@windows`, which isn't a target"), "{}", error);
+
+    let error = analyze("macro each(body: block) {\n    @web \"run()\"\n}\n").unwrap_err();
+    assert!(error.contains("its parameter `body` can't be a block"), "{}", error);
+}
+
+#[test]
+fn test_targets_fill_in_their_template() {
+    let program = Parser::new(VIBRATE).parse().unwrap();
+
+    let files = CodeGenerator::new(Target::TypeScript).generate(&program).expect("typescript");
+    let game = files.get_text("src/game.ts").unwrap();
+    assert!(game.contains("navigator.vibrate("), "{}", game);
+    assert!(game.contains("console.log(\"grump:\", 1, \"two\")"), "{}", game);
+
+    let files = CodeGenerator::new(Target::Ios).generate(&program).expect("ios");
+    let swift = files.get_text("Sources/GrumpGame/Game.swift").unwrap();
+    assert!(swift.contains("AudioServicesPlaySystemSound(kSystemSoundID_Vibrate)"), "{}", swift);
+    assert!(swift.contains("print(\"grump:\", 1, \"two\")"), "{}", swift);
+}
+
+#[test]
+fn test_targets_without_a_template_are_an_error() {
+    let program = Parser::new(VIBRATE).parse().unwrap();
+    let error = CodeGenerator::new(Target::Godot).generate(&program).unwrap_err().to_string();
+    assert!(error.contains("`vibrate!` has no `@godot` template"), "{}", error);
+}
@@ -0,0 +1,113 @@
+//! Tests for `test` items and the `grump test` runner
+
+use grump_compiler::testing::{self, Outcome, TestReport};
+
+const GAME: &str = r#"
+component Position {
+    x: float = 0.0;
+    y: float = 0.0;
+}
+
+component Velocity {
+    x: float = 0.0;
+    y: float = 0.0;
+}
+
+entity Ball {
+    Position: (0.0, 100.0);
+    Velocity: (2.0, -60.0);
+}
+
+system movement {
+    query: [Position, Velocity]
+    position.x = position.x + velocity.x * dt;
+    position.y = position.y + velocity.y * dt;
+}
+
+fn clamp01(t: float) -> float {
+    if (t < 0.0) {
+        return 0.0;
+    }
+    if (t > 1.0) {
+        return 1.0;
+    }
+    return t;
+}
+
+test "clamp keeps values in range" {
+    assert_eq(clamp01(2.0), 1.0);
+    assert(clamp01(0.5) == 0.5);
+}
+
+test "ball falls for a second" {
+    let ball = spawn("Ball");
+    simulate(60);
+    assert_eq(ball.position.x, 2.0);
+    assert_eq(ball.position.y, 40.0);
+    assert_eq(now(), 1.0);
+}
+
+test "strings are compared exactly" {
+    assert_eq(concat("a", "b"), "ba");
+}
+"#;
+
+#[test]
+fn test_assertions_and_simulation() {
+    let results = testing::run_source("game.grump", GAME, None);
+    let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, ["clamp keeps values in range", "ball falls for a second", "strings are compared exactly"]);
+    assert_eq!(results[0].outcome, Outcome::Passed);
+    assert_eq!(results[1].outcome, Outcome::Passed);
+    match &results[2].outcome {
+        Outcome::Failed(message) => assert!(message.contains("Assertion failed: \"ab\" != \"ba\"")),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+
+    let only = testing::run_source("game.grump", GAME, Some("ball"));
+    assert_eq!(only.len(), 1);
+
+    // Type errors fail the file's tests without running them
+    let broken = testing::run_source("broken.grump", "test \"bad\" {\n    assert(1);\n}\n", None);
+    assert!(matches!(broken[0].outcome, Outcome::Error(_)));
+}
+
+#[test]
+fn test_reports() {
+    let report = TestReport { results: testing::run_source("src/game.grump", GAME, None), ..Default::default() };
+    assert_eq!((report.passed(), report.failed()), (2, 1));
+
+    let human = report.human();
+    assert!(human.starts_with("running 3 tests\n"));
+    assert!(human.contains("test src/game.grump: ball falls for a second ... ok\n"));
+    assert!(human.contains("test src/game.grump: strings are compared exactly ... FAILED\n"));
+    assert!(human.contains("test result: FAILED. 2 passed; 1 failed"));
+
+    let junit = report.junit();
+    assert!(junit.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"grump\" tests=\"3\" failures=\"1\" errors=\"0\""));
+    assert!(junit.contains("<testsuite name=\"src/game.grump\" tests=\"3\" failures=\"1\" errors=\"0\""));
+    assert!(junit.contains("<testcase name=\"clamp keeps values in range\" classname=\"src.game\""));
+    assert!(junit.contains("<failure message=\"Runtime error: Assertion failed: &quot;ab&quot; != &quot;ba&quot;\">"));
+    assert_eq!(junit.matches("<testcase ").count(), 3);
+}
+
+#[test]
+fn test_discovery_skips_build_output() {
+    let dir = std::env::temp_dir().join(format!("grump-testing-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("build")).unwrap();
+    std::fs::write(dir.join("src/game.grump"), GAME).unwrap();
+    // Files without tests aren't checked, even if they don't parse
+    std::fs::write(dir.join("src/sketch.grump"), "this is not grump {").unwrap();
+    std::fs::write(dir.join("build/stale.grump"), "test \"stale\" {\n    assert(false);\n}\n").unwrap();
+
+    let files = testing::discover(&dir);
+    assert_eq!(files, [dir.join("src/game.grump"), dir.join("src/sketch.grump")]);
+
+    let report = testing::run_project(&dir, None).unwrap();
+    assert_eq!(report.results.len(), 3);
+    assert!(report.results.iter().all(|r| r.file == "src/game.grump"));
+    assert!(!report.success());
+
+    std::fs::remove_dir_all(&dir).ok();
+}